@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_alertas_anomalia_usuario_status;
+DROP TABLE IF EXISTS alertas_anomalia;
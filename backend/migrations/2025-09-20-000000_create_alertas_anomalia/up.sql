@@ -0,0 +1,23 @@
+-- Alertas de anomalia detectados sobre as transações do usuário
+-- tipo: 'valor_atipico', 'corridas_por_hora_baixa' ou 'possivel_duplicata'
+-- status: 'pendente', 'confirmado' ou 'descartado'
+-- chave identifica a detecção (ex.: 'duplicata:<id_a>:<id_b>') para não recriar alertas já tratados
+CREATE TABLE IF NOT EXISTS alertas_anomalia (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    tipo VARCHAR NOT NULL,
+    chave VARCHAR NOT NULL,
+    id_transacao VARCHAR NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    id_transacao_relacionada VARCHAR NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    dia DATE NOT NULL,
+    descricao VARCHAR NOT NULL,
+    valor_observado DOUBLE PRECISION NULL,
+    valor_esperado DOUBLE PRECISION NULL,
+    pontuacao DOUBLE PRECISION NULL,
+    status VARCHAR NOT NULL DEFAULT 'pendente',
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT uq_alertas_anomalia_usuario_chave UNIQUE (id_usuario, chave)
+);
+
+CREATE INDEX IF NOT EXISTS idx_alertas_anomalia_usuario_status ON alertas_anomalia (id_usuario, status);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_transacoes_usuario_valor_id;
+DROP INDEX IF EXISTS idx_transacoes_usuario_data_id;
@@ -0,0 +1,3 @@
+-- Paginação por cursor da listagem de transações: (data, id) e (valor, id) por usuário
+CREATE INDEX IF NOT EXISTS idx_transacoes_usuario_data_id ON transacoes (id_usuario, data DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_transacoes_usuario_valor_id ON transacoes (id_usuario, valor, id);
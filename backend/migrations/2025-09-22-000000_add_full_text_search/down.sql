@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_metas_busca;
+DROP INDEX IF EXISTS idx_sessoes_trabalho_busca;
+DROP INDEX IF EXISTS idx_transacoes_busca;
+DROP TEXT SEARCH CONFIGURATION IF EXISTS portugues_unaccent;
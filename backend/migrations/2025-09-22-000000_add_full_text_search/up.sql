@@ -0,0 +1,25 @@
+-- Busca textual em português sem acentos ("oleo" encontra "Troca de óleo")
+-- A configuração portugues_unaccent tira os acentos antes do stemming; a forma
+-- to_tsvector('portugues_unaccent', ...) é imutável e pode ser indexada.
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'portugues_unaccent') THEN
+        CREATE TEXT SEARCH CONFIGURATION portugues_unaccent (COPY = portuguese);
+        ALTER TEXT SEARCH CONFIGURATION portugues_unaccent
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, portuguese_stem;
+    END IF;
+END
+$$;
+
+-- As expressões abaixo precisam ser idênticas às usadas em services/busca
+CREATE INDEX IF NOT EXISTS idx_transacoes_busca ON transacoes
+    USING GIN (to_tsvector('portugues_unaccent', COALESCE(descricao, '')));
+
+CREATE INDEX IF NOT EXISTS idx_sessoes_trabalho_busca ON sessoes_trabalho
+    USING GIN (to_tsvector('portugues_unaccent', COALESCE(observacoes, '')));
+
+CREATE INDEX IF NOT EXISTS idx_metas_busca ON metas
+    USING GIN ((setweight(to_tsvector('portugues_unaccent', titulo), 'A') ||
+                setweight(to_tsvector('portugues_unaccent', COALESCE(descricao, '')), 'B')));
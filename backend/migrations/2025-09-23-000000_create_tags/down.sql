@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_transacoes_tags_tag;
+DROP TABLE IF EXISTS transacoes_tags;
+DROP INDEX IF EXISTS uq_tags_usuario_nome;
+DROP TABLE IF EXISTS tags;
@@ -0,0 +1,20 @@
+-- Tags livres do usuário, independentes da categoria ("aeroporto", "dinâmico", "viagem SP")
+CREATE TABLE IF NOT EXISTS tags (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    nome VARCHAR NOT NULL,
+    cor VARCHAR NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Mesmo nome (sem diferenciar maiúsculas) não se repete para o usuário
+CREATE UNIQUE INDEX IF NOT EXISTS uq_tags_usuario_nome ON tags (id_usuario, lower(nome));
+
+CREATE TABLE IF NOT EXISTS transacoes_tags (
+    id_transacao VARCHAR NOT NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    id_tag VARCHAR NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (id_transacao, id_tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transacoes_tags_tag ON transacoes_tags (id_tag);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_comprovantes_usuario;
+DROP TABLE IF EXISTS comprovantes;
@@ -0,0 +1,17 @@
+-- Comprovantes (imagens e PDFs) anexados às transações
+-- O arquivo fica no diretório COMPROVANTES_DIR; caminho e caminho_miniatura são relativos a ele
+CREATE TABLE IF NOT EXISTS comprovantes (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    id_transacao VARCHAR NOT NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    nome_arquivo VARCHAR NOT NULL,
+    mime VARCHAR NOT NULL,
+    tamanho INTEGER NOT NULL,
+    checksum_sha256 VARCHAR NOT NULL,
+    caminho VARCHAR NOT NULL,
+    caminho_miniatura VARCHAR NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT uq_comprovantes_transacao_checksum UNIQUE (id_transacao, checksum_sha256)
+);
+
+CREATE INDEX IF NOT EXISTS idx_comprovantes_usuario ON comprovantes (id_usuario);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_transacoes_divisoes_categoria;
+DROP INDEX IF EXISTS uq_transacoes_divisoes_ordem;
+DROP TABLE IF EXISTS transacoes_divisoes;
@@ -0,0 +1,13 @@
+-- Linhas de uma transação dividida entre categorias (ex.: posto = combustível + lavagem + lanche).
+-- A soma das linhas é sempre o valor da transação; sem linhas, vale a categoria da própria transação.
+CREATE TABLE IF NOT EXISTS transacoes_divisoes (
+    id VARCHAR PRIMARY KEY,
+    id_transacao VARCHAR NOT NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    id_categoria VARCHAR NOT NULL REFERENCES categorias (id),
+    valor INTEGER NOT NULL CHECK (valor > 0),
+    descricao VARCHAR NULL,
+    ordem INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_transacoes_divisoes_ordem ON transacoes_divisoes (id_transacao, ordem);
+CREATE INDEX IF NOT EXISTS idx_transacoes_divisoes_categoria ON transacoes_divisoes (id_categoria);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_transacoes_id_sessao;
+ALTER TABLE transacoes DROP COLUMN IF EXISTS id_sessao;
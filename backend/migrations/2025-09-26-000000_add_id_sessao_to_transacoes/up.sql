@@ -0,0 +1,6 @@
+-- Vínculo explícito com uma sessão de trabalho; sem vínculo, a sessão continua sendo a que
+-- contém a data da transação
+ALTER TABLE transacoes
+    ADD COLUMN IF NOT EXISTS id_sessao VARCHAR NULL REFERENCES sessoes_trabalho (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_transacoes_id_sessao ON transacoes (id_sessao) WHERE id_sessao IS NOT NULL;
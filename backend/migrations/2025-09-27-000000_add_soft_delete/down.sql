@@ -0,0 +1,15 @@
+DROP INDEX IF EXISTS idx_categorias_lixeira;
+DROP INDEX IF EXISTS idx_metas_lixeira;
+DROP INDEX IF EXISTS idx_sessoes_trabalho_lixeira;
+DROP INDEX IF EXISTS idx_transacoes_lixeira;
+
+-- o que estava na lixeira não volta como se fosse ativo
+DELETE FROM transacoes WHERE excluido_em IS NOT NULL;
+DELETE FROM sessoes_trabalho WHERE excluido_em IS NOT NULL;
+DELETE FROM metas WHERE excluido_em IS NOT NULL;
+DELETE FROM categorias WHERE excluido_em IS NOT NULL;
+
+ALTER TABLE categorias DROP COLUMN IF EXISTS excluido_em;
+ALTER TABLE metas DROP COLUMN IF EXISTS excluido_em;
+ALTER TABLE sessoes_trabalho DROP COLUMN IF EXISTS excluido_em;
+ALTER TABLE transacoes DROP COLUMN IF EXISTS excluido_em;
@@ -0,0 +1,12 @@
+-- Exclusão reversível: a linha fica na lixeira (excluido_em preenchido) até ser restaurada
+-- ou expurgada, manualmente ou pela rotina de retenção
+ALTER TABLE transacoes ADD COLUMN IF NOT EXISTS excluido_em TIMESTAMPTZ NULL;
+ALTER TABLE sessoes_trabalho ADD COLUMN IF NOT EXISTS excluido_em TIMESTAMPTZ NULL;
+ALTER TABLE metas ADD COLUMN IF NOT EXISTS excluido_em TIMESTAMPTZ NULL;
+ALTER TABLE categorias ADD COLUMN IF NOT EXISTS excluido_em TIMESTAMPTZ NULL;
+
+-- Listagem da lixeira e expurgo por idade
+CREATE INDEX IF NOT EXISTS idx_transacoes_lixeira ON transacoes (id_usuario, excluido_em) WHERE excluido_em IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_sessoes_trabalho_lixeira ON sessoes_trabalho (id_usuario, excluido_em) WHERE excluido_em IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_metas_lixeira ON metas (id_usuario, excluido_em) WHERE excluido_em IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_categorias_lixeira ON categorias (id_usuario, excluido_em) WHERE excluido_em IS NOT NULL;
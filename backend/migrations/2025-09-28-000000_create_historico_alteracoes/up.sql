@@ -0,0 +1,21 @@
+-- Histórico de alterações de transações, sessões, metas e categorias
+-- Cada linha é uma versão da entidade: o estado antes e depois da operação, quem fez e por onde
+CREATE TABLE IF NOT EXISTS historico_alteracoes (
+    id VARCHAR PRIMARY KEY,
+    -- dono do registro; NULL para categorias globais
+    id_usuario VARCHAR NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    entidade VARCHAR NOT NULL,
+    id_entidade VARCHAR NOT NULL,
+    versao INTEGER NOT NULL,
+    acao VARCHAR NOT NULL,
+    antes JSONB NULL,
+    depois JSONB NULL,
+    -- 'usuario', 'admin' ou 'sistema'
+    ator_tipo VARCHAR NOT NULL,
+    ator_id VARCHAR NULL,
+    origem VARCHAR NOT NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT uq_historico_entidade_versao UNIQUE (entidade, id_entidade, versao)
+);
+
+CREATE INDEX IF NOT EXISTS idx_historico_usuario ON historico_alteracoes (id_usuario, criado_em DESC);
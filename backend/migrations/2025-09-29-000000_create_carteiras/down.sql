@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS transferencias_carteiras;
+DROP INDEX IF EXISTS idx_transacoes_id_carteira;
+ALTER TABLE transacoes DROP COLUMN IF EXISTS id_carteira;
+DROP TABLE IF EXISTS carteiras;
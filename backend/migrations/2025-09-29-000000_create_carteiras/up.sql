@@ -0,0 +1,39 @@
+-- Onde o dinheiro está: dinheiro vivo, conta bancária, saldo na plataforma, cartão
+CREATE TABLE IF NOT EXISTS carteiras (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    nome VARCHAR NOT NULL,
+    -- 'dinheiro' | 'conta_bancaria' | 'saldo_plataforma' | 'cartao'
+    tipo VARCHAR NOT NULL,
+    -- Plataforma do saldo (mesmo nome usado nas sessões de trabalho), só para 'saldo_plataforma'
+    plataforma VARCHAR NULL,
+    -- Centavos já existentes quando a carteira foi cadastrada
+    saldo_inicial INTEGER NOT NULL DEFAULT 0,
+    arquivada BOOLEAN NOT NULL DEFAULT FALSE,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_carteiras_usuario_nome ON carteiras (id_usuario, lower(nome));
+
+ALTER TABLE transacoes
+    ADD COLUMN IF NOT EXISTS id_carteira VARCHAR NULL REFERENCES carteiras (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_transacoes_id_carteira ON transacoes (id_carteira) WHERE id_carteira IS NOT NULL;
+
+-- Movimento entre carteiras do usuário (saque do saldo da plataforma, depósito do dinheiro
+-- vivo, pagamento da fatura): não é ganho nem gasto
+CREATE TABLE IF NOT EXISTS transferencias_carteiras (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    id_origem VARCHAR NOT NULL REFERENCES carteiras (id) ON DELETE CASCADE,
+    id_destino VARCHAR NOT NULL REFERENCES carteiras (id) ON DELETE CASCADE,
+    valor INTEGER NOT NULL CHECK (valor > 0),
+    descricao VARCHAR NULL,
+    data TIMESTAMPTZ NOT NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (id_origem <> id_destino)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transferencias_origem ON transferencias_carteiras (id_origem, data);
+CREATE INDEX IF NOT EXISTS idx_transferencias_destino ON transferencias_carteiras (id_destino, data);
@@ -0,0 +1,22 @@
+-- Repasse (pagamento) que a plataforma fez ao motorista referente a um período de corridas
+CREATE TABLE IF NOT EXISTS repasses (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    plataforma VARCHAR NOT NULL,
+    -- Categoria de entrada com as corridas da plataforma (ex.: "Corrida Uber")
+    id_categoria VARCHAR NULL REFERENCES categorias (id) ON DELETE SET NULL,
+    periodo_inicio TIMESTAMPTZ NOT NULL,
+    periodo_fim TIMESTAMPTZ NOT NULL,
+    -- Centavos recebidos
+    valor INTEGER NOT NULL,
+    data_recebimento TIMESTAMPTZ NULL,
+    descricao VARCHAR NULL,
+    -- Transação lançada para cobrir a diferença
+    id_transacao_ajuste VARCHAR NULL REFERENCES transacoes (id) ON DELETE SET NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (periodo_fim > periodo_inicio)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_repasses_usuario_periodo
+    ON repasses (id_usuario, lower(plataforma), periodo_inicio, periodo_fim);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS parcelas_dividas;
+DROP TABLE IF EXISTS dividas;
@@ -0,0 +1,41 @@
+-- Financiamentos e dívidas (carro, moto, empréstimos) com cronograma Price ou SAC
+CREATE TABLE IF NOT EXISTS dividas (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    descricao VARCHAR NOT NULL,
+    credor VARCHAR NULL,
+    -- Centavos financiados
+    valor_principal INTEGER NOT NULL CHECK (valor_principal > 0),
+    -- Percentual ao mês (1.99 = 1,99% a.m.)
+    taxa_juros_mensal DOUBLE PRECISION NOT NULL CHECK (taxa_juros_mensal >= 0),
+    -- 'price' | 'sac'
+    sistema VARCHAR NOT NULL,
+    numero_parcelas INTEGER NOT NULL CHECK (numero_parcelas > 0),
+    primeiro_vencimento TIMESTAMPTZ NOT NULL,
+    -- Categoria de saída das transações de pagamento
+    id_categoria VARCHAR NULL REFERENCES categorias (id) ON DELETE SET NULL,
+    quitada_em TIMESTAMPTZ NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_dividas_usuario ON dividas (id_usuario);
+
+CREATE TABLE IF NOT EXISTS parcelas_dividas (
+    id VARCHAR PRIMARY KEY,
+    id_divida VARCHAR NOT NULL REFERENCES dividas (id) ON DELETE CASCADE,
+    numero INTEGER NOT NULL,
+    vencimento TIMESTAMPTZ NOT NULL,
+    -- Centavos: valor = amortizacao + juros; saldo_devedor é o que resta depois desta parcela
+    valor INTEGER NOT NULL,
+    amortizacao INTEGER NOT NULL,
+    juros INTEGER NOT NULL,
+    saldo_devedor INTEGER NOT NULL,
+    paga_em TIMESTAMPTZ NULL,
+    valor_pago INTEGER NULL,
+    -- Transação de saída lançada no pagamento
+    id_transacao VARCHAR NULL REFERENCES transacoes (id) ON DELETE SET NULL,
+    UNIQUE (id_divida, numero)
+);
+
+CREATE INDEX IF NOT EXISTS idx_parcelas_dividas_abertas ON parcelas_dividas (vencimento) WHERE paga_em IS NULL;
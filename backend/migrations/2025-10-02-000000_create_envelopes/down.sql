@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS movimentos_envelopes;
+DROP TABLE IF EXISTS envelopes;
@@ -0,0 +1,39 @@
+-- Reservas separadas do dinheiro livre: manutenção do veículo, impostos, reserva de emergência
+CREATE TABLE IF NOT EXISTS envelopes (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    nome VARCHAR NOT NULL,
+    -- Centavos a juntar (opcional)
+    valor_alvo INTEGER NULL CHECK (valor_alvo > 0),
+    -- 'manual' | 'entrada' (percentual de cada entrada) | 'lucro_diario' (percentual do lucro do dia)
+    regra VARCHAR NOT NULL DEFAULT 'manual',
+    percentual DOUBLE PRECISION NOT NULL DEFAULT 0 CHECK (percentual >= 0 AND percentual <= 100),
+    -- Categoria de saída usada nas retiradas quando nenhuma é informada
+    id_categoria VARCHAR NULL REFERENCES categorias (id) ON DELETE SET NULL,
+    arquivado BOOLEAN NOT NULL DEFAULT FALSE,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_envelopes_usuario_nome ON envelopes (id_usuario, lower(nome));
+
+-- Entradas (valor positivo) e saídas (negativo) de cada envelope; o saldo é a soma
+CREATE TABLE IF NOT EXISTS movimentos_envelopes (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    id_envelope VARCHAR NOT NULL REFERENCES envelopes (id) ON DELETE CASCADE,
+    -- 'alocacao' | 'deposito' | 'resgate' | 'transferencia' | 'retirada'
+    tipo VARCHAR NOT NULL,
+    valor INTEGER NOT NULL,
+    descricao VARCHAR NULL,
+    data TIMESTAMPTZ NOT NULL,
+    -- Entrada que gerou a alocação ou despesa lançada na retirada; na lixeira, o movimento não conta
+    id_transacao VARCHAR NULL REFERENCES transacoes (id) ON DELETE CASCADE,
+    -- Dia (UTC) da alocação sobre o lucro diário; um movimento por envelope e dia
+    dia DATE NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (id_envelope, dia)
+);
+
+CREATE INDEX IF NOT EXISTS idx_movimentos_envelopes_envelope ON movimentos_envelopes (id_envelope, data);
+CREATE INDEX IF NOT EXISTS idx_movimentos_envelopes_transacao ON movimentos_envelopes (id_transacao) WHERE id_transacao IS NOT NULL;
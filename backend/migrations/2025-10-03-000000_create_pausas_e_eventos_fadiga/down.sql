@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS eventos_fadiga;
+DROP TABLE IF EXISTS pausas_sessoes;
@@ -0,0 +1,33 @@
+-- Pausas dentro de uma sessão de trabalho; fim vazio = pausa em andamento
+CREATE TABLE IF NOT EXISTS pausas_sessoes (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    id_sessao VARCHAR NOT NULL REFERENCES sessoes_trabalho (id) ON DELETE CASCADE,
+    inicio TIMESTAMPTZ NOT NULL,
+    fim TIMESTAMPTZ NULL,
+    motivo VARCHAR NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (fim IS NULL OR fim > inicio)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pausas_sessoes_sessao ON pausas_sessoes (id_sessao, inicio);
+CREATE UNIQUE INDEX IF NOT EXISTS uq_pausas_sessoes_aberta ON pausas_sessoes (id_sessao) WHERE fim IS NULL;
+
+-- Limite de segurança ultrapassado: um registro por bloco contínuo, dia ou semana
+CREATE TABLE IF NOT EXISTS eventos_fadiga (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    id_sessao VARCHAR NULL REFERENCES sessoes_trabalho (id) ON DELETE SET NULL,
+    -- 'horas_continuas' | 'horas_diarias' | 'horas_semanais'
+    tipo VARCHAR NOT NULL,
+    -- Início do bloco contínuo, dia ou segunda-feira da semana (UTC)
+    referencia TIMESTAMPTZ NOT NULL,
+    limite_minutos INTEGER NOT NULL,
+    -- Maior valor observado
+    minutos INTEGER NOT NULL,
+    detectado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (id_usuario, tipo, referencia)
+);
+
+CREATE INDEX IF NOT EXISTS idx_eventos_fadiga_usuario ON eventos_fadiga (id_usuario, referencia);
@@ -0,0 +1,31 @@
+-- Corrida individual dentro do lote lançado como transação de entrada
+CREATE TABLE IF NOT EXISTS corridas (
+    id VARCHAR PRIMARY KEY,
+    id_usuario VARCHAR NOT NULL REFERENCES usuarios (id) ON DELETE CASCADE,
+    -- Transação de entrada que recebeu o ganho
+    id_transacao VARCHAR NULL REFERENCES transacoes (id) ON DELETE SET NULL,
+    id_sessao VARCHAR NULL REFERENCES sessoes_trabalho (id) ON DELETE SET NULL,
+    plataforma VARCHAR NOT NULL,
+    -- Ex.: UberX, Comfort, Moto
+    produto VARCHAR NOT NULL,
+    embarque TIMESTAMPTZ NOT NULL,
+    desembarque TIMESTAMPTZ NOT NULL,
+    distancia_km DOUBLE PRECISION NULL,
+    -- Centavos: valor pago pelo passageiro (sem gorjeta), taxa retida pela plataforma e gorjeta
+    tarifa_passageiro INTEGER NOT NULL,
+    taxa_plataforma INTEGER NOT NULL,
+    gorjeta INTEGER NOT NULL DEFAULT 0,
+    -- Multiplicador da tarifa dinâmica (1 = sem dinâmica)
+    dinamica DOUBLE PRECISION NULL,
+    observacoes VARCHAR NULL,
+    criado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (desembarque >= embarque),
+    CHECK (distancia_km IS NULL OR distancia_km >= 0),
+    CHECK (tarifa_passageiro >= 0 AND taxa_plataforma >= 0 AND gorjeta >= 0),
+    CHECK (dinamica IS NULL OR dinamica >= 1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_corridas_usuario_embarque ON corridas (id_usuario, embarque);
+CREATE INDEX IF NOT EXISTS idx_corridas_transacao ON corridas (id_transacao);
+CREATE INDEX IF NOT EXISTS idx_corridas_sessao ON corridas (id_sessao);
@@ -1,136 +1,133 @@
 
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{NaiveDate, Utc};
+use tokio::sync::{Mutex, RwLock};
 use tracing::{info, debug, warn};
-use crate::{cache::{types::*, RIDER_CACHE}, cache_key};
+use crate::{cache::{distributed, types::CacheResult, RIDER_CACHE}, cache_key};
+use crate::models::transacao::Transacao;
+use crate::services::dashboard::razao::RazaoDashboard;
 use crate::services::dashboard::service::DashboardStats;
+use crate::services::eventos::{self, DeltaDashboard};
 
-pub async fn get_cached_dashboard(user_id: &str) -> Option<DashboardStats> {
-    let key = cache_key!(dashboard, user_id);
-    
-    if let Some(stats) = RIDER_CACHE.dashboard.get(&key).await {
+/// Versão do razão de cada usuário: avança a cada escrita ou invalidação. Um carregamento
+/// que começou antes de uma escrita não pode ir para o cache (o delta dela se perderia).
+static VERSOES: once_cell::sync::Lazy<Mutex<HashMap<String, u64>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn versao_atual(versoes: &HashMap<String, u64>, user_id: &str) -> u64 {
+    // as duas só crescem, então a soma muda sempre que qualquer uma avança
+    versoes.get(user_id).copied().unwrap_or(0) + versoes.get(distributed::ALL_USERS).copied().unwrap_or(0)
+}
+
+/// Versão atual do razão do usuário; capturar antes de ler o banco e passar para `save_dashboard_to_cache`
+pub async fn versao_dashboard(user_id: &str) -> u64 {
+    versao_atual(&*VERSOES.lock().await, user_id)
+}
+
+/// Avança a versão do razão do usuário (carregamentos em andamento deixam de ser salvos);
+/// com `ALL_USERS`, a de todos os usuários
+pub async fn marcar_escrita(user_id: &str) {
+    *VERSOES.lock().await.entry(user_id.to_string()).or_insert(0) += 1;
+}
+
+/// Stats do dashboard recortados do razão em cache para `hoje`
+pub async fn get_cached_dashboard(user_id: &str, hoje: NaiveDate) -> Option<DashboardStats> {
+    let result = match RIDER_CACHE.dashboard.get(&cache_key!(dashboard, user_id)).await {
+        Some(razao_arc) if razao_arc.read().await.cobre(hoje) => CacheResult::Hit(razao_arc),
+        _ => CacheResult::Miss,
+    };
+    RIDER_CACHE.dashboard_counters.record(&result);
+
+    if let CacheResult::Hit(razao_arc) = result {
         debug!("Cache hit: dashboard para usuário {}", user_id);
-        return Some(stats);
+        return Some(razao_arc.read().await.stats(hoje));
     }
-    
+
     debug!("Cache miss: dashboard para usuário {}", user_id);
     None
 }
 
-/// Salva dashboard stats no cache
-pub async fn save_dashboard_to_cache(user_id: &str, dashboard_stats: &DashboardStats) {
+/// Salva o razão do dashboard no cache, desde que nenhuma escrita tenha ocorrido desde `versao`.
+/// Devolve `false` quando o razão ficou velho durante o carregamento (nada é salvo).
+pub async fn save_dashboard_to_cache(user_id: &str, razao: RazaoDashboard, versao: u64) -> bool {
     let key = cache_key!(dashboard, user_id);
-    
-    RIDER_CACHE.dashboard.insert(key, dashboard_stats.clone()).await;
-    
+
+    // a trava das versões serializa o salvamento com `apply_transaction_delta`
+    let versoes = VERSOES.lock().await;
+    if versao_atual(&versoes, user_id) != versao {
+        debug!("Razão de {} alterado durante o carregamento; não será salvo", user_id);
+        return false;
+    }
+    RIDER_CACHE.dashboard.insert(key, Arc::new(RwLock::new(razao))).await;
+    drop(versoes);
+
     info!("Dashboard salvo no cache para usuário {}", user_id);
+    true
 }
 
-/// Aplica uma transação aos stats do dashboard (cálculo incremental)
-pub fn apply_transaction_to_dashboard(dashboard: &mut DashboardStats, transacao: &TransacaoCached) {
-    // Frontend já envia valores em centavos, não precisamos multiplicar por 100
-    let valor = transacao.transacao.valor;
-    
-    match transacao.transacao.tipo.as_str() {
-        "entrada" => {
-            // Adicionar aos ganhos (valor já vem em centavos do frontend)
-            dashboard.ganhos_hoje = Some(dashboard.ganhos_hoje.unwrap_or(0) + valor);
-            dashboard.ganhos_ontem = Some(dashboard.ganhos_ontem.unwrap_or(0) + valor);
-            dashboard.ganhos_semana = Some(dashboard.ganhos_semana.unwrap_or(0) + valor);
-            dashboard.ganhos_mes = Some(dashboard.ganhos_mes.unwrap_or(0) + valor);
-            
-            // Incrementar contadores se aplicável
-            dashboard.corridas_hoje = Some(dashboard.corridas_hoje.unwrap_or(0) + transacao.transacao.eventos as u32);
-            dashboard.corridas_ontem = Some(dashboard.corridas_ontem.unwrap_or(0) + transacao.transacao.eventos as u32);
-            dashboard.corridas_semana = Some(dashboard.corridas_semana.unwrap_or(0) + transacao.transacao.eventos as u32);
-            dashboard.corridas_mes = Some(dashboard.corridas_mes.unwrap_or(0) + transacao.transacao.eventos as u32);
-            // km: soma direta (transacao.transacao.km é Option<f64>)
-            let tx_km = transacao.transacao.km.unwrap_or(0.0);
-            dashboard.km_hoje = Some(dashboard.km_hoje.unwrap_or(0.0) + tx_km);
-            dashboard.km_ontem = Some(dashboard.km_ontem.unwrap_or(0.0) + tx_km);
-            dashboard.km_semana = Some(dashboard.km_semana.unwrap_or(0.0) + tx_km);
-            dashboard.km_mes = Some(dashboard.km_mes.unwrap_or(0.0) + tx_km);
-        }
-        "saida" => {
-            // Adicionar aos gastos (valor já vem em centavos do frontend)
-            let valor_gasto = valor.abs();
-            
-            dashboard.gastos_hoje = Some(dashboard.gastos_hoje.unwrap_or(0) + valor_gasto);
-            dashboard.gastos_ontem = Some(dashboard.gastos_ontem.unwrap_or(0) + valor_gasto);
-            dashboard.gastos_semana = Some(dashboard.gastos_semana.unwrap_or(0) + valor_gasto);
-            dashboard.gastos_mes = Some(dashboard.gastos_mes.unwrap_or(0) + valor_gasto);
-        }
-        _ => {
-            warn!("Tipo de transação desconhecido: {} para usuário", transacao.transacao.tipo);
-        }
+/// Aplica uma transação ao razão do dashboard (cálculo incremental).
+/// `sinal` 1 soma (criação), -1 retira (exclusão); a edição retira a versão antiga e soma a nova.
+pub fn apply_transaction_to_dashboard(dashboard: &mut RazaoDashboard, transacao: &Transacao, sinal: i64) {
+    if !matches!(transacao.tipo.as_str(), "entrada" | "saida") {
+        warn!("Tipo de transação desconhecido: {} para usuário", transacao.tipo);
     }
-    
-    // Recalcular lucros
-    recalculate_profits(dashboard);
-    
-    debug!("Transação aplicada incrementalmente ao dashboard: {} de valor {}", 
-           transacao.transacao.tipo, valor);
+    dashboard.aplicar(transacao, sinal);
+
+    debug!("Transação aplicada incrementalmente ao dashboard: {} de valor {} (sinal {})",
+           transacao.tipo, transacao.valor, sinal);
 }
 
-/// Recalcula os lucros baseado nos ganhos e gastos atuais
-fn recalculate_profits(dashboard: &mut DashboardStats) {
-    if let (Some(ganhos), Some(gastos)) = (dashboard.ganhos_hoje, dashboard.gastos_hoje) {
-        dashboard.lucro_hoje = Some(ganhos - gastos);
-    }
-    if let (Some(ganhos), Some(gastos)) = (dashboard.ganhos_ontem, dashboard.gastos_ontem) {
-        dashboard.lucro_ontem = Some(ganhos - gastos);
-    }
-    if let (Some(ganhos), Some(gastos)) = (dashboard.ganhos_semana, dashboard.gastos_semana) {
-        dashboard.lucro_semana = Some(ganhos - gastos);
+/// Aplica ao razão em cache a troca de `antes` por `depois` (None = não existe).
+/// Com clientes conectados em `/api/eventos`, devolve os campos do dashboard que mudaram.
+pub async fn apply_transaction_delta(user_id: &str, antes: Option<&Transacao>, depois: Option<&Transacao>) -> Option<DeltaDashboard> {
+    let key = cache_key!(dashboard, user_id);
+
+    // mesmo sem razão em cache a escrita avança a versão: um carregamento em andamento
+    // pode ter lido o banco antes dela e será descartado em `save_dashboard_to_cache`
+    let mut versoes = VERSOES.lock().await;
+    *versoes.entry(user_id.to_string()).or_insert(0) += 1;
+    let razao_arc = RIDER_CACHE.dashboard.get(&key).await?;
+    let mut razao = razao_arc.write().await;
+    drop(versoes);
+    let hoje = Utc::now().date_naive();
+    let stats_antes = (eventos::tem_inscritos(user_id) && razao.cobre(hoje)).then(|| razao.stats(hoje));
+
+    if let Some(antes) = antes {
+        apply_transaction_to_dashboard(&mut razao, antes, -1);
     }
-    if let (Some(ganhos), Some(gastos)) = (dashboard.ganhos_mes, dashboard.gastos_mes) {
-        dashboard.lucro_mes = Some(ganhos - gastos);
+    if let Some(depois) = depois {
+        apply_transaction_to_dashboard(&mut razao, depois, 1);
     }
+
+    stats_antes.map(|stats_antes| eventos::delta_dashboard(&stats_antes, &razao.stats(hoje)))
 }
 
-/// Calcula dashboard de forma incremental usando apenas transações novas
+/// Calcula dashboard de forma incremental a partir do razão em cache
 pub async fn calculate_dashboard_incremental(user_id: &str) -> Option<DashboardStats> {
-    // Buscar dashboard em cache
-    let mut cached_dashboard = match get_cached_dashboard(user_id).await {
-        Some(dashboard) => dashboard,
+    // Os deltas já foram aplicados na escrita; aqui só recortamos os períodos do dia atual
+    let stats = match get_cached_dashboard(user_id, Utc::now().date_naive()).await {
+        Some(stats) => stats,
         None => {
             debug!("Nenhum dashboard em cache para usuário {}", user_id);
             return None;
         }
     };
-    
-    // Buscar transações novas
-    let new_transactions = match crate::cache::transacao::get_new_transactions(user_id).await {
-        Some(transactions) if !transactions.is_empty() => transactions,
-        Some(_transactions) => return Some(cached_dashboard),
-        None => {
-            debug!("Nenhuma transação nova para usuário {}", user_id);
-            return Some(cached_dashboard); // Retorna cache existente
-        }
-    };
-    
-    info!("Calculando dashboard incrementalmente para usuário {} com {} transações novas", 
-          user_id, new_transactions.len());
-    
-    // Aplicar cada transação nova ao dashboard
-    for transaction in new_transactions.iter() {
-        apply_transaction_to_dashboard(&mut cached_dashboard, transaction);
-    }
-    
-    // Marcar transações como processadas
+
+    // Marcar transações como processadas (já refletidas no dashboard servido)
     crate::cache::transacao::mark_transactions_as_processed(user_id).await;
-    
-    // Salvar dashboard atualizado no cache
-    save_dashboard_to_cache(user_id, &cached_dashboard).await;
-    
-    info!("Dashboard incremental calculado e salvo para usuário {}", user_id);
-    Some(cached_dashboard)
+
+    info!("Dashboard incremental calculado para usuário {}", user_id);
+    Some(stats)
 }
 
 /// Limpa cache de dashboard de um usuário específico
 pub async fn clear_user_dashboard_cache(user_id: &str) {
     let key = cache_key!(dashboard, user_id);
-    
+
+    marcar_escrita(user_id).await;
     RIDER_CACHE.dashboard.remove(&key).await;
-    
+
     info!("Cache de dashboard limpo para usuário {}", user_id);
 }
 
@@ -138,80 +135,107 @@ pub async fn clear_user_dashboard_cache(user_id: &str) {
 pub async fn clear_user_caches(user_id: &str) {
     crate::cache::transacao::clear_user_transaction_cache(user_id).await;
     clear_user_dashboard_cache(user_id).await;
-    
+
     warn!("MECANISMO DE SEGURANÇA: todos os caches limpos para usuário {}", user_id);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
-    use crate::models::transacao::Transacao;
-    
-    fn create_test_dashboard() -> DashboardStats {
-        DashboardStats {
-            ganhos_hoje: Some(10000),
-            ganhos_ontem: Some(9000),
-            ganhos_semana: Some(50000),
-            ganhos_mes: Some(200000),
-            gastos_hoje: Some(2000),
-            gastos_ontem: Some(1500),
-            gastos_semana: Some(10000),
-            gastos_mes: Some(40000),
-            lucro_hoje: Some(8000),
-            lucro_ontem: Some(7500),
-            lucro_semana: Some(40000),
-            lucro_mes: Some(160000),
-            corridas_hoje: Some(10),
-            corridas_ontem: Some(8),
-            corridas_semana: Some(50),
-            corridas_mes: Some(200),
-            km_hoje: Some(120.0),
-            km_ontem: Some(110.0),
-            km_semana: Some(700.0),
-            km_mes: Some(3200.0),
-            ..Default::default()
-        }
+    use chrono::{Duration, Utc};
+    use crate::services::dashboard::razao::DadosFixos;
+
+    fn create_test_dashboard() -> RazaoDashboard {
+        let hoje = Utc::now().date_naive();
+        let mut razao = RazaoDashboard::novo("test_user", RazaoDashboard::inicio_necessario(hoje), DadosFixos::default());
+        razao.aplicar(&create_test_transacao(10000, "entrada", 10), 1);
+        razao.aplicar(&create_test_transacao(2000, "saida", 0), 1);
+        razao
     }
-    
-    fn create_test_transacao_cached(valor: i32, tipo: &str) -> TransacaoCached {
-        let transacao = Transacao {
+
+    fn create_test_transacao(valor: i32, tipo: &str, eventos: i32) -> Transacao {
+        Transacao {
             id: "test_tx".to_string(),
             id_usuario: "test_user".to_string(),
             id_categoria: "cat1".to_string(),
             valor,
-            eventos: 1,
+            eventos,
             km: Some(0.0),
             descricao: Some("Teste".to_string()),
             tipo: tipo.to_string(),
             data: Utc::now(),
             criado_em: Utc::now(),
             atualizado_em: Utc::now(),
-        };
-        
-        TransacaoCached::from_transacao(transacao, true)
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
+        }
     }
-    
+
     #[tokio::test]
     async fn test_apply_entrada_transaction() {
         let mut dashboard = create_test_dashboard();
-        let transacao = create_test_transacao_cached(50, "entrada");
-        
-        apply_transaction_to_dashboard(&mut dashboard, &transacao);
-        
-        assert_eq!(dashboard.ganhos_hoje, Some(10050)); // 10000 + 50 (valores em centavos)
-        assert_eq!(dashboard.corridas_hoje, Some(11)); // 10 + 1
-        assert_eq!(dashboard.lucro_hoje, Some(8050)); // 10050 - 2000
+        let transacao = create_test_transacao(50, "entrada", 1);
+
+        apply_transaction_to_dashboard(&mut dashboard, &transacao, 1);
+
+        let stats = dashboard.stats(Utc::now().date_naive());
+        assert_eq!(stats.ganhos_hoje, Some(10050)); // 10000 + 50 (valores em centavos)
+        assert_eq!(stats.corridas_hoje, Some(11)); // 10 + 1
+        assert_eq!(stats.lucro_hoje, Some(8050)); // 10050 - 2000
     }
-    
+
     #[tokio::test]
     async fn test_apply_saida_transaction() {
         let mut dashboard = create_test_dashboard();
-        let transacao = create_test_transacao_cached(-30, "saida");
-        
-        apply_transaction_to_dashboard(&mut dashboard, &transacao);
+        let transacao = create_test_transacao(30, "saida", 0);
+
+        apply_transaction_to_dashboard(&mut dashboard, &transacao, 1);
+
+        let stats = dashboard.stats(Utc::now().date_naive());
+        assert_eq!(stats.gastos_hoje, Some(2030)); // 2000 + 30 (já em centavos)
+        assert_eq!(stats.lucro_hoje, Some(7970)); // 10000 - 2030
+    }
+
+    #[tokio::test]
+    async fn test_edicao_move_transacao_de_dia() {
+        let mut dashboard = create_test_dashboard();
+        let antes = create_test_transacao(10000, "entrada", 10);
+        let mut depois = antes.clone();
+        depois.data = antes.data - Duration::days(1);
+
+        apply_transaction_to_dashboard(&mut dashboard, &antes, -1);
+        apply_transaction_to_dashboard(&mut dashboard, &depois, 1);
+
+        let stats = dashboard.stats(Utc::now().date_naive());
+        assert_eq!(stats.ganhos_hoje, None);
+        assert_eq!(stats.lucro_hoje, None);
+        assert_eq!(stats.ganhos_ontem, Some(10000));
+        assert_eq!(stats.ganhos_7dias[5], 10000);
+        assert_eq!(stats.ganhos_7dias[6], 0);
+    }
+
+    #[tokio::test]
+    async fn test_escrita_durante_carregamento_descarta_razao() {
+        let usuario = "test_user_corrida_carga";
+        let transacao = create_test_transacao(500, "entrada", 1);
+
+        // carregamento começa, uma escrita chega antes do salvamento: o razão lido fica de fora
+        let versao = versao_dashboard(usuario).await;
+        assert!(apply_transaction_delta(usuario, None, Some(&transacao)).await.is_none());
+        assert!(!save_dashboard_to_cache(usuario, create_test_dashboard(), versao).await);
+        assert!(get_cached_dashboard(usuario, Utc::now().date_naive()).await.is_none());
+
+        // sem escrita no meio, salva; os deltas seguintes são aplicados sobre ele
+        let versao = versao_dashboard(usuario).await;
+        assert!(save_dashboard_to_cache(usuario, create_test_dashboard(), versao).await);
+        apply_transaction_delta(usuario, None, Some(&transacao)).await;
+        let stats = get_cached_dashboard(usuario, Utc::now().date_naive()).await.unwrap();
+        assert_eq!(stats.ganhos_hoje, Some(10500));
 
-    assert_eq!(dashboard.gastos_hoje, Some(5000)); // 2000 + 3000 (valor absoluto)
-    assert_eq!(dashboard.lucro_hoje, Some(5000)); // 10000 - 5000
+        // invalidação também descarta carregamentos em andamento
+        let versao = versao_dashboard(usuario).await;
+        clear_user_dashboard_cache(usuario).await;
+        assert!(!save_dashboard_to_cache(usuario, create_test_dashboard(), versao).await);
     }
 }
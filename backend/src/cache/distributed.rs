@@ -0,0 +1,183 @@
+//! Invalidação de cache entre instâncias via LISTEN/NOTIFY do Postgres
+//!
+//! Cada réplica tem seu próprio `RIDER_CACHE`. Quem escreve atualiza ou evicta o cache local
+//! e publica o usuário no canal; as demais réplicas escutam e evictam a mesma entrada. Se a
+//! conexão de escuta cair, a réplica reconecta e descarta o cache inteiro, já que pode ter
+//! perdido avisos enquanto esteve fora.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tracing::{info, warn};
+use crate::cache::RIDER_CACHE;
+use crate::db;
+use crate::services::eventos::{self, EventoUsuario};
+
+/// Canal do NOTIFY
+pub const CHANNEL: &str = "rider_cache_invalidation";
+/// `user_id` que representa todos os usuários
+pub const ALL_USERS: &str = "*";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Intervalo do `SELECT 1` que detecta conexões mortas sem erro no socket
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// Identifica esta instância para ignorar os próprios avisos
+static INSTANCE_ID: once_cell::sync::Lazy<String> =
+    once_cell::sync::Lazy::new(|| ulid::Ulid::new().to_string());
+/// Só publica depois de `start_listener` (testes e ferramentas ficam fora do canal)
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PUBLISHER: Mutex<Option<PgConnection>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvalidationMessage {
+    pub origin: String,
+    pub user_id: String,
+}
+
+impl InvalidationMessage {
+    /// Usuário a evictar, ou None se o aviso é desta instância ou não é reconhecido
+    pub fn from_payload(payload: &str, instance_id: &str) -> Option<String> {
+        let message: InvalidationMessage = serde_json::from_str(payload).ok()?;
+        (message.origin != instance_id).then_some(message.user_id)
+    }
+}
+
+fn connect() -> ConnectionResult<PgConnection> {
+    PgConnection::establish(&db::database_url())
+}
+
+fn notify(conn: &mut PgConnection, payload: &str) -> QueryResult<usize> {
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload)
+        .execute(conn)
+}
+
+/// Publica a invalidação de um usuário (ou de todos, com `ALL_USERS`) para as outras instâncias
+pub fn publish(user_id: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let message = InvalidationMessage { origin: INSTANCE_ID.clone(), user_id: user_id.to_string() };
+    let payload = serde_json::to_string(&message).unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let mut publisher = PUBLISHER.lock().unwrap_or_else(|e| e.into_inner());
+        // uma nova tentativa com conexão nova cobre o caso de a conexão guardada ter caído
+        for _ in 0..2 {
+            if publisher.is_none() {
+                match connect() {
+                    Ok(conn) => *publisher = Some(conn),
+                    Err(e) => {
+                        warn!("Falha ao conectar para publicar invalidação de cache: {}", e);
+                        return;
+                    }
+                }
+            }
+            if let Some(conn) = publisher.as_mut() {
+                match notify(conn, &payload) {
+                    Ok(_) => return,
+                    Err(e) => {
+                        warn!("Falha ao publicar invalidação de cache: {}", e);
+                        *publisher = None;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Começa a escutar o canal e a evictar localmente o que as outras instâncias publicarem
+pub fn start_listener() {
+    ENABLED.store(true, Ordering::Relaxed);
+
+    let (sender, mut receiver) = unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(user_id) = receiver.recv().await {
+            if user_id == ALL_USERS {
+                RIDER_CACHE.evict_all_local().await;
+            } else {
+                RIDER_CACHE.evict_user_local(&user_id).await;
+                // a escrita foi em outra instância: quem está conectado aqui recarrega
+                eventos::publicar(&user_id, EventoUsuario::DadosAlterados);
+            }
+        }
+    });
+
+    let spawned = std::thread::Builder::new()
+        .name("cache-invalidation-listener".to_string())
+        .spawn(move || listen_loop(sender));
+    if let Err(e) = spawned {
+        warn!("Não foi possível iniciar a escuta de invalidações de cache: {}", e);
+    }
+}
+
+fn listen_loop(sender: UnboundedSender<String>) {
+    let mut backoff = Duration::from_secs(1);
+    let mut reconnecting = false;
+    loop {
+        match connect().and_then(|mut conn| {
+            diesel::sql_query(format!("LISTEN {CHANNEL}")).execute(&mut conn).map_err(|e| {
+                diesel::ConnectionError::BadConnection(e.to_string())
+            })?;
+            Ok(conn)
+        }) {
+            Ok(mut conn) => {
+                info!("Escutando invalidações de cache no canal {}", CHANNEL);
+                if reconnecting && sender.send(ALL_USERS.to_string()).is_err() {
+                    return;
+                }
+                backoff = Duration::from_secs(1);
+                if let Err(e) = poll(&mut conn, &sender) {
+                    warn!("Conexão de escuta de invalidações caiu: {}", e);
+                }
+                if sender.is_closed() {
+                    return;
+                }
+            }
+            Err(e) => warn!("Falha ao conectar para escutar invalidações de cache: {}", e),
+        }
+        reconnecting = true;
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+}
+
+/// Repassa os avisos até a conexão falhar (ou o runtime encerrar)
+fn poll(conn: &mut PgConnection, sender: &UnboundedSender<String>) -> QueryResult<()> {
+    let mut last_check = Instant::now();
+    loop {
+        for notification in conn.notifications_iter() {
+            let notification = notification?;
+            if let Some(user_id) = InvalidationMessage::from_payload(&notification.payload, &INSTANCE_ID) {
+                if sender.send(user_id).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        if last_check.elapsed() >= HEALTH_CHECK_INTERVAL {
+            diesel::sql_query("SELECT 1").execute(conn)?;
+            last_check = Instant::now();
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignora_avisos_da_propria_instancia() {
+        let payload = serde_json::to_string(&InvalidationMessage { origin: "a".to_string(), user_id: "u1".to_string() }).unwrap();
+        assert_eq!(InvalidationMessage::from_payload(&payload, "b"), Some("u1".to_string()));
+        assert_eq!(InvalidationMessage::from_payload(&payload, "a"), None);
+        assert_eq!(InvalidationMessage::from_payload("lixo", "b"), None);
+    }
+}
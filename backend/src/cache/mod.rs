@@ -2,21 +2,24 @@
 //! 
 //! Este módulo implementa um sistema de cache em memória usando Moka para:
 //! - Cache de transações com VecDeque (máximo 20 por usuário)  
-//! - Cache de dashboard stats por usuário
-//! - Cálculo incremental baseado em transações com flag `new`
+//! - Cache do razão diário do dashboard por usuário
+//! - Cálculo incremental: cada transação criada, editada ou excluída vira um delta no razão
+//! - Métricas de hit/miss/remoção por cache (JSON e formato Prometheus)
+//! - Invalidação entre réplicas via LISTEN/NOTIFY do Postgres (ver `distributed`)
 
 use std::sync::Arc;
 use std::time::Duration;
 use moka::future::Cache;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 
 pub mod types;
 pub mod transacao;
 pub mod dashboard;
+pub mod distributed;
 
 use types::*;
-use crate::services::dashboard::service::DashboardStats;
+use crate::services::dashboard::razao::RazaoDashboard;
 
 /// Cache global singleton para toda a aplicação
 pub static RIDER_CACHE: once_cell::sync::Lazy<RiderCache> = 
@@ -27,52 +30,134 @@ pub static RIDER_CACHE: once_cell::sync::Lazy<RiderCache> =
 pub struct RiderCache {
     /// Cache de transações por usuário (cada entrada contém VecDeque com até 20 transações)
     pub transactions: Cache<String, Arc<RwLock<TransactionCacheData>>>,
-    /// Cache de dashboard stats por usuário
-    pub dashboard: Cache<String, DashboardStats>,
+    /// Cache do razão diário do dashboard por usuário (os stats são recortados dele)
+    pub dashboard: Cache<String, Arc<RwLock<RazaoDashboard>>>,
+    /// Hits/misses/remoções do cache de transações
+    pub transactions_counters: Arc<CacheCounters>,
+    /// Hits/misses/remoções do cache de dashboard
+    pub dashboard_counters: Arc<CacheCounters>,
 }
 
 impl RiderCache {
     /// Cria uma nova instância do cache com configurações padrão
     pub fn new() -> Self {
         info!("Inicializando sistema de cache Rider Finance");
-        
+
+        let transactions_counters = Arc::new(CacheCounters::default());
+        let dashboard_counters = Arc::new(CacheCounters::default());
+        let tx_listener = transactions_counters.clone();
+        let dashboard_listener = dashboard_counters.clone();
+
         Self {
             // Cache de transações: TTL 30 minutos, máx 1000 usuários
             transactions: Cache::builder()
                 .time_to_live(Duration::from_secs(1800)) // 30 minutos
                 .max_capacity(1000)
+                .eviction_listener(move |_key, _value, cause| tx_listener.record_removal(cause))
                 .build(),
-            
-            // Cache de dashboard: TTL 30 minutos, máx 1000 usuários  
+
+            // Cache de dashboard: TTL 30 minutos, máx 1000 usuários
             dashboard: Cache::builder()
                 .time_to_live(Duration::from_secs(1800)) // 30 minutos
                 .max_capacity(1000)
+                .eviction_listener(move |_key, _value, cause| dashboard_listener.record_removal(cause))
                 .build(),
+
+            transactions_counters,
+            dashboard_counters,
         }
     }
-    
-    /// Invalida todos os caches de um usuário específico
+
+    /// Invalida todos os caches de um usuário específico (nesta e nas demais instâncias)
     pub async fn invalidate_user_caches(&self, user_id: &str) {
+        self.evict_user_local(user_id).await;
+        distributed::publish(user_id);
+    }
+
+    /// Invalida os caches de todos os usuários (nesta e nas demais instâncias)
+    pub async fn invalidate_all(&self) {
+        self.evict_all_local().await;
+        distributed::publish(distributed::ALL_USERS);
+    }
+
+    /// Remove as entradas de um usuário apenas deste processo
+    pub async fn evict_user_local(&self, user_id: &str) {
         info!("Invalidando todos os caches do usuário: {}", user_id);
-        
+
         let tx_key = format!("transactions:{user_id}");
         let dashboard_key = format!("dashboard:{user_id}");
-        
+
+        dashboard::marcar_escrita(user_id).await;
         self.transactions.remove(&tx_key).await;
         self.dashboard.remove(&dashboard_key).await;
-        
+
         info!("Caches invalidados para usuário: {}", user_id);
     }
-    
+
+    /// Remove todas as entradas apenas deste processo
+    pub async fn evict_all_local(&self) {
+        warn!("Invalidando os caches de todos os usuários");
+
+        dashboard::marcar_escrita(distributed::ALL_USERS).await;
+        self.transactions.invalidate_all();
+        self.dashboard.invalidate_all();
+        self.run_pending_tasks().await;
+    }
+
+    /// Processa remoções pendentes do Moka (contagem de entradas e listeners em dia)
+    pub async fn run_pending_tasks(&self) {
+        self.transactions.run_pending_tasks().await;
+        self.dashboard.run_pending_tasks().await;
+    }
+
+    /// Consulta o cache de transações contabilizando hit/miss
+    pub async fn lookup_transactions(&self, user_id: &str) -> CacheResult<Arc<RwLock<TransactionCacheData>>> {
+        let result = match self.transactions.get(&crate::cache_key!(transactions, user_id)).await {
+            Some(data) => CacheResult::Hit(data),
+            None => CacheResult::Miss,
+        };
+        self.transactions_counters.record(&result);
+        result
+    }
+
     /// Retorna estatísticas de uso do cache
     pub fn get_stats(&self) -> CacheStats {
+        let transactions = self.transactions_counters.snapshot(self.transactions.entry_count());
+        let dashboard = self.dashboard_counters.snapshot(self.dashboard.entry_count());
         CacheStats {
-            transactions_entries: self.transactions.entry_count(),
-            dashboard_entries: self.dashboard.entry_count(),
-            // Moka não expõe hit rate diretamente, usamos valores default por enquanto
-            transactions_hit_rate: 0.0,
-            dashboard_hit_rate: 0.0,
+            transactions_entries: transactions.entries,
+            dashboard_entries: dashboard.entries,
+            transactions_hit_rate: transactions.hit_rate,
+            dashboard_hit_rate: dashboard.hit_rate,
+            transactions,
+            dashboard,
+        }
+    }
+
+    /// Métricas no formato de exposição de texto do Prometheus
+    pub fn prometheus_metrics(&self) -> String {
+        let stats = self.get_stats();
+        let caches = [("transactions", &stats.transactions), ("dashboard", &stats.dashboard)];
+        let series = [
+            ("rider_cache_entries", "gauge", "Entradas atualmente no cache"),
+            ("rider_cache_hits_total", "counter", "Consultas atendidas pelo cache"),
+            ("rider_cache_misses_total", "counter", "Consultas que não encontraram entrada"),
+            ("rider_cache_evictions_total", "counter", "Entradas removidas por TTL ou capacidade"),
+            ("rider_cache_invalidations_total", "counter", "Entradas removidas explicitamente"),
+            ("rider_cache_hit_ratio", "gauge", "Hits sobre o total de consultas"),
+        ];
+        let valores = |m: &CacheMetrics| {
+            [m.entries.to_string(), m.hits.to_string(), m.misses.to_string(), m.evictions.to_string(), m.invalidations.to_string(), m.hit_rate.to_string()]
+        };
+
+        let mut out = String::new();
+        for (i, (nome, tipo, ajuda)) in series.iter().enumerate() {
+            out.push_str(&format!("# HELP {nome} {ajuda}\n# TYPE {nome} {tipo}\n"));
+            for (cache, metrics) in caches {
+                out.push_str(&format!("{nome}{{cache=\"{cache}\"}} {}\n", valores(metrics)[i]));
+            }
         }
+        out
     }
 }
 
@@ -109,4 +194,29 @@ mod tests {
         assert_eq!(cache_key!(transactions, "user123"), "transactions:user123");
         assert_eq!(cache_key!(dashboard, "user456"), "dashboard:user456");
     }
+
+    #[tokio::test]
+    async fn test_metricas_de_hit_miss_e_invalidacao() {
+        let cache = RiderCache::new();
+        assert!(cache.lookup_transactions("u1").await.is_miss());
+
+        cache.transactions.insert(cache_key!(transactions, "u1"), Arc::new(RwLock::new(TransactionCacheData::new()))).await;
+        assert!(cache.lookup_transactions("u1").await.is_hit());
+        assert!(cache.lookup_transactions("u1").await.is_hit());
+
+        cache.invalidate_user_caches("u1").await;
+        cache.run_pending_tasks().await;
+
+        let stats = cache.get_stats();
+        assert_eq!(stats.transactions.hits, 2);
+        assert_eq!(stats.transactions.misses, 1);
+        assert_eq!(stats.transactions.invalidations, 1);
+        assert_eq!(stats.transactions.entries, 0);
+        assert!((stats.transactions_hit_rate - 2.0 / 3.0).abs() < 1e-9);
+
+        let texto = cache.prometheus_metrics();
+        assert!(texto.contains("# TYPE rider_cache_hits_total counter"));
+        assert!(texto.contains("rider_cache_hits_total{cache=\"transactions\"} 2"));
+        assert!(texto.contains("rider_cache_misses_total{cache=\"dashboard\"} 0"));
+    }
 }
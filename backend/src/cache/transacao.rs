@@ -8,17 +8,17 @@ use tokio::sync::RwLock;
 use tracing::{info, debug, warn};
 use crate::{cache_key, cache::{RIDER_CACHE, types::*}};
 use crate::models::transacao::Transacao;
+use crate::services::eventos::{self, EventoUsuario};
 
 /// Obtém transações em cache para uma página específica (apenas páginas 1 e 2)
 pub async fn get_cached_transactions(user_id: &str, page: usize, page_size: usize) -> Option<Vec<Transacao>> {
     if page > 2 {
         debug!("Cache miss: página {} > 2 para usuário {}", page, user_id);
+        RIDER_CACHE.transactions_counters.record(&CacheResult::<()>::Miss);
         return None; // Cache só serve páginas 1 e 2
     }
 
-    let key = cache_key!(transactions, user_id);
-
-    if let Some(cache_data_arc) = RIDER_CACHE.transactions.get(&key).await {
+    if let CacheResult::Hit(cache_data_arc) = RIDER_CACHE.lookup_transactions(user_id).await {
         let cache_data = cache_data_arc.read().await;
 
         // Sempre retorna Some quando o cache existe (mesmo que vazio),
@@ -44,6 +44,11 @@ pub async fn add_new_transaction(user_id: &str, transacao: Transacao) {
 
     let mut cache_data = cache_data_arc.write().await;
 
+    // Delta no razão do dashboard (se estiver em cache); as outras réplicas apenas evictam
+    let dashboard = crate::cache::dashboard::apply_transaction_delta(user_id, None, Some(&transacao)).await;
+    crate::cache::distributed::publish(user_id);
+    eventos::publicar(user_id, EventoUsuario::TransacaoCriada { transacao: transacao.clone(), dashboard });
+
     // Adiciona transação como nova (new = true)
     cache_data.add_transaction(transacao);
 
@@ -57,6 +62,42 @@ pub async fn add_new_transaction(user_id: &str, transacao: Transacao) {
     }
 }
 
+/// Atualiza uma transação editada: substitui na lista em cache e aplica o delta no dashboard
+pub async fn update_cached_transaction(user_id: &str, antes: &Transacao, depois: Transacao) {
+    let dashboard = crate::cache::dashboard::apply_transaction_delta(user_id, Some(antes), Some(&depois)).await;
+    crate::cache::distributed::publish(user_id);
+    eventos::publicar(user_id, EventoUsuario::TransacaoAtualizada { antes: Box::new(antes.clone()), depois: Box::new(depois.clone()), dashboard });
+
+    let key = cache_key!(transactions, user_id);
+    if let Some(cache_data_arc) = RIDER_CACHE.transactions.get(&key).await {
+        let mut cache_data = cache_data_arc.write().await;
+        if antes.data != depois.data {
+            // a lista em cache segue a ordem por data; mudar a data pode mudar a página
+            drop(cache_data);
+            clear_user_transaction_cache(user_id).await;
+            return;
+        }
+        if cache_data.update_transaction(depois) {
+            debug!("Transação {} atualizada no cache do usuário {}", antes.id, user_id);
+        }
+    }
+}
+
+/// Remove uma transação excluída da lista em cache e retira seu valor do dashboard
+pub async fn remove_cached_transaction(user_id: &str, transacao: &Transacao) {
+    let dashboard = crate::cache::dashboard::apply_transaction_delta(user_id, Some(transacao), None).await;
+    crate::cache::distributed::publish(user_id);
+    eventos::publicar(user_id, EventoUsuario::TransacaoExcluida { transacao: transacao.clone(), dashboard });
+
+    let key = cache_key!(transactions, user_id);
+    if let Some(cache_data_arc) = RIDER_CACHE.transactions.get(&key).await {
+        let mut cache_data = cache_data_arc.write().await;
+        if cache_data.remove_transaction(&transacao.id) {
+            debug!("Transação {} removida do cache do usuário {}", transacao.id, user_id);
+        }
+    }
+}
+
 /// Salva transações iniciais no cache (primeira carga da página)
 pub async fn set_cached_transactions(user_id: &str, transactions: Vec<Transacao>) {
     let key = cache_key!(transactions, user_id);
@@ -148,6 +189,9 @@ mod tests {
             data: Utc::now(),
             criado_em: Utc::now(),
             atualizado_em: Utc::now(),
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
         }
     }
 
@@ -1,6 +1,8 @@
 //! Tipos de dados utilizados pelo sistema de cache
 
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use moka::notification::RemovalCause;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use crate::models::transacao::Transacao;
@@ -171,6 +173,60 @@ impl Default for TransactionCacheData {
     }
 }
 
+/// Contadores de um cache (atualizados sem lock a cada consulta/remoção)
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    /// Entradas removidas pelo próprio cache (TTL ou capacidade)
+    pub evictions: AtomicU64,
+    /// Entradas removidas explicitamente (invalidação de usuário ou geral)
+    pub invalidations: AtomicU64,
+}
+
+impl CacheCounters {
+    /// Contabiliza o resultado de uma consulta
+    pub fn record<T>(&self, result: &CacheResult<T>) {
+        match result {
+            CacheResult::Hit(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            CacheResult::Miss | CacheResult::Error(_) => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Contabiliza uma remoção notificada pelo Moka
+    pub fn record_removal(&self, cause: RemovalCause) {
+        if cause.was_evicted() {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        } else if cause == RemovalCause::Explicit {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self, entries: u64) -> CacheMetrics {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        CacheMetrics {
+            entries,
+            hits,
+            misses,
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            hit_rate: if hits + misses > 0 { hits as f64 / (hits + misses) as f64 } else { 0.0 },
+        }
+    }
+}
+
+/// Métricas de um cache num instante
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub invalidations: u64,
+    pub hit_rate: f64,
+}
+
 /// Estatísticas de uso do cache
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheStats {
@@ -182,6 +238,10 @@ pub struct CacheStats {
     pub transactions_hit_rate: f64,
     /// Taxa de hit do cache de dashboard  
     pub dashboard_hit_rate: f64,
+    /// Contadores completos do cache de transações
+    pub transactions: CacheMetrics,
+    /// Contadores completos do cache de dashboard
+    pub dashboard: CacheMetrics,
 }
 
 /// Resultado de operação de cache
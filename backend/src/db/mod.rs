@@ -4,17 +4,22 @@ use diesel::sql_query;
 use dotenvy::dotenv;
 use std::env;
 
-/// Conexão para banco de dados, decide entre produção e testes via ENVIRONMENT
-pub fn establish_connection() -> PgConnection {
+/// URL do banco, decide entre produção e testes via ENVIRONMENT
+pub fn database_url() -> String {
     // Carrega variáveis do .env padrão
     dotenv().ok();
     let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "production".to_string());
-    let database_url = if environment == "tests" {
+    if environment == "tests" {
         dotenvy::from_filename(".env.test").ok();
         env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL não definida")
     } else {
         env::var("DATABASE_URL").expect("DATABASE_URL não definida no .env")
-    };
+    }
+}
+
+/// Conexão para banco de dados (ver `database_url`)
+pub fn establish_connection() -> PgConnection {
+    let database_url = database_url();
     let mut conn = PgConnection::establish(&database_url)
         .unwrap_or_else(|_| panic!("Erro ao conectar no banco: {database_url}"));
     
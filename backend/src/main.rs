@@ -1,4 +1,4 @@
-use axum::{ Router, routing::{ post, get, put, delete, patch }, http::StatusCode };
+use axum::{ Router, routing::{ post, get, put, delete, patch }, http::StatusCode, extract::DefaultBodyLimit };
 use backend::services::auth::logout::logout_handler;
 use backend::services::auth::login::login_handler;
 use backend::services::auth::register::register_user_handler;
@@ -14,6 +14,7 @@ use std::net::SocketAddr;
 async fn main() {
     use backend::services::dashboard::dashboard_stats_handler;
     use backend::services::dashboard::dashboard_platform_handler;
+    use backend::services::dashboard::{dashboard_periodo_handler, dashboard_backtest_projecao_handler};
     use backend::services::transacao::{
         create_transacao_handler,
         get_transacao_handler,
@@ -67,6 +68,75 @@ async fn main() {
         .route("/api/validate_token", get(validate_token_handler))
         .route("/api/dashboard/stats", get(dashboard_stats_handler))
         .route("/api/dashboard/platform", get(dashboard_platform_handler))
+        .route("/api/dashboard/periodo", post(dashboard_periodo_handler))
+        .route("/api/dashboard/projecao/backtest", get(dashboard_backtest_projecao_handler))
+        .route("/api/analytics/query", post(backend::services::analytics::analytics_query_handler))
+        .route("/api/analytics/heatmap", get(backend::services::analytics::heatmap::heatmap_handler))
+        .route("/api/analytics/melhores-horarios", get(backend::services::analytics::heatmap::melhores_horarios_handler))
+        .route("/api/simulacao", post(backend::services::simulacao::simular_handler))
+        .route("/api/simulacao/ponto-equilibrio", post(backend::services::simulacao::ponto_equilibrio_handler))
+        .route("/api/busca", get(backend::services::busca::busca_handler))
+        .route("/api/tags", get(backend::services::tag::list_tags_handler))
+        .route("/api/tags/autocomplete", get(backend::services::tag::autocomplete_tags_handler))
+        .route("/api/tag", post(backend::services::tag::create_tag_handler))
+        .route("/api/tag/{id}", put(backend::services::tag::update_tag_handler))
+        .route("/api/tag/{id}", delete(backend::services::tag::delete_tag_handler))
+        .route("/api/transacao/{id}/tags", put(backend::services::tag::definir_tags_transacao_handler))
+        .route(
+            "/api/transacao/{id}/comprovantes",
+            post(backend::services::comprovante::upload_comprovantes_handler)
+                .layer(DefaultBodyLimit::max(backend::services::comprovante::limite_corpo_envio()))
+        )
+        .route("/api/transacao/{id}/comprovantes", get(backend::services::comprovante::list_comprovantes_handler))
+        .route("/api/comprovante/{id}", get(backend::services::comprovante::download_comprovante_handler))
+        .route("/api/comprovante/{id}", delete(backend::services::comprovante::delete_comprovante_handler))
+        .route("/api/comprovante/{id}/miniatura", get(backend::services::comprovante::miniatura_comprovante_handler))
+        .route("/api/lixeira", get(backend::services::lixeira::listar_lixeira_handler))
+        .route("/api/lixeira", delete(backend::services::lixeira::esvaziar_lixeira_handler))
+        .route("/api/lixeira/{tipo}/{id}/restaurar", post(backend::services::lixeira::restaurar_item_handler))
+        .route("/api/lixeira/{tipo}/{id}", delete(backend::services::lixeira::expurgar_item_handler))
+        .route("/api/historico/{entidade}/{id}", get(backend::services::historico::historico_handler))
+        .route("/api/historico/{entidade}/{id}/reverter", post(backend::services::historico::reverter_handler))
+        .route("/api/carteiras", get(backend::services::carteira::list_carteiras_handler))
+        .route("/api/carteiras", post(backend::services::carteira::create_carteira_handler))
+        .route("/api/carteiras/{id}", put(backend::services::carteira::update_carteira_handler))
+        .route("/api/carteiras/{id}", delete(backend::services::carteira::delete_carteira_handler))
+        .route("/api/carteiras/{id}/extrato", get(backend::services::carteira::extrato_carteira_handler))
+        .route("/api/carteiras/{id}/conciliar", post(backend::services::carteira::conciliar_carteira_handler))
+        .route("/api/carteiras/transferencias", get(backend::services::carteira::list_transferencias_handler))
+        .route("/api/carteiras/transferencias", post(backend::services::carteira::create_transferencia_handler))
+        .route("/api/carteiras/transferencias/{id}", delete(backend::services::carteira::delete_transferencia_handler))
+        .route("/api/repasses", get(backend::services::repasse::list_repasses_handler))
+        .route("/api/repasses", post(backend::services::repasse::create_repasse_handler))
+        .route("/api/repasses/{id}", put(backend::services::repasse::update_repasse_handler))
+        .route("/api/repasses/{id}", delete(backend::services::repasse::delete_repasse_handler))
+        .route("/api/repasses/{id}/ajuste", post(backend::services::repasse::ajustar_repasse_handler))
+        .route("/api/corridas", get(backend::services::corrida::list_corridas_handler))
+        .route("/api/corridas", post(backend::services::corrida::create_corrida_handler))
+        .route("/api/corridas/analise", get(backend::services::corrida::analise_corridas_handler))
+        .route("/api/corridas/{id}", put(backend::services::corrida::update_corrida_handler))
+        .route("/api/corridas/{id}", delete(backend::services::corrida::delete_corrida_handler))
+        .route("/api/envelopes", get(backend::services::envelope::list_envelopes_handler))
+        .route("/api/envelopes", post(backend::services::envelope::create_envelope_handler))
+        .route("/api/envelopes/movimentos", post(backend::services::envelope::mover_handler))
+        .route("/api/envelopes/{id}", put(backend::services::envelope::update_envelope_handler))
+        .route("/api/envelopes/{id}", delete(backend::services::envelope::delete_envelope_handler))
+        .route("/api/envelopes/{id}/movimentos", get(backend::services::envelope::list_movimentos_handler))
+        .route("/api/envelopes/{id}/retiradas", post(backend::services::envelope::retirar_handler))
+        .route("/api/impostos/{ano}", get(backend::services::imposto::resumo_imposto_handler))
+        .route("/api/impostos/{ano}/informe", get(backend::services::imposto::declaracao_handler))
+        .route("/api/dividas/simular", post(backend::services::divida::simular_divida_handler))
+        .route("/api/dividas", get(backend::services::divida::list_dividas_handler))
+        .route("/api/dividas", post(backend::services::divida::create_divida_handler))
+        .route("/api/dividas/{id}", get(backend::services::divida::get_divida_handler))
+        .route("/api/dividas/{id}", put(backend::services::divida::update_divida_handler))
+        .route("/api/dividas/{id}", delete(backend::services::divida::delete_divida_handler))
+        .route("/api/dividas/{id}/parcelas/{numero}/pagar", post(backend::services::divida::pagar_parcela_handler))
+        .route("/api/dividas/{id}/parcelas/{numero}/pagar", delete(backend::services::divida::estornar_parcela_handler))
+        .route("/api/dividas/{id}/quitacao", get(backend::services::divida::simular_quitacao_handler))
+        .route("/api/alertas", get(backend::services::anomalia::listar_alertas_handler))
+        .route("/api/alertas/detectar", post(backend::services::anomalia::detectar_anomalias_handler))
+        .route("/api/alertas/{id}", put(backend::services::anomalia::atualizar_alerta_handler))
         .route("/api/transacao", post(create_transacao_handler))
         .route("/api/meta", post(create_meta_handler))
         .route("/api/meta/{id}", put(backend::services::meta::update_meta_handler))
@@ -75,6 +145,7 @@ async fn main() {
         .route("/api/transacao/{id}", put(update_transacao_handler))
         .route("/api/transacao/{id}", delete(delete_transacao_handler))
         .route("/api/transacoes", post(list_transacoes_handler))
+        .route("/api/transacoes/lote", post(backend::services::transacao::lote::lote_transacoes_handler))
         .route("/api/captcha", get(generate_captcha_handler))
         .route("/api/meta/a_cumprir/{id_usuario}", get(list_metas_a_cumprir_handler))
         .route("/api/meta/a_cumprir", get(list_metas_a_cumprir_handler))
@@ -93,11 +164,18 @@ async fn main() {
             "/api/configuracao/user/{id_usuario}",
             get(backend::services::configuracao::list_configuracoes_handler)
         )
+        .route("/api/eventos", get(backend::services::eventos::eventos_handler))
         .route("/api/sessao/stop", post(encerrar_sessao_handler))
         .route("/api/sessao/start", post(iniciar_sessao_handler))
         .route("/api/sessao/list/{id_usuario}", get(listar_sessoes_handler))
         .route("/api/sessao/{id}", delete(deletar_sessao_handler))
         .route("/api/sessao/com-transacoes/{id}", get(get_sessao_com_transacoes_handler))
+        .route("/api/sessao/status", get(backend::services::fadiga::status_handler))
+        .route("/api/sessao/{id}/pausa", post(backend::services::fadiga::iniciar_pausa_handler))
+        .route("/api/sessao/{id}/pausa/encerrar", post(backend::services::fadiga::encerrar_pausa_handler))
+        .route("/api/sessao/{id}/pausas", get(backend::services::fadiga::listar_pausas_handler))
+        .route("/api/fadiga/eventos", get(backend::services::fadiga::listar_eventos_handler))
+        .route("/api/fadiga/relatorio", get(backend::services::fadiga::relatorio_handler))
         .route(
             "/api/configuracao/{id}",
             put(backend::services::configuracao::update_configuracao_handler)
@@ -174,6 +252,24 @@ async fn main() {
         .route("/api/admin/reset", post(admin_reset_password_handler))
         .route("/api/admin/create", post(create_admin_handler));
 
+    // Controle do cache em memória (métricas, inspeção e invalidação)
+    use backend::services::admin::cache as admin_cache;
+    let app = app
+        .route("/api/admin/cache", get(admin_cache::cache_stats_handler))
+        .route("/api/admin/cache", delete(admin_cache::cache_invalidate_all_handler))
+        .route("/api/admin/cache/metrics", get(admin_cache::cache_metrics_handler))
+        .route("/api/admin/cache/users/{id}", get(admin_cache::cache_user_entries_handler))
+        .route("/api/admin/cache/users/{id}", delete(admin_cache::cache_invalidate_user_handler));
+
+    // Invalidação de cache entre réplicas (LISTEN/NOTIFY)
+    backend::cache::distributed::start_listener();
+
+    // Arquivos de comprovante sem registro (transações apagadas em lote)
+    backend::services::comprovante::agendar_limpeza();
+
+    // Itens da lixeira além do prazo de retenção
+    backend::services::lixeira::agendar_expurgo();
+
     // Seed automático de configurações iniciais no main
     let conn = &mut db::establish_connection();
     configuracao::seed_configuracoes_padrao(conn);
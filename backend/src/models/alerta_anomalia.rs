@@ -0,0 +1,44 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::models::Usuario;
+use crate::schema::alertas_anomalia;
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = alertas_anomalia)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct AlertaAnomalia {
+    pub id: String,
+    pub id_usuario: String,
+    pub tipo: String,
+    pub chave: String,
+    pub id_transacao: Option<String>,
+    pub id_transacao_relacionada: Option<String>,
+    pub dia: NaiveDate,
+    pub descricao: String,
+    pub valor_observado: Option<f64>,
+    pub valor_esperado: Option<f64>,
+    pub pontuacao: Option<f64>,
+    pub status: String,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = alertas_anomalia)]
+pub struct NewAlertaAnomalia {
+    pub id: String,
+    pub id_usuario: String,
+    pub tipo: String,
+    pub chave: String,
+    pub id_transacao: Option<String>,
+    pub id_transacao_relacionada: Option<String>,
+    pub dia: NaiveDate,
+    pub descricao: String,
+    pub valor_observado: Option<f64>,
+    pub valor_esperado: Option<f64>,
+    pub pontuacao: Option<f64>,
+    pub status: String,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
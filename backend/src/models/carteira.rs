@@ -0,0 +1,50 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::Usuario;
+use crate::schema::{carteiras, transferencias_carteiras};
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = carteiras)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Carteira {
+    pub id: String,
+    pub id_usuario: String,
+    pub nome: String,
+    pub tipo: String,
+    pub plataforma: Option<String>,
+    /// Centavos
+    pub saldo_inicial: i32,
+    pub arquivada: bool,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = carteiras)]
+pub struct NewCarteira {
+    pub id: String,
+    pub id_usuario: String,
+    pub nome: String,
+    pub tipo: String,
+    pub plataforma: Option<String>,
+    pub saldo_inicial: i32,
+    pub arquivada: bool,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+/// Dinheiro movido entre duas carteiras do usuário
+#[derive(Debug, Clone, Queryable, Insertable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = transferencias_carteiras)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct TransferenciaCarteira {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_origem: String,
+    pub id_destino: String,
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub data: DateTime<Utc>,
+    pub criado_em: DateTime<Utc>,
+}
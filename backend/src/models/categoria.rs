@@ -6,7 +6,8 @@ use crate::models::Usuario;
 use crate::schema::categorias;
 
 
-#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(treat_none_as_null = true)]
 #[diesel(table_name = categorias)]
 #[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
 pub struct Categoria {
@@ -18,6 +19,8 @@ pub struct Categoria {
     pub cor: Option<String>,
     pub criado_em: DateTime<Utc>,
     pub atualizado_em: DateTime<Utc>,
+    /// Na lixeira desde (None = ativa)
+    pub excluido_em: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Insertable)]
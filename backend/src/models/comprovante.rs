@@ -0,0 +1,45 @@
+use diesel::prelude::*;
+use serde::{Serialize, Serializer};
+use chrono::{DateTime, Utc};
+use crate::models::{Transacao, Usuario};
+use crate::schema::comprovantes;
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize)]
+#[diesel(table_name = comprovantes)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+#[diesel(belongs_to(Transacao, foreign_key = id_transacao))]
+pub struct Comprovante {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_transacao: String,
+    pub nome_arquivo: String,
+    pub mime: String,
+    pub tamanho: i32,
+    pub checksum_sha256: String,
+    /// Relativo ao diretório de comprovantes; não sai na API
+    #[serde(skip_serializing)]
+    pub caminho: String,
+    /// Na API vira só `tem_miniatura`
+    #[serde(rename = "tem_miniatura", serialize_with = "serializar_presenca")]
+    pub caminho_miniatura: Option<String>,
+    pub criado_em: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = comprovantes)]
+pub struct NewComprovante {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_transacao: String,
+    pub nome_arquivo: String,
+    pub mime: String,
+    pub tamanho: i32,
+    pub checksum_sha256: String,
+    pub caminho: String,
+    pub caminho_miniatura: Option<String>,
+    pub criado_em: DateTime<Utc>,
+}
+
+fn serializar_presenca<S: Serializer>(valor: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(valor.is_some())
+}
@@ -0,0 +1,33 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::Usuario;
+use crate::schema::corridas;
+
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = corridas)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Corrida {
+    pub id: String,
+    pub id_usuario: String,
+    /// Transação de entrada que recebeu o ganho
+    pub id_transacao: Option<String>,
+    pub id_sessao: Option<String>,
+    pub plataforma: String,
+    /// Ex.: UberX, Comfort, Moto
+    pub produto: String,
+    pub embarque: DateTime<Utc>,
+    pub desembarque: DateTime<Utc>,
+    pub distancia_km: Option<f64>,
+    /// Centavos pagos pelo passageiro, sem a gorjeta
+    pub tarifa_passageiro: i32,
+    /// Centavos retidos pela plataforma
+    pub taxa_plataforma: i32,
+    /// Centavos
+    pub gorjeta: i32,
+    /// Multiplicador da dinâmica (1 = sem dinâmica)
+    pub dinamica: Option<f64>,
+    pub observacoes: Option<String>,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
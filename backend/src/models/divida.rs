@@ -0,0 +1,48 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::Usuario;
+use crate::schema::{dividas, parcelas_dividas};
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = dividas)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Divida {
+    pub id: String,
+    pub id_usuario: String,
+    pub descricao: String,
+    pub credor: Option<String>,
+    /// Centavos financiados
+    pub valor_principal: i32,
+    /// Percentual ao mês
+    pub taxa_juros_mensal: f64,
+    /// "price" ou "sac"
+    pub sistema: String,
+    pub numero_parcelas: i32,
+    pub primeiro_vencimento: DateTime<Utc>,
+    /// Categoria de saída dos pagamentos
+    pub id_categoria: Option<String>,
+    pub quitada_em: Option<DateTime<Utc>>,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = parcelas_dividas)]
+#[diesel(belongs_to(Divida, foreign_key = id_divida))]
+pub struct ParcelaDivida {
+    pub id: String,
+    pub id_divida: String,
+    pub numero: i32,
+    pub vencimento: DateTime<Utc>,
+    /// Centavos: amortização mais juros
+    pub valor: i32,
+    pub amortizacao: i32,
+    pub juros: i32,
+    /// Saldo devedor depois desta parcela
+    pub saldo_devedor: i32,
+    pub paga_em: Option<DateTime<Utc>>,
+    pub valor_pago: Option<i32>,
+    /// Transação de saída do pagamento
+    pub id_transacao: Option<String>,
+}
@@ -0,0 +1,29 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::models::{Categoria, Transacao};
+use crate::schema::transacoes_divisoes;
+
+/// Linha de uma transação dividida entre categorias
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = transacoes_divisoes)]
+#[diesel(belongs_to(Transacao, foreign_key = id_transacao))]
+#[diesel(belongs_to(Categoria, foreign_key = id_categoria))]
+pub struct TransacaoDivisao {
+    pub id: String,
+    pub id_transacao: String,
+    pub id_categoria: String,
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub ordem: i32,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = transacoes_divisoes)]
+pub struct NewTransacaoDivisao {
+    pub id: String,
+    pub id_transacao: String,
+    pub id_categoria: String,
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub ordem: i32,
+}
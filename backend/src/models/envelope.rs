@@ -0,0 +1,42 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::models::Usuario;
+use crate::schema::{envelopes, movimentos_envelopes};
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = envelopes)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Envelope {
+    pub id: String,
+    pub id_usuario: String,
+    pub nome: String,
+    /// Centavos
+    pub valor_alvo: Option<i32>,
+    /// "manual", "entrada" ou "lucro_diario"
+    pub regra: String,
+    pub percentual: f64,
+    /// Categoria padrão das retiradas
+    pub id_categoria: Option<String>,
+    pub arquivado: bool,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+/// Valor positivo entra no envelope, negativo sai
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = movimentos_envelopes)]
+#[diesel(belongs_to(Envelope, foreign_key = id_envelope))]
+pub struct MovimentoEnvelope {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_envelope: String,
+    pub tipo: String,
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub data: DateTime<Utc>,
+    pub id_transacao: Option<String>,
+    /// Só nas alocações sobre o lucro diário
+    pub dia: Option<NaiveDate>,
+    pub criado_em: DateTime<Utc>,
+}
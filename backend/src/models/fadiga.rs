@@ -0,0 +1,37 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::SessaoTrabalho;
+use crate::schema::{eventos_fadiga, pausas_sessoes};
+
+/// Pausa dentro de uma sessão; `fim` vazio enquanto em andamento
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = pausas_sessoes)]
+#[diesel(belongs_to(SessaoTrabalho, foreign_key = id_sessao))]
+pub struct PausaSessao {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_sessao: String,
+    pub inicio: DateTime<Utc>,
+    pub fim: Option<DateTime<Utc>>,
+    pub motivo: Option<String>,
+    pub criado_em: DateTime<Utc>,
+}
+
+/// Limite de segurança ultrapassado
+#[derive(Debug, Clone, Queryable, Identifiable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = eventos_fadiga)]
+pub struct EventoFadiga {
+    pub id: String,
+    pub id_usuario: String,
+    pub id_sessao: Option<String>,
+    /// "horas_continuas", "horas_diarias" ou "horas_semanais"
+    pub tipo: String,
+    /// Início do bloco contínuo, do dia ou da semana
+    pub referencia: DateTime<Utc>,
+    pub limite_minutos: i32,
+    /// Maior valor observado
+    pub minutos: i32,
+    pub detectado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
@@ -0,0 +1,25 @@
+use diesel::prelude::*;
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use crate::schema::historico_alteracoes;
+
+/// Uma versão de transação, sessão, meta ou categoria
+#[derive(Debug, Clone, Queryable, Identifiable, Serialize)]
+#[diesel(table_name = historico_alteracoes)]
+pub struct HistoricoAlteracao {
+    pub id: String,
+    pub id_usuario: Option<String>,
+    pub entidade: String,
+    pub id_entidade: String,
+    pub versao: i32,
+    pub acao: String,
+    /// Estado antes da operação (None na criação)
+    pub antes: Option<serde_json::Value>,
+    /// Estado depois da operação (None no expurgo)
+    pub depois: Option<serde_json::Value>,
+    pub ator_tipo: String,
+    pub ator_id: Option<String>,
+    /// Endpoint (ou rotina) que fez a alteração
+    pub origem: String,
+    pub criado_em: DateTime<Utc>,
+}
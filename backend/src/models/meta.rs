@@ -4,7 +4,8 @@ use chrono::{DateTime, Utc};
 use crate::models::Usuario;
 use crate::schema::metas;
 
-#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(treat_none_as_null = true)]
 #[diesel(table_name = metas)]
 #[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
 pub struct Meta {
@@ -25,6 +26,8 @@ pub struct Meta {
     pub criado_em: DateTime<Utc>,
     pub atualizado_em: DateTime<Utc>,
     pub concluida_com: Option<i32>,
+    /// Na lixeira desde (None = ativa)
+    pub excluido_em: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Insertable)]
@@ -12,5 +12,25 @@ pub use sessao_trabalho::*;
 pub use meta::*;
 pub mod admin;
 pub use admin::*;
-
-
+pub mod alerta_anomalia;
+pub use alerta_anomalia::*;
+pub mod tag;
+pub use tag::*;
+pub mod comprovante;
+pub use comprovante::*;
+pub mod divisao;
+pub use divisao::*;
+pub mod historico;
+pub use historico::*;
+pub mod carteira;
+pub use carteira::*;
+pub mod repasse;
+pub use repasse::*;
+pub mod divida;
+pub use divida::*;
+pub mod envelope;
+pub use envelope::*;
+pub mod fadiga;
+pub use fadiga::*;
+pub mod corrida;
+pub use corrida::*;
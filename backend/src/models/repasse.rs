@@ -0,0 +1,26 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::Usuario;
+use crate::schema::repasses;
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = repasses)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Repasse {
+    pub id: String,
+    pub id_usuario: String,
+    pub plataforma: String,
+    /// Categoria das corridas da plataforma
+    pub id_categoria: Option<String>,
+    pub periodo_inicio: DateTime<Utc>,
+    pub periodo_fim: DateTime<Utc>,
+    /// Centavos recebidos
+    pub valor: i32,
+    pub data_recebimento: Option<DateTime<Utc>>,
+    pub descricao: Option<String>,
+    /// Transação de ajuste lançada pela conciliação
+    pub id_transacao_ajuste: Option<String>,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
@@ -5,7 +5,8 @@ use crate::models::Usuario;
 use crate::schema::sessoes_trabalho;
 
 
-#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(treat_none_as_null = true)]
 #[diesel(table_name = sessoes_trabalho)]
 #[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
 pub struct SessaoTrabalho {
@@ -25,6 +26,8 @@ pub struct SessaoTrabalho {
     pub eh_ativa: bool,
     pub criado_em: DateTime<Utc>,
     pub atualizado_em: DateTime<Utc>,
+    /// Na lixeira desde (None = ativa)
+    pub excluido_em: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Insertable)]
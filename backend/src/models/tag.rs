@@ -0,0 +1,39 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::models::{Transacao, Usuario};
+use crate::schema::{tags, transacoes_tags};
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = tags)]
+#[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
+pub struct Tag {
+    pub id: String,
+    pub id_usuario: String,
+    pub nome: String,
+    pub cor: Option<String>,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = tags)]
+pub struct NewTag {
+    pub id: String,
+    pub id_usuario: String,
+    pub nome: String,
+    pub cor: Option<String>,
+    pub criado_em: DateTime<Utc>,
+    pub atualizado_em: DateTime<Utc>,
+}
+
+/// Vínculo transação ↔ tag
+#[derive(Debug, Clone, Queryable, Insertable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = transacoes_tags)]
+#[diesel(primary_key(id_transacao, id_tag))]
+#[diesel(belongs_to(Transacao, foreign_key = id_transacao))]
+#[diesel(belongs_to(Tag, foreign_key = id_tag))]
+pub struct TransacaoTag {
+    pub id_transacao: String,
+    pub id_tag: String,
+}
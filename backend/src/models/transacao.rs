@@ -6,7 +6,8 @@ use crate::schema::transacoes;
 use crate::models::{Usuario, Categoria};
 
 
-#[derive(Debug, Clone, Queryable, Identifiable, Associations, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable, AsChangeset, Serialize, Deserialize)]
+#[diesel(treat_none_as_null = true)]
 #[diesel(table_name = transacoes)]
 #[diesel(belongs_to(Usuario, foreign_key = id_usuario))]
 #[diesel(belongs_to(Categoria, foreign_key = id_categoria))]
@@ -22,6 +23,12 @@ pub struct Transacao {
     pub data: DateTime<Utc>,
     pub criado_em: DateTime<Utc>,
     pub atualizado_em: DateTime<Utc>,
+    /// Sessão vinculada explicitamente (sem vínculo, vale a sessão que contém `data`)
+    pub id_sessao: Option<String>,
+    /// Na lixeira desde (None = ativa)
+    pub excluido_em: Option<DateTime<Utc>>,
+    /// Carteira onde o dinheiro entrou ou de onde saiu
+    pub id_carteira: Option<String>,
 }
 
 #[derive(Debug, Insertable)]
@@ -38,6 +45,7 @@ pub struct NewTransacao {
     pub data: DateTime<Utc>,
     pub criado_em: DateTime<Utc>,
     pub atualizado_em: DateTime<Utc>,
+    pub id_carteira: Option<String>,
 }
 
 impl NewTransacao {
@@ -55,6 +63,7 @@ impl NewTransacao {
             data: now,
             criado_em: now,
             atualizado_em: now,
+            id_carteira: None,
         }
     }
 }
@@ -22,6 +22,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    carteiras (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        nome -> Varchar,
+        tipo -> Varchar,
+        plataforma -> Nullable<Varchar>,
+        saldo_inicial -> Int4,
+        arquivada -> Bool,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
 diesel::table! {
     categorias (id) {
         id -> Varchar,
@@ -32,6 +46,7 @@ diesel::table! {
         cor -> Nullable<Varchar>,
         criado_em -> Timestamptz,
         atualizado_em -> Timestamptz,
+        excluido_em -> Nullable<Timestamptz>,
     }
 }
 
@@ -69,6 +84,52 @@ diesel::table! {
         criado_em -> Timestamptz,
         atualizado_em -> Timestamptz,
         concluida_com -> Nullable<Int4>,
+        excluido_em -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    pausas_sessoes (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_sessao -> Varchar,
+        inicio -> Timestamptz,
+        fim -> Nullable<Timestamptz>,
+        motivo -> Nullable<Varchar>,
+        criado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    parcelas_dividas (id) {
+        id -> Varchar,
+        id_divida -> Varchar,
+        numero -> Int4,
+        vencimento -> Timestamptz,
+        valor -> Int4,
+        amortizacao -> Int4,
+        juros -> Int4,
+        saldo_devedor -> Int4,
+        paga_em -> Nullable<Timestamptz>,
+        valor_pago -> Nullable<Int4>,
+        id_transacao -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    repasses (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        plataforma -> Varchar,
+        id_categoria -> Nullable<Varchar>,
+        periodo_inicio -> Timestamptz,
+        periodo_fim -> Timestamptz,
+        valor -> Int4,
+        data_recebimento -> Nullable<Timestamptz>,
+        descricao -> Nullable<Varchar>,
+        id_transacao_ajuste -> Nullable<Varchar>,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
     }
 }
 
@@ -90,6 +151,7 @@ diesel::table! {
         eh_ativa -> Bool,
         criado_em -> Timestamptz,
         atualizado_em -> Timestamptz,
+        excluido_em -> Nullable<Timestamptz>,
     }
 }
 
@@ -106,6 +168,22 @@ diesel::table! {
         data -> Timestamptz,
         criado_em -> Timestamptz,
         atualizado_em -> Timestamptz,
+        id_sessao -> Nullable<Varchar>,
+        excluido_em -> Nullable<Timestamptz>,
+        id_carteira -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    transferencias_carteiras (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_origem -> Varchar,
+        id_destino -> Varchar,
+        valor -> Int4,
+        descricao -> Nullable<Varchar>,
+        data -> Timestamptz,
+        criado_em -> Timestamptz,
     }
 }
 
@@ -133,21 +211,230 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    alertas_anomalia (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        tipo -> Varchar,
+        chave -> Varchar,
+        id_transacao -> Nullable<Varchar>,
+        id_transacao_relacionada -> Nullable<Varchar>,
+        dia -> Date,
+        descricao -> Varchar,
+        valor_observado -> Nullable<Float8>,
+        valor_esperado -> Nullable<Float8>,
+        pontuacao -> Nullable<Float8>,
+        status -> Varchar,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    comprovantes (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_transacao -> Varchar,
+        nome_arquivo -> Varchar,
+        mime -> Varchar,
+        tamanho -> Int4,
+        checksum_sha256 -> Varchar,
+        caminho -> Varchar,
+        caminho_miniatura -> Nullable<Varchar>,
+        criado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    corridas (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_transacao -> Nullable<Varchar>,
+        id_sessao -> Nullable<Varchar>,
+        plataforma -> Varchar,
+        produto -> Varchar,
+        embarque -> Timestamptz,
+        desembarque -> Timestamptz,
+        distancia_km -> Nullable<Float8>,
+        tarifa_passageiro -> Int4,
+        taxa_plataforma -> Int4,
+        gorjeta -> Int4,
+        dinamica -> Nullable<Float8>,
+        observacoes -> Nullable<Varchar>,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    dividas (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        descricao -> Varchar,
+        credor -> Nullable<Varchar>,
+        valor_principal -> Int4,
+        taxa_juros_mensal -> Float8,
+        sistema -> Varchar,
+        numero_parcelas -> Int4,
+        primeiro_vencimento -> Timestamptz,
+        id_categoria -> Nullable<Varchar>,
+        quitada_em -> Nullable<Timestamptz>,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    envelopes (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        nome -> Varchar,
+        valor_alvo -> Nullable<Int4>,
+        regra -> Varchar,
+        percentual -> Float8,
+        id_categoria -> Nullable<Varchar>,
+        arquivado -> Bool,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    movimentos_envelopes (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_envelope -> Varchar,
+        tipo -> Varchar,
+        valor -> Int4,
+        descricao -> Nullable<Varchar>,
+        data -> Timestamptz,
+        id_transacao -> Nullable<Varchar>,
+        dia -> Nullable<Date>,
+        criado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    eventos_fadiga (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        id_sessao -> Nullable<Varchar>,
+        tipo -> Varchar,
+        referencia -> Timestamptz,
+        limite_minutos -> Int4,
+        minutos -> Int4,
+        detectado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    historico_alteracoes (id) {
+        id -> Varchar,
+        id_usuario -> Nullable<Varchar>,
+        entidade -> Varchar,
+        id_entidade -> Varchar,
+        versao -> Int4,
+        acao -> Varchar,
+        antes -> Nullable<Jsonb>,
+        depois -> Nullable<Jsonb>,
+        ator_tipo -> Varchar,
+        ator_id -> Nullable<Varchar>,
+        origem -> Varchar,
+        criado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Varchar,
+        id_usuario -> Varchar,
+        nome -> Varchar,
+        cor -> Nullable<Varchar>,
+        criado_em -> Timestamptz,
+        atualizado_em -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    transacoes_divisoes (id) {
+        id -> Varchar,
+        id_transacao -> Varchar,
+        id_categoria -> Varchar,
+        valor -> Int4,
+        descricao -> Nullable<Varchar>,
+        ordem -> Int4,
+    }
+}
+
+diesel::table! {
+    transacoes_tags (id_transacao, id_tag) {
+        id_transacao -> Varchar,
+        id_tag -> Varchar,
+    }
+}
+
+diesel::joinable!(alertas_anomalia -> usuarios (id_usuario));
 diesel::joinable!(assinaturas -> usuarios (id_usuario));
+diesel::joinable!(carteiras -> usuarios (id_usuario));
 diesel::joinable!(categorias -> usuarios (id_usuario));
+diesel::joinable!(comprovantes -> transacoes (id_transacao));
+diesel::joinable!(comprovantes -> usuarios (id_usuario));
 diesel::joinable!(configuracoes -> usuarios (id_usuario));
+diesel::joinable!(corridas -> sessoes_trabalho (id_sessao));
+diesel::joinable!(corridas -> transacoes (id_transacao));
+diesel::joinable!(corridas -> usuarios (id_usuario));
+diesel::joinable!(dividas -> categorias (id_categoria));
+diesel::joinable!(dividas -> usuarios (id_usuario));
+diesel::joinable!(envelopes -> categorias (id_categoria));
+diesel::joinable!(envelopes -> usuarios (id_usuario));
+diesel::joinable!(eventos_fadiga -> sessoes_trabalho (id_sessao));
+diesel::joinable!(eventos_fadiga -> usuarios (id_usuario));
+diesel::joinable!(historico_alteracoes -> usuarios (id_usuario));
 diesel::joinable!(metas -> usuarios (id_usuario));
+diesel::joinable!(movimentos_envelopes -> envelopes (id_envelope));
+diesel::joinable!(movimentos_envelopes -> transacoes (id_transacao));
+diesel::joinable!(parcelas_dividas -> dividas (id_divida));
+diesel::joinable!(parcelas_dividas -> transacoes (id_transacao));
+diesel::joinable!(pausas_sessoes -> sessoes_trabalho (id_sessao));
+diesel::joinable!(repasses -> categorias (id_categoria));
+diesel::joinable!(repasses -> transacoes (id_transacao_ajuste));
+diesel::joinable!(repasses -> usuarios (id_usuario));
 diesel::joinable!(sessoes_trabalho -> usuarios (id_usuario));
+diesel::joinable!(tags -> usuarios (id_usuario));
+diesel::joinable!(transacoes -> carteiras (id_carteira));
 diesel::joinable!(transacoes -> categorias (id_categoria));
+diesel::joinable!(transacoes -> sessoes_trabalho (id_sessao));
 diesel::joinable!(transacoes -> usuarios (id_usuario));
+diesel::joinable!(transacoes_divisoes -> categorias (id_categoria));
+diesel::joinable!(transacoes_divisoes -> transacoes (id_transacao));
+diesel::joinable!(transacoes_tags -> tags (id_tag));
+diesel::joinable!(transacoes_tags -> transacoes (id_transacao));
+diesel::joinable!(transferencias_carteiras -> usuarios (id_usuario));
 
 diesel::allow_tables_to_appear_in_same_query!(
     admins,
+    alertas_anomalia,
     assinaturas,
+    carteiras,
     categorias,
+    comprovantes,
     configuracoes,
+    corridas,
+    dividas,
+    envelopes,
+    eventos_fadiga,
+    historico_alteracoes,
     metas,
+    movimentos_envelopes,
+    parcelas_dividas,
+    pausas_sessoes,
+    repasses,
     sessoes_trabalho,
+    tags,
     transacoes,
+    transacoes_divisoes,
+    transacoes_tags,
+    transferencias_carteiras,
     usuarios,
 );
@@ -0,0 +1,121 @@
+//! Controle do cache em memória pelo admin: métricas, inspeção e invalidação
+
+use axum::{ Json, extract::Path, response::IntoResponse };
+use axum::http::{ StatusCode, HeaderMap, header };
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{ NaiveDate, Utc };
+use serde::Serialize;
+
+use crate::cache::RIDER_CACHE;
+use crate::cache::types::TransactionCacheData;
+use crate::cache_key;
+use crate::services::dashboard::service::DashboardStats;
+use super::validate_admin_cookie;
+
+#[derive(Serialize)]
+pub struct DashboardCacheEntry {
+    /// Primeiro dia coberto pelo razão
+    pub inicio: NaiveDate,
+    pub dias_com_transacao: usize,
+    pub primeiro_dia: Option<NaiveDate>,
+    pub ultimo_dia: Option<NaiveDate>,
+    pub dias_com_sessao: usize,
+    /// O que o usuário veria agora
+    pub stats: DashboardStats,
+}
+
+#[derive(Serialize)]
+pub struct UserCacheEntries {
+    pub user_id: String,
+    pub transactions: Option<TransactionCacheData>,
+    pub dashboard: Option<DashboardCacheEntry>,
+}
+
+fn unauthorized() -> axum::response::Response {
+    (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"message": "unauthorized"}))).into_response()
+}
+
+/// Scrapers não têm o cookie do admin: aceitam `Authorization: Bearer $CACHE_METRICS_TOKEN`
+fn metrics_token_valido(headers: &HeaderMap) -> bool {
+    let Ok(esperado) = std::env::var("CACHE_METRICS_TOKEN") else {
+        return false;
+    };
+    !esperado.is_empty() &&
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|token| token == esperado)
+}
+
+// Estatísticas (JSON) dos dois caches
+pub async fn cache_stats_handler(jar: CookieJar) -> impl IntoResponse {
+    if validate_admin_cookie(&jar).is_none() {
+        return unauthorized();
+    }
+    RIDER_CACHE.run_pending_tasks().await;
+    (StatusCode::OK, Json(RIDER_CACHE.get_stats())).into_response()
+}
+
+// Mesmas métricas em formato Prometheus
+pub async fn cache_metrics_handler(jar: CookieJar, headers: HeaderMap) -> impl IntoResponse {
+    if validate_admin_cookie(&jar).is_none() && !metrics_token_valido(&headers) {
+        return unauthorized();
+    }
+    RIDER_CACHE.run_pending_tasks().await;
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        RIDER_CACHE.prometheus_metrics(),
+    ).into_response()
+}
+
+// Entradas em cache de um usuário (sem contar como hit/miss)
+pub async fn cache_user_entries_handler(
+    jar: CookieJar,
+    Path(user_id): Path<String>
+) -> impl IntoResponse {
+    if validate_admin_cookie(&jar).is_none() {
+        return unauthorized();
+    }
+    let transactions = match RIDER_CACHE.transactions.get(&cache_key!(transactions, &user_id)).await {
+        Some(data) => Some(data.read().await.clone()),
+        None => None,
+    };
+    let dashboard = match RIDER_CACHE.dashboard.get(&cache_key!(dashboard, &user_id)).await {
+        Some(razao) => {
+            let razao = razao.read().await;
+            Some(DashboardCacheEntry {
+                inicio: razao.inicio,
+                dias_com_transacao: razao.dias.len(),
+                primeiro_dia: razao.dias.keys().next().copied(),
+                ultimo_dia: razao.dias.keys().next_back().copied(),
+                dias_com_sessao: razao.sessoes.len(),
+                stats: razao.stats(Utc::now().date_naive()),
+            })
+        }
+        None => None,
+    };
+    (StatusCode::OK, Json(UserCacheEntries { user_id, transactions, dashboard })).into_response()
+}
+
+// Invalida os caches de um usuário
+pub async fn cache_invalidate_user_handler(
+    jar: CookieJar,
+    Path(user_id): Path<String>
+) -> impl IntoResponse {
+    if validate_admin_cookie(&jar).is_none() {
+        return unauthorized();
+    }
+    RIDER_CACHE.invalidate_user_caches(&user_id).await;
+    (StatusCode::OK, Json(serde_json::json!({"message": "user caches invalidated"}))).into_response()
+}
+
+// Invalida os caches de todos os usuários
+pub async fn cache_invalidate_all_handler(jar: CookieJar) -> impl IntoResponse {
+    if validate_admin_cookie(&jar).is_none() {
+        return unauthorized();
+    }
+    RIDER_CACHE.invalidate_all().await;
+    (StatusCode::OK, Json(serde_json::json!({"message": "all caches invalidated"}))).into_response()
+}
@@ -11,6 +11,8 @@ use diesel::prelude::*;
 use jsonwebtoken::{ encode, decode, Header, EncodingKey, DecodingKey, Validation, Algorithm };
 use serde::{ Deserialize, Serialize };
 
+pub mod cache;
+
 #[derive(Deserialize)]
 pub struct AdminRequestResetPayload {
     pub username: String,
@@ -206,7 +208,7 @@ pub async fn admin_login_handler(Json(payload): Json<AdminLoginPayload>) -> impl
 }
 
 // Change password (basic): verifies old password and updates hash
-fn validate_admin_cookie(jar: &CookieJar) -> Option<String> {
+pub(crate) fn validate_admin_cookie(jar: &CookieJar) -> Option<String> {
     if let Some(cookie) = jar.get("admin_auth_token") {
         let token = cookie.value();
         let secret = std::env
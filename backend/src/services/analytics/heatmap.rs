@@ -0,0 +1,495 @@
+//! Heatmap de ganhos (dia da semana × hora) e recomendação dos melhores horários
+//!
+//! Cada amostra é um par (dia, hora local): ganhos das transações de entrada naquela hora
+//! divididos pelos minutos de sessão ativos na mesma hora. As taxas de cada dia são
+//! combinadas com `media_excluindo_extremos`, para que um dia atípico (corrida longa,
+//! dinâmica alta) não domine a célula.
+
+use axum::{Json, extract::Query, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::db;
+use crate::schema::configuracoes::dsl as config_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::dashboard::service::media_excluindo_extremos;
+use super::{fuso_existe, fuso_valido};
+
+const SEMANAS_PADRAO: i64 = 12;
+const MAX_SEMANAS: i64 = 52;
+const LIMITE_PADRAO: usize = 10;
+const MAX_LIMITE: usize = 50;
+/// Uma hora só vira amostra se o usuário trabalhou ao menos esse tempo nela
+const MIN_MINUTOS_AMOSTRA: f64 = 15.0;
+/// Peso (em dias) da média geral na pontuação: células com poucas amostras são puxadas para ela
+const PESO_MEDIA_GERAL: f64 = 4.0;
+
+const DIAS_SEMANA: [&str; 7] = ["segunda", "terça", "quarta", "quinta", "sexta", "sábado", "domingo"];
+
+#[derive(Deserialize, Clone, Default)]
+pub struct HeatmapParams {
+    pub semanas: Option<i64>,
+    /// Fuso IANA usado para dia da semana / hora (padrão UTC)
+    pub fuso_horario: Option<String>,
+    /// Só para a recomendação: quantos horários devolver
+    pub limite: Option<usize>,
+}
+
+/// Uma linha (dia, hora local, plataforma) vinda do banco
+#[derive(QueryableByName, Clone, Debug)]
+pub struct AmostraHorario {
+    #[diesel(sql_type = Text)]
+    pub dia: String,
+    /// 1 = segunda ... 7 = domingo (ISO)
+    #[diesel(sql_type = Integer)]
+    pub dia_semana: i32,
+    #[diesel(sql_type = Integer)]
+    pub hora: i32,
+    #[diesel(sql_type = Text)]
+    pub plataforma: String,
+    #[diesel(sql_type = Double)]
+    pub minutos: f64,
+    #[diesel(sql_type = BigInt)]
+    pub ganhos: i64,
+    #[diesel(sql_type = BigInt)]
+    pub corridas: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CelulaHeatmap {
+    pub dia_semana: u32,
+    pub hora: u32,
+    /// Centavos por hora (média sem extremos entre os dias amostrados)
+    pub rs_por_hora: Option<i32>,
+    pub corridas_por_hora: Option<f64>,
+    pub horas_trabalhadas: f64,
+    pub ganhos: i64,
+    pub dias_amostrados: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Heatmap {
+    pub data_inicio: DateTime<Utc>,
+    pub data_fim: DateTime<Utc>,
+    pub fuso_horario: String,
+    pub percentual_extremos: usize,
+    pub rs_por_hora_geral: Option<i32>,
+    /// Sempre 7 × 24 células, ordenadas por dia da semana e hora
+    pub celulas: Vec<CelulaHeatmap>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlataformaHorario {
+    pub plataforma: String,
+    pub rs_por_hora: i32,
+    pub corridas_por_hora: f64,
+    pub dias_amostrados: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HorarioRecomendado {
+    pub posicao: usize,
+    pub dia_semana: u32,
+    pub dia_semana_nome: String,
+    pub hora: u32,
+    pub rs_por_hora: i32,
+    pub corridas_por_hora: f64,
+    /// R$/h ajustado pelo tamanho da amostra (usado na ordenação)
+    pub pontuacao: i32,
+    pub dias_amostrados: usize,
+    /// 0..1, cresce com o número de dias amostrados
+    pub confianca: f64,
+    /// "baixa", "media" ou "alta"
+    pub nivel_confianca: String,
+    /// Plataformas ordenadas pelo R$/h naquele horário
+    pub plataformas: Vec<PlataformaHorario>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RecomendacaoHorarios {
+    pub data_inicio: DateTime<Utc>,
+    pub data_fim: DateTime<Utc>,
+    pub fuso_horario: String,
+    pub rs_por_hora_geral: Option<i32>,
+    pub horarios: Vec<HorarioRecomendado>,
+}
+
+/// Intervalo e fuso já validados
+#[derive(Clone, Debug)]
+pub struct ParametrosHeatmap {
+    pub inicio: DateTime<Utc>,
+    pub fim: DateTime<Utc>,
+    pub fuso_horario: String,
+    pub limite: usize,
+}
+
+pub fn validar_params(p: &HeatmapParams, agora: DateTime<Utc>) -> Result<ParametrosHeatmap, String> {
+    let semanas = p.semanas.unwrap_or(SEMANAS_PADRAO);
+    if !(1..=MAX_SEMANAS).contains(&semanas) {
+        return Err(format!("semanas deve estar entre 1 e {MAX_SEMANAS}"));
+    }
+    let fuso_horario = p.fuso_horario.clone().unwrap_or_else(|| "UTC".to_string());
+    if !fuso_valido(&fuso_horario) {
+        return Err(format!("Fuso horário inválido: {fuso_horario}"));
+    }
+    let limite = p.limite.unwrap_or(LIMITE_PADRAO);
+    if limite == 0 || limite > MAX_LIMITE {
+        return Err(format!("limite deve estar entre 1 e {MAX_LIMITE}"));
+    }
+    Ok(ParametrosHeatmap { inicio: agora - Duration::weeks(semanas), fim: agora, fuso_horario, limite })
+}
+
+/// Minutos de sessão fatiados por hora local e ganhos/corridas das entradas na mesma hora,
+/// por plataforma. Cada transação conta na sessão a que está vinculada ou, sem vínculo, na que
+/// cobre o seu horário; as demais aparecem como `sem_sessao` (sem minutos).
+const SQL_AMOSTRAS: &str = "
+    WITH fatias AS (
+        SELECT date_trunc('hour', h.hora AT TIME ZONE $4) AS slot,
+            COALESCE(s.plataforma, 'nao_informada') AS plataforma,
+            EXTRACT(EPOCH FROM LEAST(h.hora + INTERVAL '1 hour', COALESCE(s.fim, NOW())) - GREATEST(h.hora, s.inicio))::float8 / 60.0 AS minutos
+        FROM sessoes_trabalho s
+        CROSS JOIN LATERAL generate_series(date_trunc('hour', s.inicio), COALESCE(s.fim, NOW()), INTERVAL '1 hour') AS h(hora)
+        WHERE s.id_usuario = $1 AND s.excluido_em IS NULL AND s.inicio >= $2 AND s.inicio <= $3
+          AND COALESCE(s.fim, NOW()) > s.inicio
+    ),
+    ss AS (
+        SELECT slot, plataforma, SUM(minutos) AS minutos FROM fatias GROUP BY 1, 2
+    ),
+    tx AS (
+        SELECT date_trunc('hour', t.data AT TIME ZONE $4) AS slot,
+            CASE WHEN s.id IS NULL THEN 'sem_sessao' ELSE COALESCE(s.plataforma, 'nao_informada') END AS plataforma,
+            SUM(t.valor)::bigint AS ganhos,
+            SUM(t.eventos)::bigint AS corridas
+        FROM transacoes t
+        LEFT JOIN LATERAL (
+            SELECT s.id, s.plataforma
+            FROM sessoes_trabalho s
+            WHERE s.id_usuario = t.id_usuario AND s.excluido_em IS NULL
+              AND (s.id = t.id_sessao
+                   OR (t.id_sessao IS NULL AND t.data >= s.inicio AND t.data <= COALESCE(s.fim, NOW())))
+            ORDER BY s.inicio DESC
+            LIMIT 1
+        ) s ON TRUE
+        WHERE t.id_usuario = $1 AND t.excluido_em IS NULL AND t.tipo = 'entrada' AND t.data >= $2 AND t.data <= $3
+        GROUP BY 1, 2
+    )
+    SELECT to_char(COALESCE(ss.slot, tx.slot), 'YYYY-MM-DD') AS dia,
+        EXTRACT(ISODOW FROM COALESCE(ss.slot, tx.slot))::int AS dia_semana,
+        EXTRACT(HOUR FROM COALESCE(ss.slot, tx.slot))::int AS hora,
+        COALESCE(ss.plataforma, tx.plataforma) AS plataforma,
+        COALESCE(ss.minutos, 0)::float8 AS minutos,
+        COALESCE(tx.ganhos, 0)::bigint AS ganhos,
+        COALESCE(tx.corridas, 0)::bigint AS corridas
+    FROM ss FULL OUTER JOIN tx ON ss.slot = tx.slot AND ss.plataforma = tx.plataforma";
+
+pub fn carregar_amostras(conn: &mut PgConnection, id_usuario: &str, p: &ParametrosHeatmap) -> Result<Vec<AmostraHorario>, diesel::result::Error> {
+    diesel::sql_query(SQL_AMOSTRAS)
+        .bind::<Text, _>(id_usuario)
+        .bind::<Timestamptz, _>(p.inicio)
+        .bind::<Timestamptz, _>(p.fim)
+        .bind::<Text, _>(&p.fuso_horario)
+        .load::<AmostraHorario>(conn)
+}
+
+/// Percentual de extremos configurado pelo usuário (mesma chave da projeção do dashboard)
+fn percentual_extremos(conn: &mut PgConnection, id_usuario: &str) -> usize {
+    config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario))
+        .filter(config_dsl::chave.eq("projecao_percentual_extremos"))
+        .select(config_dsl::valor)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10)
+}
+
+/// Totais de um dia dentro de uma célula
+#[derive(Default, Clone, Copy)]
+struct TotalDia {
+    minutos: f64,
+    ganhos: i64,
+    corridas: i64,
+}
+
+/// R$/h e corridas/h (média sem extremos entre os dias) e quantos dias entraram na conta
+fn taxas<'a>(dias: impl Iterator<Item = &'a TotalDia>, percent: usize) -> Option<(i32, f64, usize)> {
+    let mut rs_h = Vec::new();
+    let mut corridas_h = Vec::new();
+    for d in dias.filter(|d| d.minutos >= MIN_MINUTOS_AMOSTRA) {
+        rs_h.push((d.ganhos as f64 * 60.0 / d.minutos).round() as i32);
+        // centésimos de corrida por hora, para caber no cálculo inteiro
+        corridas_h.push((d.corridas as f64 * 6000.0 / d.minutos).round() as i32);
+    }
+    if rs_h.is_empty() {
+        return None;
+    }
+    Some((
+        media_excluindo_extremos(&rs_h, percent),
+        media_excluindo_extremos(&corridas_h, percent) as f64 / 100.0,
+        rs_h.len(),
+    ))
+}
+
+fn indice(dia_semana: i32, hora: i32) -> Option<(u32, u32)> {
+    if (1..=7).contains(&dia_semana) && (0..24).contains(&hora) {
+        Some((dia_semana as u32, hora as u32))
+    } else {
+        None
+    }
+}
+
+/// R$/h geral: ganhos / horas considerando só as horas que viraram amostra
+fn rs_por_hora_geral(por_celula: &BTreeMap<(u32, u32), BTreeMap<String, TotalDia>>) -> Option<i32> {
+    let (ganhos, minutos) = por_celula
+        .values()
+        .flat_map(|dias| dias.values())
+        .filter(|d| d.minutos >= MIN_MINUTOS_AMOSTRA)
+        .fold((0i64, 0f64), |(g, m), d| (g + d.ganhos, m + d.minutos));
+    (minutos > 0.0).then(|| (ganhos as f64 * 60.0 / minutos).round() as i32)
+}
+
+fn agrupar_por_celula(amostras: &[AmostraHorario]) -> BTreeMap<(u32, u32), BTreeMap<String, TotalDia>> {
+    let mut por_celula: BTreeMap<(u32, u32), BTreeMap<String, TotalDia>> = BTreeMap::new();
+    for a in amostras {
+        let Some(chave) = indice(a.dia_semana, a.hora) else { continue };
+        let total = por_celula.entry(chave).or_default().entry(a.dia.clone()).or_default();
+        total.minutos += a.minutos;
+        total.ganhos += a.ganhos;
+        total.corridas += a.corridas;
+    }
+    por_celula
+}
+
+/// Monta as 7 × 24 células do heatmap a partir das amostras
+pub fn montar_heatmap(amostras: &[AmostraHorario], percent: usize) -> (Vec<CelulaHeatmap>, Option<i32>) {
+    let por_celula = agrupar_por_celula(amostras);
+    let mut celulas = Vec::with_capacity(7 * 24);
+    for dia_semana in 1..=7u32 {
+        for hora in 0..24u32 {
+            let dias = por_celula.get(&(dia_semana, hora));
+            let calculo = dias.and_then(|d| taxas(d.values(), percent));
+            let (minutos, ganhos) = dias
+                .map(|d| d.values().fold((0f64, 0i64), |(m, g), t| (m + t.minutos, g + t.ganhos)))
+                .unwrap_or((0.0, 0));
+            celulas.push(CelulaHeatmap {
+                dia_semana,
+                hora,
+                rs_por_hora: calculo.map(|c| c.0),
+                corridas_por_hora: calculo.map(|c| c.1),
+                horas_trabalhadas: (minutos / 60.0 * 100.0).round() / 100.0,
+                ganhos,
+                dias_amostrados: calculo.map(|c| c.2).unwrap_or(0),
+            });
+        }
+    }
+    (celulas, rs_por_hora_geral(&por_celula))
+}
+
+fn nivel_confianca(dias: usize) -> &'static str {
+    match dias {
+        d if d >= 8 => "alta",
+        d if d >= 4 => "media",
+        _ => "baixa",
+    }
+}
+
+/// Ordena os horários pelo R$/h ajustado pela amostra: com poucos dias o valor é puxado
+/// para a média geral, então uma única noite boa não fica à frente de um padrão consistente.
+pub fn recomendar(amostras: &[AmostraHorario], percent: usize, limite: usize) -> (Vec<HorarioRecomendado>, Option<i32>) {
+    let (celulas, geral) = montar_heatmap(amostras, percent);
+
+    let mut por_plataforma: BTreeMap<(u32, u32), BTreeMap<String, Vec<TotalDia>>> = BTreeMap::new();
+    for a in amostras {
+        let Some(chave) = indice(a.dia_semana, a.hora) else { continue };
+        por_plataforma
+            .entry(chave)
+            .or_default()
+            .entry(a.plataforma.clone())
+            .or_default()
+            .push(TotalDia { minutos: a.minutos, ganhos: a.ganhos, corridas: a.corridas });
+    }
+
+    let mut horarios: Vec<HorarioRecomendado> = celulas
+        .into_iter()
+        .filter_map(|c| {
+            let rs_por_hora = c.rs_por_hora?;
+            let n = c.dias_amostrados as f64;
+            let base = geral.unwrap_or(rs_por_hora) as f64;
+            let pontuacao = ((n * rs_por_hora as f64 + PESO_MEDIA_GERAL * base) / (n + PESO_MEDIA_GERAL)).round() as i32;
+            let mut plataformas: Vec<PlataformaHorario> = por_plataforma
+                .get(&(c.dia_semana, c.hora))
+                .map(|m| {
+                    m.iter()
+                        .filter_map(|(plataforma, dias)| {
+                            let (rs, corridas, dias_amostrados) = taxas(dias.iter(), percent)?;
+                            Some(PlataformaHorario { plataforma: plataforma.clone(), rs_por_hora: rs, corridas_por_hora: corridas, dias_amostrados })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            plataformas.sort_by_key(|p| std::cmp::Reverse(p.rs_por_hora));
+            Some(HorarioRecomendado {
+                posicao: 0,
+                dia_semana: c.dia_semana,
+                dia_semana_nome: DIAS_SEMANA[(c.dia_semana - 1) as usize].to_string(),
+                hora: c.hora,
+                rs_por_hora,
+                corridas_por_hora: c.corridas_por_hora.unwrap_or(0.0),
+                pontuacao,
+                dias_amostrados: c.dias_amostrados,
+                confianca: ((n / (n + PESO_MEDIA_GERAL)) * 100.0).round() / 100.0,
+                nivel_confianca: nivel_confianca(c.dias_amostrados).to_string(),
+                plataformas,
+            })
+        })
+        .collect();
+
+    horarios.sort_by(|a, b| {
+        b.pontuacao
+            .cmp(&a.pontuacao)
+            .then(b.dias_amostrados.cmp(&a.dias_amostrados))
+            .then((a.dia_semana, a.hora).cmp(&(b.dia_semana, b.hora)))
+    });
+    horarios.truncate(limite);
+    for (i, h) in horarios.iter_mut().enumerate() {
+        h.posicao = i + 1;
+    }
+    (horarios, geral)
+}
+
+fn preparar(jar: &CookieJar, params: &HeatmapParams) -> Result<(String, ParametrosHeatmap, PgConnection), (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let p = validar_params(params, Utc::now()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mut conn = db::establish_connection();
+    if !fuso_existe(&mut conn, &p.fuso_horario) {
+        return Err((StatusCode::BAD_REQUEST, format!("Fuso horário desconhecido: {}", p.fuso_horario)));
+    }
+    Ok((id_usuario, p, conn))
+}
+
+pub async fn heatmap_handler(
+    jar: CookieJar,
+    Query(params): Query<HeatmapParams>,
+) -> Result<Json<Heatmap>, (StatusCode, String)> {
+    let (id_usuario, p, mut conn) = preparar(&jar, &params)?;
+    let amostras = carregar_amostras(&mut conn, &id_usuario, &p)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao calcular heatmap: {e}")))?;
+    let percent = percentual_extremos(&mut conn, &id_usuario);
+    let (celulas, rs_por_hora_geral) = montar_heatmap(&amostras, percent);
+    Ok(Json(Heatmap {
+        data_inicio: p.inicio,
+        data_fim: p.fim,
+        fuso_horario: p.fuso_horario,
+        percentual_extremos: percent,
+        rs_por_hora_geral,
+        celulas,
+    }))
+}
+
+pub async fn melhores_horarios_handler(
+    jar: CookieJar,
+    Query(params): Query<HeatmapParams>,
+) -> Result<Json<RecomendacaoHorarios>, (StatusCode, String)> {
+    let (id_usuario, p, mut conn) = preparar(&jar, &params)?;
+    let amostras = carregar_amostras(&mut conn, &id_usuario, &p)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao calcular recomendação: {e}")))?;
+    let percent = percentual_extremos(&mut conn, &id_usuario);
+    let (horarios, rs_por_hora_geral) = recomendar(&amostras, percent, p.limite);
+    Ok(Json(RecomendacaoHorarios {
+        data_inicio: p.inicio,
+        data_fim: p.fim,
+        fuso_horario: p.fuso_horario,
+        rs_por_hora_geral,
+        horarios,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amostra(dia: u32, dia_semana: i32, hora: i32, minutos: f64, ganhos: i64, corridas: i64) -> AmostraHorario {
+        AmostraHorario {
+            dia: format!("2025-09-{dia:02}"),
+            dia_semana,
+            hora,
+            plataforma: "Uber".to_string(),
+            minutos,
+            ganhos,
+            corridas,
+        }
+    }
+
+    /// `dias` dias distintos na mesma célula, cada um com uma hora cheia
+    fn celula(dia_semana: i32, hora: i32, ganhos_por_dia: &[i64]) -> Vec<AmostraHorario> {
+        ganhos_por_dia.iter().enumerate().map(|(i, g)| amostra(i as u32 + 1, dia_semana, hora, 60.0, *g, 2)).collect()
+    }
+
+    #[test]
+    fn test_sem_amostras() {
+        let (celulas, geral) = montar_heatmap(&[], 10);
+        assert_eq!(celulas.len(), 7 * 24);
+        assert!(celulas.iter().all(|c| c.rs_por_hora.is_none() && c.dias_amostrados == 0 && c.ganhos == 0));
+        assert_eq!((celulas[0].dia_semana, celulas[0].hora, celulas[167].dia_semana, celulas[167].hora), (1, 0, 7, 23));
+        assert_eq!(geral, None);
+        let (horarios, geral) = recomendar(&[], 10, 5);
+        assert!(horarios.is_empty() && geral.is_none());
+    }
+
+    #[test]
+    fn test_corte_de_extremos_e_amostra_minima() {
+        // oito dias a R$ 20/h, um dia zerado e uma corrida longa de R$ 1.000
+        let mut amostras = celula(1, 8, &[0, 2000, 2000, 2000, 2000, 2000, 2000, 2000, 2000, 100_000]);
+        // 10 minutos não chegam a ser amostra, mas o ganho conta na célula
+        amostras.push(amostra(20, 1, 8, 10.0, 5000, 1));
+        // dia/hora fora da faixa é ignorado
+        amostras.push(amostra(21, 8, 8, 60.0, 5000, 1));
+
+        let (celulas, _) = montar_heatmap(&amostras, 10);
+        let c = &celulas[8];
+        assert_eq!((c.dia_semana, c.hora), (1, 8));
+        assert_eq!(c.rs_por_hora, Some(2000));
+        assert_eq!(c.corridas_por_hora, Some(2.0));
+        assert_eq!(c.dias_amostrados, 10);
+        assert_eq!(c.ganhos, 116_000 + 5000);
+        assert_eq!(c.horas_trabalhadas, 10.17);
+
+        // sem corte a corrida longa puxa a média
+        let (celulas, geral) = montar_heatmap(&amostras, 0);
+        assert_eq!(celulas[8].rs_por_hora, Some(11_600));
+        // geral só com as horas que viraram amostra
+        assert_eq!(geral, Some(11_600));
+    }
+
+    #[test]
+    fn test_recomendacao_respeita_limite_e_tamanho_da_amostra() {
+        let mut amostras = celula(2, 20, &[6000]);
+        amostras.extend(celula(3, 9, &[4000; 8]));
+        amostras.extend(celula(4, 10, &[1000; 8]));
+        for hora in 0..5 {
+            amostras.extend(celula(5, hora, &[500; 2]));
+        }
+
+        let (todos, geral) = recomendar(&amostras, 10, MAX_LIMITE);
+        assert_eq!(todos.len(), 8);
+        // 51.000 centavos em 27 horas, arredondado
+        assert_eq!(geral, Some(1889));
+
+        let (horarios, _) = recomendar(&amostras, 10, 2);
+        assert_eq!(horarios.len(), 2);
+        assert_eq!(horarios.iter().map(|h| h.posicao).collect::<Vec<_>>(), vec![1, 2]);
+        // oito dias a R$ 40/h ficam à frente de uma única noite a R$ 60/h
+        let (primeiro, segundo) = (&horarios[0], &horarios[1]);
+        assert_eq!((primeiro.dia_semana, primeiro.hora, primeiro.dia_semana_nome.as_str()), (3, 9, "quarta"));
+        assert_eq!((primeiro.nivel_confianca.as_str(), primeiro.confianca), ("alta", 0.67));
+        assert_eq!((segundo.dia_semana, segundo.hora, segundo.rs_por_hora), (2, 20, 6000));
+        assert!(segundo.pontuacao < segundo.rs_por_hora && segundo.nivel_confianca == "baixa");
+        assert_eq!(primeiro.plataformas.len(), 1);
+        assert_eq!((primeiro.plataformas[0].plataforma.as_str(), primeiro.plataformas[0].rs_por_hora), ("Uber", 4000));
+    }
+}
@@ -0,0 +1,617 @@
+//! API genérica de analytics (group-by / pivot) para os gráficos
+//!
+//! Em vez de um loop escrito à mão em `dashboard::service` para cada gráfico, o frontend
+//! envia métrica + bucket de tempo + dimensão + filtros e recebe uma tabela calculada no
+//! Postgres. Métrica, bucket e dimensão são enums mapeados para trechos de SQL fixos;
+//! todos os valores vindos do usuário entram como parâmetros (`bind`), nunca interpolados.
+
+use axum::{Json, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::pg::Pg;
+use diesel::sql_types::{Array, BigInt, Nullable, Double, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use crate::db;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::dashboard::periodo::MAX_DIAS_INTERVALO;
+
+pub mod heatmap;
+
+/// Máximo de linhas devolvidas (long format); acima disso a resposta vem com `truncado = true`
+pub const MAX_LINHAS: i64 = 2000;
+/// Com bucket por hora o intervalo é limitado para manter a tabela pequena
+const MAX_DIAS_BUCKET_HORA: i64 = 93;
+const MAX_CATEGORIAS_FILTRO: usize = 50;
+/// Partes `p` de cada transação `t`: uma por linha da divisão ou a própria transação.
+/// Eventos e km só contam na parte principal (ver `transacao::divisao`).
+const PARTES_TRANSACAO: &str = "CROSS JOIN LATERAL (
+                SELECT d.id_categoria, d.valor, d.ordem = 0 AS principal
+                FROM transacoes_divisoes d
+                WHERE d.id_transacao = t.id
+                UNION ALL
+                SELECT t.id_categoria, t.valor, TRUE
+                WHERE NOT EXISTS (SELECT 1 FROM transacoes_divisoes d WHERE d.id_transacao = t.id)
+            ) p";
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metrica {
+    Ganhos,
+    Gastos,
+    Lucro,
+    #[serde(alias = "eventos")]
+    Corridas,
+    Km,
+    Horas,
+    /// Centavos por hora trabalhada
+    RsPorHora,
+    /// Centavos de ganho por km rodado
+    RsPorKm,
+}
+
+impl Metrica {
+    /// Métricas que dependem de `sessoes_trabalho` (minutos trabalhados)
+    fn usa_sessoes(self) -> bool {
+        matches!(self, Metrica::Horas | Metrica::RsPorHora)
+    }
+
+    fn expressao_sql(self) -> &'static str {
+        match self {
+            Metrica::Ganhos => "tx.ganhos",
+            Metrica::Gastos => "tx.gastos",
+            Metrica::Lucro => "tx.ganhos - tx.gastos",
+            Metrica::Corridas => "tx.eventos",
+            Metrica::Km => "tx.km",
+            Metrica::RsPorKm => "CASE WHEN tx.km > 0 THEN tx.ganhos / tx.km END",
+            Metrica::Horas => "COALESCE(ss.horas, 0)",
+            Metrica::RsPorHora => "CASE WHEN ss.horas > 0 THEN COALESCE(tx.ganhos, 0) / ss.horas END",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    #[serde(alias = "hora")]
+    Hour,
+    #[serde(alias = "dia_semana")]
+    Weekday,
+    #[default]
+    #[serde(alias = "dia")]
+    Day,
+    #[serde(alias = "semana")]
+    Week,
+    #[serde(alias = "mes")]
+    Month,
+    #[serde(alias = "nenhum")]
+    Total,
+}
+
+impl Bucket {
+    /// Expressão do bucket sobre uma coluna timestamptz já convertida para o fuso pedido
+    fn expressao_sql(self, coluna: &str) -> String {
+        match self {
+            Bucket::Hour => format!("to_char({coluna}, 'HH24')"),
+            Bucket::Weekday => format!("to_char({coluna}, 'ID')"),
+            Bucket::Day => format!("to_char({coluna}, 'YYYY-MM-DD')"),
+            Bucket::Week => format!("to_char(date_trunc('week', {coluna}), 'YYYY-MM-DD')"),
+            Bucket::Month => format!("to_char({coluna}, 'YYYY-MM')"),
+            Bucket::Total => "'total'".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimensao {
+    Categoria,
+    Plataforma,
+    Tipo,
+    Clima,
+    LocalInicio,
+    /// Uma transação com várias tags entra no total de cada uma
+    Tag,
+    #[default]
+    Nenhuma,
+}
+
+impl Dimensao {
+    /// Dimensões que só existem em `transacoes` (não podem ser cruzadas com horas)
+    fn so_transacoes(self) -> bool {
+        matches!(self, Dimensao::Categoria | Dimensao::Tipo | Dimensao::Tag)
+    }
+
+    /// Expressão da dimensão na CTE de transações (`s` é a sessão que contém a transação)
+    fn expressao_transacoes(self) -> &'static str {
+        match self {
+            Dimensao::Categoria => "COALESCE(c.nome, p.id_categoria)",
+            Dimensao::Tipo => "t.tipo",
+            Dimensao::Plataforma => "COALESCE(s.plataforma, 'sem_sessao')",
+            Dimensao::Clima => "COALESCE(s.clima, 'sem_sessao')",
+            Dimensao::LocalInicio => "COALESCE(s.local_inicio, 'sem_sessao')",
+            Dimensao::Tag => "COALESCE(tg.nome, 'sem_tag')",
+            Dimensao::Nenhuma => "'todos'",
+        }
+    }
+
+    /// Expressão da dimensão na CTE de sessões
+    fn expressao_sessoes(self) -> &'static str {
+        match self {
+            Dimensao::Plataforma => "COALESCE(s.plataforma, 'sem_sessao')",
+            Dimensao::Clima => "COALESCE(s.clima, 'sem_sessao')",
+            Dimensao::LocalInicio => "COALESCE(s.local_inicio, 'sem_sessao')",
+            _ => "'todos'",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct AnalyticsFiltros {
+    pub data_inicio: Option<DateTime<Utc>>,
+    pub data_fim: Option<DateTime<Utc>>,
+    pub tipo: Option<String>,
+    pub categorias: Option<Vec<String>>,
+    /// Ids de tags: transações com pelo menos uma delas
+    pub tags: Option<Vec<String>>,
+    pub plataforma: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AnalyticsQuery {
+    pub metrica: Metrica,
+    #[serde(default)]
+    pub bucket: Bucket,
+    #[serde(default)]
+    pub agrupar_por: Dimensao,
+    #[serde(default)]
+    pub filtros: AnalyticsFiltros,
+    /// Fuso IANA usado para os buckets (padrão UTC, como o restante do dashboard)
+    pub fuso_horario: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AnalyticsLinha {
+    pub bucket: String,
+    pub grupo: String,
+    pub valor: Option<f64>,
+    pub amostras: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AnalyticsPivotLinha {
+    pub bucket: String,
+    pub valores: BTreeMap<String, Option<f64>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AnalyticsResultado {
+    pub metrica: Metrica,
+    pub bucket: Bucket,
+    pub agrupar_por: Dimensao,
+    pub data_inicio: DateTime<Utc>,
+    pub data_fim: DateTime<Utc>,
+    pub fuso_horario: String,
+    pub grupos: Vec<String>,
+    pub linhas: Vec<AnalyticsLinha>,
+    pub pivot: Vec<AnalyticsPivotLinha>,
+    pub truncado: bool,
+}
+
+#[derive(QueryableByName, Debug)]
+struct LinhaSql {
+    #[diesel(sql_type = Text)]
+    bucket: String,
+    #[diesel(sql_type = Text)]
+    grupo: String,
+    #[diesel(sql_type = Nullable<Double>)]
+    valor: Option<f64>,
+    #[diesel(sql_type = BigInt)]
+    amostras: i64,
+}
+
+/// Consulta já validada: intervalo resolvido e filtros normalizados
+#[derive(Clone, Debug)]
+pub struct ConsultaValidada {
+    pub metrica: Metrica,
+    pub bucket: Bucket,
+    pub dimensao: Dimensao,
+    pub inicio: DateTime<Utc>,
+    pub fim: DateTime<Utc>,
+    pub fuso_horario: String,
+    pub tipo: Option<String>,
+    pub categorias: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+    pub plataforma: Option<String>,
+}
+
+fn fuso_valido(fuso: &str) -> bool {
+    !fuso.is_empty()
+        && fuso.len() <= 64
+        && fuso.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'))
+}
+
+/// Valida combinação de métrica/dimensão/filtros e os limites de tamanho da consulta
+pub fn validar_consulta(q: &AnalyticsQuery, agora: DateTime<Utc>) -> Result<ConsultaValidada, String> {
+    let fim = q.filtros.data_fim.unwrap_or(agora);
+    let inicio = q.filtros.data_inicio.unwrap_or(fim - Duration::days(30));
+    if inicio > fim {
+        return Err("data_inicio deve ser anterior a data_fim".to_string());
+    }
+    let dias = (fim - inicio).num_days() + 1;
+    if dias > MAX_DIAS_INTERVALO {
+        return Err(format!("Intervalo máximo de {MAX_DIAS_INTERVALO} dias"));
+    }
+    if q.bucket == Bucket::Hour && dias > MAX_DIAS_BUCKET_HORA {
+        return Err(format!("Com bucket por hora o intervalo máximo é de {MAX_DIAS_BUCKET_HORA} dias"));
+    }
+
+    if q.metrica.usa_sessoes() && q.agrupar_por.so_transacoes() {
+        return Err("Horas e R$/h não podem ser agrupados por categoria ou tipo".to_string());
+    }
+    let tipo = q.filtros.tipo.clone().filter(|t| !t.trim().is_empty());
+    if let Some(ref t) = tipo {
+        if t != "entrada" && t != "saida" {
+            return Err(format!("Tipo inválido: {t}"));
+        }
+    }
+    let categorias = q.filtros.categorias.clone().filter(|c| !c.is_empty());
+    if let Some(ref cats) = categorias {
+        if cats.len() > MAX_CATEGORIAS_FILTRO {
+            return Err(format!("Máximo de {MAX_CATEGORIAS_FILTRO} categorias no filtro"));
+        }
+    }
+    let tags = q.filtros.tags.clone().filter(|t| !t.is_empty());
+    if let Some(ref ids) = tags {
+        if ids.len() > MAX_CATEGORIAS_FILTRO {
+            return Err(format!("Máximo de {MAX_CATEGORIAS_FILTRO} tags no filtro"));
+        }
+    }
+    if q.metrica.usa_sessoes() && (tipo.is_some() || categorias.is_some() || tags.is_some()) {
+        return Err("Filtros de tipo/categoria/tag não se aplicam a horas e R$/h".to_string());
+    }
+
+    let fuso_horario = q.fuso_horario.clone().unwrap_or_else(|| "UTC".to_string());
+    if !fuso_valido(&fuso_horario) {
+        return Err(format!("Fuso horário inválido: {fuso_horario}"));
+    }
+
+    Ok(ConsultaValidada {
+        metrica: q.metrica,
+        bucket: q.bucket,
+        dimensao: q.agrupar_por,
+        inicio,
+        fim,
+        fuso_horario,
+        tipo,
+        categorias,
+        tags,
+        plataforma: q.filtros.plataforma.clone().filter(|p| !p.trim().is_empty()),
+    })
+}
+
+/// Monta o SQL da consulta. Os parâmetros são sempre, nesta ordem:
+/// $1 usuário, $2 início, $3 fim, $4 fuso e depois tipo, categorias, tags e plataforma (se presentes).
+pub fn montar_sql(c: &ConsultaValidada) -> String {
+    let mut proximo = 5;
+    let mut filtros_tx = String::new();
+    let mut filtros_ss = String::new();
+    if c.tipo.is_some() {
+        filtros_tx.push_str(&format!(" AND t.tipo = ${proximo}"));
+        proximo += 1;
+    }
+    if c.categorias.is_some() {
+        filtros_tx.push_str(&format!(" AND p.id_categoria = ANY(${proximo})"));
+        proximo += 1;
+    }
+    if c.tags.is_some() {
+        filtros_tx.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM transacoes_tags tf WHERE tf.id_transacao = t.id AND tf.id_tag = ANY(${proximo}))"
+        ));
+        proximo += 1;
+    }
+    if c.plataforma.is_some() {
+        filtros_tx.push_str(&format!(" AND s.plataforma = ${proximo}"));
+        filtros_ss.push_str(&format!(" AND s.plataforma = ${proximo}"));
+    }
+
+    let bucket_tx = c.bucket.expressao_sql("(t.data AT TIME ZONE $4)");
+    // Sessões são fatiadas por hora para que horas trabalhadas caiam no bucket certo
+    // (uma sessão das 10h às 18h contribui para oito buckets de hora, não só para o das 10h)
+    let bucket_ss = c.bucket.expressao_sql("(h.hora AT TIME ZONE $4)");
+    let dim_tx = c.dimensao.expressao_transacoes();
+    let dim_ss = c.dimensao.expressao_sessoes();
+    // Só agrupando por tag a transação é repetida para cada tag
+    let join_tags = if c.dimensao == Dimensao::Tag {
+        "LEFT JOIN transacoes_tags tt ON tt.id_transacao = t.id
+            LEFT JOIN tags tg ON tg.id = tt.id_tag"
+    } else {
+        ""
+    };
+
+    let cte_tx = format!(
+        "tx AS (
+            SELECT {bucket_tx} AS bucket, {dim_tx} AS grupo,
+                SUM(CASE WHEN t.tipo = 'entrada' THEN p.valor ELSE 0 END)::float8 AS ganhos,
+                SUM(CASE WHEN t.tipo = 'saida' THEN p.valor ELSE 0 END)::float8 AS gastos,
+                SUM(CASE WHEN t.tipo = 'entrada' AND p.principal THEN t.eventos ELSE 0 END)::float8 AS eventos,
+                SUM(CASE WHEN p.principal THEN COALESCE(t.km, 0) ELSE 0 END)::float8 AS km,
+                COUNT(DISTINCT t.id) AS amostras
+            FROM transacoes t
+            {PARTES_TRANSACAO}
+            LEFT JOIN categorias c ON c.id = p.id_categoria
+            {join_tags}
+            LEFT JOIN LATERAL (
+                SELECT s.plataforma, s.clima, s.local_inicio
+                FROM sessoes_trabalho s
+                WHERE s.id_usuario = t.id_usuario AND s.excluido_em IS NULL
+                  AND (s.id = t.id_sessao
+                       OR (t.id_sessao IS NULL AND t.data >= s.inicio AND t.data <= COALESCE(s.fim, NOW())))
+                ORDER BY s.inicio DESC
+                LIMIT 1
+            ) s ON TRUE
+            WHERE t.id_usuario = $1 AND t.excluido_em IS NULL AND t.data >= $2 AND t.data <= $3{filtros_tx}
+            GROUP BY 1, 2
+        )"
+    );
+    let metrica = c.metrica.expressao_sql();
+    let limite = MAX_LINHAS + 1;
+
+    if c.metrica.usa_sessoes() {
+        format!(
+            "WITH {cte_tx},
+            ss AS (
+                SELECT {bucket_ss} AS bucket, {dim_ss} AS grupo,
+                    SUM(EXTRACT(EPOCH FROM LEAST(h.hora + INTERVAL '1 hour', COALESCE(s.fim, NOW())) - GREATEST(h.hora, s.inicio)))::float8 / 3600.0 AS horas,
+                    COUNT(DISTINCT s.id) AS amostras
+                FROM sessoes_trabalho s
+                CROSS JOIN LATERAL generate_series(date_trunc('hour', s.inicio), COALESCE(s.fim, NOW()), INTERVAL '1 hour') AS h(hora)
+                WHERE s.id_usuario = $1 AND s.excluido_em IS NULL AND s.inicio >= $2 AND s.inicio <= $3
+                  AND COALESCE(s.fim, NOW()) > s.inicio{filtros_ss}
+                GROUP BY 1, 2
+            )
+            SELECT COALESCE(tx.bucket, ss.bucket) AS bucket,
+                COALESCE(tx.grupo, ss.grupo) AS grupo,
+                ({metrica})::float8 AS valor,
+                COALESCE(ss.amostras, 0) AS amostras
+            FROM tx FULL OUTER JOIN ss ON tx.bucket = ss.bucket AND tx.grupo = ss.grupo
+            ORDER BY 1, 2
+            LIMIT {limite}"
+        )
+    } else {
+        format!(
+            "WITH {cte_tx}
+            SELECT tx.bucket AS bucket, tx.grupo AS grupo, ({metrica})::float8 AS valor, tx.amostras AS amostras
+            FROM tx
+            ORDER BY 1, 2
+            LIMIT {limite}"
+        )
+    }
+}
+
+/// Monta a visão pivotada (uma linha por bucket, uma coluna por grupo)
+pub fn pivotar(linhas: &[AnalyticsLinha]) -> (Vec<String>, Vec<AnalyticsPivotLinha>) {
+    let grupos: BTreeSet<String> = linhas.iter().map(|l| l.grupo.clone()).collect();
+    let mut por_bucket: BTreeMap<String, BTreeMap<String, Option<f64>>> = BTreeMap::new();
+    for l in linhas {
+        por_bucket.entry(l.bucket.clone()).or_default().insert(l.grupo.clone(), l.valor);
+    }
+    let pivot = por_bucket
+        .into_iter()
+        .map(|(bucket, mut valores)| {
+            for g in &grupos {
+                valores.entry(g.clone()).or_insert(None);
+            }
+            AnalyticsPivotLinha { bucket, valores }
+        })
+        .collect();
+    (grupos.into_iter().collect(), pivot)
+}
+
+pub fn executar_consulta(conn: &mut PgConnection, id_usuario: &str, c: &ConsultaValidada) -> Result<AnalyticsResultado, diesel::result::Error> {
+    let sql = montar_sql(c);
+    let mut query = diesel::sql_query(sql)
+        .into_boxed::<Pg>()
+        .bind::<Text, _>(id_usuario.to_string())
+        .bind::<Timestamptz, _>(c.inicio)
+        .bind::<Timestamptz, _>(c.fim)
+        .bind::<Text, _>(c.fuso_horario.clone());
+    if let Some(ref t) = c.tipo {
+        query = query.bind::<Text, _>(t.clone());
+    }
+    if let Some(ref cats) = c.categorias {
+        query = query.bind::<Array<Text>, _>(cats.clone());
+    }
+    if let Some(ref ids) = c.tags {
+        query = query.bind::<Array<Text>, _>(ids.clone());
+    }
+    if let Some(ref p) = c.plataforma {
+        query = query.bind::<Text, _>(p.clone());
+    }
+
+    let mut linhas: Vec<AnalyticsLinha> = query
+        .load::<LinhaSql>(conn)?
+        .into_iter()
+        .map(|l| AnalyticsLinha { bucket: l.bucket, grupo: l.grupo, valor: l.valor, amostras: l.amostras })
+        .collect();
+    let truncado = linhas.len() as i64 > MAX_LINHAS;
+    linhas.truncate(MAX_LINHAS as usize);
+    let (grupos, pivot) = pivotar(&linhas);
+
+    Ok(AnalyticsResultado {
+        metrica: c.metrica,
+        bucket: c.bucket,
+        agrupar_por: c.dimensao,
+        data_inicio: c.inicio,
+        data_fim: c.fim,
+        fuso_horario: c.fuso_horario.clone(),
+        grupos,
+        linhas,
+        pivot,
+        truncado,
+    })
+}
+
+fn fuso_existe(conn: &mut PgConnection, fuso: &str) -> bool {
+    #[derive(QueryableByName)]
+    struct Existe {
+        #[diesel(sql_type = BigInt)]
+        total: i64,
+    }
+    diesel::sql_query("SELECT COUNT(*) AS total FROM pg_timezone_names WHERE name = $1")
+        .bind::<Text, _>(fuso)
+        .get_result::<Existe>(conn)
+        .map(|r| r.total > 0)
+        .unwrap_or(false)
+}
+
+#[axum::debug_handler]
+pub async fn analytics_query_handler(
+    jar: CookieJar,
+    Json(payload): Json<AnalyticsQuery>,
+) -> Result<Json<AnalyticsResultado>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let consulta = validar_consulta(&payload, Utc::now()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    if !fuso_existe(conn, &consulta.fuso_horario) {
+        return Err((StatusCode::BAD_REQUEST, format!("Fuso horário desconhecido: {}", consulta.fuso_horario)));
+    }
+    executar_consulta(conn, &id_usuario, &consulta)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao executar consulta: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const METRICAS: [Metrica; 8] = [
+        Metrica::Ganhos, Metrica::Gastos, Metrica::Lucro, Metrica::Corridas,
+        Metrica::Km, Metrica::Horas, Metrica::RsPorHora, Metrica::RsPorKm,
+    ];
+    const BUCKETS: [Bucket; 6] = [Bucket::Hour, Bucket::Weekday, Bucket::Day, Bucket::Week, Bucket::Month, Bucket::Total];
+    const DIMENSOES: [Dimensao; 7] = [
+        Dimensao::Categoria, Dimensao::Plataforma, Dimensao::Tipo, Dimensao::Clima,
+        Dimensao::LocalInicio, Dimensao::Tag, Dimensao::Nenhuma,
+    ];
+    const HOSTIL: &str = "x'); DROP TABLE transacoes; --";
+
+    fn agora() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 9, 3, 12, 0, 0).unwrap()
+    }
+
+    fn consulta(json: serde_json::Value) -> Result<AnalyticsQuery, serde_json::Error> {
+        serde_json::from_value(json)
+    }
+
+    #[test]
+    fn test_whitelist_de_metricas_e_dimensoes() {
+        assert!(consulta(serde_json::json!({"metrica": "ganhos; DROP TABLE transacoes"})).is_err());
+        assert!(consulta(serde_json::json!({"metrica": "ganhos", "agrupar_por": "t.id_usuario"})).is_err());
+        assert!(consulta(serde_json::json!({"metrica": "ganhos", "bucket": "minuto"})).is_err());
+        assert!(consulta(serde_json::json!({"agrupar_por": "categoria"})).is_err());
+
+        // aliases em português caem nos mesmos enums
+        let q = consulta(serde_json::json!({"metrica": "eventos", "bucket": "dia_semana", "agrupar_por": "local_inicio"})).unwrap();
+        assert_eq!((q.metrica, q.bucket, q.agrupar_por), (Metrica::Corridas, Bucket::Weekday, Dimensao::LocalInicio));
+        let padrao = consulta(serde_json::json!({"metrica": "km"})).unwrap();
+        assert_eq!((padrao.bucket, padrao.agrupar_por), (Bucket::Day, Dimensao::Nenhuma));
+    }
+
+    #[test]
+    fn test_validacao_rejeita_combinacoes_invalidas() {
+        let valida = |json| validar_consulta(&consulta(json).unwrap(), agora());
+        assert!(valida(serde_json::json!({"metrica": "horas", "agrupar_por": "categoria"})).is_err());
+        assert!(valida(serde_json::json!({"metrica": "rs_por_hora", "filtros": {"tipo": "entrada"}})).is_err());
+        assert!(valida(serde_json::json!({"metrica": "ganhos", "filtros": {"tipo": HOSTIL}})).is_err());
+        assert!(valida(serde_json::json!({"metrica": "ganhos", "fuso_horario": "UTC'; --"})).is_err());
+        assert!(valida(serde_json::json!({"metrica": "ganhos", "bucket": "hora", "filtros": {"data_inicio": "2025-01-01T00:00:00Z"}})).is_err());
+        assert!(valida(serde_json::json!({"metrica": "ganhos", "filtros": {"data_inicio": "2025-09-10T00:00:00Z", "data_fim": "2025-09-01T00:00:00Z"}})).is_err());
+        let c = valida(serde_json::json!({"metrica": "ganhos", "fuso_horario": "America/Sao_Paulo", "filtros": {"tipo": " ", "categorias": []}})).unwrap();
+        assert_eq!((c.tipo, c.categorias, c.inicio), (None, None, agora() - Duration::days(30)));
+    }
+
+    #[test]
+    fn test_sql_so_usa_trechos_fixos_e_parametros() {
+        for metrica in METRICAS {
+            for bucket in BUCKETS {
+                for dimensao in DIMENSOES {
+                    let sessoes = metrica.usa_sessoes();
+                    if sessoes && dimensao.so_transacoes() {
+                        continue;
+                    }
+                    let c = ConsultaValidada {
+                        metrica,
+                        bucket,
+                        dimensao,
+                        inicio: agora() - Duration::days(7),
+                        fim: agora(),
+                        fuso_horario: "America/Sao_Paulo".to_string(),
+                        tipo: (!sessoes).then(|| HOSTIL.to_string()),
+                        categorias: (!sessoes).then(|| vec![HOSTIL.to_string()]),
+                        tags: (!sessoes).then(|| vec![HOSTIL.to_string()]),
+                        plataforma: Some(HOSTIL.to_string()),
+                    };
+                    let sql = montar_sql(&c);
+                    let contexto = format!("{metrica:?}/{bucket:?}/{dimensao:?}");
+                    // valores do usuário nunca entram no texto, só os trechos dos enums
+                    assert!(!sql.contains("DROP") && !sql.contains("Sao_Paulo"), "{contexto}");
+                    assert!(sql.contains(metrica.expressao_sql()), "{contexto}");
+                    assert!(sql.contains(dimensao.expressao_transacoes()), "{contexto}");
+                    assert!(sql.contains(&bucket.expressao_sql("(t.data AT TIME ZONE $4)")), "{contexto}");
+                    // parâmetros numerados na ordem dos binds de `executar_consulta`
+                    let binds = if sessoes { 5 } else { 8 };
+                    assert!(sql.contains(&format!("${binds}")) && !sql.contains(&format!("${}", binds + 1)), "{contexto}");
+                    assert_eq!(sql.contains("FULL OUTER JOIN ss"), sessoes, "{contexto}");
+                    assert!(sql.ends_with(&format!("LIMIT {}", MAX_LINHAS + 1)), "{contexto}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_numeracao_de_parametros_pula_filtros_ausentes() {
+        let c = ConsultaValidada {
+            metrica: Metrica::Ganhos,
+            bucket: Bucket::Day,
+            dimensao: Dimensao::Nenhuma,
+            inicio: agora() - Duration::days(7),
+            fim: agora(),
+            fuso_horario: "UTC".to_string(),
+            tipo: None,
+            categorias: None,
+            tags: Some(vec!["tag1".to_string()]),
+            plataforma: Some("Uber".to_string()),
+        };
+        let sql = montar_sql(&c);
+        assert!(sql.contains("tf.id_tag = ANY($5)"));
+        assert!(sql.contains("s.plataforma = $6"));
+        assert!(!sql.contains("$7") && !sql.contains("t.tipo = $") && !sql.contains("LEFT JOIN tags"));
+    }
+
+    fn linha(bucket: &str, grupo: &str, valor: Option<f64>) -> AnalyticsLinha {
+        AnalyticsLinha { bucket: bucket.to_string(), grupo: grupo.to_string(), valor, amostras: 1 }
+    }
+
+    #[test]
+    fn test_pivot_ordena_colunas_e_preenche_celulas_ausentes() {
+        let linhas = vec![
+            linha("2025-09-02", "Uber", Some(50.0)),
+            linha("2025-09-01", "Uber", Some(10.0)),
+            linha("2025-09-01", "99", None),
+            linha("2025-09-02", "Combustível", Some(7.5)),
+        ];
+        let (grupos, pivot) = pivotar(&linhas);
+        assert_eq!(grupos, vec!["99", "Combustível", "Uber"]);
+        assert_eq!(pivot.iter().map(|p| p.bucket.as_str()).collect::<Vec<_>>(), vec!["2025-09-01", "2025-09-02"]);
+        let celulas = |i: usize| pivot[i].valores.iter().map(|(g, v)| (g.as_str(), *v)).collect::<Vec<_>>();
+        assert_eq!(celulas(0), vec![("99", None), ("Combustível", None), ("Uber", Some(10.0))]);
+        assert_eq!(celulas(1), vec![("99", None), ("Combustível", Some(7.5)), ("Uber", Some(50.0))]);
+
+        let (grupos, pivot) = pivotar(&[]);
+        assert!(grupos.is_empty() && pivot.is_empty());
+    }
+}
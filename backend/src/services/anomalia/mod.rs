@@ -0,0 +1,495 @@
+//! Detecção de anomalias em `transacoes`
+//!
+//! Três detectores, todos sobre os últimos `HISTORICO_DIAS` dias e gerando alertas só
+//! para o que caiu na janela recente (`JANELA_DIAS`):
+//! - valor atípico: mediana/MAD por categoria (ex.: abastecimento 3× o normal);
+//! - corridas por hora baixa: dia com corridas/h muito abaixo da mediana do usuário;
+//! - possível duplicata: mesma categoria/valor/descrição lançada duas vezes em minutos.
+//!
+//! Os alertas ficam em `alertas_anomalia`; a `chave` de cada detecção impede que um alerta
+//! já confirmado ou descartado seja recriado na próxima execução.
+
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::warn;
+use crate::db;
+use crate::models::{AlertaAnomalia, NewAlertaAnomalia, Transacao};
+use crate::schema::alertas_anomalia::dsl as alerta_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::utils::relatorio::formatar_moeda;
+
+const HISTORICO_DIAS: i64 = 180;
+const JANELA_DIAS: i64 = 30;
+/// Amostras mínimas para estatística de uma categoria / dos dias trabalhados
+const MIN_AMOSTRAS: usize = 8;
+/// Limite do z-score robusto (Iglewicz & Hoaglin)
+const LIMITE_Z: f64 = 3.5;
+/// Com MAD zero (valores sempre iguais), atípico é o que passar desse múltiplo da mediana
+const MULTIPLO_SEM_DISPERSAO: f64 = 3.0;
+/// Valor precisa ser ao menos esse múltiplo da mediana, mesmo com z alto
+const MULTIPLO_MINIMO: f64 = 1.5;
+const MINUTOS_DUPLICATA: i64 = 10;
+/// Dias com menos tempo de sessão que isso não entram no cálculo de corridas/h
+const MIN_MINUTOS_DIA: f64 = 60.0;
+const LIMITE_LISTAGEM: i64 = 200;
+
+pub const TIPO_VALOR_ATIPICO: &str = "valor_atipico";
+pub const TIPO_CORRIDAS_POR_HORA: &str = "corridas_por_hora_baixa";
+pub const TIPO_DUPLICATA: &str = "possivel_duplicata";
+pub const STATUS_PENDENTE: &str = "pendente";
+const STATUS_VALIDOS: [&str; 3] = ["pendente", "confirmado", "descartado"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deteccao {
+    pub tipo: &'static str,
+    pub chave: String,
+    pub id_transacao: Option<String>,
+    pub id_transacao_relacionada: Option<String>,
+    pub dia: NaiveDate,
+    pub descricao: String,
+    pub valor_observado: Option<f64>,
+    pub valor_esperado: Option<f64>,
+    pub pontuacao: Option<f64>,
+}
+
+/// Corridas e minutos trabalhados de um dia
+#[derive(Clone, Copy, Debug)]
+pub struct DiaProdutividade {
+    pub dia: NaiveDate,
+    pub corridas: i64,
+    pub minutos: f64,
+}
+
+fn mediana(v: &mut [f64]) -> f64 {
+    v.sort_by(|a, b| a.total_cmp(b));
+    let n = v.len();
+    if n % 2 == 1 { v[n / 2] } else { (v[n / 2 - 1] + v[n / 2]) / 2.0 }
+}
+
+/// Mediana e desvio absoluto mediano (MAD)
+pub fn mediana_mad(valores: &[f64]) -> Option<(f64, f64)> {
+    if valores.is_empty() {
+        return None;
+    }
+    let mut v = valores.to_vec();
+    let med = mediana(&mut v);
+    let mut desvios: Vec<f64> = valores.iter().map(|x| (x - med).abs()).collect();
+    Some((med, mediana(&mut desvios)))
+}
+
+/// z-score robusto; `None` quando o MAD é zero
+fn z_robusto(x: f64, med: f64, mad: f64) -> Option<f64> {
+    (mad > 0.0).then(|| 0.6745 * (x - med) / mad)
+}
+
+fn arred(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Transações muito acima do valor típico da sua categoria (mesmo tipo)
+pub fn detectar_valores_atipicos(transacoes: &[Transacao], inicio_janela: DateTime<Utc>, nomes: &HashMap<String, String>) -> Vec<Deteccao> {
+    let mut por_categoria: BTreeMap<(&str, &str), Vec<&Transacao>> = BTreeMap::new();
+    for t in transacoes.iter().filter(|t| t.valor > 0) {
+        por_categoria.entry((t.id_categoria.as_str(), t.tipo.as_str())).or_default().push(t);
+    }
+    let mut deteccoes = Vec::new();
+    for ((categoria, _), lista) in por_categoria {
+        if lista.len() < MIN_AMOSTRAS {
+            continue;
+        }
+        let valores: Vec<f64> = lista.iter().map(|t| t.valor as f64).collect();
+        let Some((med, mad)) = mediana_mad(&valores) else { continue };
+        if med <= 0.0 {
+            continue;
+        }
+        let nome = nomes.get(categoria).map(String::as_str).unwrap_or(categoria);
+        for t in lista.iter().filter(|t| t.data >= inicio_janela) {
+            let x = t.valor as f64;
+            let pontuacao = match z_robusto(x, med, mad) {
+                Some(z) if z > LIMITE_Z && x >= med * MULTIPLO_MINIMO => z,
+                None if x >= med * MULTIPLO_SEM_DISPERSAO => x / med,
+                _ => continue,
+            };
+            deteccoes.push(Deteccao {
+                tipo: TIPO_VALOR_ATIPICO,
+                chave: format!("valor:{}", t.id),
+                id_transacao: Some(t.id.clone()),
+                id_transacao_relacionada: None,
+                dia: t.data.date_naive(),
+                descricao: format!(
+                    "{nome}: {} é {:.1}× o valor típico ({})",
+                    formatar_moeda(t.valor),
+                    x / med,
+                    formatar_moeda(med.round() as i32)
+                ),
+                valor_observado: Some(x),
+                valor_esperado: Some(med),
+                pontuacao: Some(arred(pontuacao)),
+            });
+        }
+    }
+    deteccoes
+}
+
+/// Dias recentes com corridas por hora muito abaixo da mediana dos dias trabalhados
+pub fn detectar_corridas_por_hora(dias: &[DiaProdutividade], inicio_janela: NaiveDate) -> Vec<Deteccao> {
+    let validos: Vec<(NaiveDate, f64)> = dias
+        .iter()
+        .filter(|d| d.minutos >= MIN_MINUTOS_DIA)
+        .map(|d| (d.dia, d.corridas as f64 * 60.0 / d.minutos))
+        .collect();
+    if validos.len() < MIN_AMOSTRAS {
+        return Vec::new();
+    }
+    let taxas: Vec<f64> = validos.iter().map(|(_, t)| *t).collect();
+    let Some((med, mad)) = mediana_mad(&taxas) else { return Vec::new() };
+    if med <= 0.0 {
+        return Vec::new();
+    }
+    validos
+        .iter()
+        .filter(|(dia, _)| *dia >= inicio_janela)
+        .filter_map(|(dia, x)| {
+            let pontuacao = match z_robusto(*x, med, mad) {
+                Some(z) if z < -LIMITE_Z && *x <= med / MULTIPLO_MINIMO => z,
+                None if *x <= med / MULTIPLO_SEM_DISPERSAO => x / med,
+                _ => return None,
+            };
+            Some(Deteccao {
+                tipo: TIPO_CORRIDAS_POR_HORA,
+                chave: format!("corridas_hora:{dia}"),
+                id_transacao: None,
+                id_transacao_relacionada: None,
+                dia: *dia,
+                descricao: format!("{}: {:.2} corridas/h, abaixo do normal ({:.2} corridas/h)", dia.format("%d/%m/%Y"), x, med),
+                valor_observado: Some(arred(*x)),
+                valor_esperado: Some(arred(med)),
+                pontuacao: Some(arred(pontuacao)),
+            })
+        })
+        .collect()
+}
+
+fn descricao_normalizada(t: &Transacao) -> String {
+    t.descricao.as_deref().unwrap_or("").trim().to_lowercase()
+}
+
+/// Pares com mesmo tipo, categoria, valor, km e descrição lançados com poucos minutos de diferença
+pub fn detectar_duplicatas(transacoes: &[Transacao], inicio_janela: DateTime<Utc>) -> Vec<Deteccao> {
+    let mut ordenadas: Vec<&Transacao> = transacoes.iter().collect();
+    ordenadas.sort_by_key(|t| t.data);
+    let limite = Duration::minutes(MINUTOS_DUPLICATA);
+    let mut deteccoes = Vec::new();
+    for (i, a) in ordenadas.iter().enumerate() {
+        for b in ordenadas[i + 1..].iter().take_while(|b| b.data - a.data <= limite) {
+            if b.data < inicio_janela
+                || a.tipo != b.tipo
+                || a.id_categoria != b.id_categoria
+                || a.valor != b.valor
+                || a.km != b.km
+                || descricao_normalizada(a) != descricao_normalizada(b)
+            {
+                continue;
+            }
+            let (x, y) = if a.id < b.id { (a, b) } else { (b, a) };
+            let minutos = (b.data - a.data).num_minutes();
+            deteccoes.push(Deteccao {
+                tipo: TIPO_DUPLICATA,
+                chave: format!("duplicata:{}:{}", x.id, y.id),
+                id_transacao: Some(y.id.clone()),
+                id_transacao_relacionada: Some(x.id.clone()),
+                dia: b.data.date_naive(),
+                descricao: format!("Possível lançamento duplicado: {} registrado duas vezes com {minutos} min de diferença", formatar_moeda(a.valor)),
+                valor_observado: Some(a.valor as f64),
+                valor_esperado: None,
+                pontuacao: None,
+            });
+        }
+    }
+    deteccoes
+}
+
+fn carregar_produtividade(conn: &mut PgConnection, id_usuario: &str, transacoes: &[Transacao], inicio: DateTime<Utc>) -> Vec<DiaProdutividade> {
+    use crate::schema::sessoes_trabalho::dsl as sessao_dsl;
+    let sessoes: Vec<(DateTime<Utc>, Option<i32>)> = sessao_dsl::sessoes_trabalho
+        .filter(sessao_dsl::id_usuario.eq(id_usuario))
+        .filter(sessao_dsl::excluido_em.is_null())
+        .filter(sessao_dsl::inicio.ge(inicio))
+        .filter(sessao_dsl::eh_ativa.eq(false))
+        .select((sessao_dsl::inicio, sessao_dsl::total_minutos))
+        .load(conn)
+        .unwrap_or_default();
+    let mut por_dia: BTreeMap<NaiveDate, (i64, f64)> = BTreeMap::new();
+    for (inicio_sessao, minutos) in sessoes {
+        por_dia.entry(inicio_sessao.date_naive()).or_default().1 += minutos.unwrap_or(0) as f64;
+    }
+    for t in transacoes.iter().filter(|t| t.tipo == "entrada") {
+        if let Some(d) = por_dia.get_mut(&t.data.date_naive()) {
+            d.0 += t.eventos as i64;
+        }
+    }
+    por_dia
+        .into_iter()
+        .map(|(dia, (corridas, minutos))| DiaProdutividade { dia, corridas, minutos })
+        .collect()
+}
+
+/// Roda todos os detectores sobre o histórico do usuário
+pub fn detectar(conn: &mut PgConnection, id_usuario: &str, agora: DateTime<Utc>) -> Vec<Deteccao> {
+    use crate::schema::transacoes::dsl as transacao_dsl;
+    use crate::schema::categorias::dsl as categoria_dsl;
+    let inicio = agora - Duration::days(HISTORICO_DIAS);
+    let inicio_janela = agora - Duration::days(JANELA_DIAS);
+    let transacoes: Vec<Transacao> = transacao_dsl::transacoes
+        .filter(transacao_dsl::id_usuario.eq(id_usuario))
+        .filter(transacao_dsl::excluido_em.is_null())
+        .filter(transacao_dsl::data.ge(inicio))
+        .load(conn)
+        .unwrap_or_default();
+    let nomes: HashMap<String, String> = categoria_dsl::categorias
+        .select((categoria_dsl::id, categoria_dsl::nome))
+        .filter(categoria_dsl::id_usuario.eq(id_usuario).or(categoria_dsl::id_usuario.is_null()))
+        .filter(categoria_dsl::excluido_em.is_null())
+        .load::<(String, String)>(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    let produtividade = carregar_produtividade(conn, id_usuario, &transacoes, inicio);
+    let mut deteccoes = detectar_valores_atipicos(&transacoes, inicio_janela, &nomes);
+    deteccoes.extend(detectar_corridas_por_hora(&produtividade, inicio_janela.date_naive()));
+    deteccoes.extend(detectar_duplicatas(&transacoes, inicio_janela));
+    deteccoes
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ResumoDeteccao {
+    pub novos: usize,
+    /// Pendentes que deixaram de ser anomalia (ex.: transação corrigida)
+    pub removidos: usize,
+    pub pendentes: i64,
+}
+
+impl ResumoDeteccao {
+    /// A contagem de pendentes do dashboard vem dos dados fixos do razão e precisa ser recarregada
+    pub fn alterou_pendentes(&self) -> bool {
+        self.novos + self.removidos > 0
+    }
+}
+
+/// Grava as detecções novas e remove alertas pendentes da janela que não se confirmam mais.
+/// Alertas confirmados/descartados nunca são alterados.
+pub fn sincronizar_alertas(conn: &mut PgConnection, id_usuario: &str, agora: DateTime<Utc>) -> Result<ResumoDeteccao, diesel::result::Error> {
+    let deteccoes = detectar(conn, id_usuario, agora);
+    let inicio_janela = (agora - Duration::days(JANELA_DIAS)).date_naive();
+    conn.transaction(|conn| {
+        let novos_alertas: Vec<NewAlertaAnomalia> = deteccoes
+            .iter()
+            .map(|d| NewAlertaAnomalia {
+                id: ulid::Ulid::new().to_string(),
+                id_usuario: id_usuario.to_string(),
+                tipo: d.tipo.to_string(),
+                chave: d.chave.clone(),
+                id_transacao: d.id_transacao.clone(),
+                id_transacao_relacionada: d.id_transacao_relacionada.clone(),
+                dia: d.dia,
+                descricao: d.descricao.clone(),
+                valor_observado: d.valor_observado,
+                valor_esperado: d.valor_esperado,
+                pontuacao: d.pontuacao,
+                status: STATUS_PENDENTE.to_string(),
+                criado_em: agora,
+                atualizado_em: agora,
+            })
+            .collect();
+        let novos = diesel::insert_into(alerta_dsl::alertas_anomalia)
+            .values(&novos_alertas)
+            .on_conflict((alerta_dsl::id_usuario, alerta_dsl::chave))
+            .do_nothing()
+            .execute(conn)?;
+
+        let chaves: HashSet<&str> = deteccoes.iter().map(|d| d.chave.as_str()).collect();
+        let obsoletos: Vec<String> = alerta_dsl::alertas_anomalia
+            .filter(alerta_dsl::id_usuario.eq(id_usuario))
+            .filter(alerta_dsl::status.eq(STATUS_PENDENTE))
+            .filter(alerta_dsl::dia.ge(inicio_janela))
+            .select((alerta_dsl::id, alerta_dsl::chave))
+            .load::<(String, String)>(conn)?
+            .into_iter()
+            .filter(|(_, chave)| !chaves.contains(chave.as_str()))
+            .map(|(id, _)| id)
+            .collect();
+        let removidos = diesel::delete(alerta_dsl::alertas_anomalia.filter(alerta_dsl::id.eq_any(&obsoletos))).execute(conn)?;
+
+        Ok(ResumoDeteccao { novos, removidos, pendentes: contar_pendentes(conn, id_usuario) })
+    })
+}
+
+/// Reexecuta a detecção fora do caminho da requisição (após criar/editar/excluir transação)
+pub fn agendar_deteccao(id_usuario: String) {
+    if id_usuario.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let usuario = id_usuario.clone();
+        let resumo = tokio::task::spawn_blocking(move || {
+            let conn = &mut db::establish_connection();
+            sincronizar_alertas(conn, &usuario, Utc::now())
+        })
+        .await;
+        match resumo {
+            Ok(Ok(resumo)) if resumo.alterou_pendentes() => {
+                eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Falha ao detectar anomalias para {}: {}", id_usuario, e),
+            Err(e) => warn!("Falha ao detectar anomalias para {}: {}", id_usuario, e),
+        }
+    });
+}
+
+pub fn contar_pendentes(conn: &mut PgConnection, id_usuario: &str) -> i64 {
+    alerta_dsl::alertas_anomalia
+        .filter(alerta_dsl::id_usuario.eq(id_usuario))
+        .filter(alerta_dsl::status.eq(STATUS_PENDENTE))
+        .count()
+        .get_result(conn)
+        .unwrap_or(0)
+}
+
+#[derive(Deserialize)]
+pub struct ListarAlertasQuery {
+    /// "pendente" (padrão), "confirmado", "descartado" ou "todos"
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AtualizarAlertaPayload {
+    pub status: String,
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+pub async fn listar_alertas_handler(
+    jar: CookieJar,
+    Query(q): Query<ListarAlertasQuery>,
+) -> Result<Json<Vec<AlertaAnomalia>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let status = q.status.unwrap_or_else(|| STATUS_PENDENTE.to_string());
+    if status != "todos" && !STATUS_VALIDOS.contains(&status.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("Status inválido: {status}")));
+    }
+    let conn = &mut db::establish_connection();
+    let mut query = alerta_dsl::alertas_anomalia
+        .filter(alerta_dsl::id_usuario.eq(&id_usuario))
+        .into_boxed();
+    if status != "todos" {
+        query = query.filter(alerta_dsl::status.eq(status));
+    }
+    query
+        .order((alerta_dsl::dia.desc(), alerta_dsl::criado_em.desc()))
+        .limit(LIMITE_LISTAGEM)
+        .load::<AlertaAnomalia>(conn)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao listar alertas: {e}")))
+}
+
+pub async fn detectar_anomalias_handler(jar: CookieJar) -> Result<Json<ResumoDeteccao>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let resumo = sincronizar_alertas(conn, &id_usuario, Utc::now())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao detectar anomalias: {e}")))?;
+    if resumo.alterou_pendentes() {
+        eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    }
+    Ok(Json(resumo))
+}
+
+/// Confirma, descarta ou reabre um alerta do usuário
+pub async fn atualizar_alerta_handler(
+    jar: CookieJar,
+    Path(id_alerta): Path<String>,
+    Json(payload): Json<AtualizarAlertaPayload>,
+) -> Result<Json<AlertaAnomalia>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    if !STATUS_VALIDOS.contains(&payload.status.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, format!("Status inválido: {}", payload.status)));
+    }
+    let conn = &mut db::establish_connection();
+    let erro = |e: diesel::result::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao atualizar alerta: {e}"));
+    let do_usuario = alerta_dsl::alertas_anomalia
+        .filter(alerta_dsl::id.eq(&id_alerta))
+        .filter(alerta_dsl::id_usuario.eq(&id_usuario));
+    let status_anterior = do_usuario
+        .select(alerta_dsl::status)
+        .first::<String>(conn)
+        .optional()
+        .map_err(erro)?
+        .ok_or((StatusCode::NOT_FOUND, "Alerta não encontrado".to_string()))?;
+    let alerta = diesel::update(do_usuario)
+        .set((alerta_dsl::status.eq(&payload.status), alerta_dsl::atualizado_em.eq(Utc::now())))
+        .get_result::<AlertaAnomalia>(conn)
+        .map_err(erro)?;
+    // entrar ou sair de pendente muda a contagem do dashboard
+    if (status_anterior == STATUS_PENDENTE) != (alerta.status == STATUS_PENDENTE) {
+        eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    }
+    Ok(Json(alerta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tx(id: &str, categoria: &str, valor: i32, data: DateTime<Utc>) -> Transacao {
+        Transacao {
+            id: id.to_string(),
+            id_usuario: "u".to_string(),
+            id_categoria: categoria.to_string(),
+            valor,
+            eventos: 1,
+            km: None,
+            descricao: None,
+            tipo: "saida".to_string(),
+            data,
+            criado_em: data,
+            atualizado_em: data,
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
+        }
+    }
+
+    #[test]
+    fn test_abastecimento_tres_vezes_o_normal() {
+        let base = Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap();
+        let mut lista: Vec<Transacao> = (0..10)
+            .map(|i| tx(&format!("t{i}"), "comb", 10000 + (i % 3) * 500, base + Duration::days(i as i64)))
+            .collect();
+        lista.push(tx("caro", "comb", 31000, base + Duration::days(11)));
+        let d = detectar_valores_atipicos(&lista, base, &HashMap::new());
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].id_transacao.as_deref(), Some("caro"));
+    }
+
+    #[test]
+    fn test_duplicata_em_minutos() {
+        let base = Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap();
+        let lista = vec![
+            tx("a", "comb", 5000, base),
+            tx("b", "comb", 5000, base + Duration::minutes(3)),
+            tx("c", "comb", 5000, base + Duration::minutes(40)),
+        ];
+        let d = detectar_duplicatas(&lista, base);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].chave, "duplicata:a:b");
+    }
+}
@@ -0,0 +1,262 @@
+//! Busca textual em transações, sessões e metas
+//!
+//! Usa a configuração `portugues_unaccent` (português com remoção de acentos) criada na
+//! migração da busca. As expressões `to_tsvector(...)` daqui são as mesmas dos índices GIN,
+//! senão o Postgres não os usa. Os resultados das três tabelas vêm numa única lista,
+//! ordenada por relevância.
+
+use axum::{Json, extract::Query, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+const LIMITE_PADRAO: i64 = 20;
+const MAX_LIMITE: i64 = 100;
+/// Opções do `ts_headline`: trechos curtos com os termos encontrados entre os marcadores
+/// chr(2)/chr(3), trocados por `<mark>` só depois de escapar o texto (ver `destacar`)
+const OPCOES_DESTAQUE: &str = "'StartSel=' || chr(2) || ', StopSel=' || chr(3) || \
+     ', MaxWords=20, MinWords=8, MaxFragments=2, FragmentDelimiter=\" … \"'";
+const MARCA_INICIO: char = '\u{2}';
+const MARCA_FIM: char = '\u{3}';
+
+const VETOR_TRANSACAO: &str = "to_tsvector('portugues_unaccent', COALESCE(t.descricao, ''))";
+const VETOR_SESSAO: &str = "to_tsvector('portugues_unaccent', COALESCE(s.observacoes, ''))";
+const VETOR_META: &str = "(setweight(to_tsvector('portugues_unaccent', m.titulo), 'A') || \
+     setweight(to_tsvector('portugues_unaccent', COALESCE(m.descricao, '')), 'B'))";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoResultado {
+    Transacao,
+    Sessao,
+    Meta,
+}
+
+impl TipoResultado {
+    const TODOS: [TipoResultado; 3] = [TipoResultado::Transacao, TipoResultado::Sessao, TipoResultado::Meta];
+
+    fn nome(self) -> &'static str {
+        match self {
+            TipoResultado::Transacao => "transacao",
+            TipoResultado::Sessao => "sessao",
+            TipoResultado::Meta => "meta",
+        }
+    }
+
+    /// SELECT desta tabela; $1 = usuário, $2 = consulta, $3/$4 = período
+    fn subconsulta(self) -> String {
+        let (tabela, alias, vetor, titulo, texto, data, valor) = match self {
+            TipoResultado::Transacao => (
+                "transacoes",
+                "t",
+                VETOR_TRANSACAO,
+                "COALESCE(t.descricao, '')",
+                "COALESCE(t.descricao, '')",
+                "t.data",
+                "t.valor",
+            ),
+            TipoResultado::Sessao => (
+                "sessoes_trabalho",
+                "s",
+                VETOR_SESSAO,
+                "COALESCE(s.plataforma, 'Sessão de trabalho')",
+                "COALESCE(s.observacoes, '')",
+                "s.inicio",
+                "s.total_ganhos",
+            ),
+            TipoResultado::Meta => (
+                "metas",
+                "m",
+                VETOR_META,
+                "m.titulo",
+                "concat_ws(' — ', m.titulo, m.descricao)",
+                "m.data_inicio",
+                "m.valor_alvo",
+            ),
+        };
+        format!(
+            "SELECT '{tipo}'::text AS tipo, {alias}.id AS id, {titulo} AS titulo, \
+                ts_headline('portugues_unaccent', translate({texto}, chr(2) || chr(3), ''), q.consulta, {OPCOES_DESTAQUE}) AS trecho, \
+                ts_rank({vetor}, q.consulta)::float8 AS relevancia, \
+                {data} AS data, {valor} AS valor \
+             FROM {tabela} {alias}, q \
+             WHERE {alias}.id_usuario = $1 AND {alias}.excluido_em IS NULL AND {vetor} @@ q.consulta \
+               AND ($3::timestamptz IS NULL OR {data} >= $3) \
+               AND ($4::timestamptz IS NULL OR {data} <= $4)",
+            tipo = self.nome(),
+        )
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct BuscaParams {
+    /// Texto livre; aceita a sintaxe do `websearch_to_tsquery` ("frase exata", -excluir, or)
+    pub q: String,
+    /// Lista separada por vírgula: transacao, sessao, meta (padrão: todos)
+    pub tipos: Option<String>,
+    pub data_inicio: Option<DateTime<Utc>>,
+    pub data_fim: Option<DateTime<Utc>>,
+    pub limite: Option<i64>,
+}
+
+#[derive(QueryableByName, Serialize, Clone, Debug)]
+pub struct ResultadoBusca {
+    #[diesel(sql_type = Text)]
+    pub tipo: String,
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub titulo: String,
+    /// Trecho em HTML escapado, com os termos encontrados marcados com `<mark>`
+    #[diesel(sql_type = Text)]
+    pub trecho: String,
+    #[diesel(sql_type = Double)]
+    pub relevancia: f64,
+    /// Data da transação, início da sessão ou início da meta
+    #[diesel(sql_type = Timestamptz)]
+    pub data: DateTime<Utc>,
+    /// Valor da transação, ganhos da sessão ou alvo da meta (centavos)
+    #[diesel(sql_type = Integer)]
+    pub valor: i32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RespostaBusca {
+    pub consulta: String,
+    pub resultados: Vec<ResultadoBusca>,
+}
+
+/// Tipos pedidos em `tipos` (vazio ou ausente = todos)
+pub fn tipos_pedidos(tipos: Option<&str>) -> Result<Vec<TipoResultado>, String> {
+    let Some(tipos) = tipos.map(str::trim).filter(|t| !t.is_empty()) else {
+        return Ok(TipoResultado::TODOS.to_vec());
+    };
+    let mut pedidos = Vec::new();
+    for nome in tipos.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let tipo = TipoResultado::TODOS
+            .into_iter()
+            .find(|t| t.nome() == nome)
+            .ok_or_else(|| format!("Tipo de resultado inválido: {nome}"))?;
+        if !pedidos.contains(&tipo) {
+            pedidos.push(tipo);
+        }
+    }
+    Ok(pedidos)
+}
+
+/// Escapa o trecho do `ts_headline` (texto do usuário) e troca os marcadores por `<mark>`
+pub fn destacar(trecho: &str) -> String {
+    let mut html = String::with_capacity(trecho.len());
+    for c in trecho.chars() {
+        match c {
+            MARCA_INICIO => html.push_str("<mark>"),
+            MARCA_FIM => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+pub fn buscar(
+    conn: &mut PgConnection,
+    id_usuario: &str,
+    consulta: &str,
+    tipos: &[TipoResultado],
+    periodo: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    limite: i64,
+) -> QueryResult<Vec<ResultadoBusca>> {
+    if tipos.is_empty() {
+        return Ok(Vec::new());
+    }
+    let partes: Vec<String> = tipos.iter().map(|t| t.subconsulta()).collect();
+    let sql = format!(
+        "WITH q AS (SELECT websearch_to_tsquery('portugues_unaccent', $2) AS consulta) \
+         SELECT * FROM ({}) r \
+         ORDER BY relevancia DESC, data DESC \
+         LIMIT $5",
+        partes.join(" UNION ALL ")
+    );
+    diesel::sql_query(sql)
+        .bind::<Text, _>(id_usuario)
+        .bind::<Text, _>(consulta)
+        .bind::<Nullable<Timestamptz>, _>(periodo.0)
+        .bind::<Nullable<Timestamptz>, _>(periodo.1)
+        .bind::<BigInt, _>(limite)
+        .load::<ResultadoBusca>(conn)
+        .map(|resultados| {
+            resultados
+                .into_iter()
+                .map(|r| ResultadoBusca { trecho: destacar(&r.trecho), ..r })
+                .collect()
+        })
+}
+
+pub async fn busca_handler(
+    jar: CookieJar,
+    Query(params): Query<BuscaParams>,
+) -> Result<Json<RespostaBusca>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let consulta = params.q.trim().to_string();
+    if consulta.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Informe o texto da busca".to_string()));
+    }
+    let tipos = tipos_pedidos(params.tipos.as_deref()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let limite = params.limite.unwrap_or(LIMITE_PADRAO).clamp(1, MAX_LIMITE);
+
+    let conn = &mut db::establish_connection();
+    let resultados = buscar(conn, &id_usuario, &consulta, &tipos, (params.data_inicio, params.data_fim), limite)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro na busca: {e}")))?;
+    Ok(Json(RespostaBusca { consulta, resultados }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tipos_pedidos() {
+        assert_eq!(tipos_pedidos(None).unwrap(), TipoResultado::TODOS.to_vec());
+        assert_eq!(tipos_pedidos(Some(" ")).unwrap(), TipoResultado::TODOS.to_vec());
+        assert_eq!(
+            tipos_pedidos(Some("meta, transacao,meta")).unwrap(),
+            vec![TipoResultado::Meta, TipoResultado::Transacao]
+        );
+        assert!(tipos_pedidos(Some("corrida")).is_err());
+    }
+
+    #[test]
+    fn test_destaque_escapa_texto_do_usuario() {
+        // formato devolvido pelo ts_headline com os marcadores
+        let trecho = "troca do \u{2}pneu\u{3} <img src=x onerror=alert(1)> & \"oficina\" d'\u{2}pneus\u{3}";
+        assert_eq!(
+            destacar(trecho),
+            "troca do <mark>pneu</mark> &lt;img src=x onerror=alert(1)&gt; &amp; &quot;oficina&quot; d&#39;<mark>pneus</mark>"
+        );
+        assert_eq!(destacar("<mark>falso</mark>"), "&lt;mark&gt;falso&lt;/mark&gt;");
+        assert_eq!(destacar(""), "");
+    }
+
+    #[test]
+    fn test_subconsulta_filtra_usuario_lixeira_e_periodo() {
+        for tipo in TipoResultado::TODOS {
+            let sql = tipo.subconsulta();
+            assert!(sql.contains(&format!("'{}'::text AS tipo", tipo.nome())));
+            assert!(sql.contains(".id_usuario = $1 AND"));
+            assert!(sql.contains(".excluido_em IS NULL AND"));
+            assert!(sql.contains("@@ q.consulta"));
+            assert!(sql.contains(">= $3") && sql.contains("<= $4"));
+            // o texto do usuário nunca traz os marcadores do destaque
+            assert!(sql.contains("translate(") && !sql.contains("<mark>"));
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! Carteiras: onde está o dinheiro do usuário (dinheiro vivo, conta, saldo na plataforma, cartão)
+//!
+//! A transação aponta opcionalmente para uma carteira (`id_carteira`). Transferências entre
+//! carteiras ficam em tabela própria e não contam como ganho nem gasto. O saldo de uma carteira
+//! é o saldo inicial, mais entradas, menos saídas, mais transferências recebidas, menos enviadas;
+//! transações sem carteira não entram em saldo nenhum.
+
+use std::collections::HashMap;
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::{Carteira, NewCarteira, TransferenciaCarteira};
+use crate::schema::carteiras::dsl as c_dsl;
+use crate::schema::transacoes::dsl as t_dsl;
+use crate::schema::transferencias_carteiras::dsl as tr_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+pub const MAX_TAMANHO_NOME: usize = 40;
+/// Movimentos devolvidos no extrato (os mais recentes do período)
+const MAX_EXTRATO: i64 = 1000;
+const MAX_TRANSFERENCIAS: i64 = 500;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoCarteira {
+    Dinheiro,
+    ContaBancaria,
+    /// Saldo acumulado no app da plataforma, sacado de tempos em tempos
+    SaldoPlataforma,
+    Cartao,
+}
+
+impl TipoCarteira {
+    pub fn nome(self) -> &'static str {
+        match self {
+            TipoCarteira::Dinheiro => "dinheiro",
+            TipoCarteira::ContaBancaria => "conta_bancaria",
+            TipoCarteira::SaldoPlataforma => "saldo_plataforma",
+            TipoCarteira::Cartao => "cartao",
+        }
+    }
+
+    pub fn de_nome(nome: &str) -> Option<Self> {
+        [TipoCarteira::Dinheiro, TipoCarteira::ContaBancaria, TipoCarteira::SaldoPlataforma, TipoCarteira::Cartao]
+            .into_iter()
+            .find(|t| t.nome() == nome)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateCarteiraPayload {
+    pub nome: String,
+    pub tipo: TipoCarteira,
+    /// Só para `saldo_plataforma` (ex.: "Uber", "99")
+    pub plataforma: Option<String>,
+    /// Centavos
+    pub saldo_inicial: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCarteiraPayload {
+    pub nome: Option<String>,
+    pub tipo: Option<TipoCarteira>,
+    /// Texto vazio apaga
+    pub plataforma: Option<String>,
+    pub saldo_inicial: Option<i32>,
+    /// Arquivada some das escolhas de carteira, mas mantém o histórico
+    pub arquivada: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CarteiraComSaldo {
+    #[serde(flatten)]
+    pub carteira: Carteira,
+    /// Centavos
+    pub saldo: i64,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListarCarteirasParams {
+    /// Saldo até esta data (padrão: agora)
+    pub ate: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub incluir_arquivadas: bool,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ExtratoParams {
+    pub data_inicio: Option<DateTime<Utc>>,
+    pub data_fim: Option<DateTime<Utc>>,
+}
+
+/// Linha do extrato com o saldo logo depois dela
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct MovimentoCarteira {
+    /// "entrada", "saida" ou "transferencia"
+    #[diesel(sql_type = Text)]
+    pub tipo: String,
+    /// Id da transação ou da transferência
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Timestamptz)]
+    pub data: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub descricao: Option<String>,
+    /// Na transferência, a outra carteira
+    #[diesel(sql_type = Nullable<Text>)]
+    pub id_carteira_contraparte: Option<String>,
+    /// Centavos com sinal: positivo entra, negativo sai
+    #[diesel(sql_type = BigInt)]
+    pub valor: i64,
+    #[diesel(sql_type = BigInt)]
+    pub saldo: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTransferenciaPayload {
+    pub id_origem: String,
+    pub id_destino: String,
+    /// Centavos
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub data: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListarTransferenciasParams {
+    /// Só as que saem ou entram nesta carteira
+    pub id_carteira: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConciliarPayload {
+    /// Saldo que o usuário vê no banco, no app ou na mão (centavos)
+    pub saldo_declarado: i64,
+    /// Momento do saldo declarado (padrão: agora)
+    pub data: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Conciliacao {
+    pub id_carteira: String,
+    pub data: DateTime<Utc>,
+    pub saldo_declarado: i64,
+    pub saldo_calculado: i64,
+    /// Declarado menos calculado: positivo é dinheiro sem lançamento, negativo é lançamento a mais
+    pub diferenca: i64,
+    pub conferido: bool,
+}
+
+impl Conciliacao {
+    pub fn nova(id_carteira: String, data: DateTime<Utc>, saldo_declarado: i64, saldo_calculado: i64) -> Self {
+        let diferenca = saldo_declarado - saldo_calculado;
+        Conciliacao { id_carteira, data, saldo_declarado, saldo_calculado, diferenca, conferido: diferenca == 0 }
+    }
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn nao_encontrada() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Carteira não encontrada".to_string())
+}
+
+fn nome_duplicado(e: diesel::result::Error, nome: &str) -> (StatusCode, String) {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) =>
+            (StatusCode::CONFLICT, format!("Já existe a carteira {nome}")),
+        e => erro_interno(e),
+    }
+}
+
+pub fn normalizar_nome(nome: &str) -> Option<String> {
+    let nome = nome.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!nome.is_empty() && nome.chars().count() <= MAX_TAMANHO_NOME).then_some(nome)
+}
+
+/// Plataforma só faz sentido no saldo de plataforma
+fn plataforma_para(tipo: TipoCarteira, plataforma: Option<&str>) -> Option<String> {
+    plataforma
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && tipo == TipoCarteira::SaldoPlataforma)
+        .map(str::to_string)
+}
+
+pub fn validar_transferencia(payload: &CreateTransferenciaPayload) -> Result<(), String> {
+    if payload.valor <= 0 {
+        return Err("O valor da transferência deve ser positivo".to_string());
+    }
+    if payload.id_origem == payload.id_destino {
+        return Err("Origem e destino devem ser carteiras diferentes".to_string());
+    }
+    Ok(())
+}
+
+fn buscar(conn: &mut PgConnection, id_usuario: &str, id_carteira: &str) -> QueryResult<Option<Carteira>> {
+    c_dsl::carteiras
+        .filter(c_dsl::id.eq(id_carteira))
+        .filter(c_dsl::id_usuario.eq(id_usuario))
+        .first(conn)
+        .optional()
+}
+
+/// A carteira é do usuário e não está arquivada (pode receber lançamentos)
+pub fn carteira_valida(conn: &mut PgConnection, id_usuario: &str, id_carteira: &str) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        c_dsl::carteiras
+            .filter(c_dsl::id.eq(id_carteira))
+            .filter(c_dsl::id_usuario.eq(id_usuario))
+            .filter(c_dsl::arquivada.eq(false)),
+    ))
+    .get_result(conn)
+}
+
+#[derive(QueryableByName)]
+struct LinhaSaldo {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = BigInt)]
+    saldo: i64,
+}
+
+/// Saldo de cada carteira do usuário em `ate`
+pub fn saldos(conn: &mut PgConnection, id_usuario: &str, ate: DateTime<Utc>) -> QueryResult<HashMap<String, i64>> {
+    let linhas: Vec<LinhaSaldo> = diesel::sql_query(
+        "SELECT c.id,
+                c.saldo_inicial::BIGINT
+                + COALESCE((SELECT SUM(CASE WHEN t.tipo = 'entrada' THEN t.valor ELSE -t.valor END)
+                            FROM transacoes t
+                            WHERE t.id_carteira = c.id AND t.excluido_em IS NULL AND t.data <= $2), 0)
+                + COALESCE((SELECT SUM(tr.valor) FROM transferencias_carteiras tr
+                            WHERE tr.id_destino = c.id AND tr.data <= $2), 0)
+                - COALESCE((SELECT SUM(tr.valor) FROM transferencias_carteiras tr
+                            WHERE tr.id_origem = c.id AND tr.data <= $2), 0) AS saldo
+         FROM carteiras c
+         WHERE c.id_usuario = $1",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Timestamptz, _>(ate)
+    .load(conn)?;
+    Ok(linhas.into_iter().map(|l| (l.id, l.saldo)).collect())
+}
+
+pub fn saldo(conn: &mut PgConnection, carteira: &Carteira, ate: DateTime<Utc>) -> QueryResult<i64> {
+    Ok(saldos(conn, &carteira.id_usuario, ate)?.remove(&carteira.id).unwrap_or(carteira.saldo_inicial as i64))
+}
+
+/// Movimentos da carteira no período, do mais recente para o mais antigo, com o saldo acumulado
+pub fn extrato(conn: &mut PgConnection, carteira: &Carteira, params: &ExtratoParams) -> QueryResult<Vec<MovimentoCarteira>> {
+    diesel::sql_query(
+        "WITH movimentos AS (
+             SELECT t.tipo, t.id, t.data, t.descricao, NULL::VARCHAR AS id_carteira_contraparte,
+                    (CASE WHEN t.tipo = 'entrada' THEN t.valor ELSE -t.valor END)::BIGINT AS valor
+             FROM transacoes t
+             WHERE t.id_carteira = $1 AND t.excluido_em IS NULL
+             UNION ALL
+             SELECT 'transferencia', tr.id, tr.data, tr.descricao, tr.id_destino, -tr.valor::BIGINT
+             FROM transferencias_carteiras tr WHERE tr.id_origem = $1
+             UNION ALL
+             SELECT 'transferencia', tr.id, tr.data, tr.descricao, tr.id_origem, tr.valor::BIGINT
+             FROM transferencias_carteiras tr WHERE tr.id_destino = $1
+         ), acumulado AS (
+             SELECT m.*, $2 + SUM(m.valor) OVER (ORDER BY m.data, m.id ROWS UNBOUNDED PRECEDING) AS saldo
+             FROM movimentos m
+         )
+         SELECT tipo, id, data, descricao, id_carteira_contraparte, valor, saldo::BIGINT AS saldo
+         FROM acumulado
+         WHERE ($3::TIMESTAMPTZ IS NULL OR data >= $3) AND ($4::TIMESTAMPTZ IS NULL OR data <= $4)
+         ORDER BY data DESC, id DESC
+         LIMIT $5",
+    )
+    .bind::<Text, _>(&carteira.id)
+    .bind::<BigInt, _>(carteira.saldo_inicial as i64)
+    .bind::<Nullable<Timestamptz>, _>(params.data_inicio)
+    .bind::<Nullable<Timestamptz>, _>(params.data_fim)
+    .bind::<BigInt, _>(MAX_EXTRATO)
+    .load(conn)
+}
+
+pub async fn list_carteiras_handler(
+    jar: CookieJar,
+    Query(params): Query<ListarCarteirasParams>,
+) -> Result<Json<Vec<CarteiraComSaldo>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let mut query = c_dsl::carteiras.filter(c_dsl::id_usuario.eq(&id_usuario)).into_boxed();
+    if !params.incluir_arquivadas {
+        query = query.filter(c_dsl::arquivada.eq(false));
+    }
+    let carteiras: Vec<Carteira> = query.order(c_dsl::nome.asc()).load(conn).map_err(erro_interno)?;
+    let mut saldos = saldos(conn, &id_usuario, params.ate.unwrap_or_else(Utc::now)).map_err(erro_interno)?;
+    Ok(Json(
+        carteiras
+            .into_iter()
+            .map(|c| {
+                let saldo = saldos.remove(&c.id).unwrap_or(c.saldo_inicial as i64);
+                CarteiraComSaldo { carteira: c, saldo }
+            })
+            .collect(),
+    ))
+}
+
+pub async fn create_carteira_handler(
+    jar: CookieJar,
+    Json(payload): Json<CreateCarteiraPayload>,
+) -> Result<(StatusCode, Json<Carteira>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let nome = normalizar_nome(&payload.nome)
+        .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?;
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let nova = NewCarteira {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        nome,
+        tipo: payload.tipo.nome().to_string(),
+        plataforma: plataforma_para(payload.tipo, payload.plataforma.as_deref()),
+        saldo_inicial: payload.saldo_inicial.unwrap_or(0),
+        arquivada: false,
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    let carteira = diesel::insert_into(c_dsl::carteiras)
+        .values(&nova)
+        .get_result::<Carteira>(conn)
+        .map_err(|e| nome_duplicado(e, &nova.nome))?;
+    Ok((StatusCode::CREATED, Json(carteira)))
+}
+
+pub async fn update_carteira_handler(
+    jar: CookieJar,
+    Path(id_carteira): Path<String>,
+    Json(payload): Json<UpdateCarteiraPayload>,
+) -> Result<Json<Carteira>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let atual = buscar(conn, &id_usuario, &id_carteira).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let nome = match payload.nome.as_deref() {
+        Some(nome) => normalizar_nome(nome)
+            .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?,
+        None => atual.nome.clone(),
+    };
+    let tipo = payload.tipo.or(TipoCarteira::de_nome(&atual.tipo)).unwrap_or(TipoCarteira::Dinheiro);
+    let plataforma = plataforma_para(tipo, payload.plataforma.as_deref().or(atual.plataforma.as_deref()));
+    let carteira = diesel::update(c_dsl::carteiras.find(&atual.id))
+        .set((
+            c_dsl::nome.eq(&nome),
+            c_dsl::tipo.eq(tipo.nome()),
+            c_dsl::plataforma.eq(plataforma),
+            c_dsl::saldo_inicial.eq(payload.saldo_inicial.unwrap_or(atual.saldo_inicial)),
+            c_dsl::arquivada.eq(payload.arquivada.unwrap_or(atual.arquivada)),
+            c_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Carteira>(conn)
+        .map_err(|e| nome_duplicado(e, &nome))?;
+    Ok(Json(carteira))
+}
+
+/// Só apaga carteira sem nenhum lançamento; com histórico, arquive
+pub async fn delete_carteira_handler(
+    jar: CookieJar,
+    Path(id_carteira): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let carteira = buscar(conn, &id_usuario, &id_carteira).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let com_transacoes: bool = diesel::select(diesel::dsl::exists(t_dsl::transacoes.filter(t_dsl::id_carteira.eq(&carteira.id))))
+        .get_result(conn)
+        .map_err(erro_interno)?;
+    let com_transferencias: bool = diesel::select(diesel::dsl::exists(
+        tr_dsl::transferencias_carteiras.filter(tr_dsl::id_origem.eq(&carteira.id).or(tr_dsl::id_destino.eq(&carteira.id))),
+    ))
+    .get_result(conn)
+    .map_err(erro_interno)?;
+    if com_transacoes || com_transferencias {
+        return Err((StatusCode::CONFLICT, "A carteira tem lançamentos; arquive-a em vez de excluir".to_string()));
+    }
+    diesel::delete(c_dsl::carteiras.find(&carteira.id)).execute(conn).map_err(erro_interno)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn extrato_carteira_handler(
+    jar: CookieJar,
+    Path(id_carteira): Path<String>,
+    Query(params): Query<ExtratoParams>,
+) -> Result<Json<Vec<MovimentoCarteira>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let carteira = buscar(conn, &id_usuario, &id_carteira).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    extrato(conn, &carteira, &params).map(Json).map_err(erro_interno)
+}
+
+pub async fn conciliar_carteira_handler(
+    jar: CookieJar,
+    Path(id_carteira): Path<String>,
+    Json(payload): Json<ConciliarPayload>,
+) -> Result<Json<Conciliacao>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let carteira = buscar(conn, &id_usuario, &id_carteira).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let data = payload.data.unwrap_or_else(Utc::now);
+    let calculado = saldo(conn, &carteira, data).map_err(erro_interno)?;
+    Ok(Json(Conciliacao::nova(carteira.id, data, payload.saldo_declarado, calculado)))
+}
+
+pub async fn list_transferencias_handler(
+    jar: CookieJar,
+    Query(params): Query<ListarTransferenciasParams>,
+) -> Result<Json<Vec<TransferenciaCarteira>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let mut query = tr_dsl::transferencias_carteiras.filter(tr_dsl::id_usuario.eq(&id_usuario)).into_boxed();
+    if let Some(carteira) = params.id_carteira.as_deref() {
+        query = query.filter(tr_dsl::id_origem.eq(carteira).or(tr_dsl::id_destino.eq(carteira)));
+    }
+    query
+        .order((tr_dsl::data.desc(), tr_dsl::id.desc()))
+        .limit(MAX_TRANSFERENCIAS)
+        .load(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+pub async fn create_transferencia_handler(
+    jar: CookieJar,
+    Json(payload): Json<CreateTransferenciaPayload>,
+) -> Result<(StatusCode, Json<TransferenciaCarteira>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar_transferencia(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    for id_carteira in [&payload.id_origem, &payload.id_destino] {
+        if !carteira_valida(conn, &id_usuario, id_carteira).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+        }
+    }
+    let agora = Utc::now();
+    let transferencia = TransferenciaCarteira {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        id_origem: payload.id_origem,
+        id_destino: payload.id_destino,
+        valor: payload.valor,
+        descricao: payload.descricao.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+        data: payload.data.unwrap_or(agora),
+        criado_em: agora,
+    };
+    diesel::insert_into(tr_dsl::transferencias_carteiras)
+        .values(&transferencia)
+        .execute(conn)
+        .map_err(erro_interno)?;
+    Ok((StatusCode::CREATED, Json(transferencia)))
+}
+
+pub async fn delete_transferencia_handler(
+    jar: CookieJar,
+    Path(id_transferencia): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let apagadas = diesel::delete(
+        tr_dsl::transferencias_carteiras
+            .filter(tr_dsl::id.eq(&id_transferencia))
+            .filter(tr_dsl::id_usuario.eq(&id_usuario)),
+    )
+    .execute(conn)
+    .map_err(erro_interno)?;
+    if apagadas == 0 {
+        return Err((StatusCode::NOT_FOUND, "Transferência não encontrada".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transferencia_e_conciliacao() {
+        let transferencia = |origem: &str, destino: &str, valor| CreateTransferenciaPayload {
+            id_origem: origem.to_string(),
+            id_destino: destino.to_string(),
+            valor,
+            descricao: None,
+            data: None,
+        };
+        assert!(validar_transferencia(&transferencia("a", "b", 5000)).is_ok());
+        assert!(validar_transferencia(&transferencia("a", "a", 5000)).is_err());
+        assert!(validar_transferencia(&transferencia("a", "b", 0)).is_err());
+
+        let conciliacao = Conciliacao::nova("a".to_string(), Utc::now(), 12_000, 12_550);
+        assert_eq!(conciliacao.diferenca, -550);
+        assert!(!conciliacao.conferido);
+
+        assert_eq!(TipoCarteira::de_nome("saldo_plataforma"), Some(TipoCarteira::SaldoPlataforma));
+        assert_eq!(normalizar_nome("  Saldo   Uber "), Some("Saldo Uber".to_string()));
+        assert_eq!(plataforma_para(TipoCarteira::Dinheiro, Some("Uber")), None);
+        assert_eq!(plataforma_para(TipoCarteira::SaldoPlataforma, Some(" Uber ")), Some("Uber".to_string()));
+    }
+}
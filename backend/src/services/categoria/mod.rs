@@ -6,7 +6,8 @@ use diesel::prelude::*;
 use serde::{Serialize, Deserialize};
 use crate::db;
 use crate::schema::categorias::dsl::*;
-use crate::models::{Categoria, NewCategoria};
+use crate::models::{Categoria, NewCategoria, Transacao};
+use crate::services::historico::{self, Acao, Origem};
 
 use crate::schema::transacoes::dsl as trans_dsl;
 use chrono::{DateTime, Utc};
@@ -46,6 +47,7 @@ pub async fn list_categorias_autenticado_handler(jar: CookieJar) -> Json<Vec<Cat
     let conn = &mut db::establish_connection();
     let results = categorias
         .filter(id_usuario.eq(usuario_id_val.clone()))
+        .filter(excluido_em.is_null())
         .order(nome.asc())
         .load::<Categoria>(conn)
         .unwrap_or_default();
@@ -118,6 +120,9 @@ pub async fn create_categoria_handler(jar: CookieJar, Json(payload): Json<Create
         .values(&nova_categoria)
         .execute(conn)
         .expect("Erro ao inserir categoria");
+    if let (Some(dono), Ok(criada)) = (&nova_categoria.id_usuario, categorias.find(&nova_categoria.id).first::<Categoria>(conn)) {
+        historico::anotar(conn, &Origem::usuario(dono, "POST /api/categoria"), Acao::Criacao, None, Some(&criada));
+    }
     Json(CategoriaResponse {
         id: nova_categoria.id,
         id_usuario: nova_categoria.id_usuario,
@@ -132,7 +137,7 @@ pub async fn create_categoria_handler(jar: CookieJar, Json(payload): Json<Create
 
 pub async fn get_categoria_handler(Path(id_param): Path<String>) -> Json<Option<CategoriaResponse>> {
     let conn = &mut db::establish_connection();
-    match categorias.filter(id.eq(id_param)).first::<Categoria>(conn) {
+    match categorias.filter(id.eq(id_param)).filter(excluido_em.is_null()).first::<Categoria>(conn) {
         Ok(c) => Json(Some(CategoriaResponse {
             id: c.id,
             id_usuario: c.id_usuario,
@@ -147,16 +152,28 @@ pub async fn get_categoria_handler(Path(id_param): Path<String>) -> Json<Option<
     }
 }
 
-pub async fn delete_categoria_handler(Path(id_param): Path<String>) -> Json<bool> {
+pub async fn delete_categoria_handler(Path(id_param): Path<String>, jar: CookieJar) -> Json<bool> {
     let conn = &mut db::establish_connection();
     // Prevent deletion of reserved categories by name (if they belong to user)
     // Try to fetch category and if its name is reserved, disallow
-    if let Ok(Some(cat)) = categorias.filter(id.eq(&id_param)).first::<Categoria>(conn).optional() {
+    let antes = categorias.filter(id.eq(&id_param)).filter(excluido_em.is_null()).first::<Categoria>(conn).optional().ok().flatten();
+    if let Some(cat) = &antes {
         if cat.nome == "Corrida Uber" || cat.nome == "Corrida 99" {
             return Json(false);
         }
     }
-    let count = diesel::delete(categorias.filter(id.eq(id_param))).execute(conn).unwrap_or(0);
+    let agora = Utc::now();
+    let count = diesel::update(categorias.filter(id.eq(id_param)).filter(excluido_em.is_null()))
+        .set(excluido_em.eq(Some(agora)))
+        .execute(conn)
+        .unwrap_or(0);
+    // autor: usuário logado ou, sem cookie, o dono da categoria
+    let autor = crate::services::auth::login::extract_user_id_from_cookie(&jar)
+        .or_else(|| antes.as_ref().and_then(|c| c.id_usuario.clone()));
+    if let (Some(antes), Some(autor), true) = (&antes, autor, count > 0) {
+        let depois = Categoria { excluido_em: Some(agora), ..antes.clone() };
+        historico::anotar(conn, &Origem::usuario(&autor, "DELETE /api/categoria/{id}"), Acao::Exclusao, Some(antes), Some(&depois));
+    }
     Json(count > 0)
 }
 
@@ -184,8 +201,9 @@ pub async fn update_categoria_handler(Path(id_param): Path<String>, jar: axum_ex
     let conn = &mut db::establish_connection();
 
     // Verify exists
-    match categorias.filter(id.eq(&id_param)).filter(id_usuario.eq(Some(usuario_id_val.clone()))).first::<Categoria>(conn).optional() {
+    match categorias.filter(id.eq(&id_param)).filter(id_usuario.eq(Some(usuario_id_val.clone()))).filter(excluido_em.is_null()).first::<Categoria>(conn).optional() {
         Ok(Some(existing)) => {
+            let existing_antes = existing.clone();
             // prepare updated values
             let new_nome = payload.nome.unwrap_or(existing.nome);
             let new_tipo = payload.tipo.unwrap_or(existing.tipo);
@@ -203,7 +221,10 @@ pub async fn update_categoria_handler(Path(id_param): Path<String>, jar: axum_ex
 
             // Return updated
             match categorias.filter(id.eq(&id_param)).first::<Categoria>(conn) {
-                Ok(c) => Json(Some(CategoriaResponse { id: c.id, id_usuario: c.id_usuario, nome: c.nome, tipo: c.tipo, icone: c.icone, cor: c.cor, criado_em: c.criado_em, atualizado_em: c.atualizado_em })),
+                Ok(c) => {
+                    historico::anotar(conn, &Origem::usuario(&usuario_id_val, "PUT /api/categoria/{id}"), Acao::Atualizacao, Some(&existing_antes), Some(&c));
+                    Json(Some(CategoriaResponse { id: c.id, id_usuario: c.id_usuario, nome: c.nome, tipo: c.tipo, icone: c.icone, cor: c.cor, criado_em: c.criado_em, atualizado_em: c.atualizado_em }))
+                }
                 Err(_) => Json(None),
             }
         }
@@ -230,6 +251,7 @@ pub async fn preview_delete_categoria_handler(Path(id_param): Path<String>, jar:
     let count: i64 = trans_dsl::transacoes
         .filter(trans_dsl::id_categoria.eq(id_param))
         .filter(trans_dsl::id_usuario.eq(usuario_id_val))
+        .filter(trans_dsl::excluido_em.is_null())
         .count()
         .get_result(conn)
         .unwrap_or(0);
@@ -293,6 +315,18 @@ pub struct ExecuteDeleteResponse {
     pub deleted_category: bool,
 }
 
+/// Manda a categoria do usuário para a lixeira, com histórico
+fn excluir_categoria(conn: &mut PgConnection, id_param: &str, usuario_id_val: &str, agora: DateTime<Utc>, origem: &Origem) -> QueryResult<bool> {
+    let alvo = categorias.filter(id.eq(id_param)).filter(id_usuario.eq(usuario_id_val)).filter(excluido_em.is_null());
+    let Some(antes) = alvo.first::<Categoria>(conn).optional()? else {
+        return Ok(false);
+    };
+    diesel::update(alvo).set(excluido_em.eq(Some(agora))).execute(conn)?;
+    let depois = Categoria { excluido_em: Some(agora), ..antes.clone() };
+    historico::registrar(conn, origem, Acao::Exclusao, Some(&antes), Some(&depois))?;
+    Ok(true)
+}
+
 pub async fn execute_delete_categoria_handler(Path(id_param): Path<String>, jar: axum_extra::extract::cookie::CookieJar, Json(payload): Json<ExecuteDeletePayload>) -> Json<ExecuteDeleteResponse> {
     let conn = &mut db::establish_connection();
 
@@ -306,6 +340,7 @@ pub async fn execute_delete_categoria_handler(Path(id_param): Path<String>, jar:
 
     // Garante que operamos apenas nas categorias do usuário
     // Usa transação para garantir atomicidade
+    let origem = Origem::usuario(&usuario_id_val, "POST /api/categoria/{id}/execute-delete");
     let result = conn.transaction::<(i64,i64,bool), diesel::result::Error, _>(|conn_inner| {
         if payload.method == "migrate" {
             let target = match payload.target_id {
@@ -314,7 +349,7 @@ pub async fn execute_delete_categoria_handler(Path(id_param): Path<String>, jar:
             };
 
             // Verifica que target pertence ao usuário
-            let target_exists = categorias.filter(id.eq(&target)).filter(id_usuario.eq(Some(usuario_id_val.clone()))).first::<Categoria>(conn_inner).optional()?;
+            let target_exists = categorias.filter(id.eq(&target)).filter(id_usuario.eq(Some(usuario_id_val.clone()))).filter(excluido_em.is_null()).first::<Categoria>(conn_inner).optional()?;
             if target_exists.is_none() {
                 return Err(diesel::result::Error::RollbackTransaction);
             }
@@ -325,12 +360,26 @@ pub async fn execute_delete_categoria_handler(Path(id_param): Path<String>, jar:
                 }
             }
 
+            let movidas_antes: Vec<Transacao> = trans_dsl::transacoes.filter(trans_dsl::id_categoria.eq(&id_param)).filter(trans_dsl::id_usuario.eq(&usuario_id_val)).load(conn_inner)?;
             let migrated = diesel::update(trans_dsl::transacoes.filter(trans_dsl::id_categoria.eq(&id_param)).filter(trans_dsl::id_usuario.eq(&usuario_id_val)))
-                .set(trans_dsl::id_categoria.eq(target))
+                .set(trans_dsl::id_categoria.eq(&target))
                 .execute(conn_inner)?;
-
-            let deleted_cat = diesel::delete(categorias.filter(id.eq(&id_param)).filter(id_usuario.eq(Some(usuario_id_val.clone())))).execute(conn_inner)?;
-            Ok((migrated as i64, 0i64, deleted_cat > 0))
+            let ids_movidas: Vec<&String> = movidas_antes.iter().map(|t| &t.id).collect();
+            let movidas_depois: Vec<Transacao> = trans_dsl::transacoes.filter(trans_dsl::id.eq_any(ids_movidas)).load(conn_inner)?;
+            historico::registrar_lote(conn_inner, &origem, Acao::Atualizacao, &movidas_antes, &movidas_depois)?;
+            // linhas de transações divididas do usuário acompanham a migração
+            diesel::sql_query(
+                "UPDATE transacoes_divisoes d SET id_categoria = $1 FROM transacoes t \
+                 WHERE d.id_transacao = t.id AND d.id_categoria = $2 AND t.id_usuario = $3",
+            )
+            .bind::<diesel::sql_types::Text, _>(&target)
+            .bind::<diesel::sql_types::Text, _>(&id_param)
+            .bind::<diesel::sql_types::Text, _>(&usuario_id_val)
+            .execute(conn_inner)?;
+
+            // transações já na lixeira também migram, para voltarem à categoria nova se restauradas
+            let deleted_cat = excluir_categoria(conn_inner, &id_param, &usuario_id_val, Utc::now(), &origem)?;
+            Ok((migrated as i64, 0i64, deleted_cat))
         } else {
             // delete transactions then category
             // Disallow deleting reserved categories
@@ -339,12 +388,34 @@ pub async fn execute_delete_categoria_handler(Path(id_param): Path<String>, jar:
                     return Err(diesel::result::Error::RollbackTransaction);
                 }
             }
-            let deleted_tx = diesel::delete(trans_dsl::transacoes.filter(trans_dsl::id_categoria.eq(&id_param)).filter(trans_dsl::id_usuario.eq(&usuario_id_val))).execute(conn_inner)?;
-            let deleted_cat = diesel::delete(categorias.filter(id.eq(&id_param)).filter(id_usuario.eq(Some(usuario_id_val.clone())))).execute(conn_inner)?;
-            Ok((0i64, deleted_tx as i64, deleted_cat > 0))
+            // linhas desta categoria em transações de outra categoria passam para a categoria da
+            // transação (a soma da divisão se mantém); as da própria categoria saem com ela
+            diesel::sql_query(
+                "UPDATE transacoes_divisoes d SET id_categoria = t.id_categoria FROM transacoes t \
+                 WHERE d.id_transacao = t.id AND d.id_categoria = $1 AND t.id_usuario = $2 AND t.id_categoria <> $1",
+            )
+            .bind::<diesel::sql_types::Text, _>(&id_param)
+            .bind::<diesel::sql_types::Text, _>(&usuario_id_val)
+            .execute(conn_inner)?;
+            // categoria e transações vão para a lixeira com o mesmo instante: restaurar a
+            // categoria traz de volta exatamente essas transações
+            let agora = Utc::now();
+            let ativas = trans_dsl::transacoes.filter(trans_dsl::id_categoria.eq(&id_param)).filter(trans_dsl::id_usuario.eq(&usuario_id_val)).filter(trans_dsl::excluido_em.is_null());
+            let excluidas_antes: Vec<Transacao> = ativas.load(conn_inner)?;
+            let deleted_tx = diesel::update(ativas)
+                .set(trans_dsl::excluido_em.eq(Some(agora)))
+                .execute(conn_inner)?;
+            let excluidas_depois: Vec<Transacao> = excluidas_antes.iter().map(|t| Transacao { excluido_em: Some(agora), ..t.clone() }).collect();
+            historico::registrar_lote(conn_inner, &origem, Acao::Exclusao, &excluidas_antes, &excluidas_depois)?;
+            crate::services::envelope::aplicar_regras_lote(conn_inner, &excluidas_antes, &excluidas_depois)?;
+            let deleted_cat = excluir_categoria(conn_inner, &id_param, &usuario_id_val, agora, &origem)?;
+            Ok((0i64, deleted_tx as i64, deleted_cat))
         }
     });
 
+    if let Ok((_, _, true)) = result {
+        crate::services::eventos::invalidar_e_publicar(&usuario_id_val, crate::services::eventos::EventoUsuario::DadosAlterados).await;
+    }
     match result {
         Ok((migrated, deleted_tx, deleted_cat)) => Json(ExecuteDeleteResponse { migrated_count: migrated, deleted_transactions_count: deleted_tx, deleted_category: deleted_cat }),
         Err(_) => Json(ExecuteDeleteResponse { migrated_count: 0, deleted_transactions_count: 0, deleted_category: false }),
@@ -0,0 +1,455 @@
+//! Comprovantes (fotos de recibo e PDFs) anexados às transações
+//!
+//! Os arquivos ficam em disco, em `COMPROVANTES_DIR/<id_usuario>/<id>.<ext>`, com uma
+//! miniatura JPEG ao lado para imagens; o banco guarda só os metadados. O tipo é decidido
+//! pelos primeiros bytes do arquivo, não pelo que o navegador informa.
+//!
+//! Transação na lixeira mantém os arquivos; eles saem quando ela é expurgada. Caminhos que
+//! apagam transações sem passar pela lixeira (exclusão de usuário) contam com a limpeza
+//! periódica de arquivos órfãos.
+
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::{Path as FsPath, PathBuf};
+use std::time::{Duration, SystemTime};
+use axum::body::Body;
+use axum::extract::{Multipart, Path};
+use axum::http::{StatusCode, header};
+use axum::response::Response;
+use axum::Json;
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use crate::db;
+use crate::models::{Comprovante, NewComprovante};
+use crate::schema::comprovantes::dsl as c_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+/// Tamanho máximo de cada arquivo, se `COMPROVANTES_MAX_BYTES` não estiver definido
+const MAX_BYTES_PADRAO: usize = 8 * 1024 * 1024;
+pub const MAX_POR_TRANSACAO: usize = 10;
+/// Arquivos aceitos num mesmo envio
+pub const MAX_POR_ENVIO: usize = 5;
+const LADO_MINIATURA: u32 = 320;
+/// Imagens maiores que isso (em pixels por lado) não ganham miniatura
+const MAX_LADO_IMAGEM: u32 = 12_000;
+const MAX_NOME_ARQUIVO: usize = 120;
+/// Arquivo sem registro no banco só é apagado depois disso (pode ser um envio em andamento)
+const IDADE_MINIMA_ORFAO: Duration = Duration::from_secs(60 * 60);
+const INTERVALO_LIMPEZA: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Tipos aceitos: (mime, extensão, é imagem)
+const TIPOS: [(&str, &str, bool); 4] = [
+    ("image/jpeg", "jpg", true),
+    ("image/png", "png", true),
+    ("image/webp", "webp", true),
+    ("application/pdf", "pdf", false),
+];
+
+pub fn diretorio() -> PathBuf {
+    std::env::var("COMPROVANTES_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/comprovantes"))
+}
+
+pub fn limite_bytes() -> usize {
+    std::env::var("COMPROVANTES_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(MAX_BYTES_PADRAO)
+}
+
+/// Limite do corpo da requisição de envio (todos os arquivos mais a folga do multipart)
+pub fn limite_corpo_envio() -> usize {
+    limite_bytes() * MAX_POR_ENVIO + 64 * 1024
+}
+
+/// Mime e extensão pelo conteúdo do arquivo
+pub fn detectar_tipo(bytes: &[u8]) -> Option<(&'static str, &'static str, bool)> {
+    let mime = if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "image/webp"
+    } else if bytes.starts_with(b"%PDF-") {
+        "application/pdf"
+    } else {
+        return None;
+    };
+    TIPOS.into_iter().find(|(m, _, _)| *m == mime)
+}
+
+/// Nome original só com caracteres seguros para o `Content-Disposition`
+pub fn sanitizar_nome(nome: Option<&str>, extensao: &str) -> String {
+    let base = nome
+        .map(|n| n.rsplit(['/', '\\']).next().unwrap_or(n))
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') { c } else { '_' })
+        .take(MAX_NOME_ARQUIVO)
+        .collect::<String>();
+    let base = base.trim().trim_start_matches('.');
+    if base.is_empty() {
+        format!("comprovante.{extensao}")
+    } else {
+        base.to_string()
+    }
+}
+
+pub fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Miniatura JPEG de até `LADO_MINIATURA` px; None se a imagem não puder ser lida
+pub fn gerar_miniatura(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut leitor = image::ImageReader::new(Cursor::new(bytes)).with_guessed_format().ok()?;
+    let mut limites = image::Limits::default();
+    limites.max_image_width = Some(MAX_LADO_IMAGEM);
+    limites.max_image_height = Some(MAX_LADO_IMAGEM);
+    leitor.limits(limites);
+    let imagem = leitor.decode().ok()?;
+    let mini = imagem.thumbnail(LADO_MINIATURA, LADO_MINIATURA).to_rgb8();
+    let mut saida = Cursor::new(Vec::new());
+    mini.write_to(&mut saida, image::ImageFormat::Jpeg).ok()?;
+    Some(saida.into_inner())
+}
+
+fn erro_interno(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+pub fn da_transacao(conn: &mut PgConnection, id_transacao: &str) -> QueryResult<Vec<Comprovante>> {
+    c_dsl::comprovantes
+        .filter(c_dsl::id_transacao.eq(id_transacao))
+        .order(c_dsl::criado_em.asc())
+        .load(conn)
+}
+
+pub fn das_transacoes(conn: &mut PgConnection, ids: &[String]) -> QueryResult<Vec<Comprovante>> {
+    c_dsl::comprovantes.filter(c_dsl::id_transacao.eq_any(ids)).load(conn)
+}
+
+fn do_usuario(conn: &mut PgConnection, id_usuario: &str, id: &str) -> Result<Comprovante, (StatusCode, String)> {
+    c_dsl::comprovantes
+        .filter(c_dsl::id.eq(id))
+        .filter(c_dsl::id_usuario.eq(id_usuario))
+        .first::<Comprovante>(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Comprovante não encontrado".to_string()))
+}
+
+/// Apaga do disco os arquivos dos comprovantes (os registros já saíram do banco)
+pub async fn remover_arquivos(comprovantes: &[Comprovante]) {
+    let raiz = diretorio();
+    for c in comprovantes {
+        for relativo in std::iter::once(&c.caminho).chain(c.caminho_miniatura.as_ref()) {
+            if let Err(e) = tokio::fs::remove_file(raiz.join(relativo)).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Falha ao apagar arquivo do comprovante {}: {}", c.id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Lê um arquivo do multipart respeitando o limite de tamanho
+async fn ler_campo(mut campo: axum::extract::multipart::Field<'_>, limite: usize) -> Result<Vec<u8>, (StatusCode, String)> {
+    let mut bytes = Vec::new();
+    while let Some(pedaco) = campo.chunk().await.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))? {
+        if bytes.len() + pedaco.len() > limite {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Cada arquivo pode ter no máximo {} KB", limite / 1024)));
+        }
+        bytes.extend_from_slice(&pedaco);
+    }
+    Ok(bytes)
+}
+
+/// Grava arquivo (e miniatura) e registra no banco; se o registro falhar, apaga os arquivos
+async fn salvar(
+    conn: &mut PgConnection,
+    id_usuario: &str,
+    id_transacao: &str,
+    nome_original: Option<String>,
+    bytes: Vec<u8>,
+) -> Result<Comprovante, (StatusCode, String)> {
+    let (mime, extensao, eh_imagem) = detectar_tipo(&bytes)
+        .ok_or((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Envie uma imagem (JPEG, PNG, WebP) ou um PDF".to_string()))?;
+    let soma = checksum(&bytes);
+    let repetido = c_dsl::comprovantes
+        .filter(c_dsl::id_transacao.eq(id_transacao))
+        .filter(c_dsl::checksum_sha256.eq(&soma))
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(erro_interno)?;
+    if repetido > 0 {
+        return Err((StatusCode::CONFLICT, "Este arquivo já está anexado à transação".to_string()));
+    }
+
+    let id = ulid::Ulid::new().to_string();
+    let pasta = diretorio().join(id_usuario);
+    tokio::fs::create_dir_all(&pasta).await.map_err(erro_interno)?;
+    let caminho = format!("{id_usuario}/{id}.{extensao}");
+    tokio::fs::write(diretorio().join(&caminho), &bytes).await.map_err(erro_interno)?;
+
+    let tamanho = bytes.len() as i32;
+    let miniatura = if eh_imagem {
+        tokio::task::spawn_blocking(move || gerar_miniatura(&bytes)).await.ok().flatten()
+    } else {
+        None
+    };
+    let caminho_miniatura = match miniatura {
+        Some(mini) => {
+            let relativo = format!("{id_usuario}/{id}_miniatura.jpg");
+            match tokio::fs::write(diretorio().join(&relativo), mini).await {
+                Ok(()) => Some(relativo),
+                Err(e) => {
+                    warn!("Falha ao gravar miniatura do comprovante {}: {}", id, e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let novo = NewComprovante {
+        id,
+        id_usuario: id_usuario.to_string(),
+        id_transacao: id_transacao.to_string(),
+        nome_arquivo: sanitizar_nome(nome_original.as_deref(), extensao),
+        mime: mime.to_string(),
+        tamanho,
+        checksum_sha256: soma,
+        caminho,
+        caminho_miniatura,
+        criado_em: Utc::now(),
+    };
+    match diesel::insert_into(c_dsl::comprovantes).values(&novo).get_result::<Comprovante>(conn) {
+        Ok(c) => Ok(c),
+        Err(e) => {
+            for relativo in std::iter::once(&novo.caminho).chain(novo.caminho_miniatura.as_ref()) {
+                let _ = tokio::fs::remove_file(diretorio().join(relativo)).await;
+            }
+            Err(erro_interno(e))
+        }
+    }
+}
+
+/// Envia um ou mais arquivos (campos multipart com nome de arquivo) para a transação
+pub async fn upload_comprovantes_handler(
+    jar: CookieJar,
+    Path(id_transacao): Path<String>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Vec<Comprovante>>), (StatusCode, String)> {
+    use crate::schema::transacoes::dsl as t_dsl;
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let existe = t_dsl::transacoes
+        .filter(t_dsl::id.eq(&id_transacao))
+        .filter(t_dsl::id_usuario.eq(&id_usuario))
+        .filter(t_dsl::excluido_em.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .map_err(erro_interno)?;
+    if existe == 0 {
+        return Err((StatusCode::NOT_FOUND, "Transação não encontrada".to_string()));
+    }
+    let ja_anexados = da_transacao(conn, &id_transacao).map_err(erro_interno)?.len();
+
+    let limite = limite_bytes();
+    let mut salvos = Vec::new();
+    while let Some(campo) = multipart.next_field().await.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))? {
+        let Some(nome) = campo.file_name().map(str::to_string) else {
+            continue;
+        };
+        if salvos.len() >= MAX_POR_ENVIO || ja_anexados + salvos.len() >= MAX_POR_TRANSACAO {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Máximo de {MAX_POR_ENVIO} arquivos por envio e {MAX_POR_TRANSACAO} por transação"),
+            ));
+        }
+        let bytes = ler_campo(campo, limite).await?;
+        if bytes.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, format!("Arquivo vazio: {nome}")));
+        }
+        salvos.push(salvar(conn, &id_usuario, &id_transacao, Some(nome), bytes).await?);
+    }
+    if salvos.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Nenhum arquivo enviado".to_string()));
+    }
+    info!("{} comprovante(s) anexado(s) à transação {}", salvos.len(), id_transacao);
+    Ok((StatusCode::CREATED, Json(salvos)))
+}
+
+pub async fn list_comprovantes_handler(
+    jar: CookieJar,
+    Path(id_transacao): Path<String>,
+) -> Result<Json<Vec<Comprovante>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let comprovantes = da_transacao(conn, &id_transacao).map_err(erro_interno)?;
+    Ok(Json(comprovantes.into_iter().filter(|c| c.id_usuario == id_usuario).collect()))
+}
+
+async fn servir_arquivo(relativo: &str, mime: &str, nome: &str) -> Result<Response, (StatusCode, String)> {
+    let bytes = tokio::fs::read(diretorio().join(relativo)).await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            (StatusCode::NOT_FOUND, "Arquivo do comprovante não encontrado".to_string())
+        } else {
+            erro_interno(e)
+        }
+    })?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::CONTENT_DISPOSITION, format!("inline; filename=\"{nome}\""))
+        .header(header::CACHE_CONTROL, "private, max-age=3600")
+        .header("X-Content-Type-Options", "nosniff")
+        .body(Body::from(bytes))
+        .map_err(erro_interno)
+}
+
+pub async fn download_comprovante_handler(
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let c = do_usuario(conn, &id_usuario, &id)?;
+    servir_arquivo(&c.caminho, &c.mime, &c.nome_arquivo).await
+}
+
+pub async fn miniatura_comprovante_handler(
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let c = do_usuario(conn, &id_usuario, &id)?;
+    let relativo = c.caminho_miniatura.ok_or((StatusCode::NOT_FOUND, "Comprovante sem miniatura".to_string()))?;
+    servir_arquivo(&relativo, "image/jpeg", "miniatura.jpg").await
+}
+
+pub async fn delete_comprovante_handler(
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<Json<bool>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let c = do_usuario(conn, &id_usuario, &id)?;
+    diesel::delete(c_dsl::comprovantes.filter(c_dsl::id.eq(&c.id))).execute(conn).map_err(erro_interno)?;
+    remover_arquivos(std::slice::from_ref(&c)).await;
+    Ok(Json(true))
+}
+
+/// Id do comprovante a partir do nome do arquivo (`<id>.<ext>` ou `<id>_miniatura.jpg`)
+fn id_do_arquivo(caminho: &FsPath) -> Option<String> {
+    let nome = caminho.file_stem()?.to_str()?;
+    Some(nome.strip_suffix("_miniatura").unwrap_or(nome).to_string())
+}
+
+/// Apaga arquivos sem registro no banco (transações removidas em lote, usuários excluídos)
+pub fn limpar_orfaos(conn: &mut PgConnection) -> std::io::Result<usize> {
+    let raiz = diretorio();
+    if !raiz.exists() {
+        return Ok(0);
+    }
+    let mut candidatos = Vec::new();
+    for pasta in std::fs::read_dir(&raiz)? {
+        let pasta = pasta?.path();
+        if !pasta.is_dir() {
+            continue;
+        }
+        for arquivo in std::fs::read_dir(&pasta)? {
+            let arquivo = arquivo?;
+            let antigo = arquivo
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|m| SystemTime::now().duration_since(m).ok())
+                .is_some_and(|idade| idade >= IDADE_MINIMA_ORFAO);
+            if let (true, Some(id)) = (antigo, id_do_arquivo(&arquivo.path())) {
+                candidatos.push((id, arquivo.path()));
+            }
+        }
+    }
+
+    let mut existentes: HashSet<String> = HashSet::new();
+    for lote in candidatos.chunks(500) {
+        let ids: Vec<&str> = lote.iter().map(|(id, _)| id.as_str()).collect();
+        let encontrados: Vec<String> = c_dsl::comprovantes
+            .filter(c_dsl::id.eq_any(ids))
+            .select(c_dsl::id)
+            .load(conn)
+            .map_err(std::io::Error::other)?;
+        existentes.extend(encontrados);
+    }
+
+    let mut removidos = 0;
+    for (id, caminho) in candidatos {
+        if !existentes.contains(&id) && std::fs::remove_file(&caminho).is_ok() {
+            removidos += 1;
+        }
+    }
+    Ok(removidos)
+}
+
+/// Limpeza de órfãos na subida do servidor e depois uma vez por dia
+pub fn agendar_limpeza() {
+    tokio::spawn(async {
+        loop {
+            let resultado = tokio::task::spawn_blocking(|| {
+                let conn = &mut db::establish_connection();
+                limpar_orfaos(conn)
+            })
+            .await;
+            match resultado {
+                Ok(Ok(n)) if n > 0 => info!("{} arquivo(s) de comprovante órfão(s) removido(s)", n),
+                Ok(Err(e)) => warn!("Falha na limpeza de comprovantes órfãos: {}", e),
+                _ => {}
+            }
+            tokio::time::sleep(INTERVALO_LIMPEZA).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detectar_tipo_pelo_conteudo() {
+        assert_eq!(detectar_tipo(b"%PDF-1.7 ...").map(|t| t.0), Some("application/pdf"));
+        assert_eq!(detectar_tipo(b"\x89PNG\r\n\x1a\n....").map(|t| t.1), Some("png"));
+        assert_eq!(detectar_tipo(b"RIFF\0\0\0\0WEBPVP8 ").map(|t| t.0), Some("image/webp"));
+        assert_eq!(detectar_tipo(b"<html>"), None);
+    }
+
+    #[test]
+    fn test_sanitizar_nome() {
+        assert_eq!(sanitizar_nome(Some("../../etc/passwd"), "pdf"), "passwd");
+        assert_eq!(sanitizar_nome(Some("nota \"fiscal\".pdf"), "pdf"), "nota _fiscal_.pdf");
+        assert_eq!(sanitizar_nome(Some(".."), "jpg"), "comprovante.jpg");
+        assert_eq!(sanitizar_nome(None, "jpg"), "comprovante.jpg");
+    }
+
+    #[test]
+    fn test_miniatura_de_png() {
+        let imagem = image::RgbImage::from_pixel(1000, 500, image::Rgb([200, 10, 10]));
+        let mut png = Cursor::new(Vec::new());
+        imagem.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let mini = gerar_miniatura(png.get_ref()).unwrap();
+        assert_eq!(detectar_tipo(&mini).map(|t| t.0), Some("image/jpeg"));
+        let lida = image::load_from_memory(&mini).unwrap();
+        assert_eq!((lida.width(), lida.height()), (LADO_MINIATURA, LADO_MINIATURA / 2));
+    }
+}
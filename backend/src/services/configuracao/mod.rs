@@ -301,7 +301,7 @@ pub fn seed_configuracoes_padrao(conn: &mut diesel::PgConnection) {
             chave: "projecao_metodo".to_string(),
             valor: Some("regressao_linear".to_string()),
             categoria: Some("dashboard".to_string()),
-            descricao: Some("Método de cálculo da projeção: regressao_linear, media, mediana, media_movel_3, media_movel_7, media_movel_30".to_string()),
+            descricao: Some("Método de cálculo da projeção: regressao_linear, media, mediana, media_movel_3, media_movel_7, media_movel_30, sazonal_semanal ou automatico (melhor no backtest)".to_string()),
             tipo_dado: Some("string".to_string()),
             eh_publica: false,
             criado_em: now,
@@ -332,6 +332,115 @@ pub fn seed_configuracoes_padrao(conn: &mut diesel::PgConnection) {
             criado_em: now,
             atualizado_em: now,
         },
+        // Parâmetros fiscais (mudam todo ano; o usuário pode sobrescrever)
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::imposto::CHAVE_TABELA.to_string(),
+            valor: Some(r#"[{"ate":2428.80,"aliquota":0,"deducao":0},{"ate":2826.65,"aliquota":7.5,"deducao":182.16},{"ate":3751.05,"aliquota":15,"deducao":394.16},{"ate":4664.68,"aliquota":22.5,"deducao":675.49},{"ate":null,"aliquota":27.5,"deducao":908.73}]"#.to_string()),
+            categoria: Some("imposto".to_string()),
+            descricao: Some("Tabela progressiva mensal do IRPF (reais): faixas com ate (vazio na última), aliquota (%) e deducao".to_string()),
+            tipo_dado: Some("json".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::imposto::CHAVE_DESCONTO_SIMPLIFICADO.to_string(),
+            valor: Some("607.20".to_string()),
+            categoria: Some("imposto".to_string()),
+            descricao: Some("Desconto simplificado mensal do Carnê-Leão (reais)".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::imposto::CHAVE_PRESUNCAO.to_string(),
+            valor: Some("60".to_string()),
+            categoria: Some("imposto".to_string()),
+            descricao: Some("Percentual tributável da receita de transporte de passageiros".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::imposto::CHAVE_LIMITE_MEI.to_string(),
+            valor: Some("81000.00".to_string()),
+            categoria: Some("imposto".to_string()),
+            descricao: Some("Limite anual de faturamento do MEI (reais)".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::imposto::CHAVE_DAS_MEI.to_string(),
+            valor: Some("80.90".to_string()),
+            categoria: Some("imposto".to_string()),
+            descricao: Some("Valor mensal do DAS do MEI prestador de serviços (reais)".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::fadiga::CHAVE_MAX_HORAS_CONTINUAS.to_string(),
+            valor: Some("5.5".to_string()),
+            categoria: Some("fadiga".to_string()),
+            descricao: Some("Horas seguidas de trabalho sem pausa antes do alerta".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::fadiga::CHAVE_PAUSA_MINIMA.to_string(),
+            valor: Some("30".to_string()),
+            categoria: Some("fadiga".to_string()),
+            descricao: Some("Duração mínima (minutos) para uma pausa interromper as horas contínuas".to_string()),
+            tipo_dado: Some("integer".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::fadiga::CHAVE_MAX_HORAS_DIARIAS.to_string(),
+            valor: Some("10".to_string()),
+            categoria: Some("fadiga".to_string()),
+            descricao: Some("Limite de horas trabalhadas por dia".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
+        NewConfiguracao {
+            id: Ulid::new().to_string(),
+            id_usuario: None,
+            chave: crate::services::fadiga::CHAVE_MAX_HORAS_SEMANAIS.to_string(),
+            valor: Some("60".to_string()),
+            categoria: Some("fadiga".to_string()),
+            descricao: Some("Limite de horas trabalhadas por semana (segunda a domingo)".to_string()),
+            tipo_dado: Some("decimal".to_string()),
+            eh_publica: false,
+            criado_em: now,
+            atualizado_em: now,
+        },
 
     ];
     // Adiciona valor_assinatura se não existir
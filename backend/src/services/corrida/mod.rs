@@ -0,0 +1,478 @@
+//! Corridas individuais: o detalhe por trás da transação de entrada que lança o lote
+//!
+//! Cada corrida guarda horários, distância, tarifa paga pelo passageiro, taxa da plataforma,
+//! gorjeta, dinâmica e produto. O ganho do motorista é tarifa menos taxa mais gorjeta; daí saem a
+//! taxa efetiva da plataforma (taxa sobre a tarifa, sem a gorjeta, que vai inteira para o
+//! motorista), a participação da gorjeta no ganho e o ganho por km. Corridas cuja transação está
+//! na lixeira ficam fora das listagens e das análises.
+
+use std::collections::HashMap;
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::{Corrida, SessaoTrabalho, Transacao};
+use crate::schema::corridas::dsl as c_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+#[derive(Deserialize)]
+pub struct CorridaPayload {
+    pub id_transacao: Option<String>,
+    /// Sem sessão, vale a da transação ou a que contém o embarque
+    pub id_sessao: Option<String>,
+    /// Sem plataforma, vale a da sessão
+    pub plataforma: Option<String>,
+    pub produto: String,
+    pub embarque: DateTime<Utc>,
+    pub desembarque: DateTime<Utc>,
+    pub distancia_km: Option<f64>,
+    /// Centavos
+    pub tarifa_passageiro: i32,
+    pub taxa_plataforma: i32,
+    pub gorjeta: Option<i32>,
+    pub dinamica: Option<f64>,
+    pub observacoes: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CorridasParams {
+    /// Embarque a partir de
+    pub inicio: Option<DateTime<Utc>>,
+    pub fim: Option<DateTime<Utc>>,
+    pub plataforma: Option<String>,
+    pub produto: Option<String>,
+    pub id_sessao: Option<String>,
+    pub id_transacao: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct MetricasCorrida {
+    /// Tarifa menos taxa mais gorjeta (centavos)
+    pub ganho_motorista: i64,
+    pub minutos: i64,
+    /// Percentual da tarifa retido pela plataforma
+    pub taxa_efetiva: Option<f64>,
+    /// Percentual do ganho que veio de gorjeta
+    pub participacao_gorjeta: Option<f64>,
+    /// Centavos por km
+    pub ganho_por_km: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CorridaDetalhada {
+    #[serde(flatten)]
+    pub corrida: Corrida,
+    #[serde(flatten)]
+    pub metricas: MetricasCorrida,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ResumoCorridas {
+    pub corridas: i64,
+    pub tarifa_total: i64,
+    pub taxa_total: i64,
+    pub gorjeta_total: i64,
+    pub ganho_total: i64,
+    pub km_total: f64,
+    pub minutos_total: i64,
+    pub taxa_efetiva: Option<f64>,
+    pub participacao_gorjeta: Option<f64>,
+    /// Só corridas com distância informada (centavos por km)
+    pub ganho_por_km: Option<i64>,
+    /// Centavos por hora em corrida
+    pub ganho_por_hora: Option<i64>,
+    pub ticket_medio: Option<i64>,
+    pub corridas_com_dinamica: i64,
+    /// Média do multiplicador nas corridas com dinâmica
+    pub dinamica_media: Option<f64>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GrupoCorridas {
+    pub plataforma: String,
+    /// Vazio no agrupamento só por plataforma
+    pub produto: Option<String>,
+    #[serde(flatten)]
+    pub resumo: ResumoCorridas,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AnaliseCorridas {
+    pub total: ResumoCorridas,
+    pub por_plataforma: Vec<GrupoCorridas>,
+    pub por_produto: Vec<GrupoCorridas>,
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn nao_encontrada() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Corrida não encontrada".to_string())
+}
+
+fn percentual(parte: i64, todo: i64) -> Option<f64> {
+    (todo > 0).then(|| (parte as f64 * 10000.0 / todo as f64).round() / 100.0)
+}
+
+fn texto(valor: Option<&str>) -> Option<String> {
+    valor.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+pub fn validar(payload: &CorridaPayload) -> Result<(), String> {
+    if payload.produto.trim().is_empty() {
+        return Err("Informe o produto (ex.: UberX, Comfort, Moto)".to_string());
+    }
+    if payload.desembarque < payload.embarque {
+        return Err("O desembarque deve ser depois do embarque".to_string());
+    }
+    if payload.distancia_km.is_some_and(|km| !km.is_finite() || km < 0.0) {
+        return Err("Distância inválida".to_string());
+    }
+    if payload.tarifa_passageiro < 0 || payload.taxa_plataforma < 0 || payload.gorjeta.unwrap_or(0) < 0 {
+        return Err("Tarifa, taxa e gorjeta não podem ser negativas".to_string());
+    }
+    if payload.dinamica.is_some_and(|d| !d.is_finite() || d < 1.0) {
+        return Err("A dinâmica é um multiplicador a partir de 1".to_string());
+    }
+    Ok(())
+}
+
+pub fn metricas(c: &Corrida) -> MetricasCorrida {
+    let ganho = c.tarifa_passageiro as i64 - c.taxa_plataforma as i64 + c.gorjeta as i64;
+    MetricasCorrida {
+        ganho_motorista: ganho,
+        minutos: (c.desembarque - c.embarque).num_minutes(),
+        taxa_efetiva: percentual(c.taxa_plataforma as i64, c.tarifa_passageiro as i64),
+        participacao_gorjeta: percentual(c.gorjeta as i64, ganho),
+        ganho_por_km: c.distancia_km.filter(|km| *km > 0.0).map(|km| (ganho as f64 / km).round() as i64),
+    }
+}
+
+pub fn resumir<'a>(corridas: impl IntoIterator<Item = &'a Corrida>) -> ResumoCorridas {
+    let mut r = ResumoCorridas::default();
+    let (mut ganho_com_km, mut soma_dinamica) = (0i64, 0f64);
+    for c in corridas {
+        let m = metricas(c);
+        r.corridas += 1;
+        r.tarifa_total += c.tarifa_passageiro as i64;
+        r.taxa_total += c.taxa_plataforma as i64;
+        r.gorjeta_total += c.gorjeta as i64;
+        r.ganho_total += m.ganho_motorista;
+        r.minutos_total += m.minutos;
+        if let Some(km) = c.distancia_km.filter(|km| *km > 0.0) {
+            r.km_total += km;
+            ganho_com_km += m.ganho_motorista;
+        }
+        if let Some(d) = c.dinamica.filter(|d| *d > 1.0) {
+            r.corridas_com_dinamica += 1;
+            soma_dinamica += d;
+        }
+    }
+    r.taxa_efetiva = percentual(r.taxa_total, r.tarifa_total);
+    r.participacao_gorjeta = percentual(r.gorjeta_total, r.ganho_total);
+    r.ganho_por_km = (r.km_total > 0.0).then(|| (ganho_com_km as f64 / r.km_total).round() as i64);
+    r.ganho_por_hora = (r.minutos_total > 0).then(|| r.ganho_total * 60 / r.minutos_total);
+    r.ticket_medio = (r.corridas > 0).then(|| r.ganho_total / r.corridas);
+    r.dinamica_media = (r.corridas_com_dinamica > 0).then(|| (soma_dinamica * 100.0 / r.corridas_com_dinamica as f64).round() / 100.0);
+    r
+}
+
+/// Agrupa sem diferenciar maiúsculas; o nome exibido é o da primeira corrida do grupo.
+/// Grupos do maior para o menor ganho
+pub fn agrupar(corridas: &[Corrida], por_produto: bool) -> Vec<GrupoCorridas> {
+    let mut grupos: HashMap<(String, String), Vec<&Corrida>> = HashMap::new();
+    let mut ordem = Vec::new();
+    for c in corridas {
+        let produto = if por_produto { c.produto.trim().to_lowercase() } else { String::new() };
+        let chave = (c.plataforma.trim().to_lowercase(), produto);
+        grupos.entry(chave.clone()).or_insert_with(|| {
+            ordem.push(chave);
+            Vec::new()
+        }).push(c);
+    }
+    let mut resultado: Vec<GrupoCorridas> = ordem
+        .into_iter()
+        .map(|chave| {
+            let itens = &grupos[&chave];
+            GrupoCorridas {
+                plataforma: itens[0].plataforma.clone(),
+                produto: por_produto.then(|| itens[0].produto.clone()),
+                resumo: resumir(itens.iter().copied()),
+            }
+        })
+        .collect();
+    resultado.sort_by_key(|g| std::cmp::Reverse(g.resumo.ganho_total));
+    resultado
+}
+
+pub fn analisar(corridas: &[Corrida]) -> AnaliseCorridas {
+    AnaliseCorridas {
+        total: resumir(corridas),
+        por_plataforma: agrupar(corridas, false),
+        por_produto: agrupar(corridas, true),
+    }
+}
+
+/// Corridas do usuário, fora as de transação na lixeira, em ordem de embarque
+pub fn carregar(conn: &mut PgConnection, id_usuario: &str, params: &CorridasParams) -> QueryResult<Vec<Corrida>> {
+    use crate::schema::transacoes::dsl as t_dsl;
+    let mut query = c_dsl::corridas
+        .left_join(t_dsl::transacoes)
+        .filter(c_dsl::id_usuario.eq(id_usuario))
+        .filter(t_dsl::id.is_null().or(t_dsl::excluido_em.is_null()))
+        .select(Corrida::as_select())
+        .into_boxed();
+    if let Some(inicio) = params.inicio {
+        query = query.filter(c_dsl::embarque.ge(inicio));
+    }
+    if let Some(fim) = params.fim {
+        query = query.filter(c_dsl::embarque.le(fim));
+    }
+    if let Some(plataforma) = texto(params.plataforma.as_deref()) {
+        query = query.filter(c_dsl::plataforma.ilike(plataforma));
+    }
+    if let Some(produto) = texto(params.produto.as_deref()) {
+        query = query.filter(c_dsl::produto.ilike(produto));
+    }
+    if let Some(sessao) = params.id_sessao.as_deref() {
+        query = query.filter(c_dsl::id_sessao.eq(sessao));
+    }
+    if let Some(transacao) = params.id_transacao.as_deref() {
+        query = query.filter(c_dsl::id_transacao.eq(transacao));
+    }
+    query.order((c_dsl::embarque.asc(), c_dsl::id.asc())).load(conn)
+}
+
+fn buscar(conn: &mut PgConnection, id_usuario: &str, id_corrida: &str) -> QueryResult<Option<Corrida>> {
+    c_dsl::corridas
+        .filter(c_dsl::id.eq(id_corrida))
+        .filter(c_dsl::id_usuario.eq(id_usuario))
+        .first(conn)
+        .optional()
+}
+
+/// Vínculos da corrida: transação de entrada, sessão e plataforma
+struct Vinculos {
+    id_transacao: Option<String>,
+    id_sessao: Option<String>,
+    plataforma: String,
+}
+
+fn vincular(conn: &mut PgConnection, id_usuario: &str, payload: &CorridaPayload) -> Result<Vinculos, (StatusCode, String)> {
+    use crate::schema::sessoes_trabalho::dsl as s_dsl;
+    use crate::schema::transacoes::dsl as t_dsl;
+    let transacao: Option<Transacao> = match payload.id_transacao.as_deref() {
+        Some(id_transacao) => Some(
+            t_dsl::transacoes
+                .filter(t_dsl::id.eq(id_transacao))
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.is_null())
+                .first(conn)
+                .optional()
+                .map_err(erro_interno)?
+                .ok_or((StatusCode::BAD_REQUEST, "Transação não encontrada".to_string()))?,
+        ),
+        None => None,
+    };
+    if transacao.as_ref().is_some_and(|t| t.tipo != "entrada") {
+        return Err((StatusCode::BAD_REQUEST, "A corrida só pode ser vinculada a uma transação de entrada".to_string()));
+    }
+    let ativas = s_dsl::sessoes_trabalho.filter(s_dsl::id_usuario.eq(id_usuario)).filter(s_dsl::excluido_em.is_null());
+    let sessao: Option<SessaoTrabalho> = match payload.id_sessao.as_deref().or(transacao.as_ref().and_then(|t| t.id_sessao.as_deref())) {
+        Some(id_sessao) => Some(
+            ativas
+                .filter(s_dsl::id.eq(id_sessao))
+                .first(conn)
+                .optional()
+                .map_err(erro_interno)?
+                .ok_or((StatusCode::BAD_REQUEST, "Sessão não encontrada".to_string()))?,
+        ),
+        None => ativas
+            .filter(s_dsl::inicio.le(payload.embarque))
+            .filter(s_dsl::fim.ge(payload.embarque).or(s_dsl::fim.is_null().and(s_dsl::eh_ativa.eq(true))))
+            .order(s_dsl::inicio.desc())
+            .first(conn)
+            .optional()
+            .map_err(erro_interno)?,
+    };
+    let plataforma = texto(payload.plataforma.as_deref())
+        .or_else(|| sessao.as_ref().and_then(|s| texto(s.plataforma.as_deref())))
+        .ok_or((StatusCode::BAD_REQUEST, "Informe a plataforma".to_string()))?;
+    Ok(Vinculos { id_transacao: transacao.map(|t| t.id), id_sessao: sessao.map(|s| s.id), plataforma })
+}
+
+fn detalhar(corrida: Corrida) -> CorridaDetalhada {
+    CorridaDetalhada { metricas: metricas(&corrida), corrida }
+}
+
+pub async fn list_corridas_handler(
+    jar: CookieJar,
+    Query(params): Query<CorridasParams>,
+) -> Result<Json<Vec<CorridaDetalhada>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let corridas = carregar(conn, &id_usuario, &params).map_err(erro_interno)?;
+    Ok(Json(corridas.into_iter().map(detalhar).collect()))
+}
+
+pub async fn analise_corridas_handler(
+    jar: CookieJar,
+    Query(params): Query<CorridasParams>,
+) -> Result<Json<AnaliseCorridas>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let corridas = carregar(conn, &id_usuario, &params).map_err(erro_interno)?;
+    Ok(Json(analisar(&corridas)))
+}
+
+pub async fn create_corrida_handler(
+    jar: CookieJar,
+    Json(payload): Json<CorridaPayload>,
+) -> Result<(StatusCode, Json<CorridaDetalhada>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    let vinculos = vincular(conn, &id_usuario, &payload)?;
+    let agora = Utc::now();
+    let corrida = Corrida {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        id_transacao: vinculos.id_transacao,
+        id_sessao: vinculos.id_sessao,
+        plataforma: vinculos.plataforma,
+        produto: payload.produto.trim().to_string(),
+        embarque: payload.embarque,
+        desembarque: payload.desembarque,
+        distancia_km: payload.distancia_km,
+        tarifa_passageiro: payload.tarifa_passageiro,
+        taxa_plataforma: payload.taxa_plataforma,
+        gorjeta: payload.gorjeta.unwrap_or(0),
+        dinamica: payload.dinamica,
+        observacoes: texto(payload.observacoes.as_deref()),
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    diesel::insert_into(c_dsl::corridas).values(&corrida).execute(conn).map_err(erro_interno)?;
+    Ok((StatusCode::CREATED, Json(detalhar(corrida))))
+}
+
+pub async fn update_corrida_handler(
+    jar: CookieJar,
+    Path(id_corrida): Path<String>,
+    Json(payload): Json<CorridaPayload>,
+) -> Result<Json<CorridaDetalhada>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    let atual = buscar(conn, &id_usuario, &id_corrida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let vinculos = vincular(conn, &id_usuario, &payload)?;
+    diesel::update(c_dsl::corridas.find(&atual.id))
+        .set((
+            c_dsl::id_transacao.eq(vinculos.id_transacao),
+            c_dsl::id_sessao.eq(vinculos.id_sessao),
+            c_dsl::plataforma.eq(vinculos.plataforma),
+            c_dsl::produto.eq(payload.produto.trim()),
+            c_dsl::embarque.eq(payload.embarque),
+            c_dsl::desembarque.eq(payload.desembarque),
+            c_dsl::distancia_km.eq(payload.distancia_km),
+            c_dsl::tarifa_passageiro.eq(payload.tarifa_passageiro),
+            c_dsl::taxa_plataforma.eq(payload.taxa_plataforma),
+            c_dsl::gorjeta.eq(payload.gorjeta.unwrap_or(0)),
+            c_dsl::dinamica.eq(payload.dinamica),
+            c_dsl::observacoes.eq(texto(payload.observacoes.as_deref())),
+            c_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Corrida>(conn)
+        .map(|c| Json(detalhar(c)))
+        .map_err(erro_interno)
+}
+
+pub async fn delete_corrida_handler(
+    jar: CookieJar,
+    Path(id_corrida): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let apagadas = diesel::delete(c_dsl::corridas.filter(c_dsl::id.eq(&id_corrida)).filter(c_dsl::id_usuario.eq(&id_usuario)))
+        .execute(conn)
+        .map_err(erro_interno)?;
+    if apagadas == 0 {
+        return Err(nao_encontrada());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Valores em centavos: (tarifa, taxa, gorjeta)
+    fn corrida(plataforma: &str, produto: &str, minutos: i64, km: Option<f64>, (tarifa, taxa, gorjeta): (i32, i32, i32), dinamica: Option<f64>) -> Corrida {
+        let embarque = Utc.with_ymd_and_hms(2025, 9, 15, 10, 0, 0).unwrap();
+        Corrida {
+            id: ulid::Ulid::new().to_string(),
+            id_usuario: "u1".to_string(),
+            id_transacao: None,
+            id_sessao: None,
+            plataforma: plataforma.to_string(),
+            produto: produto.to_string(),
+            embarque,
+            desembarque: embarque + chrono::Duration::minutes(minutos),
+            distancia_km: km,
+            tarifa_passageiro: tarifa,
+            taxa_plataforma: taxa,
+            gorjeta,
+            dinamica,
+            observacoes: None,
+            criado_em: embarque,
+            atualizado_em: embarque,
+        }
+    }
+
+    #[test]
+    fn test_metricas_da_corrida() {
+        // R$ 25,00 pagos, R$ 6,25 de taxa, R$ 5,00 de gorjeta, 10 km
+        let m = metricas(&corrida("Uber", "UberX", 20, Some(10.0), (2500, 625, 500), None));
+        assert_eq!(m, MetricasCorrida {
+            ganho_motorista: 2375,
+            minutos: 20,
+            taxa_efetiva: Some(25.0),
+            participacao_gorjeta: Some(21.05),
+            ganho_por_km: Some(238),
+        });
+        let sem_tarifa = metricas(&corrida("Uber", "UberX", 0, Some(0.0), (0, 0, 0), None));
+        assert_eq!((sem_tarifa.taxa_efetiva, sem_tarifa.participacao_gorjeta, sem_tarifa.ganho_por_km), (None, None, None));
+    }
+
+    #[test]
+    fn test_analise_por_plataforma_e_produto() {
+        let corridas = vec![
+            corrida("Uber", "UberX", 30, Some(10.0), (2000, 500, 0), None),
+            corrida("uber", "Comfort", 30, Some(10.0), (4000, 1000, 1000), Some(1.5)),
+            corrida("99", "Moto", 15, None, (1000, 200, 0), Some(1.2)),
+            corrida("Uber", "uberx", 15, Some(5.0), (1000, 300, 0), None),
+        ];
+        let a = analisar(&corridas);
+        assert_eq!(a.total.corridas, 4);
+        assert_eq!(a.total.ganho_total, 1500 + 4000 + 800 + 700);
+        assert_eq!(a.total.taxa_efetiva, Some(25.0));
+        // a corrida sem km fica fora do R$/km
+        assert_eq!(a.total.ganho_por_km, Some(248));
+        assert_eq!(a.total.ganho_por_hora, Some(4666));
+        assert_eq!((a.total.corridas_com_dinamica, a.total.dinamica_media), (2, Some(1.35)));
+
+        assert_eq!(a.por_plataforma.iter().map(|g| (g.plataforma.as_str(), g.resumo.corridas)).collect::<Vec<_>>(), vec![("Uber", 3), ("99", 1)]);
+        let produtos: Vec<_> = a.por_produto.iter().map(|g| (g.produto.as_deref().unwrap(), g.resumo.ganho_total)).collect();
+        assert_eq!(produtos, vec![("Comfort", 4000), ("UberX", 2200), ("Moto", 800)]);
+        let comfort = &a.por_produto[0].resumo;
+        assert_eq!((comfort.participacao_gorjeta, comfort.ticket_medio), (Some(25.0), Some(4000)));
+    }
+}
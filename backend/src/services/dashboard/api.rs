@@ -1,9 +1,12 @@
-use axum::{Json};
+use axum::{Json, extract::Query, http::StatusCode};
 use axum_extra::extract::cookie::CookieJar;
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use crate::db;
-use crate::services::dashboard::service::{self, PlatformResult, DashboardStats};
+use crate::services::dashboard::service::{self, PlatformResult, DashboardStats, DashboardFiltro};
+use crate::services::dashboard::periodo::{self, DashboardPeriodo};
+use crate::services::dashboard::projecao::{self, BacktestParams, BacktestResposta};
+use crate::services::dashboard::razao;
 
 #[derive(Deserialize, Serialize)]
 pub struct Claims {
@@ -31,9 +34,14 @@ pub async fn dashboard_stats_handler(
     };
 
     // CACHE LAYER: Tentar cálculo incremental primeiro
-    if let Some(stats) = crate::cache::dashboard::calculate_dashboard_incremental(&id_usuario).await {
+    if let Some(mut stats) = crate::cache::dashboard::calculate_dashboard_incremental(&id_usuario).await {
         // Verificar mecanismo de segurança
         if crate::cache::transacao::check_cache_safety(&id_usuario).await {
+            // a detecção roda em segundo plano, então o contador não vem do cache
+            let conn = &mut db::establish_connection();
+            stats.alertas_pendentes = crate::services::anomalia::contar_pendentes(conn, &id_usuario);
+            stats.proximas_parcelas = crate::services::divida::proximas_parcelas(conn, &id_usuario);
+            stats.envelopes = crate::services::envelope::resumo(conn, &id_usuario);
             return Json(stats);
         } else {
             // Muitas transações novas - limpar cache e recalcular
@@ -42,12 +50,17 @@ pub async fn dashboard_stats_handler(
     }
 
     // Cache miss ou mecanismo de segurança ativado - calcular do zero
+    // (mesmo cálculo de `service::compute_dashboard_stats`, guardando o razão no cache)
+    // (a versão é lida antes do banco: se uma escrita chegar no meio, o razão não vai para o cache)
+    let versao = crate::cache::dashboard::versao_dashboard(&id_usuario).await;
     let conn = &mut db::establish_connection();
-    let stats = service::compute_dashboard_stats(conn, &id_usuario);
-    
+    let hoje = chrono::Utc::now().date_naive();
+    let razao = razao::carregar(conn, &id_usuario, hoje);
+    let stats = razao.stats(hoje);
+
     // Salvar no cache para próximas consultas
-    crate::cache::dashboard::save_dashboard_to_cache(&id_usuario, &stats).await;
-    
+    crate::cache::dashboard::save_dashboard_to_cache(&id_usuario, razao, versao).await;
+
     Json(stats)
 }
 
@@ -63,3 +76,31 @@ pub async fn dashboard_platform_handler(
     let results = service::compute_platforms(conn, &id_usuario, None);
     Json(results)
 }
+
+/// Dashboard de um intervalo arbitrário, filtrável por tipo/categoria e com comparação opcional
+#[axum::debug_handler]
+pub async fn dashboard_periodo_handler(
+    jar: CookieJar,
+    Json(filtro): Json<DashboardFiltro>,
+) -> Result<Json<DashboardPeriodo>, (StatusCode, String)> {
+    let id_usuario = extract_user_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let conn = &mut db::establish_connection();
+    periodo::compute_dashboard_periodo(conn, &id_usuario, &filtro)
+        .map(Json)
+        .map_err(Into::into)
+}
+
+/// Backtest dos métodos de projeção sobre o histórico do usuário
+#[axum::debug_handler]
+pub async fn dashboard_backtest_projecao_handler(
+    jar: CookieJar,
+    Query(params): Query<BacktestParams>,
+) -> Result<Json<BacktestResposta>, (StatusCode, String)> {
+    let id_usuario = extract_user_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let conn = &mut db::establish_connection();
+    projecao::compute_backtest(conn, &id_usuario, &params)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
@@ -1,5 +1,6 @@
-
-
 pub mod api;
 pub mod service;
-pub use api::{dashboard_stats_handler, dashboard_platform_handler};
\ No newline at end of file
+pub mod periodo;
+pub mod projecao;
+pub mod razao;
+pub use api::{dashboard_stats_handler, dashboard_platform_handler, dashboard_periodo_handler, dashboard_backtest_projecao_handler};
\ No newline at end of file
@@ -0,0 +1,517 @@
+//! Dashboard para intervalos arbitrários
+//!
+//! Usa o `DashboardFiltro` (periodo, data_inicio, data_fim, tipo, categoria) para calcular
+//! as mesmas métricas do dashboard principal em qualquer intervalo, com série diária,
+//! top sources, plataformas e comparação com o período anterior ou com o ano anterior.
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::models::{Categoria, Transacao};
+use crate::services::dashboard::service::{DashboardFiltro, PlatformResult, TopSourceItem, TopSources};
+use crate::services::transacao::divisao;
+
+/// Tamanho máximo do intervalo, em dias (mantém série diária e queries limitadas)
+pub const MAX_DIAS_INTERVALO: i64 = 731;
+const TOP_SOURCES_LIMITE: usize = 5;
+const PLATAFORMAS: [&str; 2] = ["Corrida Uber", "Corrida 99"];
+
+#[derive(Serialize, Clone)]
+pub struct ResumoPeriodo {
+    pub inicio: DateTime<Utc>,
+    pub fim: DateTime<Utc>,
+    pub ganhos: i64,
+    pub gastos: i64,
+    pub lucro: i64,
+    pub corridas: u32,
+    pub horas: i32,
+    pub km: f64,
+    pub transacoes: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SerieDiariaItem {
+    pub data: NaiveDate,
+    pub label: String,
+    pub ganhos: i64,
+    pub gastos: i64,
+    pub lucro: i64,
+    pub corridas: u32,
+    pub horas: i32,
+    pub km: f64,
+}
+
+/// Variação percentual em relação ao período comparado (None quando a base é zero)
+#[derive(Serialize, Clone, Default)]
+pub struct VariacaoPeriodo {
+    pub ganhos: Option<f64>,
+    pub gastos: Option<f64>,
+    pub lucro: Option<f64>,
+    pub corridas: Option<f64>,
+    pub horas: Option<f64>,
+    pub km: Option<f64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ComparacaoPeriodo {
+    pub modo: String,
+    pub resumo: ResumoPeriodo,
+    pub serie_diaria: Vec<SerieDiariaItem>,
+    pub variacao_percentual: VariacaoPeriodo,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DashboardPeriodo {
+    pub periodo: String,
+    pub resumo: ResumoPeriodo,
+    pub serie_diaria: Vec<SerieDiariaItem>,
+    pub top_sources: TopSources,
+    pub platforms: HashMap<String, PlatformResult>,
+    pub comparacao: Option<ComparacaoPeriodo>,
+}
+
+/// Filtro inválido vira 400; falha do banco vira 500 em vez de um dashboard zerado
+pub enum ErroPeriodo {
+    Invalido(String),
+    Banco(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ErroPeriodo {
+    fn from(e: diesel::result::Error) -> Self {
+        ErroPeriodo::Banco(e)
+    }
+}
+
+impl From<ErroPeriodo> for (StatusCode, String) {
+    fn from(e: ErroPeriodo) -> Self {
+        match e {
+            ErroPeriodo::Invalido(msg) => (StatusCode::BAD_REQUEST, msg),
+            ErroPeriodo::Banco(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao calcular o dashboard: {e}")),
+        }
+    }
+}
+
+fn inicio_do_dia(dia: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&dia.and_hms_opt(0, 0, 0).unwrap())
+}
+
+fn fim_do_dia(dia: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&dia.and_hms_opt(23, 59, 59).unwrap())
+}
+
+fn primeiro_dia_mes(ano: i32, mes: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(ano, mes, 1).unwrap()
+}
+
+fn ultimo_dia_mes(ano: i32, mes: u32) -> NaiveDate {
+    let proximo = if mes == 12 { primeiro_dia_mes(ano + 1, 1) } else { primeiro_dia_mes(ano, mes + 1) };
+    proximo - Duration::days(1)
+}
+
+/// Resolve o intervalo pedido: datas explícitas têm prioridade sobre `periodo`.
+/// Retorna (rótulo do período, início, fim).
+pub fn resolver_intervalo(filtro: &DashboardFiltro, agora: DateTime<Utc>) -> Result<(String, DateTime<Utc>, DateTime<Utc>), String> {
+    let hoje = agora.date_naive();
+    let (rotulo, inicio, fim) = match (filtro.data_inicio, filtro.data_fim) {
+        (Some(ini), Some(f)) => ("personalizado".to_string(), ini, f),
+        (Some(_), None) | (None, Some(_)) => {
+            return Err("Informe data_inicio e data_fim juntos".to_string());
+        }
+        (None, None) => {
+            let periodo = filtro.periodo.clone().unwrap_or_else(|| "mes".to_string());
+            let (ini, f) = match periodo.as_str() {
+                "hoje" => (hoje, hoje),
+                "ontem" => (hoje - Duration::days(1), hoje - Duration::days(1)),
+                "semana" => {
+                    let ini = hoje - Duration::days(hoje.weekday().num_days_from_monday() as i64);
+                    (ini, ini + Duration::days(6))
+                }
+                "semana_passada" => {
+                    let ini = hoje - Duration::days(hoje.weekday().num_days_from_monday() as i64 + 7);
+                    (ini, ini + Duration::days(6))
+                }
+                "7dias" => (hoje - Duration::days(6), hoje),
+                "30dias" => (hoje - Duration::days(29), hoje),
+                "mes" => (primeiro_dia_mes(hoje.year(), hoje.month()), ultimo_dia_mes(hoje.year(), hoje.month())),
+                "mes_passado" => {
+                    let (ano, mes) = if hoje.month() == 1 { (hoje.year() - 1, 12) } else { (hoje.year(), hoje.month() - 1) };
+                    (primeiro_dia_mes(ano, mes), ultimo_dia_mes(ano, mes))
+                }
+                "ano" => (primeiro_dia_mes(hoje.year(), 1), ultimo_dia_mes(hoje.year(), 12)),
+                outro => return Err(format!("Período inválido: {outro}")),
+            };
+            (periodo, inicio_do_dia(ini), fim_do_dia(f))
+        }
+    };
+
+    if inicio > fim {
+        return Err("data_inicio deve ser anterior a data_fim".to_string());
+    }
+    if (fim.date_naive() - inicio.date_naive()).num_days() + 1 > MAX_DIAS_INTERVALO {
+        return Err(format!("Intervalo máximo de {MAX_DIAS_INTERVALO} dias"));
+    }
+    Ok((rotulo, inicio, fim))
+}
+
+/// Intervalo usado na comparação: "periodo_anterior" (mesmo tamanho, imediatamente antes)
+/// ou "ano_anterior" (mesmas datas no ano anterior)
+pub fn intervalo_comparacao(modo: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    match modo {
+        "periodo_anterior" => {
+            let duracao = fim - inicio + Duration::seconds(1);
+            Ok((inicio - duracao, inicio - Duration::seconds(1)))
+        }
+        "ano_anterior" => {
+            // 29/02 não existe no ano anterior: cai para 365 dias antes
+            let recuar = |d: DateTime<Utc>| d.with_year(d.year() - 1).unwrap_or(d - Duration::days(365));
+            Ok((recuar(inicio), recuar(fim)))
+        }
+        outro => Err(format!("Modo de comparação inválido: {outro}")),
+    }
+}
+
+fn carregar_transacoes(conn: &mut PgConnection, id_usuario: &str, filtro: &DashboardFiltro, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> QueryResult<Vec<Transacao>> {
+    use crate::schema::transacoes::dsl as t_dsl;
+    let mut query = t_dsl::transacoes
+        .filter(t_dsl::id_usuario.eq(id_usuario))
+        .filter(t_dsl::excluido_em.is_null())
+        .filter(t_dsl::data.ge(inicio))
+        .filter(t_dsl::data.le(fim))
+        .into_boxed();
+    if let Some(ref cat) = filtro.categoria {
+        use crate::schema::transacoes_divisoes::dsl as div_dsl;
+        query = query.filter(t_dsl::id_categoria.eq(cat).or(t_dsl::id.eq_any(
+            div_dsl::transacoes_divisoes.filter(div_dsl::id_categoria.eq(cat)).select(div_dsl::id_transacao)
+        )));
+    }
+    if let Some(ref tipo_f) = filtro.tipo {
+        query = query.filter(t_dsl::tipo.eq(tipo_f));
+    }
+    let transacoes = query.order(t_dsl::data.asc()).load::<Transacao>(conn)?;
+    // divididas entram por linha; com filtro de categoria, só as linhas dela
+    let ids: Vec<String> = transacoes.iter().map(|t| t.id.clone()).collect();
+    let divisoes = divisao::por_transacao(conn, &ids)?;
+    let mut partes: Vec<Transacao> = transacoes
+        .iter()
+        .flat_map(|t| divisao::partes(t, divisoes.get(&t.id).map(Vec::as_slice).unwrap_or_default()))
+        .collect();
+    if let Some(ref cat) = filtro.categoria {
+        partes.retain(|t| &t.id_categoria == cat);
+    }
+    Ok(partes)
+}
+
+/// Minutos trabalhados por dia (pela data de início da sessão)
+fn minutos_por_dia(conn: &mut PgConnection, id_usuario: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> QueryResult<BTreeMap<NaiveDate, i64>> {
+    use crate::schema::sessoes_trabalho::dsl as s_dsl;
+    let sessoes: Vec<(DateTime<Utc>, Option<i32>)> = s_dsl::sessoes_trabalho
+        .filter(s_dsl::id_usuario.eq(id_usuario))
+        .filter(s_dsl::excluido_em.is_null())
+        .filter(s_dsl::inicio.ge(inicio))
+        .filter(s_dsl::inicio.le(fim))
+        .select((s_dsl::inicio, s_dsl::total_minutos))
+        .load(conn)?;
+    let mut mapa = BTreeMap::new();
+    for (ini, minutos) in sessoes {
+        *mapa.entry(ini.date_naive()).or_insert(0) += minutos.unwrap_or(0) as i64;
+    }
+    Ok(mapa)
+}
+
+/// Agrega as transações (já filtradas) em um resumo e em uma série diária contínua
+pub fn agregar_periodo(transacoes: &[Transacao], minutos: &BTreeMap<NaiveDate, i64>, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> (ResumoPeriodo, Vec<SerieDiariaItem>) {
+    let mut serie: BTreeMap<NaiveDate, SerieDiariaItem> = BTreeMap::new();
+    let mut dia = inicio.date_naive();
+    while dia <= fim.date_naive() {
+        let minutos_dia = minutos.get(&dia).copied().unwrap_or(0);
+        serie.insert(dia, SerieDiariaItem {
+            data: dia,
+            label: dia.format("%d/%m").to_string(),
+            ganhos: 0,
+            gastos: 0,
+            lucro: 0,
+            corridas: 0,
+            horas: (minutos_dia / 60) as i32,
+            km: 0.0,
+        });
+        dia += Duration::days(1);
+    }
+
+    for t in transacoes {
+        if let Some(item) = serie.get_mut(&t.data.date_naive()) {
+            match t.tipo.as_str() {
+                "entrada" => {
+                    item.ganhos += t.valor as i64;
+                    item.corridas += t.eventos.max(0) as u32;
+                }
+                "saida" => item.gastos += t.valor as i64,
+                _ => {}
+            }
+            item.km += t.km.unwrap_or(0.0);
+        }
+    }
+
+    let serie: Vec<SerieDiariaItem> = serie
+        .into_values()
+        .map(|mut item| {
+            item.lucro = item.ganhos - item.gastos;
+            item
+        })
+        .collect();
+
+    let ganhos: i64 = serie.iter().map(|i| i.ganhos).sum();
+    let gastos: i64 = serie.iter().map(|i| i.gastos).sum();
+    let total_minutos: i64 = minutos.values().sum();
+    let resumo = ResumoPeriodo {
+        inicio,
+        fim,
+        ganhos,
+        gastos,
+        lucro: ganhos - gastos,
+        corridas: serie.iter().map(|i| i.corridas).sum(),
+        horas: (total_minutos / 60) as i32,
+        km: serie.iter().map(|i| i.km).sum(),
+        // partes de uma mesma transação dividida contam uma vez
+        transacoes: transacoes.iter().map(|t| t.id.as_str()).collect::<HashSet<_>>().len(),
+    };
+    (resumo, serie)
+}
+
+fn top_sources(transacoes: &[Transacao], categorias: &HashMap<String, Categoria>, periodo: &str) -> TopSources {
+    let ranking = |tipo: &str| -> Vec<TopSourceItem> {
+        let mut somas: HashMap<&str, i64> = HashMap::new();
+        for t in transacoes.iter().filter(|t| t.tipo == tipo) {
+            *somas.entry(t.id_categoria.as_str()).or_insert(0) += t.valor as i64;
+        }
+        let mut itens: Vec<(&str, i64)> = somas.into_iter().collect();
+        itens.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        itens
+            .into_iter()
+            .take(TOP_SOURCES_LIMITE)
+            .map(|(cat_id, valor)| {
+                let categoria = categorias.get(cat_id);
+                TopSourceItem {
+                    periodo: periodo.to_string(),
+                    tipo: tipo.to_string(),
+                    categoria_id: Some(cat_id.to_string()),
+                    nome: categoria.map(|c| c.nome.clone()),
+                    icone: categoria.and_then(|c| c.icone.clone()),
+                    cor: categoria.and_then(|c| c.cor.clone()),
+                    valor,
+                }
+            })
+            .collect()
+    };
+    TopSources { receitas: ranking("entrada"), despesas: ranking("saida") }
+}
+
+fn platforms(transacoes: &[Transacao], categorias: &HashMap<String, Categoria>, periodo: &str) -> HashMap<String, PlatformResult> {
+    let mut results = HashMap::new();
+    for name in PLATAFORMAS {
+        let cats: Vec<&Categoria> = categorias.values().filter(|c| c.nome == name).collect();
+        let cat_ids: Vec<&str> = cats.iter().map(|c| c.id.as_str()).collect();
+        let entradas = transacoes
+            .iter()
+            .filter(|t| t.tipo == "entrada" && cat_ids.contains(&t.id_categoria.as_str()));
+        let (ganhos, corridas) = entradas.fold((0i32, 0u32), |(g, c), t| (g + t.valor, c + t.eventos.max(0) as u32));
+        let (icone, cor) = cats.first().map(|c| (c.icone.clone(), c.cor.clone())).unwrap_or((None, None));
+        results.insert(name.to_string(), PlatformResult { ganhos, corridas, icone, cor, periodo: periodo.to_string() });
+    }
+    results
+}
+
+fn variacao(atual: f64, anterior: f64) -> Option<f64> {
+    if anterior == 0.0 {
+        return None;
+    }
+    Some((((atual - anterior) / anterior.abs()) * 1000.0).round() / 10.0)
+}
+
+fn calcular_variacao(atual: &ResumoPeriodo, anterior: &ResumoPeriodo) -> VariacaoPeriodo {
+    VariacaoPeriodo {
+        ganhos: variacao(atual.ganhos as f64, anterior.ganhos as f64),
+        gastos: variacao(atual.gastos as f64, anterior.gastos as f64),
+        lucro: variacao(atual.lucro as f64, anterior.lucro as f64),
+        corridas: variacao(atual.corridas as f64, anterior.corridas as f64),
+        horas: variacao(atual.horas as f64, anterior.horas as f64),
+        km: variacao(atual.km, anterior.km),
+    }
+}
+
+/// Calcula o dashboard de um intervalo arbitrário a partir do `DashboardFiltro`
+pub fn compute_dashboard_periodo(conn: &mut PgConnection, id_usuario: &str, filtro: &DashboardFiltro) -> Result<DashboardPeriodo, ErroPeriodo> {
+    use crate::schema::categorias::dsl as cat_dsl;
+
+    let (rotulo, inicio, fim) = resolver_intervalo(filtro, Utc::now()).map_err(ErroPeriodo::Invalido)?;
+    let comparacao_intervalo = match filtro.comparar_com.as_deref() {
+        Some(modo) => Some((modo.to_string(), intervalo_comparacao(modo, inicio, fim).map_err(ErroPeriodo::Invalido)?)),
+        None => None,
+    };
+
+    let categorias: HashMap<String, Categoria> = cat_dsl::categorias
+        .filter(cat_dsl::id_usuario.eq(Some(id_usuario.to_string())))
+        .filter(cat_dsl::excluido_em.is_null())
+        .load::<Categoria>(conn)?
+        .into_iter()
+        .map(|c| (c.id.clone(), c))
+        .collect();
+
+    let transacoes = carregar_transacoes(conn, id_usuario, filtro, inicio, fim)?;
+    let minutos = minutos_por_dia(conn, id_usuario, inicio, fim)?;
+    let (resumo, serie_diaria) = agregar_periodo(&transacoes, &minutos, inicio, fim);
+
+    let comparacao = match comparacao_intervalo {
+        Some((modo, (c_inicio, c_fim))) => {
+            let c_transacoes = carregar_transacoes(conn, id_usuario, filtro, c_inicio, c_fim)?;
+            let c_minutos = minutos_por_dia(conn, id_usuario, c_inicio, c_fim)?;
+            let (c_resumo, c_serie) = agregar_periodo(&c_transacoes, &c_minutos, c_inicio, c_fim);
+            Some(ComparacaoPeriodo {
+                modo,
+                variacao_percentual: calcular_variacao(&resumo, &c_resumo),
+                resumo: c_resumo,
+                serie_diaria: c_serie,
+            })
+        }
+        None => None,
+    };
+
+    Ok(DashboardPeriodo {
+        top_sources: top_sources(&transacoes, &categorias, &rotulo),
+        platforms: platforms(&transacoes, &categorias, &rotulo),
+        periodo: rotulo,
+        resumo,
+        serie_diaria,
+        comparacao,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn em(ano: i32, mes: u32, dia: u32, hora: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(ano, mes, dia, hora, 0, 0).unwrap()
+    }
+
+    fn filtro(periodo: &str) -> DashboardFiltro {
+        DashboardFiltro { periodo: Some(periodo.to_string()), ..Default::default() }
+    }
+
+    fn intervalo(periodo: &str, agora: DateTime<Utc>) -> (NaiveDate, NaiveDate) {
+        let (rotulo, inicio, fim) = resolver_intervalo(&filtro(periodo), agora).unwrap();
+        assert_eq!(rotulo, periodo);
+        assert_eq!((inicio.time(), fim.time()), (chrono::NaiveTime::MIN, chrono::NaiveTime::from_hms_opt(23, 59, 59).unwrap()));
+        (inicio.date_naive(), fim.date_naive())
+    }
+
+    fn dia(ano: i32, mes: u32, dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(ano, mes, dia).unwrap()
+    }
+
+    fn transacao(id: &str, tipo: &str, valor: i32, eventos: i32, km: Option<f64>, data: DateTime<Utc>) -> Transacao {
+        Transacao {
+            id: id.to_string(),
+            id_usuario: "u1".to_string(),
+            id_categoria: "c_uber".to_string(),
+            valor,
+            eventos,
+            km,
+            descricao: None,
+            tipo: tipo.to_string(),
+            data,
+            criado_em: data,
+            atualizado_em: data,
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
+        }
+    }
+
+    #[test]
+    fn test_periodos_nomeados() {
+        // quarta-feira, 01/01/2025
+        let agora = em(2025, 1, 1, 15);
+        assert_eq!(intervalo("hoje", agora), (dia(2025, 1, 1), dia(2025, 1, 1)));
+        assert_eq!(intervalo("ontem", agora), (dia(2024, 12, 31), dia(2024, 12, 31)));
+        assert_eq!(intervalo("semana", agora), (dia(2024, 12, 30), dia(2025, 1, 5)));
+        assert_eq!(intervalo("semana_passada", agora), (dia(2024, 12, 23), dia(2024, 12, 29)));
+        assert_eq!(intervalo("7dias", agora), (dia(2024, 12, 26), dia(2025, 1, 1)));
+        assert_eq!(intervalo("30dias", agora), (dia(2024, 12, 3), dia(2025, 1, 1)));
+        assert_eq!(intervalo("mes_passado", agora), (dia(2024, 12, 1), dia(2024, 12, 31)));
+        assert_eq!(intervalo("ano", agora), (dia(2025, 1, 1), dia(2025, 12, 31)));
+
+        // domingo ainda é da semana que começou na segunda; fevereiro bissexto termina no dia 29
+        let domingo = em(2024, 2, 11, 23);
+        assert_eq!(intervalo("semana", domingo), (dia(2024, 2, 5), dia(2024, 2, 11)));
+        assert_eq!(intervalo("mes", domingo), (dia(2024, 2, 1), dia(2024, 2, 29)));
+        assert_eq!(intervalo("mes", em(2025, 2, 11, 0)), (dia(2025, 2, 1), dia(2025, 2, 28)));
+        assert_eq!(intervalo("mes_passado", em(2024, 3, 31, 0)), (dia(2024, 2, 1), dia(2024, 2, 29)));
+
+        // sem período, vale o mês corrente
+        let (rotulo, inicio, _) = resolver_intervalo(&DashboardFiltro::default(), agora).unwrap();
+        assert_eq!((rotulo.as_str(), inicio), ("mes", em(2025, 1, 1, 0)));
+    }
+
+    #[test]
+    fn test_intervalo_personalizado_e_invalidos() {
+        let agora = em(2025, 9, 3, 12);
+        let personalizado = |inicio, fim| DashboardFiltro {
+            periodo: Some("ano".to_string()),
+            data_inicio: Some(inicio),
+            data_fim: Some(fim),
+            ..Default::default()
+        };
+        // datas explícitas vencem o período e são usadas como vieram
+        let (rotulo, inicio, fim) = resolver_intervalo(&personalizado(em(2025, 8, 10, 6), em(2025, 8, 20, 18)), agora).unwrap();
+        assert_eq!((rotulo.as_str(), inicio, fim), ("personalizado", em(2025, 8, 10, 6), em(2025, 8, 20, 18)));
+
+        assert!(resolver_intervalo(&personalizado(em(2025, 8, 20, 0), em(2025, 8, 10, 0)), agora).is_err());
+        let so_inicio = DashboardFiltro { data_inicio: Some(em(2025, 8, 1, 0)), ..Default::default() };
+        assert!(resolver_intervalo(&so_inicio, agora).is_err());
+        assert!(resolver_intervalo(&filtro("trimestre"), agora).is_err());
+
+        // limite de dias conta os dois extremos
+        let inicio = em(2023, 9, 3, 0);
+        assert!(resolver_intervalo(&personalizado(inicio, inicio + Duration::days(MAX_DIAS_INTERVALO - 1)), agora).is_ok());
+        assert!(resolver_intervalo(&personalizado(inicio, inicio + Duration::days(MAX_DIAS_INTERVALO)), agora).is_err());
+    }
+
+    #[test]
+    fn test_intervalo_comparacao() {
+        let (inicio, fim) = (em(2024, 3, 1, 0), fim_do_dia(dia(2024, 3, 10)));
+        assert_eq!(intervalo_comparacao("periodo_anterior", inicio, fim).unwrap(), (em(2024, 2, 20, 0), fim_do_dia(dia(2024, 2, 29))));
+        assert_eq!(intervalo_comparacao("ano_anterior", inicio, fim).unwrap(), (em(2023, 3, 1, 0), fim_do_dia(dia(2023, 3, 10))));
+        // 29/02 não existe em 2023
+        let bissexto = em(2024, 2, 29, 0);
+        assert_eq!(intervalo_comparacao("ano_anterior", bissexto, bissexto).unwrap().0, bissexto - Duration::days(365));
+        assert!(intervalo_comparacao("semana", inicio, fim).is_err());
+    }
+
+    #[test]
+    fn test_agregar_periodo() {
+        let (inicio, fim) = (em(2025, 9, 1, 0), fim_do_dia(dia(2025, 9, 3)));
+        let transacoes = vec![
+            transacao("t1", "entrada", 5000, 3, Some(40.0), em(2025, 9, 1, 9)),
+            transacao("t2", "saida", 1200, 1, None, em(2025, 9, 1, 20)),
+            // duas linhas da mesma transação dividida
+            transacao("t3", "saida", 300, 0, Some(2.5), em(2025, 9, 3, 7)),
+            transacao("t3", "saida", 200, 0, None, em(2025, 9, 3, 7)),
+            // fora do intervalo: não entra na série (a query já recorta; a contagem não)
+            transacao("t4", "entrada", 9999, 9, None, em(2025, 9, 4, 1)),
+        ];
+        let minutos = BTreeMap::from([(dia(2025, 9, 1), 150), (dia(2025, 9, 3), 50)]);
+        let (resumo, serie) = agregar_periodo(&transacoes, &minutos, inicio, fim);
+
+        // série contínua, com o dia sem movimento zerado
+        assert_eq!(serie.iter().map(|i| i.label.as_str()).collect::<Vec<_>>(), vec!["01/09", "02/09", "03/09"]);
+        assert_eq!((serie[0].ganhos, serie[0].gastos, serie[0].lucro, serie[0].corridas, serie[0].horas, serie[0].km), (5000, 1200, 3800, 3, 2, 40.0));
+        assert_eq!((serie[1].ganhos, serie[1].gastos, serie[1].corridas, serie[1].horas), (0, 0, 0, 0));
+        assert_eq!((serie[2].gastos, serie[2].lucro, serie[2].horas, serie[2].km), (500, -500, 0, 2.5));
+
+        assert_eq!((resumo.ganhos, resumo.gastos, resumo.lucro, resumo.corridas), (5000, 1700, 3300, 3));
+        // horas do resumo somam os minutos antes de arredondar
+        assert_eq!((resumo.horas, resumo.km, resumo.transacoes), (3, 42.5, 4));
+    }
+}
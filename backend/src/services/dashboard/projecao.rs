@@ -0,0 +1,459 @@
+//! Motor de projeção de ganhos
+//!
+//! Cada método anunciado em `projecao_metodo` é um `ModeloProjecao`: recebe o histórico
+//! diário de ganhos (dias completos, já preenchido com zeros) e prevê os próximos dias.
+//! `projecao_mes` / `projecao_semana` = realizado até ontem + previsão de hoje até o fim
+//! do período. O backtest roda todos os modelos sobre o próprio histórico do usuário;
+//! com `projecao_metodo = "automatico"` o de menor erro é usado.
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::schema::configuracoes::dsl as config_dsl;
+use super::service::{media_excluindo_extremos, regressao_linear};
+
+/// Métodos aceitos em `projecao_metodo`
+pub const METODOS: [&str; 7] = [
+    "regressao_linear",
+    "media",
+    "mediana",
+    "media_movel_3",
+    "media_movel_7",
+    "media_movel_30",
+    "sazonal_semanal",
+];
+pub const METODO_AUTOMATICO: &str = "automatico";
+
+/// Dias de histórico carregados para projeção e backtest
+pub const HISTORICO_DIAS: i64 = 180;
+/// Janela usada por média, mediana e regressão
+const JANELA_PADRAO: usize = 30;
+/// Semanas consideradas pelo modelo sazonal
+const SEMANAS_SAZONAL: usize = 8;
+/// Mínimo de dias antes de uma origem do backtest
+const MIN_HISTORICO_BACKTEST: usize = 7;
+const BACKTEST_DIAS_PADRAO: usize = 28;
+const BACKTEST_HORIZONTE_PADRAO: usize = 7;
+const MAX_BACKTEST_DIAS: usize = 120;
+const MAX_BACKTEST_HORIZONTE: usize = 31;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiaHistorico {
+    pub data: NaiveDate,
+    /// Ganhos do dia em centavos
+    pub ganhos: i64,
+}
+
+pub trait ModeloProjecao {
+    fn nome(&self) -> &'static str;
+    /// Previsão (centavos) para cada data, usando só o histórico informado
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64>;
+}
+
+fn ultimos(historico: &[DiaHistorico], n: usize) -> &[DiaHistorico] {
+    &historico[historico.len().saturating_sub(n)..]
+}
+
+fn como_i32(historico: &[DiaHistorico]) -> Vec<i32> {
+    historico.iter().map(|d| d.ganhos.clamp(0, i32::MAX as i64) as i32).collect()
+}
+
+fn media_simples(historico: &[DiaHistorico]) -> f64 {
+    if historico.is_empty() {
+        return 0.0;
+    }
+    historico.iter().map(|d| d.ganhos as f64).sum::<f64>() / historico.len() as f64
+}
+
+/// Média dos últimos 30 dias, excluindo o percentual de extremos configurado
+pub struct Media {
+    pub percentual_extremos: usize,
+}
+
+impl ModeloProjecao for Media {
+    fn nome(&self) -> &'static str {
+        "media"
+    }
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64> {
+        let v = media_excluindo_extremos(&como_i32(ultimos(historico, JANELA_PADRAO)), self.percentual_extremos) as f64;
+        vec![v; datas.len()]
+    }
+}
+
+pub struct Mediana;
+
+impl ModeloProjecao for Mediana {
+    fn nome(&self) -> &'static str {
+        "mediana"
+    }
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64> {
+        let mut v: Vec<i64> = ultimos(historico, JANELA_PADRAO).iter().map(|d| d.ganhos).collect();
+        v.sort();
+        let mediana = match v.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => v[n / 2] as f64,
+            n => (v[n / 2 - 1] + v[n / 2]) as f64 / 2.0,
+        };
+        vec![mediana; datas.len()]
+    }
+}
+
+pub struct MediaMovel {
+    pub janela: usize,
+}
+
+impl ModeloProjecao for MediaMovel {
+    fn nome(&self) -> &'static str {
+        match self.janela {
+            3 => "media_movel_3",
+            7 => "media_movel_7",
+            _ => "media_movel_30",
+        }
+    }
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64> {
+        vec![media_simples(ultimos(historico, self.janela)); datas.len()]
+    }
+}
+
+/// Reta ajustada aos últimos 30 dias, extrapolada para cada data (nunca negativa)
+pub struct RegressaoLinear;
+
+impl ModeloProjecao for RegressaoLinear {
+    fn nome(&self) -> &'static str {
+        "regressao_linear"
+    }
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64> {
+        let janela = ultimos(historico, JANELA_PADRAO);
+        let Some(base) = janela.first().map(|d| d.data) else {
+            return vec![0.0; datas.len()];
+        };
+        let xs: Vec<f64> = janela.iter().map(|d| (d.data - base).num_days() as f64).collect();
+        let ys: Vec<f64> = janela.iter().map(|d| d.ganhos as f64).collect();
+        match regressao_linear(&xs, &ys) {
+            Some((a, b)) => datas.iter().map(|d| (a * (*d - base).num_days() as f64 + b).max(0.0)).collect(),
+            None => vec![media_simples(janela); datas.len()],
+        }
+    }
+}
+
+/// Média (sem extremos) das últimas semanas no mesmo dia da semana: sexta e sábado
+/// costumam render bem mais que segunda, e as médias simples achatam essa diferença.
+pub struct SazonalSemanal {
+    pub percentual_extremos: usize,
+}
+
+impl ModeloProjecao for SazonalSemanal {
+    fn nome(&self) -> &'static str {
+        "sazonal_semanal"
+    }
+    fn prever(&self, historico: &[DiaHistorico], datas: &[NaiveDate]) -> Vec<f64> {
+        let janela = ultimos(historico, SEMANAS_SAZONAL * 7);
+        let geral = media_simples(janela);
+        datas
+            .iter()
+            .map(|data| {
+                let mesmos: Vec<DiaHistorico> = janela.iter().filter(|d| d.data.weekday() == data.weekday()).copied().collect();
+                if mesmos.is_empty() {
+                    geral
+                } else {
+                    media_excluindo_extremos(&como_i32(&mesmos), self.percentual_extremos) as f64
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn modelo_por_nome(nome: &str, percentual_extremos: usize) -> Option<Box<dyn ModeloProjecao>> {
+    let modelo: Box<dyn ModeloProjecao> = match nome {
+        "regressao_linear" => Box::new(RegressaoLinear),
+        "media" => Box::new(Media { percentual_extremos }),
+        "mediana" => Box::new(Mediana),
+        "media_movel_3" => Box::new(MediaMovel { janela: 3 }),
+        "media_movel_7" => Box::new(MediaMovel { janela: 7 }),
+        "media_movel_30" => Box::new(MediaMovel { janela: 30 }),
+        "sazonal_semanal" => Box::new(SazonalSemanal { percentual_extremos }),
+        _ => return None,
+    };
+    Some(modelo)
+}
+
+pub fn todos_modelos(percentual_extremos: usize) -> Vec<Box<dyn ModeloProjecao>> {
+    METODOS.iter().filter_map(|m| modelo_por_nome(m, percentual_extremos)).collect()
+}
+
+/// Ganhos realizados do período + previsão de `hoje` até `fim`. Hoje ainda está em andamento,
+/// então vale o maior entre o já realizado e o previsto para o dia.
+pub fn projetar_periodo(
+    modelo: &dyn ModeloProjecao,
+    historico: &[DiaHistorico],
+    hoje: NaiveDate,
+    ganhos_hoje: i64,
+    inicio: NaiveDate,
+    fim: NaiveDate,
+) -> i32 {
+    let realizado: i64 = historico.iter().filter(|d| d.data >= inicio && d.data < hoje).map(|d| d.ganhos).sum();
+    let datas: Vec<NaiveDate> = hoje.iter_days().take_while(|d| *d <= fim).collect();
+    let previsto = modelo.prever(historico, &datas);
+    let restante: f64 = previsto
+        .iter()
+        .enumerate()
+        .map(|(i, v)| if i == 0 { v.max(ganhos_hoje as f64) } else { *v })
+        .sum();
+    (realizado as f64 + restante).round() as i32
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ResultadoBacktest {
+    pub metodo: String,
+    /// Erro absoluto médio por dia (centavos)
+    pub mae: f64,
+    /// Raiz do erro quadrático médio (centavos)
+    pub rmse: f64,
+    /// Soma dos erros absolutos / soma dos ganhos reais, em %
+    pub wape: Option<f64>,
+    /// Erro médio com sinal: positivo = método superestima
+    pub vies: f64,
+    pub previsoes: usize,
+}
+
+/// Avalia cada modelo prevendo, a partir de cada um dos últimos `dias` dias, os `horizonte`
+/// dias seguintes só com o histórico anterior. Resultado ordenado do menor para o maior MAE.
+pub fn backtest(historico: &[DiaHistorico], dias: usize, horizonte: usize, modelos: &[Box<dyn ModeloProjecao>]) -> Vec<ResultadoBacktest> {
+    let n = historico.len();
+    let primeira_origem = n.saturating_sub(dias).max(MIN_HISTORICO_BACKTEST);
+    let mut resultados: Vec<ResultadoBacktest> = modelos
+        .iter()
+        .map(|modelo| {
+            let (mut abs, mut quad, mut sinal, mut real, mut total) = (0f64, 0f64, 0f64, 0f64, 0usize);
+            for origem in primeira_origem..n {
+                let alvo = &historico[origem..(origem + horizonte).min(n)];
+                let datas: Vec<NaiveDate> = alvo.iter().map(|d| d.data).collect();
+                let previsto = modelo.prever(&historico[..origem], &datas);
+                for (p, d) in previsto.iter().zip(alvo) {
+                    let erro = p - d.ganhos as f64;
+                    abs += erro.abs();
+                    quad += erro * erro;
+                    sinal += erro;
+                    real += d.ganhos as f64;
+                    total += 1;
+                }
+            }
+            let arred = |v: f64| (v * 100.0).round() / 100.0;
+            let t = total.max(1) as f64;
+            ResultadoBacktest {
+                metodo: modelo.nome().to_string(),
+                mae: arred(abs / t),
+                rmse: arred((quad / t).sqrt()),
+                wape: (real > 0.0).then(|| arred(abs / real * 100.0)),
+                vies: arred(sinal / t),
+                previsoes: total,
+            }
+        })
+        .filter(|r| r.previsoes > 0)
+        .collect();
+    resultados.sort_by(|a, b| a.mae.total_cmp(&b.mae));
+    resultados
+}
+
+/// Histórico diário de ganhos (UTC, como o restante do dashboard) de `inicio` até o dia
+/// anterior a `hoje`, preenchido com zero nos dias sem entrada. Começa no primeiro dia com
+/// transação para que o período antes do cadastro não conte como dias parados.
+pub fn carregar_historico(conn: &mut PgConnection, id_usuario: &str, hoje: NaiveDate) -> Result<Vec<DiaHistorico>, diesel::result::Error> {
+    #[derive(QueryableByName)]
+    struct Linha {
+        #[diesel(sql_type = Date)]
+        dia: NaiveDate,
+        #[diesel(sql_type = BigInt)]
+        ganhos: i64,
+    }
+    let inicio = hoje - Duration::days(HISTORICO_DIAS);
+    let linhas: Vec<Linha> = diesel::sql_query(
+        "SELECT (t.data AT TIME ZONE 'UTC')::date AS dia, SUM(t.valor)::bigint AS ganhos
+         FROM transacoes t
+         WHERE t.id_usuario = $1 AND t.excluido_em IS NULL AND t.tipo = 'entrada' AND t.data >= $2 AND t.data < $3
+         GROUP BY 1 ORDER BY 1",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Timestamptz, _>(Utc.from_utc_datetime(&inicio.and_hms_opt(0, 0, 0).unwrap()))
+    .bind::<Timestamptz, _>(Utc.from_utc_datetime(&hoje.and_hms_opt(0, 0, 0).unwrap()))
+    .load(conn)?;
+
+    let Some(primeiro) = linhas.first().map(|l| l.dia) else {
+        return Ok(Vec::new());
+    };
+    let por_dia: HashMap<NaiveDate, i64> = linhas.into_iter().map(|l| (l.dia, l.ganhos)).collect();
+    Ok(primeiro
+        .iter_days()
+        .take_while(|d| *d < hoje)
+        .map(|data| DiaHistorico { data, ganhos: por_dia.get(&data).copied().unwrap_or(0) })
+        .collect())
+}
+
+fn ganhos_do_dia(conn: &mut PgConnection, id_usuario: &str, dia: NaiveDate) -> i64 {
+    use crate::schema::transacoes::dsl as transacao_dsl;
+    transacao_dsl::transacoes
+        .filter(transacao_dsl::id_usuario.eq(id_usuario))
+        .filter(transacao_dsl::excluido_em.is_null())
+        .filter(transacao_dsl::tipo.eq("entrada"))
+        .filter(transacao_dsl::data.ge(Utc.from_utc_datetime(&dia.and_hms_opt(0, 0, 0).unwrap())))
+        .filter(transacao_dsl::data.le(Utc.from_utc_datetime(&dia.and_hms_opt(23, 59, 59).unwrap())))
+        .select(diesel::dsl::sum(transacao_dsl::valor))
+        .first::<Option<i64>>(conn)
+        .ok()
+        .flatten()
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Projecao {
+    pub projecao_mes: Option<i32>,
+    pub projecao_semana: Option<i32>,
+    /// Método efetivamente usado (resolve "automatico" e valores desconhecidos)
+    pub metodo: String,
+}
+
+/// Resolve o método configurado: "automatico" escolhe o melhor do backtest e valores
+/// desconhecidos caem em "media_movel_3", o padrão histórico do dashboard.
+pub fn resolver_metodo(metodo: &str, historico: &[DiaHistorico], percentual_extremos: usize) -> Box<dyn ModeloProjecao> {
+    let nome = if metodo == METODO_AUTOMATICO {
+        backtest(historico, BACKTEST_DIAS_PADRAO, BACKTEST_HORIZONTE_PADRAO, &todos_modelos(percentual_extremos))
+            .first()
+            .map(|r| r.metodo.clone())
+            .unwrap_or_else(|| "media_movel_3".to_string())
+    } else {
+        metodo.to_string()
+    };
+    modelo_por_nome(&nome, percentual_extremos).unwrap_or_else(|| Box::new(MediaMovel { janela: 3 }))
+}
+
+/// Projeção de ganhos do mês e da semana correntes (semana começa na segunda)
+pub fn compute_projecao(conn: &mut PgConnection, id_usuario: &str, metodo: &str, percentual_extremos: usize, hoje: NaiveDate) -> Projecao {
+    let historico = carregar_historico(conn, id_usuario, hoje).unwrap_or_default();
+    let ganhos_hoje = ganhos_do_dia(conn, id_usuario, hoje);
+    projetar(metodo, percentual_extremos, &historico, ganhos_hoje, hoje)
+}
+
+/// Mesma projeção de `compute_projecao` sobre um histórico já carregado
+pub fn projetar(metodo: &str, percentual_extremos: usize, historico: &[DiaHistorico], ganhos_hoje: i64, hoje: NaiveDate) -> Projecao {
+    let modelo = resolver_metodo(metodo, historico, percentual_extremos);
+
+    let inicio_mes = hoje.with_day(1).unwrap();
+    let fim_mes = inicio_mes
+        .checked_add_months(chrono::Months::new(1))
+        .map(|d| d - Duration::days(1))
+        .unwrap_or(hoje);
+    let inicio_semana = hoje - Duration::days(hoje.weekday().num_days_from_monday() as i64);
+    let fim_semana = inicio_semana + Duration::days(6);
+
+    Projecao {
+        projecao_mes: Some(projetar_periodo(modelo.as_ref(), historico, hoje, ganhos_hoje, inicio_mes, fim_mes)),
+        projecao_semana: Some(projetar_periodo(modelo.as_ref(), historico, hoje, ganhos_hoje, inicio_semana, fim_semana)),
+        metodo: modelo.nome().to_string(),
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct BacktestParams {
+    pub dias: Option<usize>,
+    pub horizonte: Option<usize>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BacktestResposta {
+    pub dias_historico: usize,
+    pub dias_avaliados: usize,
+    pub horizonte: usize,
+    pub metodo_configurado: String,
+    pub melhor_metodo: Option<String>,
+    pub metodos: Vec<ResultadoBacktest>,
+}
+
+fn config_usuario(conn: &mut PgConnection, id_usuario: &str, chave: &str) -> Option<String> {
+    config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario))
+        .filter(config_dsl::chave.eq(chave))
+        .select(config_dsl::valor)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+}
+
+/// Erro de cada método de projeção sobre o histórico do próprio usuário
+pub fn compute_backtest(conn: &mut PgConnection, id_usuario: &str, params: &BacktestParams) -> Result<BacktestResposta, String> {
+    let dias = params.dias.unwrap_or(BACKTEST_DIAS_PADRAO);
+    let horizonte = params.horizonte.unwrap_or(BACKTEST_HORIZONTE_PADRAO);
+    if dias == 0 || dias > MAX_BACKTEST_DIAS {
+        return Err(format!("dias deve estar entre 1 e {MAX_BACKTEST_DIAS}"));
+    }
+    if horizonte == 0 || horizonte > MAX_BACKTEST_HORIZONTE {
+        return Err(format!("horizonte deve estar entre 1 e {MAX_BACKTEST_HORIZONTE}"));
+    }
+
+    let historico = carregar_historico(conn, id_usuario, Utc::now().date_naive())
+        .map_err(|e| format!("Erro ao carregar histórico: {e}"))?;
+    let percentual = config_usuario(conn, id_usuario, "projecao_percentual_extremos")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10);
+    let metodo_configurado = config_usuario(conn, id_usuario, "projecao_metodo").unwrap_or_else(|| "media_movel_3".to_string());
+
+    let metodos = backtest(&historico, dias, horizonte, &todos_modelos(percentual));
+    Ok(BacktestResposta {
+        dias_historico: historico.len(),
+        dias_avaliados: dias.min(historico.len().saturating_sub(MIN_HISTORICO_BACKTEST)),
+        horizonte,
+        metodo_configurado,
+        melhor_metodo: metodos.first().map(|r| r.metodo.clone()),
+        metodos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Semanas em que sexta e sábado rendem o triplo dos outros dias
+    fn historico_semanal(semanas: i64) -> Vec<DiaHistorico> {
+        let inicio = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(); // segunda
+        (0..semanas * 7)
+            .map(|i| {
+                let data = inicio + Duration::days(i);
+                let ganhos = match data.weekday() {
+                    chrono::Weekday::Fri | chrono::Weekday::Sat => 30000,
+                    _ => 10000,
+                };
+                DiaHistorico { data, ganhos }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_todos_metodos_anunciados_existem() {
+        for m in METODOS {
+            assert_eq!(modelo_por_nome(m, 10).map(|x| x.nome()), Some(m));
+        }
+        assert!(modelo_por_nome("inexistente", 10).is_none());
+    }
+
+    #[test]
+    fn test_sazonal_vence_backtest_com_padrao_semanal() {
+        let h = historico_semanal(10);
+        let r = backtest(&h, 28, 7, &todos_modelos(10));
+        assert_eq!(r.len(), METODOS.len());
+        assert_eq!(r[0].metodo, "sazonal_semanal");
+        assert_eq!(r[0].mae, 0.0);
+    }
+
+    #[test]
+    fn test_projetar_periodo_soma_realizado_e_previsto() {
+        let h = historico_semanal(4);
+        let hoje = h.last().unwrap().data + Duration::days(1); // segunda
+        let fim = hoje + Duration::days(6);
+        let modelo = SazonalSemanal { percentual_extremos: 0 };
+        // semana inteira prevista: 5 × 10000 + 2 × 30000
+        assert_eq!(projetar_periodo(&modelo, &h, hoje, 0, hoje, fim), 110000);
+        // hoje já rendeu mais que o previsto: vale o realizado
+        assert_eq!(projetar_periodo(&modelo, &h, hoje, 15000, hoje, fim), 115000);
+    }
+}
@@ -0,0 +1,715 @@
+//! Razão diário do dashboard
+//!
+//! Agrega por dia (UTC) as transações e sessões de trabalho do usuário. Os stats do dashboard
+//! são sempre recortados do razão para o `hoje` da consulta, então a virada do dia não exige
+//! recálculo. `compute_dashboard_stats` monta o razão a partir do banco; o cache guarda o
+//! mesmo razão e aplica deltas com sinal a cada transação criada, editada ou excluída.
+//! Transações divididas entram por parte (ver `transacao::divisao`); escritas nelas
+//! invalidam o razão em vez de aplicar delta.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use diesel::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use crate::models::{Categoria, Transacao};
+use crate::models::configuracao::Configuracao;
+use crate::services::transacao::divisao;
+use super::projecao::{self, DiaHistorico, HISTORICO_DIAS};
+use super::service::{media_movel, regressao_linear, DashboardStats, PlatformResult, TopSourceItem, TopSources};
+
+const PLATAFORMAS: [&str; 2] = ["Corrida Uber", "Corrida 99"];
+
+/// Soma de valor/eventos e quantidade de linhas (distingue "sem transações" de "soma zero")
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Acumulado {
+    pub valor: i64,
+    pub eventos: i64,
+    pub linhas: i64,
+}
+
+impl Acumulado {
+    fn somar(&mut self, transacao: &Transacao, sinal: i64) {
+        self.valor += sinal * transacao.valor as i64;
+        self.eventos += sinal * transacao.eventos as i64;
+        self.linhas += sinal;
+    }
+
+    fn juntar(&mut self, outro: &Acumulado) {
+        self.valor += outro.valor;
+        self.eventos += outro.eventos;
+        self.linhas += outro.linhas;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TotaisDia {
+    pub ganhos: Acumulado,
+    pub gastos: Acumulado,
+    /// Quilometragem em metros (somas inteiras não acumulam erro de arredondamento)
+    pub km_metros: i64,
+    /// Por (tipo, id_categoria)
+    pub categorias: BTreeMap<(String, String), Acumulado>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TotaisSessaoDia {
+    pub minutos: i64,
+    /// Sessões com `total_minutos` preenchido (ativas não contam)
+    pub com_minutos: i64,
+    pub corridas: i64,
+}
+
+/// Dados do dashboard que não vêm de transações nem sessões
+#[derive(Clone, Debug, Default)]
+pub struct DadosFixos {
+    pub projecao_metodo: String,
+    pub percentual_extremos: usize,
+    pub eficiencia: i32,
+    pub meta_diaria: Option<i32>,
+    pub meta_semanal: Option<i32>,
+    pub categorias: HashMap<String, Categoria>,
+    pub alertas_pendentes: i64,
+    pub proximas_parcelas: Vec<crate::services::divida::ParcelaProxima>,
+    pub envelopes: Vec<crate::services::envelope::SaldoEnvelope>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RazaoDashboard {
+    pub id_usuario: String,
+    /// Primeiro dia coberto; transações anteriores nunca entram em nenhuma janela
+    pub inicio: NaiveDate,
+    pub dias: BTreeMap<NaiveDate, TotaisDia>,
+    pub sessoes: BTreeMap<NaiveDate, TotaisSessaoDia>,
+    pub fixos: DadosFixos,
+}
+
+fn dia_utc(data: DateTime<Utc>) -> NaiveDate {
+    data.date_naive()
+}
+
+fn km_em_metros(km: Option<f64>) -> i64 {
+    (km.unwrap_or(0.0) * 1000.0).round() as i64
+}
+
+fn metros_em_km(metros: i64) -> f64 {
+    metros as f64 / 1000.0
+}
+
+/// Primeiro e último dia do mês de `dia`
+fn limites_mes(dia: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let inicio = dia.with_day(1).unwrap();
+    let fim = inicio
+        .checked_add_months(chrono::Months::new(1))
+        .map(|d| d - Duration::days(1))
+        .unwrap_or(dia);
+    (inicio, fim)
+}
+
+impl RazaoDashboard {
+    pub fn novo(id_usuario: &str, inicio: NaiveDate, fixos: DadosFixos) -> Self {
+        Self {
+            id_usuario: id_usuario.to_string(),
+            inicio,
+            dias: BTreeMap::new(),
+            sessoes: BTreeMap::new(),
+            fixos,
+        }
+    }
+
+    /// Primeiro dia que o razão precisa cobrir para responder por `hoje`
+    pub fn inicio_necessario(hoje: NaiveDate) -> NaiveDate {
+        hoje - Duration::days(HISTORICO_DIAS)
+    }
+
+    /// O razão só serve para datas em que todas as janelas caem dentro da cobertura
+    pub fn cobre(&self, hoje: NaiveDate) -> bool {
+        self.inicio <= Self::inicio_necessario(hoje)
+    }
+
+    /// Soma (`sinal` = 1) ou retira (`sinal` = -1) uma transação dos totais do seu dia
+    pub fn aplicar(&mut self, transacao: &Transacao, sinal: i64) {
+        let dia = dia_utc(transacao.data);
+        if dia < self.inicio {
+            return;
+        }
+        let totais = self.dias.entry(dia).or_default();
+        match transacao.tipo.as_str() {
+            "entrada" => totais.ganhos.somar(transacao, sinal),
+            "saida" => totais.gastos.somar(transacao, sinal),
+            _ => {}
+        }
+        totais.km_metros += sinal * km_em_metros(transacao.km);
+        totais
+            .categorias
+            .entry((transacao.tipo.clone(), transacao.id_categoria.clone()))
+            .or_default()
+            .somar(transacao, sinal);
+    }
+
+    /// Edição: retira a versão anterior e soma a nova (que pode ter mudado de dia)
+    pub fn atualizar(&mut self, antes: &Transacao, depois: &Transacao) {
+        self.aplicar(antes, -1);
+        self.aplicar(depois, 1);
+    }
+
+    pub fn registrar_sessao(&mut self, inicio: DateTime<Utc>, total_minutos: Option<i32>, total_corridas: i32) {
+        let dia = dia_utc(inicio);
+        if dia < self.inicio {
+            return;
+        }
+        let totais = self.sessoes.entry(dia).or_default();
+        if let Some(minutos) = total_minutos {
+            totais.minutos += minutos as i64;
+            totais.com_minutos += 1;
+        }
+        totais.corridas += total_corridas as i64;
+    }
+
+    fn somar_dias(&self, inicio: NaiveDate, fim: NaiveDate) -> (Acumulado, Acumulado, i64) {
+        let (mut ganhos, mut gastos, mut metros) = (Acumulado::default(), Acumulado::default(), 0);
+        for totais in self.dias.range(inicio..=fim).map(|(_, t)| t) {
+            ganhos.juntar(&totais.ganhos);
+            gastos.juntar(&totais.gastos);
+            metros += totais.km_metros;
+        }
+        (ganhos, gastos, metros)
+    }
+
+    fn horas(&self, inicio: NaiveDate, fim: NaiveDate) -> Option<i32> {
+        let (minutos, com_minutos) = self
+            .sessoes
+            .range(inicio..=fim)
+            .fold((0i64, 0i64), |(m, n), (_, s)| (m + s.minutos, n + s.com_minutos));
+        (com_minutos > 0).then_some(minutos as i32 / 60)
+    }
+
+    fn dia(&self, dia: NaiveDate) -> TotaisDia {
+        self.dias.get(&dia).cloned().unwrap_or_default()
+    }
+
+    fn sessao(&self, dia: NaiveDate) -> TotaisSessaoDia {
+        self.sessoes.get(&dia).cloned().unwrap_or_default()
+    }
+
+    fn top_source(&self, tipo: &str, inicio: NaiveDate, fim: NaiveDate, periodo: &str) -> TopSourceItem {
+        let mut por_categoria: BTreeMap<&str, Acumulado> = BTreeMap::new();
+        for totais in self.dias.range(inicio..=fim).map(|(_, t)| t) {
+            for ((t, categoria), acumulado) in &totais.categorias {
+                if t == tipo {
+                    por_categoria.entry(categoria.as_str()).or_default().juntar(acumulado);
+                }
+            }
+        }
+        // empate: menor id de categoria, para o resultado não depender da ordem de inserção
+        let mut melhor: Option<(&str, i64)> = None;
+        for (categoria, acumulado) in por_categoria.iter().filter(|(_, a)| a.linhas > 0) {
+            if melhor.is_none_or(|(_, valor)| acumulado.valor > valor) {
+                melhor = Some((categoria, acumulado.valor));
+            }
+        }
+        let categoria = melhor.and_then(|(id, _)| self.fixos.categorias.get(id));
+        TopSourceItem {
+            periodo: periodo.to_string(),
+            tipo: tipo.to_string(),
+            categoria_id: melhor.map(|(id, _)| id.to_string()),
+            nome: categoria.map(|c| c.nome.clone()),
+            icone: categoria.and_then(|c| c.icone.clone()),
+            cor: categoria.and_then(|c| c.cor.clone()),
+            valor: melhor.map(|(_, valor)| valor).unwrap_or(0),
+        }
+    }
+
+    fn plataformas(&self, hoje: NaiveDate) -> HashMap<String, PlatformResult> {
+        let totais = self.dia(hoje);
+        PLATAFORMAS
+            .iter()
+            .map(|nome| {
+                let mut categorias: Vec<&Categoria> = self
+                    .fixos
+                    .categorias
+                    .values()
+                    .filter(|c| c.id_usuario.as_deref() == Some(self.id_usuario.as_str()) && c.nome == *nome)
+                    .collect();
+                categorias.sort_by(|a, b| a.id.cmp(&b.id));
+                let mut soma = Acumulado::default();
+                for categoria in &categorias {
+                    if let Some(acumulado) = totais.categorias.get(&("entrada".to_string(), categoria.id.clone())) {
+                        soma.juntar(acumulado);
+                    }
+                }
+                let (icone, cor) = categorias.first().map(|c| (c.icone.clone(), c.cor.clone())).unwrap_or((None, None));
+                let resultado = PlatformResult {
+                    ganhos: soma.valor as i32,
+                    corridas: soma.eventos.try_into().unwrap_or(0),
+                    icone,
+                    cor,
+                    periodo: "hoje".to_string(),
+                };
+                (nome.to_string(), resultado)
+            })
+            .collect()
+    }
+
+    /// Histórico diário de ganhos no formato de `projecao::carregar_historico`
+    fn historico_ganhos(&self, hoje: NaiveDate) -> Vec<DiaHistorico> {
+        let inicio = Self::inicio_necessario(hoje);
+        let Some(primeiro) = self
+            .dias
+            .range(inicio..hoje)
+            .find(|(_, t)| t.ganhos.linhas > 0)
+            .map(|(dia, _)| *dia)
+        else {
+            return Vec::new();
+        };
+        primeiro
+            .iter_days()
+            .take_while(|d| *d < hoje)
+            .map(|data| DiaHistorico { data, ganhos: self.dias.get(&data).map(|t| t.ganhos.valor).unwrap_or(0) })
+            .collect()
+    }
+
+    /// Stats do dashboard com os períodos recortados para `hoje`
+    pub fn stats(&self, hoje: NaiveDate) -> DashboardStats {
+        let ontem = hoje - Duration::days(1);
+        let inicio_semana = hoje - Duration::days(hoje.weekday().num_days_from_monday() as i64);
+        let fim_semana = inicio_semana + Duration::days(6);
+        let inicio_semana_passada = inicio_semana - Duration::days(7);
+        let fim_semana_passada = inicio_semana - Duration::days(1);
+        let (inicio_mes, fim_mes) = limites_mes(hoje);
+        let (inicio_mes_passado, fim_mes_passado) = limites_mes(inicio_mes - Duration::days(1));
+
+        let periodos = [
+            (hoje, hoje),
+            (ontem, ontem),
+            (inicio_semana, fim_semana),
+            (inicio_semana_passada, fim_semana_passada),
+            (inicio_mes, fim_mes),
+            (inicio_mes_passado, fim_mes_passado),
+        ];
+        let somas: Vec<(Acumulado, Acumulado, i64)> = periodos.iter().map(|(i, f)| self.somar_dias(*i, *f)).collect();
+        let horas: Vec<Option<i32>> = periodos.iter().map(|(i, f)| self.horas(*i, *f)).collect();
+        let ganhos = |p: usize| (somas[p].0.linhas > 0).then_some(somas[p].0.valor as i32);
+        let gastos = |p: usize| (somas[p].1.linhas > 0).then_some(somas[p].1.valor as i32);
+        let lucro = |p: usize| ganhos(p).zip(gastos(p)).map(|(g, s)| g - s);
+        let corridas = |p: usize| (somas[p].0.linhas > 0).then_some(somas[p].0.eventos as u32);
+        let km = |p: usize| Some(metros_em_km(somas[p].2));
+
+        // séries diárias: transações para valores/km, sessões para corridas/horas
+        let serie = |dias: i64| {
+            let datas: Vec<NaiveDate> = (0..dias).rev().map(|i| hoje - Duration::days(i)).collect();
+            let totais: Vec<(TotaisDia, TotaisSessaoDia)> = datas.iter().map(|d| (self.dia(*d), self.sessao(*d))).collect();
+            (datas, totais)
+        };
+        let (_, semana) = serie(7);
+        let (datas_30, trinta) = serie(30);
+        let ganhos_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(d, _)| d.ganhos.valor as i32).collect::<Vec<i32>>();
+        let gastos_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(d, _)| d.gastos.valor as i32).collect::<Vec<i32>>();
+        let lucro_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(d, _)| d.ganhos.valor as i32 - d.gastos.valor as i32).collect::<Vec<i32>>();
+        let corridas_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(_, s)| s.corridas.try_into().unwrap_or(0)).collect::<Vec<u32>>();
+        let horas_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(_, s)| s.minutos as i32 / 60).collect::<Vec<i32>>();
+        let km_de = |t: &[(TotaisDia, TotaisSessaoDia)]| t.iter().map(|(d, _)| metros_em_km(d.km_metros)).collect::<Vec<f64>>();
+
+        let ganhos_7dias = ganhos_de(&semana);
+        let gastos_7dias = gastos_de(&semana);
+        let corridas_7dias = corridas_de(&semana);
+        let ganhos_30dias = ganhos_de(&trinta);
+        let corridas_30dias = corridas_de(&trinta);
+        let ganhos_mes: Vec<i32> = inicio_mes.iter_days().take_while(|d| *d <= fim_mes).map(|d| self.dia(d).ganhos.valor as i32).collect();
+        let gastos_mes: Vec<i32> = inicio_mes.iter_days().take_while(|d| *d <= fim_mes).map(|d| self.dia(d).gastos.valor as i32).collect();
+
+        // Tendência usando média móvel conforme configuração
+        let metodo = self.fixos.projecao_metodo.as_str();
+        let tendencia = |serie_7: &[i32], serie_longa: &[i32]| match metodo {
+            "media_movel_3" => media_movel(serie_7, 3),
+            "media_movel_7" => media_movel(serie_7, 7),
+            "media_movel_30" => media_movel(serie_longa, 30),
+            "regressao_linear" if serie_7.len() >= 2 => {
+                let xs: Vec<f64> = (0..serie_7.len()).map(|i| i as f64).collect();
+                let ys: Vec<f64> = serie_7.iter().map(|&v| v as f64).collect();
+                regressao_linear(&xs, &ys).map(|(a, _b)| a.round() as i32)
+            }
+            _ => None,
+        };
+        let corridas_7dias_i32: Vec<i32> = corridas_7dias.iter().map(|&v| v as i32).collect();
+        let corridas_30dias_i32: Vec<i32> = corridas_30dias.iter().map(|&v| v as i32).collect();
+
+        let projecao = projecao::projetar(
+            metodo,
+            self.fixos.percentual_extremos,
+            &self.historico_ganhos(hoje),
+            self.dia(hoje).ganhos.valor,
+            hoje,
+        );
+
+        let janelas_top = [("diario", hoje), ("7dias", hoje - Duration::days(7)), ("30dias", hoje - Duration::days(30))];
+        let top_sources = TopSources {
+            receitas: janelas_top.iter().map(|(p, inicio)| self.top_source("entrada", *inicio, hoje, p)).collect(),
+            despesas: janelas_top.iter().map(|(p, inicio)| self.top_source("saida", *inicio, hoje, p)).collect(),
+        };
+
+        DashboardStats {
+            ganhos_hoje: ganhos(0),
+            ganhos_ontem: ganhos(1),
+            ganhos_semana: ganhos(2),
+            ganhos_semana_passada: ganhos(3),
+            ganhos_mes: ganhos(4),
+            ganhos_mes_passado: ganhos(5),
+
+            gastos_hoje: gastos(0),
+            gastos_ontem: gastos(1),
+            gastos_semana: gastos(2),
+            gastos_semana_passada: gastos(3),
+            gastos_mes: gastos(4),
+            gastos_mes_passado: gastos(5),
+
+            lucro_hoje: lucro(0),
+            lucro_ontem: lucro(1),
+            lucro_semana: lucro(2),
+            lucro_semana_passada: lucro(3),
+            lucro_mes: lucro(4),
+            lucro_mes_passado: lucro(5),
+
+            corridas_hoje: corridas(0),
+            corridas_ontem: corridas(1),
+            corridas_semana: corridas(2),
+            corridas_semana_passada: corridas(3),
+            corridas_mes: corridas(4),
+            corridas_mes_passado: corridas(5),
+
+            horas_hoje: horas[0],
+            horas_ontem: horas[1],
+            horas_semana: horas[2],
+            horas_semana_passada: horas[3],
+            horas_mes: horas[4],
+            horas_mes_passado: horas[5],
+
+            km_hoje: km(0),
+            km_ontem: km(1),
+            km_semana: km(2),
+            km_semana_passada: km(3),
+            km_mes: km(4),
+            km_mes_passado: km(5),
+
+            eficiencia: Some(self.fixos.eficiencia),
+            meta_diaria: self.fixos.meta_diaria,
+            meta_semanal: self.fixos.meta_semanal,
+            tendencia_ganhos: tendencia(&ganhos_7dias, &ganhos_mes),
+            tendencia_gastos: tendencia(&gastos_7dias, &gastos_mes),
+            tendencia_corridas: tendencia(&corridas_7dias_i32, &corridas_30dias_i32),
+            lucro_7dias: lucro_de(&semana),
+            horas_7dias: horas_de(&semana),
+            km_7dias: km_de(&semana),
+            ganhos_7dias,
+            gastos_7dias,
+            corridas_7dias,
+            ultimos_30_dias_labels: datas_30.iter().map(|d| d.format("%d/%m").to_string()).collect(),
+            gastos_30dias: gastos_de(&trinta),
+            lucro_30dias: lucro_de(&trinta),
+            horas_30dias: horas_de(&trinta),
+            km_30dias: km_de(&trinta),
+            ganhos_30dias,
+            corridas_30dias,
+            projecao_mes: projecao.projecao_mes,
+            projecao_semana: projecao.projecao_semana,
+            projecao_metodo_usado: projecao.metodo,
+            trend_method: self.fixos.projecao_metodo.clone(),
+            platforms: self.plataformas(hoje),
+            top_sources,
+            alertas_pendentes: self.fixos.alertas_pendentes,
+            proximas_parcelas: self.fixos.proximas_parcelas.clone(),
+            envelopes: self.fixos.envelopes.clone(),
+        }
+    }
+}
+
+fn carregar_fixos(conn: &mut PgConnection, id_usuario: &str) -> DadosFixos {
+    use crate::schema::categorias::dsl as cat_dsl;
+    use crate::schema::configuracoes::dsl as config_dsl;
+    use crate::schema::metas::dsl as meta_dsl;
+
+    let configs: Vec<Configuracao> = config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario))
+        .load(conn)
+        .unwrap_or_default();
+    let get_config = |chave: &str| -> Option<String> {
+        configs.iter().find(|c| c.chave == chave).and_then(|c| c.valor.clone())
+    };
+
+    let total_metas: i32 = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).filter(meta_dsl::excluido_em.is_null()).count().get_result::<i64>(conn).unwrap_or(0) as i32;
+    let metas_concluidas: i32 = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).filter(meta_dsl::excluido_em.is_null()).filter(meta_dsl::eh_concluida.eq(true)).count().get_result::<i64>(conn).unwrap_or(0) as i32;
+    let meta_ativa = meta_dsl::metas
+        .filter(meta_dsl::id_usuario.eq(id_usuario))
+        .filter(meta_dsl::excluido_em.is_null())
+        .filter(meta_dsl::eh_ativa.eq(true))
+        .order_by(meta_dsl::data_inicio.desc())
+        .select(meta_dsl::valor_alvo)
+        .first::<i32>(conn)
+        .ok();
+
+    let categorias: Vec<Categoria> = cat_dsl::categorias
+        .filter(cat_dsl::id_usuario.eq(id_usuario).or(cat_dsl::id_usuario.is_null()))
+        .filter(cat_dsl::excluido_em.is_null())
+        .load(conn)
+        .unwrap_or_default();
+
+    DadosFixos {
+        projecao_metodo: get_config("projecao_metodo").unwrap_or_else(|| "media_movel_3".to_string()),
+        percentual_extremos: get_config("projecao_percentual_extremos").and_then(|v| v.parse().ok()).unwrap_or(10),
+        eficiencia: if total_metas > 0 { (metas_concluidas * 100) / total_metas } else { 0 },
+        meta_diaria: meta_ativa,
+        meta_semanal: meta_ativa,
+        categorias: categorias.into_iter().map(|c| (c.id.clone(), c)).collect(),
+        alertas_pendentes: crate::services::anomalia::contar_pendentes(conn, id_usuario),
+        proximas_parcelas: crate::services::divida::proximas_parcelas(conn, id_usuario),
+        envelopes: crate::services::envelope::resumo(conn, id_usuario),
+    }
+}
+
+/// Monta o razão do usuário a partir do banco, cobrindo as janelas de `hoje` em diante
+pub fn carregar(conn: &mut PgConnection, id_usuario: &str, hoje: NaiveDate) -> RazaoDashboard {
+    use crate::schema::sessoes_trabalho::dsl as sessao_dsl;
+    use crate::schema::transacoes::dsl as transacao_dsl;
+
+    let inicio = RazaoDashboard::inicio_necessario(hoje);
+    let inicio_utc = Utc.from_utc_datetime(&inicio.and_hms_opt(0, 0, 0).unwrap());
+    let mut razao = RazaoDashboard::novo(id_usuario, inicio, carregar_fixos(conn, id_usuario));
+
+    let transacoes: Vec<Transacao> = transacao_dsl::transacoes
+        .filter(transacao_dsl::id_usuario.eq(id_usuario))
+        .filter(transacao_dsl::excluido_em.is_null())
+        .filter(transacao_dsl::data.ge(inicio_utc))
+        .load(conn)
+        .unwrap_or_default();
+    for transacao in &divisao::expandir(conn, transacoes) {
+        razao.aplicar(transacao, 1);
+    }
+
+    let sessoes: Vec<(DateTime<Utc>, Option<i32>, i32)> = sessao_dsl::sessoes_trabalho
+        .filter(sessao_dsl::id_usuario.eq(id_usuario))
+        .filter(sessao_dsl::excluido_em.is_null())
+        .filter(sessao_dsl::inicio.ge(inicio_utc))
+        .select((sessao_dsl::inicio, sessao_dsl::total_minutos, sessao_dsl::total_corridas))
+        .load(conn)
+        .unwrap_or_default();
+    for (inicio, minutos, corridas) in sessoes {
+        razao.registrar_sessao(inicio, minutos, corridas);
+    }
+    razao
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn transacao(id: usize, rng: &mut StdRng, hoje: NaiveDate) -> Transacao {
+        let dia = hoje + Duration::days(rng.random_range(-70..=10));
+        let data = Utc.from_utc_datetime(&dia.and_hms_opt(rng.random_range(0..24), rng.random_range(0..60), 0).unwrap());
+        let tipo = if rng.random_bool(0.7) { "entrada" } else { "saida" };
+        Transacao {
+            id: format!("tx{id}"),
+            id_usuario: "u1".to_string(),
+            id_categoria: ["c_uber", "c_99", "c_comb"][rng.random_range(0..3)].to_string(),
+            valor: rng.random_range(0..5000),
+            eventos: rng.random_range(0..4),
+            km: if rng.random_bool(0.5) { Some(rng.random_range(0..400) as f64 / 10.0) } else { None },
+            descricao: None,
+            tipo: tipo.to_string(),
+            data,
+            criado_em: data,
+            atualizado_em: data,
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
+        }
+    }
+
+    fn razao_com(transacoes: &[Transacao], hoje: NaiveDate) -> RazaoDashboard {
+        let categorias = [("c_uber", "Corrida Uber"), ("c_99", "Corrida 99"), ("c_comb", "Combustível")]
+            .iter()
+            .map(|(id, nome)| {
+                let categoria = Categoria {
+                    id: id.to_string(),
+                    id_usuario: Some("u1".to_string()),
+                    nome: nome.to_string(),
+                    tipo: "entrada".to_string(),
+                    icone: None,
+                    cor: None,
+                    criado_em: Utc::now(),
+                    atualizado_em: Utc::now(),
+                    excluido_em: None,
+                };
+                (id.to_string(), categoria)
+            })
+            .collect();
+        let fixos = DadosFixos { projecao_metodo: "sazonal_semanal".to_string(), percentual_extremos: 10, categorias, ..Default::default() };
+        let mut razao = RazaoDashboard::novo("u1", RazaoDashboard::inicio_necessario(hoje), fixos);
+        for t in transacoes {
+            razao.aplicar(t, 1);
+        }
+        razao.registrar_sessao(Utc.from_utc_datetime(&hoje.and_hms_opt(8, 0, 0).unwrap()), Some(300), 12);
+        razao
+    }
+
+    fn como_json(stats: &DashboardStats) -> serde_json::Value {
+        serde_json::to_value(stats).unwrap()
+    }
+
+    fn data(dia: NaiveDate, hora: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&dia.and_hms_opt(hora, 0, 0).unwrap())
+    }
+
+    /// Soma direta das transações de um tipo com data em `[inicio, fim]`, sem passar pelo razão
+    /// (None quando não há nenhuma, como no dashboard)
+    fn soma_direta(transacoes: &[Transacao], tipo: &str, inicio: NaiveDate, fim: NaiveDate) -> Option<(i32, u32)> {
+        let linhas: Vec<&Transacao> = transacoes
+            .iter()
+            .filter(|t| t.tipo == tipo && (inicio..=fim).contains(&t.data.date_naive()))
+            .collect();
+        (!linhas.is_empty()).then(|| (linhas.iter().map(|t| t.valor).sum(), linhas.iter().map(|t| t.eventos as u32).sum()))
+    }
+
+    /// Sequências aleatórias de criação/edição/exclusão aplicadas como deltas sobre o
+    /// razão inicial devem dar exatamente os stats do razão montado do zero com o estado final.
+    #[test]
+    fn test_deltas_equivalem_ao_calculo_completo() {
+        let hoje = NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+        for semente in 0..200u64 {
+            let mut rng = StdRng::seed_from_u64(semente);
+            let mut atuais: Vec<Transacao> = (0..rng.random_range(0..40)).map(|i| transacao(i, &mut rng, hoje)).collect();
+            let mut incremental = razao_com(&atuais, hoje);
+
+            for passo in 0..30 {
+                match rng.random_range(0..3) {
+                    0 => {
+                        let nova = transacao(1000 + passo, &mut rng, hoje);
+                        incremental.aplicar(&nova, 1);
+                        atuais.push(nova);
+                    }
+                    1 if !atuais.is_empty() => {
+                        let i = rng.random_range(0..atuais.len());
+                        let mut depois = transacao(0, &mut rng, hoje);
+                        depois.id = atuais[i].id.clone();
+                        incremental.atualizar(&atuais[i], &depois);
+                        atuais[i] = depois;
+                    }
+                    _ if !atuais.is_empty() => {
+                        let removida = atuais.swap_remove(rng.random_range(0..atuais.len()));
+                        incremental.aplicar(&removida, -1);
+                    }
+                    _ => {}
+                }
+            }
+
+            let stats = incremental.stats(hoje);
+            let completo = razao_com(&atuais, hoje);
+            assert_eq!(como_json(&stats), como_json(&completo.stats(hoje)), "semente {semente}");
+
+            // e os totais batem com a soma direta do estado final (quarta-feira, 03/09/2025)
+            let dia = |m: u32, d: u32| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+            let periodos = [
+                (dia(9, 3), dia(9, 3), stats.ganhos_hoje, stats.gastos_hoje, stats.corridas_hoje),
+                (dia(9, 2), dia(9, 2), stats.ganhos_ontem, stats.gastos_ontem, stats.corridas_ontem),
+                (dia(9, 1), dia(9, 7), stats.ganhos_semana, stats.gastos_semana, stats.corridas_semana),
+                (dia(8, 25), dia(8, 31), stats.ganhos_semana_passada, stats.gastos_semana_passada, stats.corridas_semana_passada),
+                (dia(9, 1), dia(9, 30), stats.ganhos_mes, stats.gastos_mes, stats.corridas_mes),
+                (dia(8, 1), dia(8, 31), stats.ganhos_mes_passado, stats.gastos_mes_passado, stats.corridas_mes_passado),
+            ];
+            for (inicio, fim, ganhos, gastos, corridas) in periodos {
+                let entradas = soma_direta(&atuais, "entrada", inicio, fim);
+                assert_eq!(ganhos, entradas.map(|(v, _)| v), "semente {semente}, {inicio}..{fim}");
+                assert_eq!(corridas, entradas.map(|(_, e)| e), "semente {semente}, {inicio}..{fim}");
+                assert_eq!(gastos, soma_direta(&atuais, "saida", inicio, fim).map(|(v, _)| v), "semente {semente}, {inicio}..{fim}");
+            }
+            for (i, d) in (0..30).rev().map(|i| hoje - Duration::days(i)).enumerate() {
+                assert_eq!(stats.ganhos_30dias[i], soma_direta(&atuais, "entrada", d, d).map_or(0, |(v, _)| v), "semente {semente}, {d}");
+                assert_eq!(stats.gastos_30dias[i], soma_direta(&atuais, "saida", d, d).map_or(0, |(v, _)| v), "semente {semente}, {d}");
+            }
+        }
+    }
+
+    /// Criação, edição e exclusão conferidas contra totais calculados à mão
+    #[test]
+    fn test_deltas_conferem_com_totais_esperados() {
+        let hoje = NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let nova = |id: usize, tipo: &str, valor: i32, eventos: i32, dia: NaiveDate, rng: &mut StdRng| {
+            let mut t = transacao(id, rng, hoje);
+            (t.tipo, t.valor, t.eventos, t.km, t.data) = (tipo.to_string(), valor, eventos, Some(10.0), data(dia, 12));
+            t
+        };
+        let ontem = hoje - Duration::days(1);
+        let mes_passado = NaiveDate::from_ymd_opt(2025, 8, 20).unwrap();
+        let mut razao = razao_com(&[], hoje);
+
+        let a = nova(1, "entrada", 5000, 3, hoje, &mut rng);
+        let b = nova(2, "entrada", 2500, 2, ontem, &mut rng);
+        let c = nova(3, "saida", 1200, 0, hoje, &mut rng);
+        let d = nova(4, "entrada", 7000, 4, mes_passado, &mut rng);
+        for t in [&a, &b, &c, &d] {
+            razao.aplicar(t, 1);
+        }
+        // a entrada de ontem passa para hoje com outro valor; a saída é excluída
+        let mut b2 = b.clone();
+        (b2.valor, b2.data) = (3000, data(hoje, 15));
+        razao.atualizar(&b, &b2);
+        razao.aplicar(&c, -1);
+
+        let stats = razao.stats(hoje);
+        assert_eq!(stats.ganhos_hoje, Some(8000));
+        assert_eq!(stats.corridas_hoje, Some(5));
+        assert_eq!(stats.gastos_hoje, None);
+        assert_eq!(stats.lucro_hoje, None);
+        assert_eq!(stats.ganhos_ontem, None);
+        assert_eq!(stats.ganhos_semana, Some(8000));
+        assert_eq!(stats.ganhos_mes, Some(8000));
+        assert_eq!(stats.ganhos_mes_passado, Some(7000));
+        assert_eq!(stats.corridas_mes_passado, Some(4));
+        assert_eq!(stats.ganhos_semana_passada, None);
+        assert_eq!(stats.km_hoje, Some(20.0));
+        assert_eq!(stats.km_mes_passado, Some(10.0));
+        assert_eq!(stats.ganhos_7dias, vec![0, 0, 0, 0, 0, 0, 8000]);
+        assert_eq!(stats.gastos_7dias, vec![0; 7]);
+    }
+
+    /// Depois da meia-noite o mesmo razão responde pelos novos períodos sem recálculo
+    #[test]
+    fn test_virada_de_dia_recorta_novas_janelas() {
+        let hoje = NaiveDate::from_ymd_opt(2025, 9, 30).unwrap();
+        for semente in 0..50u64 {
+            let mut rng = StdRng::seed_from_u64(semente);
+            let transacoes: Vec<Transacao> = (0..60).map(|i| transacao(i, &mut rng, hoje)).collect();
+            let antigo = razao_com(&transacoes, hoje);
+            for dias in 1..=8 {
+                let depois = hoje + Duration::days(dias);
+                assert!(antigo.cobre(depois));
+                let mut novo = razao_com(&transacoes, depois);
+                novo.sessoes = antigo.sessoes.clone();
+                assert_eq!(como_json(&antigo.stats(depois)), como_json(&novo.stats(depois)), "semente {semente}, +{dias}d");
+            }
+        }
+    }
+
+    #[test]
+    fn test_transacao_entra_no_periodo_da_sua_data() {
+        let hoje = NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut t = transacao(1, &mut rng, hoje);
+        t.tipo = "entrada".to_string();
+        t.valor = 5000;
+        t.eventos = 2;
+        t.data = Utc.from_utc_datetime(&(hoje - Duration::days(1)).and_hms_opt(22, 0, 0).unwrap());
+        let mut razao = razao_com(&[], hoje);
+        razao.aplicar(&t, 1);
+
+        let stats = razao.stats(hoje);
+        assert_eq!(stats.ganhos_hoje, None);
+        assert_eq!(stats.ganhos_ontem, Some(5000));
+        assert_eq!(stats.corridas_ontem, Some(2));
+        assert_eq!(stats.ganhos_7dias[5], 5000);
+        assert_eq!(stats.ganhos_mes_passado, None);
+
+        razao.aplicar(&t, -1);
+        assert_eq!(razao.stats(hoje).ganhos_ontem, None);
+    }
+}
@@ -1,12 +1,9 @@
-use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use chrono::{DateTime, Local, TimeZone, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::models::configuracao::Configuracao;
-use crate::schema::configuracoes::dsl as config_dsl;
 use crate::schema::transacoes::dsl as transacao_dsl;
-use crate::schema::sessoes_trabalho::dsl as sessao_dsl;
-use crate::schema::metas::dsl as meta_dsl;
+use super::razao;
 
 
 // Tipos públicos para uso pela camada API
@@ -76,9 +73,17 @@ pub struct DashboardStats {
     pub km_30dias: Vec<f64>,
     pub projecao_mes: Option<i32>,
     pub projecao_semana: Option<i32>,
+    /// Método que gerou as projeções (com "automatico", o vencedor do backtest)
+    pub projecao_metodo_usado: String,
     pub trend_method: String,
     pub platforms: std::collections::HashMap<String, PlatformResult>,
     pub top_sources: TopSources,
+    /// Alertas de anomalia aguardando confirmação/descarte
+    pub alertas_pendentes: i64,
+    /// Parcelas de dívidas vencidas ou a vencer nos próximos 30 dias
+    pub proximas_parcelas: Vec<crate::services::divida::ParcelaProxima>,
+    /// Saldo dos envelopes ativos
+    pub envelopes: Vec<crate::services::envelope::SaldoEnvelope>,
 }
 
 #[derive(Serialize, Clone, Default)]
@@ -98,13 +103,15 @@ pub struct TopSources {
     pub despesas: Vec<TopSourceItem>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, Default)]
 pub struct DashboardFiltro {
     pub periodo: Option<String>,
     pub data_inicio: Option<DateTime<Utc>>,
     pub data_fim: Option<DateTime<Utc>>,
     pub tipo: Option<String>,
     pub categoria: Option<String>,
+    /// "periodo_anterior" (mesmo tamanho, imediatamente antes) ou "ano_anterior"
+    pub comparar_com: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -123,8 +130,8 @@ pub struct PlatformResult {
 
 // ---------------- Utilitárias e cálculos (migradas da antiga função)
 //
-// regressao_linear, media_movel, media_excluindo_extremos, helpers de soma,
-// top_source_for_period, compute_dashboard_stats, compute_platforms
+// regressao_linear, media_movel, media_excluindo_extremos, compute_dashboard_stats,
+// compute_platforms
 //
 
 pub fn regressao_linear(xs: &[f64], ys: &[f64]) -> Option<(f64, f64)> {
@@ -146,7 +153,7 @@ pub fn regressao_linear(xs: &[f64], ys: &[f64]) -> Option<(f64, f64)> {
     Some((a, b))
 }
 
-fn media_movel(data: &[i32], window: usize) -> Option<i32> {
+pub fn media_movel(data: &[i32], window: usize) -> Option<i32> {
     if data.len() < window || window == 0 {
         return None;
     }
@@ -156,7 +163,7 @@ fn media_movel(data: &[i32], window: usize) -> Option<i32> {
     Some((sum as f64 / window as f64).round() as i32)
 }
 
-fn media_excluindo_extremos(data: &[i32], percent: usize) -> i32 {
+pub fn media_excluindo_extremos(data: &[i32], percent: usize) -> i32 {
     if data.is_empty() {
         return 0;
     }
@@ -171,751 +178,12 @@ fn media_excluindo_extremos(data: &[i32], percent: usize) -> i32 {
     (sum as f64 / (slice.len() as f64)).round() as i32
 }
 
-fn soma_periodo(tipo: &str, id_usuario: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>, conn: &mut diesel::PgConnection) -> Option<i32> {
-    use crate::schema::transacoes::dsl as transacao_dsl_local;
-    transacao_dsl_local::transacoes
-        .filter(transacao_dsl_local::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl_local::data.ge(inicio))
-        .filter(transacao_dsl_local::data.le(fim))
-        .filter(transacao_dsl_local::tipo.eq(tipo))
-        .select(diesel::dsl::sum(transacao_dsl_local::valor))
-        .first::<Option<i64>>(conn).ok().flatten().map(|v| v as i32)
-}
-
-fn soma_corridas(id_usuario: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>, conn: &mut diesel::PgConnection) -> Option<u32> {
-    use crate::schema::transacoes::dsl as transacao_dsl_local;
-    transacao_dsl_local::transacoes
-        .filter(transacao_dsl_local::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl_local::data.ge(inicio))
-        .filter(transacao_dsl_local::data.le(fim))
-        .filter(transacao_dsl_local::tipo.eq("entrada"))
-        .select(diesel::dsl::sum(transacao_dsl_local::eventos))
-        .first::<Option<i64>>(conn).ok().flatten().map(|v| v as u32)
-}
-
-fn soma_horas(id_usuario: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>, conn: &mut diesel::PgConnection) -> Option<i32> {
-    use crate::schema::sessoes_trabalho::dsl as sessao_dsl_local;
-    let minutos = sessao_dsl_local::sessoes_trabalho
-        .filter(sessao_dsl_local::id_usuario.eq(id_usuario))
-        .filter(sessao_dsl_local::inicio.ge(inicio))
-        .filter(sessao_dsl_local::inicio.le(fim))
-        .select(diesel::dsl::sum(sessao_dsl_local::total_minutos))
-        .first::<Option<i64>>(conn).ok().flatten().map(|v| v as i32);
-    minutos.map(|m| m / 60)
-}
-
-fn top_source_for_period(tipo: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>, id_usuario: &str, conn: &mut diesel::PgConnection) -> TopSourceItem {
-    use crate::schema::transacoes::dsl as trans_dsl;
-    use crate::schema::categorias::dsl as cat_dsl;
-
-    let base_query = trans_dsl::transacoes
-        .filter(trans_dsl::id_usuario.eq(id_usuario))
-        .filter(trans_dsl::data.ge(inicio))
-        .filter(trans_dsl::data.le(fim))
-        .filter(trans_dsl::tipo.eq(tipo));
-
-    let vec_res: Vec<(String, Option<i64>)> = base_query
-        .group_by(trans_dsl::id_categoria)
-        .select((trans_dsl::id_categoria, diesel::dsl::sum(trans_dsl::valor)))
-        .order(diesel::dsl::sum(trans_dsl::valor).desc())
-        .load::<(String, Option<i64>)>(conn)
-        .unwrap_or_default();
-
-    let res = vec_res.into_iter().next();
-
-    if let Some((cat_id, cnt_opt)) = res {
-        let cnt = cnt_opt.unwrap_or(0);
-        let categoria: Option<crate::models::Categoria> = cat_dsl::categorias
-            .filter(cat_dsl::id.eq(&cat_id))
-            .first::<crate::models::Categoria>(conn)
-            .optional()
-            .unwrap_or(None);
-        TopSourceItem {
-            periodo: "".to_string(),
-            tipo: tipo.to_string(),
-            categoria_id: Some(cat_id.clone()),
-            nome: categoria.as_ref().map(|c| c.nome.clone()),
-            icone: categoria.as_ref().and_then(|c| c.icone.clone()),
-            cor: categoria.as_ref().and_then(|c| c.cor.clone()),
-            valor: cnt,
-        }
-    } else {
-        TopSourceItem {
-            periodo: "".to_string(),
-            tipo: tipo.to_string(),
-            categoria_id: None,
-            nome: None,
-            icone: None,
-            cor: None,
-            valor: 0,
-        }
-    }
-}
-
 // Função pública que encapsula todo o cálculo do dashboard (antes estava no handler)
-// ALTERAÇÃO: removeu `params: DashboardFiltro`
+// ALTERAÇÃO: removeu `params: DashboardFiltro` (intervalos arbitrários: ver `periodo::compute_dashboard_periodo`)
+// Os períodos são recortados do razão diário (ver `razao`), o mesmo que o cache atualiza por deltas
 pub fn compute_dashboard_stats(conn: &mut diesel::PgConnection, id_usuario: &str) -> DashboardStats {
-    // equivalente ao início do handler original
-    let now = Utc::now().naive_utc();
-
-    // Buscar configurações do usuário
-    let configs: Vec<Configuracao> = config_dsl::configuracoes
-        .filter(config_dsl::id_usuario.eq(id_usuario))
-        .load(conn)
-        .unwrap_or_default();
-    let get_config = |chave: &str| -> Option<String> {
-        configs.iter().find(|c| c.chave == chave).and_then(|c| c.valor.clone())
-    };
-    let projecao_metodo = get_config("projecao_metodo").unwrap_or_else(|| "media_movel_3".to_string());
-    let projecao_percentual_extremos: usize = get_config("projecao_percentual_extremos").and_then(|v| v.parse().ok()).unwrap_or(10);
-
-    // OBS: não recebemos mais filtros via params => comportamento padrão:
-    // INICIO DO DIA '00:00' ATÉ AGORA
-    let inicio = Utc.from_utc_datetime(&now.date().and_hms_opt(0, 0, 0).unwrap());
-    let fim = Utc.from_utc_datetime(&now);
-    // Arrays dos últimos 7 dias
-    let mut ganhos_7dias = Vec::with_capacity(7);
-    let mut gastos_7dias = Vec::with_capacity(7);
-    let mut lucro_7dias = Vec::with_capacity(7);
-    let mut corridas_7dias = Vec::with_capacity(7);
-    let mut horas_7dias = Vec::with_capacity(7);
-    let mut km_7dias = Vec::with_capacity(7);
-    for i in (0..7).rev() {
-        let dia = now.date() - Duration::days(i);
-        let inicio_dia = Utc.from_utc_datetime(&dia.and_hms_opt(0, 0, 0).unwrap());
-        let fim_dia = Utc.from_utc_datetime(&dia.and_hms_opt(23, 59, 59).unwrap());
-        let ganhos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("entrada"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        let gastos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("saida"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        let corridas_dia: u32 = sessao_dsl::sessoes_trabalho
-            .filter(sessao_dsl::id_usuario.eq(id_usuario))
-            .filter(sessao_dsl::inicio.ge(inicio_dia))
-            .filter(sessao_dsl::inicio.le(fim_dia))
-            .select(diesel::dsl::sum(sessao_dsl::total_corridas))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0).try_into().unwrap_or(0);
-        let horas_dia: i32 = sessao_dsl::sessoes_trabalho
-            .filter(sessao_dsl::id_usuario.eq(id_usuario))
-            .filter(sessao_dsl::inicio.ge(inicio_dia))
-            .filter(sessao_dsl::inicio.le(fim_dia))
-            .select(diesel::dsl::sum(sessao_dsl::total_minutos))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32 / 60;
-        // quilometragem do dia
-        let km_dia_result = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .select(diesel::dsl::sum(transacao_dsl::km))
-            .first::<Option<f64>>(conn);
-
-        let km_dia: f64 = match km_dia_result {
-            Ok(Some(val)) => val,
-            Ok(None) => 0.0,
-            Err(e) => {
-                println!("[DEBUG] Erro na query km_dia (7dias): {:?}", e);
-                0.0
-            }
-        };
-
-        ganhos_7dias.push(ganhos_dia);
-        gastos_7dias.push(gastos_dia);
-        lucro_7dias.push(ganhos_dia - gastos_dia);
-        corridas_7dias.push(corridas_dia);
-        horas_7dias.push(horas_dia);
-        km_7dias.push(km_dia);
-    }
-
-    // Arrays dos últimos 30 dias corridos
-    let mut ultimos_30_dias_labels = Vec::new();
-    let mut ganhos_30dias = Vec::new();
-    let mut gastos_30dias = Vec::new();
-    let mut lucro_30dias = Vec::new();
-    let mut corridas_30dias = Vec::new();
-    let mut horas_30dias = Vec::new();
-    let mut km_30dias = Vec::new();
-    for i in (0..30).rev() {
-        let data_dia = now.date() - Duration::days(i);
-        ultimos_30_dias_labels.push(data_dia.format("%d/%m").to_string());
-        let inicio_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(0, 0, 0).unwrap());
-        let fim_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(23, 59, 59).unwrap());
-        let ganhos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("entrada"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        let gastos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("saida"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        let corridas_dia: u32 = sessao_dsl::sessoes_trabalho
-            .filter(sessao_dsl::id_usuario.eq(id_usuario))
-            .filter(sessao_dsl::inicio.ge(inicio_dia))
-            .filter(sessao_dsl::inicio.le(fim_dia))
-            .select(diesel::dsl::sum(sessao_dsl::total_corridas))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0).try_into().unwrap_or(0);
-        let horas_dia: i32 = sessao_dsl::sessoes_trabalho
-            .filter(sessao_dsl::id_usuario.eq(id_usuario))
-            .filter(sessao_dsl::inicio.ge(inicio_dia))
-            .filter(sessao_dsl::inicio.le(fim_dia))
-            .select(diesel::dsl::sum(sessao_dsl::total_minutos))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32 / 60;
-        let km_dia_result = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .select(diesel::dsl::sum(transacao_dsl::km))
-            .first::<Option<f64>>(conn);
-
-        let km_dia: f64 = match km_dia_result {
-            Ok(Some(val)) => val,
-            Ok(None) => 0.0,
-            Err(e) => {
-                println!("[DEBUG] Erro na query km_dia (30dias): {:?}", e);
-                0.0
-            }
-        };
-
-        ganhos_30dias.push(ganhos_dia);
-        gastos_30dias.push(gastos_dia);
-        lucro_30dias.push(ganhos_dia - gastos_dia);
-        corridas_30dias.push(corridas_dia);
-        horas_30dias.push(horas_dia);
-        km_30dias.push(km_dia);
-    }
-
-    // Projeção do mês corrente
-    let hoje = now.date();
-    let mes = hoje.month();
-    let ano = hoje.year();
-    let inicio_mes = chrono::NaiveDate::from_ymd_opt(ano, mes, 1).unwrap();
-    let dias_passados_mes = (hoje - inicio_mes).num_days() + 1;
-    let dias_no_mes = chrono::NaiveDate::from_ymd_opt(ano, mes % 12 + 1, 1)
-        .unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(ano + 1, 1, 1).unwrap())
-        .signed_duration_since(inicio_mes)
-        .num_days();
-    let mut soma_ganhos_mes = 0;
-    for i in 0..dias_passados_mes {
-        let data_dia = inicio_mes + chrono::Duration::days(i);
-        let inicio_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(0, 0, 0).unwrap());
-        let fim_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(23, 59, 59).unwrap());
-        let ganhos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("entrada"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        soma_ganhos_mes += ganhos_dia;
-    }
-    let projecao_mes = if dias_passados_mes > 0 {
-        Some((soma_ganhos_mes as f64 / dias_passados_mes as f64 * dias_no_mes as f64).round() as i32)
-    } else {
-        None
-    };
-
-    // Projeção da semana corrente
-    let inicio_semana = hoje - chrono::Duration::days(hoje.weekday().num_days_from_monday() as i64);
-    let dias_passados_semana = (hoje - inicio_semana).num_days() + 1;
-    let mut soma_ganhos_semana = 0;
-    for i in 0..dias_passados_semana {
-        let data_dia = inicio_semana + chrono::Duration::days(i);
-        let inicio_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(0, 0, 0).unwrap());
-        let fim_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(23, 59, 59).unwrap());
-        let ganhos_dia: i32 = transacao_dsl::transacoes
-            .filter(transacao_dsl::id_usuario.eq(id_usuario))
-            .filter(transacao_dsl::data.ge(inicio_dia))
-            .filter(transacao_dsl::data.le(fim_dia))
-            .filter(transacao_dsl::tipo.eq("entrada"))
-            .select(diesel::dsl::sum(transacao_dsl::valor))
-            .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-        soma_ganhos_semana += ganhos_dia;
-    }
-    let projecao_semana = if dias_passados_semana > 0 {
-        Some((soma_ganhos_semana as f64 / dias_passados_semana as f64 * 7.0).round() as i32)
-    } else {
-        None
-    };
-
-    // queries customizadas baseadas em periodo calculado acima
-    let ganhos_query = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio))
-        .filter(transacao_dsl::data.le(fim))
-        .filter(transacao_dsl::tipo.eq("entrada"))
-        .into_boxed();
-    let gastos_query = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio))
-        .filter(transacao_dsl::data.le(fim))
-        .filter(transacao_dsl::tipo.eq("saida"))
-        .into_boxed();
-
-    // Buscar todos os valores de ganhos para cálculo customizado
-    let ganhos_valores: Vec<i32> = ganhos_query.select(transacao_dsl::valor).load(conn).unwrap_or_default();
-    let ganhos: i32 = if projecao_metodo == "mediana" {
-        // Cálculo por mediana
-        let mut v = ganhos_valores.clone();
-        v.sort();
-        let len = v.len();
-        if len == 0 {
-            0
-        } else if len % 2 == 1 {
-            v[len / 2]
-        } else {
-            (v[len / 2 - 1] + v[len / 2]) / 2
-        }
-    } else if projecao_metodo == "media_movel_3" {
-        media_movel(&ganhos_7dias, 3).unwrap_or(0)
-    } else if projecao_metodo == "media_movel_7" {
-        media_movel(&ganhos_7dias, 7).unwrap_or(0)
-    } else if projecao_metodo == "media_movel_30" {
-        media_movel(&ganhos_30dias, 30).unwrap_or(0)
-    } else {
-        // Cálculo por média, excluindo extremos se configurado
-        media_excluindo_extremos(&ganhos_valores, projecao_percentual_extremos)
-    };
-    let gastos: i32 = gastos_query.select(diesel::dsl::sum(transacao_dsl::valor)).first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-    let _lucro = ganhos - gastos;
-    let _corridas: u32 = sessao_dsl::sessoes_trabalho
-        .filter(sessao_dsl::id_usuario.eq(id_usuario))
-        .filter(sessao_dsl::inicio.ge(inicio))
-        .filter(sessao_dsl::inicio.le(fim))
-        .select(diesel::dsl::sum(sessao_dsl::total_corridas))
-        .first::<Option<i64>>(conn)
-        .unwrap_or(Some(0))
-        .unwrap_or(0)
-        .try_into()
-        .unwrap_or(0);
-    let minutos: i32 = sessao_dsl::sessoes_trabalho.filter(sessao_dsl::id_usuario.eq(id_usuario)).filter(sessao_dsl::inicio.ge(inicio)).filter(sessao_dsl::inicio.le(fim)).select(diesel::dsl::sum(sessao_dsl::total_minutos)).first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-    let _horas = minutos / 60;
-    let total_metas: i32 = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).count().get_result::<i64>(conn).unwrap_or(0) as i32;
-    let metas_concluidas: i32 = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).filter(meta_dsl::eh_concluida.eq(true)).count().get_result::<i64>(conn).unwrap_or(0) as i32;
-    let eficiencia = if total_metas > 0 { (metas_concluidas * 100) / total_metas } else { 0 };
-    let meta_diaria = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).filter(meta_dsl::eh_ativa.eq(true)).order_by(meta_dsl::data_inicio.desc()).select(meta_dsl::valor_alvo).first::<i32>(conn).ok();
-    let meta_semanal = meta_dsl::metas.filter(meta_dsl::id_usuario.eq(id_usuario)).filter(meta_dsl::eh_ativa.eq(true)).order_by(meta_dsl::data_inicio.desc()).select(meta_dsl::valor_alvo).first::<i32>(conn).ok();
-
-    // Arrays do mês atual (por dia)
-    let mut ganhos_mes = Vec::new();
-    let mut gastos_mes = Vec::new();
-    let mut lucro_mes = Vec::new();
-    let mes_atual = now.date().month();
-    let ano_atual = now.date().year();
-    // Descobre o número de dias do mês atual
-    let dias_no_mes = chrono::NaiveDate::from_ymd_opt(ano_atual, mes_atual, 1)
-        .unwrap()
-        .with_month(mes_atual % 12 + 1)
-        .unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(ano_atual + 1, 1, 1).unwrap())
-        .signed_duration_since(chrono::NaiveDate::from_ymd_opt(ano_atual, mes_atual, 1).unwrap())
-        .num_days();
-    for dia in 1..=dias_no_mes.try_into().unwrap_or(0) {
-        if let Some(data_dia) = chrono::NaiveDate::from_ymd_opt(ano_atual, mes_atual, dia) {
-            let inicio_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(0, 0, 0).unwrap());
-            let fim_dia = Utc.from_utc_datetime(&data_dia.and_hms_opt(23, 59, 59).unwrap());
-            let ganhos_dia: i32 = transacao_dsl::transacoes
-                .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                .filter(transacao_dsl::data.ge(inicio_dia))
-                .filter(transacao_dsl::data.le(fim_dia))
-                .filter(transacao_dsl::tipo.eq("entrada"))
-                .select(diesel::dsl::sum(transacao_dsl::valor))
-                .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-            let gastos_dia: i32 = transacao_dsl::transacoes
-                .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                .filter(transacao_dsl::data.ge(inicio_dia))
-                .filter(transacao_dsl::data.le(fim_dia))
-                .filter(transacao_dsl::tipo.eq("saida"))
-                .select(diesel::dsl::sum(transacao_dsl::valor))
-                .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32;
-            ganhos_mes.push(ganhos_dia);
-            gastos_mes.push(gastos_dia);
-            lucro_mes.push(ganhos_dia - gastos_dia);
-        }
-    }
-
-    
-
-    // Tendência usando média móvel conforme configuração
-    let trend_regression_from_i32 = |data: &Vec<i32>| -> Option<i32> {
-        if data.len() < 2 {
-            return None;
-        }
-        let n = data.len();
-        let xs: Vec<f64> = (0..n).map(|i| i as f64).collect();
-        let ys: Vec<f64> = data.iter().map(|&v| v as f64).collect();
-        regressao_linear(&xs, &ys).map(|(a, _b)| a.round() as i32)
-    };
-
-    let tendencia_ganhos = match projecao_metodo.as_str() {
-        "media_movel_3" => media_movel(&ganhos_7dias, 3),
-        "media_movel_7" => media_movel(&ganhos_7dias, 7),
-        "media_movel_30" => media_movel(&ganhos_mes, 30),
-        "regressao_linear" => trend_regression_from_i32(&ganhos_7dias),
-        _ => None,
-    };
-
-    let tendencia_gastos = match projecao_metodo.as_str() {
-        "media_movel_3" => media_movel(&gastos_7dias, 3),
-        "media_movel_7" => media_movel(&gastos_7dias, 7),
-        "media_movel_30" => media_movel(&gastos_mes, 30),
-        "regressao_linear" => trend_regression_from_i32(&gastos_7dias),
-        _ => None,
-    };
-    let corridas_7dias_i32: Vec<i32> = corridas_7dias.iter().map(|&v| v as i32).collect();
-    let corridas_30dias_i32: Vec<i32> = corridas_30dias.iter().map(|&v| v as i32).collect();
-
-    let tendencia_corridas = match projecao_metodo.as_str() {
-        "media_movel_3" => media_movel(&corridas_7dias_i32, 3),
-        "media_movel_7" => media_movel(&corridas_7dias_i32, 7),
-        "media_movel_30" => media_movel(&corridas_30dias_i32, 30),
-        "regressao_linear" => trend_regression_from_i32(&corridas_7dias_i32),
-        _ => None,
-    };
-
-    // Datas para períodos
-    let hoje = now.date();
-    let ontem = hoje - Duration::days(1);
-    let inicio_hoje = Utc.from_utc_datetime(&hoje.and_hms_opt(0,0,0).unwrap());
-    let fim_hoje = Utc.from_utc_datetime(&hoje.and_hms_opt(23,59,59).unwrap());
-    let inicio_ontem = Utc.from_utc_datetime(&ontem.and_hms_opt(0,0,0).unwrap());
-    let fim_ontem = Utc.from_utc_datetime(&ontem.and_hms_opt(23,59,59).unwrap());
-
-    let inicio_7dias = now - Duration::days(7);
-    let fim_7dias = now;
-    let inicio_7dias_ndt = Utc.from_utc_datetime(&inicio_7dias.date().and_hms_opt(0,0,0).unwrap());
-    let fim_7dias_ndt = Utc.from_utc_datetime(&fim_7dias.date().and_hms_opt(23,59,59).unwrap());
-
-    let inicio_30dias = now - Duration::days(30);
-    let fim_30dias = now;
-    let inicio_30dias_ndt = Utc.from_utc_datetime(&inicio_30dias.date().and_hms_opt(0,0,0).unwrap());
-    let fim_30dias_ndt = Utc.from_utc_datetime(&fim_30dias.date().and_hms_opt(23,59,59).unwrap());
-
-    let inicio_semana = hoje - Duration::days(hoje.weekday().num_days_from_monday() as i64);
-    let fim_semana = inicio_semana + Duration::days(6);
-    let inicio_semana_ndt = Utc.from_utc_datetime(&inicio_semana.and_hms_opt(0,0,0).unwrap());
-    let fim_semana_ndt = Utc.from_utc_datetime(&fim_semana.and_hms_opt(23,59,59).unwrap());
-
-    let inicio_semana_passada = inicio_semana - Duration::days(7);
-    let fim_semana_passada = inicio_semana - Duration::days(1);
-    let inicio_semana_passada_ndt = Utc.from_utc_datetime(&inicio_semana_passada.and_hms_opt(0,0,0).unwrap());
-    let fim_semana_passada_ndt = Utc.from_utc_datetime(&fim_semana_passada.and_hms_opt(23,59,59).unwrap());
-
-    let mes = hoje.month();
-    let ano = hoje.year();
-    let inicio_mes = chrono::NaiveDate::from_ymd_opt(ano, mes, 1).unwrap();
-    let dias_mes = chrono::NaiveDate::from_ymd_opt(ano, mes % 12 + 1, 1).unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(ano + 1, 1, 1).unwrap()).signed_duration_since(inicio_mes).num_days();
-    let fim_mes = inicio_mes + Duration::days(dias_mes - 1);
-    let inicio_mes_ndt = Utc.from_utc_datetime(&inicio_mes.and_hms_opt(0,0,0).unwrap());
-    let fim_mes_ndt = Utc.from_utc_datetime(&fim_mes.and_hms_opt(23,59,59).unwrap());
-
-    let mes_passado = if mes == 1 { 12 } else { mes - 1 };
-    let ano_mes_passado = if mes == 1 { ano - 1 } else { ano };
-    let inicio_mes_passado = chrono::NaiveDate::from_ymd_opt(ano_mes_passado, mes_passado, 1).unwrap();
-    let dias_mes_passado = chrono::NaiveDate::from_ymd_opt(ano_mes_passado, mes_passado % 12 + 1, 1).unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(ano_mes_passado + 1, 1, 1).unwrap()).signed_duration_since(inicio_mes_passado).num_days();
-    let fim_mes_passado = inicio_mes_passado + Duration::days(dias_mes_passado - 1);
-    let inicio_mes_passado_ndt = Utc.from_utc_datetime(&inicio_mes_passado.and_hms_opt(0,0,0).unwrap());
-    let fim_mes_passado_ndt = Utc.from_utc_datetime(&fim_mes_passado.and_hms_opt(23,59,59).unwrap());
-
-    // Debug: mostrar períodos de data para diagnóstico
-    println!("[DEBUG] Períodos de data calculados:");
-    println!("[DEBUG] Hoje: {} a {}", inicio_hoje, fim_hoje);
-    println!("[DEBUG] Semana: {} a {}", inicio_semana_ndt, fim_semana_ndt);
-    println!("[DEBUG] Mês: {} a {}", inicio_mes_ndt, fim_mes_ndt);
-
-    // Soma de km para períodos agregados (agora que variáveis de data estão em escopo)
-    let km_hoje_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_hoje))
-        .filter(transacao_dsl::data.le(fim_hoje))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_hoje: f64 = match km_hoje_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_hoje: {:?}", e);
-            0.0
-        }
-    };
-
-    let km_ontem_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_ontem))
-        .filter(transacao_dsl::data.le(fim_ontem))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_ontem: f64 = match km_ontem_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_ontem: {:?}", e);
-            0.0
-        }
-    };
-
-    let km_semana_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_semana_ndt))
-        .filter(transacao_dsl::data.le(fim_semana_ndt))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_semana: f64 = match km_semana_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_semana: {:?}", e);
-            0.0
-        }
-    };
-
-    let km_semana_passada_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_semana_passada_ndt))
-        .filter(transacao_dsl::data.le(fim_semana_passada_ndt))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_semana_passada: f64 = match km_semana_passada_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_semana_passada: {:?}", e);
-            0.0
-        }
-    };
-
-    let km_mes_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_mes_ndt))
-        .filter(transacao_dsl::data.le(fim_mes_ndt))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_mes: f64 = match km_mes_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_mes: {:?}", e);
-            0.0
-        }
-    };
-
-    let km_mes_passado_result = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::data.ge(inicio_mes_passado_ndt))
-        .filter(transacao_dsl::data.le(fim_mes_passado_ndt))
-        .select(diesel::dsl::sum(transacao_dsl::km))
-        .first::<Option<f64>>(conn);
-
-    let km_mes_passado: f64 = match km_mes_passado_result {
-        Ok(Some(val)) => val,
-        Ok(None) => 0.0,
-        Err(e) => {
-            println!("[DEBUG] Erro na query km_mes_passado: {:?}", e);
-            0.0
-        }
-    };
-
-    // Debug: contar quantas transações têm km não nulo
-    let total_transacoes_com_km: i64 = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::km.is_not_null())
-        .count()
-        .first(conn)
-        .unwrap_or(0);
-
-    let total_transacoes: i64 = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .count()
-        .first(conn)
-        .unwrap_or(0);
-
-    // Debug: mostrar algumas transações de exemplo
-    let sample_transactions: Vec<(String, chrono::DateTime<Utc>, Option<f64>)> = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .filter(transacao_dsl::km.is_not_null())
-        .select((transacao_dsl::id, transacao_dsl::data, transacao_dsl::km))
-        .limit(5)
-        .load(conn)
-        .unwrap_or_default();
-
-    // Debug: mostrar intervalo de datas das transações
-    let data_min: Option<chrono::DateTime<Utc>> = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .select(diesel::dsl::min(transacao_dsl::data))
-        .first(conn)
-        .unwrap_or(None);
-    
-    let data_max: Option<chrono::DateTime<Utc>> = transacao_dsl::transacoes
-        .filter(transacao_dsl::id_usuario.eq(id_usuario))
-        .select(diesel::dsl::max(transacao_dsl::data))
-        .first(conn)
-        .unwrap_or(None);
-
-    println!("[DEBUG] Intervalo de datas das transações: {:?} a {:?}", data_min, data_max);
-    println!("[DEBUG] Exemplos de transações com km:");
-    for (id, data, km) in sample_transactions {
-        println!("[DEBUG]   ID: {}, Data: {}, KM: {:?}", id, data, km);
-    }
-
-    println!("[DEBUG] KM agregados para user {}: hoje={}, ontem={}, semana={}, mes={}", 
-        id_usuario, km_hoje, km_ontem, km_semana, km_mes);
-    println!("[DEBUG] Transações com km não nulo: {} de {} transações totais", 
-        total_transacoes_com_km, total_transacoes);
-
-    // construir mapa de platforms
-    let mut platforms_map: HashMap<String, PlatformResult> = HashMap::new();
-    let platform_names = ["Corrida Uber".to_string(), "Corrida 99".to_string()];
-    for name in platform_names.iter() {
-        use crate::schema::categorias::dsl as cat_dsl;
-        let categorias: Vec<crate::models::Categoria> = cat_dsl::categorias
-            .filter(cat_dsl::id_usuario.eq(Some(id_usuario.to_string())))
-            .filter(cat_dsl::nome.eq(name))
-            .load::<crate::models::Categoria>(conn)
-            .unwrap_or_default();
-        let cat_ids: Vec<String> = categorias.iter().map(|c| c.id.clone()).collect();
-
-        let ganhos_p: i32 = if cat_ids.is_empty() {
-            0
-        } else {
-            transacao_dsl::transacoes
-                .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                .filter(transacao_dsl::data.ge(inicio_hoje))
-                .filter(transacao_dsl::data.le(fim_hoje))
-                .filter(transacao_dsl::tipo.eq("entrada"))
-                .filter(transacao_dsl::id_categoria.eq_any(cat_ids.clone()))
-                .select(diesel::dsl::sum(transacao_dsl::valor))
-                .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32
-        };
-        let corridas_i64: i64 = if cat_ids.is_empty() {
-            0
-        } else {
-            transacao_dsl::transacoes
-                .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                .filter(transacao_dsl::data.ge(inicio_hoje))
-                .filter(transacao_dsl::data.le(fim_hoje))
-                .filter(transacao_dsl::tipo.eq("entrada"))
-                .filter(transacao_dsl::id_categoria.eq_any(cat_ids.clone()))
-                .select(diesel::dsl::sum(transacao_dsl::eventos))
-                .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0)
-        };
-        let corridas_p: u32 = corridas_i64.try_into().unwrap_or(0);
-        let (icone, cor) = categorias.first().map(|c| (c.icone.clone(), c.cor.clone())).unwrap_or((None, None));
-        platforms_map.insert(name.clone(), PlatformResult { ganhos: ganhos_p, corridas: corridas_p, icone, cor, periodo: "hoje".to_string() });
-    }
-
-    // obter top sources (receitas e despesas)
-    let mut receitas: Vec<TopSourceItem> = Vec::new();
-    let mut despesas: Vec<TopSourceItem> = Vec::new();
-
-    let mut r_today = top_source_for_period("entrada", inicio_hoje, fim_hoje, id_usuario, conn);
-    r_today.periodo = "diario".to_string();
-    receitas.push(r_today);
-    let mut r_7 = top_source_for_period("entrada", inicio_7dias_ndt, fim_7dias_ndt, id_usuario, conn);
-    r_7.periodo = "7dias".to_string();
-    receitas.push(r_7);
-    let mut r_30 = top_source_for_period("entrada", inicio_30dias_ndt, fim_30dias_ndt, id_usuario, conn);
-    r_30.periodo = "30dias".to_string();
-    receitas.push(r_30);
-
-    let mut d_today = top_source_for_period("saida", inicio_hoje, fim_hoje, id_usuario, conn);
-    d_today.periodo = "diario".to_string();
-    despesas.push(d_today);
-    let mut d_7 = top_source_for_period("saida", inicio_7dias_ndt, fim_7dias_ndt, id_usuario, conn);
-    d_7.periodo = "7dias".to_string();
-    despesas.push(d_7);
-    let mut d_30 = top_source_for_period("saida", inicio_30dias_ndt, fim_30dias_ndt, id_usuario, conn);
-    d_30.periodo = "30dias".to_string();
-    despesas.push(d_30);
-
-    let top_sources = TopSources { receitas, despesas };
-
-    // finalmente montar stats incluindo platforms
-    DashboardStats {
-        ganhos_hoje: soma_periodo("entrada", id_usuario, inicio_hoje, fim_hoje, conn),
-        ganhos_ontem: soma_periodo("entrada", id_usuario, inicio_ontem, fim_ontem, conn),
-        ganhos_semana: soma_periodo("entrada", id_usuario, inicio_semana_ndt, fim_semana_ndt, conn),
-        ganhos_semana_passada: soma_periodo("entrada", id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn),
-        ganhos_mes: soma_periodo("entrada", id_usuario, inicio_mes_ndt, fim_mes_ndt, conn),
-        ganhos_mes_passado: soma_periodo("entrada", id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn),
-
-        gastos_hoje: soma_periodo("saida", id_usuario, inicio_hoje, fim_hoje, conn),
-        gastos_ontem: soma_periodo("saida", id_usuario, inicio_ontem, fim_ontem, conn),
-        gastos_semana: soma_periodo("saida", id_usuario, inicio_semana_ndt, fim_semana_ndt, conn),
-        gastos_semana_passada: soma_periodo("saida", id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn),
-        gastos_mes: soma_periodo("saida", id_usuario, inicio_mes_ndt, fim_mes_ndt, conn),
-        gastos_mes_passado: soma_periodo("saida", id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn),
-
-    // km aggregates
-    km_hoje: Some(km_hoje),
-    km_ontem: Some(km_ontem),
-    km_semana: Some(km_semana),
-    km_semana_passada: Some(km_semana_passada),
-    km_mes: Some(km_mes),
-    km_mes_passado: Some(km_mes_passado),
-
-        lucro_hoje: soma_periodo("entrada", id_usuario, inicio_hoje, fim_hoje, conn).zip(soma_periodo("saida", id_usuario, inicio_hoje, fim_hoje, conn)).map(|(g, s)| g - s),
-        lucro_ontem: soma_periodo("entrada", id_usuario, inicio_ontem, fim_ontem, conn).zip(soma_periodo("saida", id_usuario, inicio_ontem, fim_ontem, conn)).map(|(g, s)| g - s),
-        lucro_semana: soma_periodo("entrada", id_usuario, inicio_semana_ndt, fim_semana_ndt, conn).zip(soma_periodo("saida", id_usuario, inicio_semana_ndt, fim_semana_ndt, conn)).map(|(g, s)| g - s),
-        lucro_semana_passada: soma_periodo("entrada", id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn).zip(soma_periodo("saida", id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn)).map(|(g, s)| g - s),
-        lucro_mes: soma_periodo("entrada", id_usuario, inicio_mes_ndt, fim_mes_ndt, conn).zip(soma_periodo("saida", id_usuario, inicio_mes_ndt, fim_mes_ndt, conn)).map(|(g, s)| g - s),
-        lucro_mes_passado: soma_periodo("entrada", id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn).zip(soma_periodo("saida", id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn)).map(|(g, s)| g - s),
-
-        corridas_hoje: soma_corridas(id_usuario, inicio_hoje, fim_hoje, conn),
-        corridas_ontem: soma_corridas(id_usuario, inicio_ontem, fim_ontem, conn),
-        corridas_semana: soma_corridas(id_usuario, inicio_semana_ndt, fim_semana_ndt, conn),
-        corridas_semana_passada: soma_corridas(id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn),
-        corridas_mes: soma_corridas(id_usuario, inicio_mes_ndt, fim_mes_ndt, conn),
-        corridas_mes_passado: soma_corridas(id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn),
-
-        horas_hoje: soma_horas(id_usuario, inicio_hoje, fim_hoje, conn),
-        horas_ontem: soma_horas(id_usuario, inicio_ontem, fim_hoje, conn),
-        horas_semana: soma_horas(id_usuario, inicio_semana_ndt, fim_semana_ndt, conn),
-        horas_semana_passada: soma_horas(id_usuario, inicio_semana_passada_ndt, fim_semana_passada_ndt, conn),
-        horas_mes: soma_horas(id_usuario, inicio_mes_ndt, fim_mes_ndt, conn),
-        horas_mes_passado: soma_horas(id_usuario, inicio_mes_passado_ndt, fim_mes_passado_ndt, conn),
-
-        eficiencia: Some(eficiencia),
-        meta_diaria,
-        meta_semanal,
-        tendencia_ganhos,
-        tendencia_gastos,
-        tendencia_corridas,
-        ganhos_7dias,
-        gastos_7dias,
-        lucro_7dias,
-        corridas_7dias,
-        horas_7dias,
-    km_7dias,
-        ultimos_30_dias_labels,
-        ganhos_30dias,
-        gastos_30dias,
-        lucro_30dias,
-        corridas_30dias,
-        horas_30dias,
-    km_30dias,
-        projecao_mes,
-        projecao_semana,
-        trend_method: projecao_metodo,
-        platforms: platforms_map,
-        top_sources,
-    }
+    let hoje = Utc::now().date_naive();
+    razao::carregar(conn, id_usuario, hoje).stats(hoje)
 }
 
 // Função pública para computar platforms (migrada do handler anterior)
@@ -927,42 +195,32 @@ pub fn compute_platforms(conn: &mut diesel::PgConnection, id_usuario: &str, name
 
     let mut results: HashMap<String, PlatformResult> = HashMap::new();
     if let Some(names_csv) = names_csv {
+        // entradas do dia, com as divididas já separadas por categoria
+        let entradas_hoje: Vec<crate::models::Transacao> = transacao_dsl::transacoes
+            .filter(transacao_dsl::id_usuario.eq(id_usuario))
+            .filter(transacao_dsl::excluido_em.is_null())
+            .filter(transacao_dsl::data.ge(inicio_hoje))
+            .filter(transacao_dsl::data.le(fim_hoje))
+            .filter(transacao_dsl::tipo.eq("entrada"))
+            .load(conn)
+            .unwrap_or_default();
+        let entradas_hoje = crate::services::transacao::divisao::expandir(conn, entradas_hoje);
         let names: Vec<String> = names_csv.split(',').map(|s| s.trim().to_string()).collect();
         for name in names {
             use crate::schema::categorias::dsl as cat_dsl;
             let categorias: Vec<crate::models::Categoria> = cat_dsl::categorias
                 .filter(cat_dsl::id_usuario.eq(Some(id_usuario.to_string())))
                 .filter(cat_dsl::nome.eq(&name))
+                .filter(cat_dsl::excluido_em.is_null())
                 .load::<crate::models::Categoria>(conn)
                 .unwrap_or_default();
 
             let cat_ids: Vec<String> = categorias.iter().map(|c| c.id.clone()).collect();
-
-            let ganhos: i32 = if cat_ids.is_empty() {
-                0
-            } else {
-                transacao_dsl::transacoes
-                    .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                    .filter(transacao_dsl::data.ge(inicio_hoje))
-                    .filter(transacao_dsl::data.le(fim_hoje))
-                    .filter(transacao_dsl::tipo.eq("entrada"))
-                    .filter(transacao_dsl::id_categoria.eq_any(cat_ids.clone()))
-                    .select(diesel::dsl::sum(transacao_dsl::valor))
-                    .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0) as i32
-            };
-
-            let corridas_i64: i64 = if cat_ids.is_empty() {
-                0
-            } else {
-                transacao_dsl::transacoes
-                    .filter(transacao_dsl::id_usuario.eq(id_usuario))
-                    .filter(transacao_dsl::data.ge(inicio_hoje))
-                    .filter(transacao_dsl::data.le(fim_hoje))
-                    .filter(transacao_dsl::tipo.eq("entrada"))
-                    .filter(transacao_dsl::id_categoria.eq_any(cat_ids.clone()))
-                    .select(diesel::dsl::sum(transacao_dsl::eventos))
-                    .first::<Option<i64>>(conn).unwrap_or(Some(0)).unwrap_or(0)
-            };
+            let (ganhos_i64, corridas_i64) = entradas_hoje
+                .iter()
+                .filter(|t| cat_ids.contains(&t.id_categoria))
+                .fold((0i64, 0i64), |(g, c), t| (g + t.valor as i64, c + t.eventos as i64));
+            let ganhos = ganhos_i64 as i32;
             let corridas: u32 = corridas_i64.try_into().unwrap_or(0);
             let (icone, cor) = categorias.first().map(|c| (c.icone.clone(), c.cor.clone())).unwrap_or((None, None));
             let pr = PlatformResult { ganhos, corridas, icone, cor, periodo: "hoje".to_string() };
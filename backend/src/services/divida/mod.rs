@@ -0,0 +1,677 @@
+//! Financiamentos e dívidas com cronograma de parcelas
+//!
+//! Ao cadastrar a dívida o cronograma inteiro é gerado (Price: parcelas iguais; SAC: amortização
+//! constante e juros decrescentes) e fica gravado. Pagar uma parcela lança uma transação de saída
+//! na categoria da dívida; estornar manda essa transação para a lixeira. O saldo devedor é a
+//! amortização das parcelas ainda abertas.
+//!
+//! A quitação antecipada é só simulação: as parcelas a vencer são trazidas a valor presente pela
+//! taxa do contrato (redução proporcional dos juros), as vencidas entram pelo valor cheio.
+
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Months, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::{Divida, NewTransacao, ParcelaDivida, Transacao};
+use crate::schema::dividas::dsl as d_dsl;
+use crate::schema::parcelas_dividas::dsl as p_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
+
+pub const MAX_PARCELAS: i32 = 480;
+/// Parcelas abertas que vencem até este número de dias entram no dashboard
+const DIAS_PROXIMAS_PARCELAS: i64 = 30;
+const MAX_PROXIMAS_PARCELAS: i64 = 10;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Sistema {
+    /// Parcelas iguais
+    Price,
+    /// Amortização constante
+    Sac,
+}
+
+impl Sistema {
+    pub fn nome(self) -> &'static str {
+        match self {
+            Sistema::Price => "price",
+            Sistema::Sac => "sac",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SimularDividaPayload {
+    /// Centavos
+    pub valor_principal: i32,
+    /// Percentual ao mês
+    pub taxa_juros_mensal: f64,
+    pub sistema: Sistema,
+    pub numero_parcelas: i32,
+    pub primeiro_vencimento: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateDividaPayload {
+    pub descricao: String,
+    pub credor: Option<String>,
+    pub id_categoria: Option<String>,
+    #[serde(flatten)]
+    pub condicoes: SimularDividaPayload,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDividaPayload {
+    pub descricao: Option<String>,
+    /// Texto vazio apaga
+    pub credor: Option<String>,
+    pub id_categoria: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct PagarParcelaPayload {
+    /// Padrão: agora
+    pub data: Option<DateTime<Utc>>,
+    /// Centavos efetivamente pagos (com multa ou desconto); padrão: valor da parcela
+    pub valor: Option<i32>,
+    pub id_carteira: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct QuitacaoParams {
+    /// Data da quitação simulada (padrão: agora)
+    pub data: Option<DateTime<Utc>>,
+}
+
+/// Linha do cronograma antes de ser gravada
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ParcelaCalculada {
+    pub numero: i32,
+    pub vencimento: DateTime<Utc>,
+    pub valor: i32,
+    pub amortizacao: i32,
+    pub juros: i32,
+    pub saldo_devedor: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResumoDivida {
+    #[serde(flatten)]
+    pub divida: Divida,
+    pub parcelas_pagas: i64,
+    pub parcelas_restantes: i64,
+    /// Amortização ainda não paga (centavos)
+    pub saldo_devedor: i64,
+    pub total_pago: i64,
+    /// Juros das parcelas abertas, se pagas em dia
+    pub juros_restantes: i64,
+    pub proxima_parcela: Option<ParcelaDivida>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DividaDetalhe {
+    #[serde(flatten)]
+    pub resumo: ResumoDivida,
+    pub parcelas: Vec<ParcelaDivida>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SimulacaoQuitacao {
+    pub data: DateTime<Utc>,
+    pub parcelas_restantes: usize,
+    pub saldo_devedor: i64,
+    /// Soma das parcelas abertas pelo valor cheio
+    pub total_restante: i64,
+    /// Quanto pagar na data para quitar
+    pub valor_quitacao: i64,
+    /// Juros que deixam de ser pagos
+    pub economia: i64,
+}
+
+/// Parcela aberta para o card do dashboard
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ParcelaProxima {
+    pub id_divida: String,
+    pub descricao: String,
+    pub numero: i32,
+    pub numero_parcelas: i32,
+    pub vencimento: DateTime<Utc>,
+    pub valor: i32,
+    pub atrasada: bool,
+}
+
+pub fn validar(condicoes: &SimularDividaPayload) -> Result<(), String> {
+    if condicoes.valor_principal <= 0 {
+        return Err("O valor financiado deve ser positivo".to_string());
+    }
+    if !(1..=MAX_PARCELAS).contains(&condicoes.numero_parcelas) {
+        return Err(format!("O número de parcelas deve ser de 1 a {MAX_PARCELAS}"));
+    }
+    if !condicoes.taxa_juros_mensal.is_finite() || !(0.0..=100.0).contains(&condicoes.taxa_juros_mensal) {
+        return Err("A taxa de juros mensal deve ser de 0 a 100%".to_string());
+    }
+    Ok(())
+}
+
+/// Cronograma completo; a última parcela absorve os arredondamentos e zera o saldo
+pub fn cronograma(condicoes: &SimularDividaPayload) -> Vec<ParcelaCalculada> {
+    let n = condicoes.numero_parcelas.max(1);
+    let i = condicoes.taxa_juros_mensal / 100.0;
+    let principal = condicoes.valor_principal as i64;
+    let prestacao_price = if i > 0.0 {
+        (principal as f64 * i / (1.0 - (1.0 + i).powi(-n))).round() as i64
+    } else {
+        (principal as f64 / n as f64).round() as i64
+    };
+    let amortizacao_sac = principal / n as i64;
+
+    let mut saldo = principal;
+    (1..=n)
+        .map(|numero| {
+            let juros = (saldo as f64 * i).round() as i64;
+            let amortizacao = if numero == n {
+                saldo
+            } else {
+                match condicoes.sistema {
+                    Sistema::Price => (prestacao_price - juros).clamp(0, saldo),
+                    Sistema::Sac => amortizacao_sac.min(saldo),
+                }
+            };
+            saldo -= amortizacao;
+            let vencimento = condicoes
+                .primeiro_vencimento
+                .checked_add_months(Months::new((numero - 1) as u32))
+                .unwrap_or(condicoes.primeiro_vencimento);
+            ParcelaCalculada {
+                numero,
+                vencimento,
+                valor: (amortizacao + juros) as i32,
+                amortizacao: amortizacao as i32,
+                juros: juros as i32,
+                saldo_devedor: saldo as i32,
+            }
+        })
+        .collect()
+}
+
+/// Valor para quitar em `data`: parcelas a vencer descontadas pela taxa (meses de 30 dias),
+/// vencidas pelo valor cheio
+pub fn valor_quitacao(abertas: &[(DateTime<Utc>, i32)], taxa_juros_mensal: f64, data: DateTime<Utc>) -> i64 {
+    let i = taxa_juros_mensal / 100.0;
+    abertas
+        .iter()
+        .map(|(vencimento, valor)| {
+            let dias = (*vencimento - data).num_seconds() as f64 / 86_400.0;
+            if dias <= 0.0 || i == 0.0 {
+                *valor as f64
+            } else {
+                *valor as f64 / (1.0 + i).powf(dias / 30.0)
+            }
+        })
+        .sum::<f64>()
+        .round() as i64
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn nao_encontrada() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Dívida não encontrada".to_string())
+}
+
+/// Falha no pagamento/estorno: erro do banco ou parcela alterada por outra requisição no meio
+enum ErroParcela {
+    Banco(diesel::result::Error),
+    Conflito(&'static str),
+}
+
+impl From<diesel::result::Error> for ErroParcela {
+    fn from(e: diesel::result::Error) -> Self {
+        ErroParcela::Banco(e)
+    }
+}
+
+impl From<ErroParcela> for (StatusCode, String) {
+    fn from(e: ErroParcela) -> Self {
+        match e {
+            ErroParcela::Banco(e) => erro_interno(e),
+            ErroParcela::Conflito(msg) => (StatusCode::CONFLICT, msg.to_string()),
+        }
+    }
+}
+
+fn texto(valor: Option<String>) -> Option<String> {
+    valor.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Categoria ativa do usuário (ou global)
+pub fn categoria_valida(conn: &mut PgConnection, id_usuario: &str, id_categoria: &str) -> QueryResult<bool> {
+    use crate::schema::categorias::dsl as cat_dsl;
+    diesel::select(diesel::dsl::exists(
+        cat_dsl::categorias
+            .filter(cat_dsl::id.eq(id_categoria))
+            .filter(cat_dsl::id_usuario.eq(id_usuario).or(cat_dsl::id_usuario.is_null()))
+            .filter(cat_dsl::excluido_em.is_null()),
+    ))
+    .get_result(conn)
+}
+
+fn buscar(conn: &mut PgConnection, id_usuario: &str, id_divida: &str) -> QueryResult<Option<Divida>> {
+    d_dsl::dividas
+        .filter(d_dsl::id.eq(id_divida))
+        .filter(d_dsl::id_usuario.eq(id_usuario))
+        .first(conn)
+        .optional()
+}
+
+fn parcelas(conn: &mut PgConnection, id_divida: &str) -> QueryResult<Vec<ParcelaDivida>> {
+    p_dsl::parcelas_dividas.filter(p_dsl::id_divida.eq(id_divida)).order(p_dsl::numero.asc()).load(conn)
+}
+
+pub fn resumir(divida: Divida, parcelas: &[ParcelaDivida]) -> ResumoDivida {
+    let (pagas, abertas): (Vec<&ParcelaDivida>, Vec<&ParcelaDivida>) = parcelas.iter().partition(|p| p.paga_em.is_some());
+    ResumoDivida {
+        parcelas_pagas: pagas.len() as i64,
+        parcelas_restantes: abertas.len() as i64,
+        saldo_devedor: abertas.iter().map(|p| p.amortizacao as i64).sum(),
+        total_pago: pagas.iter().map(|p| p.valor_pago.unwrap_or(p.valor) as i64).sum(),
+        juros_restantes: abertas.iter().map(|p| p.juros as i64).sum(),
+        proxima_parcela: abertas.first().map(|p| (*p).clone()),
+        divida,
+    }
+}
+
+/// Parcelas abertas vencidas ou que vencem nos próximos dias, para o dashboard
+pub fn proximas_parcelas(conn: &mut PgConnection, id_usuario: &str) -> Vec<ParcelaProxima> {
+    let agora = Utc::now();
+    let linhas: Vec<(ParcelaDivida, Divida)> = p_dsl::parcelas_dividas
+        .inner_join(d_dsl::dividas)
+        .filter(d_dsl::id_usuario.eq(id_usuario))
+        .filter(p_dsl::paga_em.is_null())
+        .filter(p_dsl::vencimento.le(agora + chrono::Duration::days(DIAS_PROXIMAS_PARCELAS)))
+        .order((p_dsl::vencimento.asc(), p_dsl::numero.asc()))
+        .limit(MAX_PROXIMAS_PARCELAS)
+        .load(conn)
+        .unwrap_or_default();
+    linhas
+        .into_iter()
+        .map(|(parcela, divida)| ParcelaProxima {
+            id_divida: divida.id,
+            descricao: divida.descricao,
+            numero: parcela.numero,
+            numero_parcelas: divida.numero_parcelas,
+            vencimento: parcela.vencimento,
+            valor: parcela.valor,
+            atrasada: parcela.vencimento < agora,
+        })
+        .collect()
+}
+
+pub async fn simular_divida_handler(Json(payload): Json<SimularDividaPayload>) -> Result<Json<Vec<ParcelaCalculada>>, (StatusCode, String)> {
+    validar(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(cronograma(&payload)))
+}
+
+pub async fn list_dividas_handler(jar: CookieJar) -> Result<Json<Vec<ResumoDivida>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let dividas: Vec<Divida> = d_dsl::dividas
+        .filter(d_dsl::id_usuario.eq(&id_usuario))
+        .order((d_dsl::quitada_em.is_not_null(), d_dsl::criado_em.desc()))
+        .load(conn)
+        .map_err(erro_interno)?;
+    let todas: Vec<ParcelaDivida> = ParcelaDivida::belonging_to(&dividas)
+        .order(p_dsl::numero.asc())
+        .load(conn)
+        .map_err(erro_interno)?;
+    let agrupadas = todas.grouped_by(&dividas);
+    Ok(Json(dividas.into_iter().zip(agrupadas).map(|(d, p)| resumir(d, &p)).collect()))
+}
+
+pub async fn get_divida_handler(
+    jar: CookieJar,
+    Path(id_divida): Path<String>,
+) -> Result<Json<DividaDetalhe>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let parcelas = parcelas(conn, &divida.id).map_err(erro_interno)?;
+    Ok(Json(DividaDetalhe { resumo: resumir(divida, &parcelas), parcelas }))
+}
+
+pub async fn create_divida_handler(
+    jar: CookieJar,
+    Json(payload): Json<CreateDividaPayload>,
+) -> Result<(StatusCode, Json<DividaDetalhe>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar(&payload.condicoes).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let descricao = texto(Some(payload.descricao)).ok_or((StatusCode::BAD_REQUEST, "Informe a descrição".to_string()))?;
+    let conn = &mut db::establish_connection();
+    if let Some(categoria) = payload.id_categoria.as_deref() {
+        if !categoria_valida(conn, &id_usuario, categoria).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+        }
+    }
+    let agora = Utc::now();
+    let condicoes = payload.condicoes;
+    let divida = Divida {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        descricao,
+        credor: texto(payload.credor),
+        valor_principal: condicoes.valor_principal,
+        taxa_juros_mensal: condicoes.taxa_juros_mensal,
+        sistema: condicoes.sistema.nome().to_string(),
+        numero_parcelas: condicoes.numero_parcelas,
+        primeiro_vencimento: condicoes.primeiro_vencimento,
+        id_categoria: payload.id_categoria,
+        quitada_em: None,
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    let parcelas: Vec<ParcelaDivida> = cronograma(&condicoes)
+        .into_iter()
+        .map(|p| ParcelaDivida {
+            id: ulid::Ulid::new().to_string(),
+            id_divida: divida.id.clone(),
+            numero: p.numero,
+            vencimento: p.vencimento,
+            valor: p.valor,
+            amortizacao: p.amortizacao,
+            juros: p.juros,
+            saldo_devedor: p.saldo_devedor,
+            paga_em: None,
+            valor_pago: None,
+            id_transacao: None,
+        })
+        .collect();
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(d_dsl::dividas).values(&divida).execute(conn)?;
+        diesel::insert_into(p_dsl::parcelas_dividas).values(&parcelas).execute(conn)?;
+        Ok(())
+    })
+    .map_err(erro_interno)?;
+    // as próximas parcelas do dashboard vêm dos dados fixos do razão
+    eventos::invalidar_e_publicar(&divida.id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok((StatusCode::CREATED, Json(DividaDetalhe { resumo: resumir(divida, &parcelas), parcelas })))
+}
+
+/// Só os dados descritivos; valor, taxa e prazo ficam como no contrato
+pub async fn update_divida_handler(
+    jar: CookieJar,
+    Path(id_divida): Path<String>,
+    Json(payload): Json<UpdateDividaPayload>,
+) -> Result<Json<Divida>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let atual = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    if let Some(categoria) = payload.id_categoria.as_deref() {
+        if !categoria_valida(conn, &id_usuario, categoria).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+        }
+    }
+    let descricao = match payload.descricao {
+        Some(d) => texto(Some(d)).ok_or((StatusCode::BAD_REQUEST, "Informe a descrição".to_string()))?,
+        None => atual.descricao,
+    };
+    let credor = match payload.credor {
+        Some(c) => texto(Some(c)),
+        None => atual.credor,
+    };
+    let divida = diesel::update(d_dsl::dividas.find(&atual.id))
+        .set((
+            d_dsl::descricao.eq(descricao),
+            d_dsl::credor.eq(credor),
+            d_dsl::id_categoria.eq(payload.id_categoria.or(atual.id_categoria)),
+            d_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Divida>(conn)
+        .map_err(erro_interno)?;
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok(Json(divida))
+}
+
+/// Apaga a dívida e o cronograma; as transações dos pagamentos continuam
+pub async fn delete_divida_handler(
+    jar: CookieJar,
+    Path(id_divida): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let apagadas = diesel::delete(d_dsl::dividas.filter(d_dsl::id.eq(&id_divida)).filter(d_dsl::id_usuario.eq(&id_usuario)))
+        .execute(conn)
+        .map_err(erro_interno)?;
+    if apagadas == 0 {
+        return Err(nao_encontrada());
+    }
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn pagar_parcela_handler(
+    jar: CookieJar,
+    Path((id_divida, numero)): Path<(String, i32)>,
+    payload: Option<Json<PagarParcelaPayload>>,
+) -> Result<Json<DividaDetalhe>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let conn = &mut db::establish_connection();
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let parcela: ParcelaDivida = p_dsl::parcelas_dividas
+        .filter(p_dsl::id_divida.eq(&divida.id))
+        .filter(p_dsl::numero.eq(numero))
+        .first(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Parcela não encontrada".to_string()))?;
+    if parcela.paga_em.is_some() {
+        return Err((StatusCode::CONFLICT, "Parcela já paga".to_string()));
+    }
+    let Some(id_categoria) = divida.id_categoria.clone() else {
+        return Err((StatusCode::BAD_REQUEST, "Defina a categoria de despesa da dívida antes de pagar".to_string()));
+    };
+    let valor = payload.valor.unwrap_or(parcela.valor);
+    if valor <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "O valor pago deve ser positivo".to_string()));
+    }
+    if let Some(carteira) = payload.id_carteira.as_deref() {
+        if !crate::services::carteira::carteira_valida(conn, &id_usuario, carteira).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+        }
+    }
+    let data = payload.data.unwrap_or_else(Utc::now);
+    let nova = NewTransacao {
+        eventos: 0,
+        km: None,
+        descricao: Some(format!("{} — parcela {}/{}", divida.descricao, parcela.numero, divida.numero_parcelas)),
+        data,
+        id_carteira: payload.id_carteira,
+        ..NewTransacao::new(id_usuario.clone(), id_categoria, valor, "saida".to_string())
+    };
+
+    let transacao = conn
+        .transaction::<Transacao, ErroParcela, _>(|conn| {
+            use crate::schema::transacoes::dsl as t_dsl;
+            let transacao: Transacao = diesel::insert_into(t_dsl::transacoes).values(&nova).get_result(conn)?;
+            // só marca se ainda estiver aberta: num pagamento concorrente, o segundo desfaz a saída
+            let marcadas = diesel::update(p_dsl::parcelas_dividas.find(&parcela.id).filter(p_dsl::paga_em.is_null()))
+                .set((
+                    p_dsl::paga_em.eq(Some(data)),
+                    p_dsl::valor_pago.eq(Some(valor)),
+                    p_dsl::id_transacao.eq(Some(&transacao.id)),
+                ))
+                .execute(conn)?;
+            if marcadas == 0 {
+                return Err(ErroParcela::Conflito("Parcela já paga"));
+            }
+            let abertas: i64 = p_dsl::parcelas_dividas
+                .filter(p_dsl::id_divida.eq(&divida.id))
+                .filter(p_dsl::paga_em.is_null())
+                .count()
+                .get_result(conn)?;
+            if abertas == 0 {
+                diesel::update(d_dsl::dividas.find(&divida.id))
+                    .set((d_dsl::quitada_em.eq(Some(data)), d_dsl::atualizado_em.eq(Utc::now())))
+                    .execute(conn)?;
+            }
+            let origem = Origem::usuario(&id_usuario, "POST /api/dividas/{id}/parcelas/{numero}/pagar");
+            historico::registrar(conn, &origem, Acao::Criacao, None, Some(&transacao))?;
+            crate::services::envelope::aplicar_regras(conn, None, Some(&transacao))?;
+            Ok(transacao)
+        })?;
+
+    // além da saída, mudam as próximas parcelas dos dados fixos: recarrega em vez do delta
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::TransacaoCriada { transacao, dashboard: None }).await;
+    crate::services::anomalia::agendar_deteccao(id_usuario.clone());
+
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let parcelas = parcelas(conn, &divida.id).map_err(erro_interno)?;
+    Ok(Json(DividaDetalhe { resumo: resumir(divida, &parcelas), parcelas }))
+}
+
+/// Desfaz o pagamento: a parcela volta a ficar aberta e a transação vai para a lixeira
+pub async fn estornar_parcela_handler(
+    jar: CookieJar,
+    Path((id_divida, numero)): Path<(String, i32)>,
+) -> Result<Json<DividaDetalhe>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let parcela: ParcelaDivida = p_dsl::parcelas_dividas
+        .filter(p_dsl::id_divida.eq(&divida.id))
+        .filter(p_dsl::numero.eq(numero))
+        .filter(p_dsl::paga_em.is_not_null())
+        .first(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Parcela paga não encontrada".to_string()))?;
+
+    let excluida = conn
+        .transaction::<Option<Transacao>, ErroParcela, _>(|conn| {
+            use crate::schema::transacoes::dsl as t_dsl;
+            let reabertas = diesel::update(p_dsl::parcelas_dividas.find(&parcela.id).filter(p_dsl::paga_em.is_not_null()))
+                .set((
+                    p_dsl::paga_em.eq(None::<DateTime<Utc>>),
+                    p_dsl::valor_pago.eq(None::<i32>),
+                    p_dsl::id_transacao.eq(None::<String>),
+                ))
+                .execute(conn)?;
+            if reabertas == 0 {
+                return Err(ErroParcela::Conflito("Parcela já estornada"));
+            }
+            diesel::update(d_dsl::dividas.find(&divida.id))
+                .set((d_dsl::quitada_em.eq(None::<DateTime<Utc>>), d_dsl::atualizado_em.eq(Utc::now())))
+                .execute(conn)?;
+            let Some(id_transacao) = parcela.id_transacao.as_deref() else {
+                return Ok(None);
+            };
+            let alvo = t_dsl::transacoes.filter(t_dsl::id.eq(id_transacao)).filter(t_dsl::excluido_em.is_null());
+            let Some(transacao) = alvo.first::<Transacao>(conn).optional()? else {
+                return Ok(None);
+            };
+            let agora = Utc::now();
+            diesel::update(alvo).set(t_dsl::excluido_em.eq(Some(agora))).execute(conn)?;
+            let na_lixeira = Transacao { excluido_em: Some(agora), ..transacao.clone() };
+            let origem = Origem::usuario(&id_usuario, "DELETE /api/dividas/{id}/parcelas/{numero}/pagar");
+            historico::registrar(conn, &origem, Acao::Exclusao, Some(&transacao), Some(&na_lixeira))?;
+            crate::services::envelope::aplicar_regras(conn, Some(&transacao), None)?;
+            Ok(Some(transacao))
+        })?;
+
+    // a parcela reaberta volta para as próximas parcelas dos dados fixos: recarrega em vez do delta
+    match excluida {
+        Some(transacao) => {
+            eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::TransacaoExcluida { transacao, dashboard: None }).await;
+            crate::services::anomalia::agendar_deteccao(id_usuario.clone());
+        }
+        None => eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await,
+    }
+
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let parcelas = parcelas(conn, &divida.id).map_err(erro_interno)?;
+    Ok(Json(DividaDetalhe { resumo: resumir(divida, &parcelas), parcelas }))
+}
+
+pub async fn simular_quitacao_handler(
+    jar: CookieJar,
+    Path(id_divida): Path<String>,
+    Query(params): Query<QuitacaoParams>,
+) -> Result<Json<SimulacaoQuitacao>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let divida = buscar(conn, &id_usuario, &id_divida).map_err(erro_interno)?.ok_or_else(nao_encontrada)?;
+    let abertas: Vec<ParcelaDivida> = parcelas(conn, &divida.id)
+        .map_err(erro_interno)?
+        .into_iter()
+        .filter(|p| p.paga_em.is_none())
+        .collect();
+    let data = params.data.unwrap_or_else(Utc::now);
+    let total_restante: i64 = abertas.iter().map(|p| p.valor as i64).sum();
+    let fluxo: Vec<(DateTime<Utc>, i32)> = abertas.iter().map(|p| (p.vencimento, p.valor)).collect();
+    let valor = valor_quitacao(&fluxo, divida.taxa_juros_mensal, data);
+    Ok(Json(SimulacaoQuitacao {
+        data,
+        parcelas_restantes: abertas.len(),
+        saldo_devedor: abertas.iter().map(|p| p.amortizacao as i64).sum(),
+        total_restante,
+        valor_quitacao: valor,
+        economia: total_restante - valor,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condicoes(sistema: Sistema, taxa: f64) -> SimularDividaPayload {
+        SimularDividaPayload {
+            valor_principal: 1_000_000,
+            taxa_juros_mensal: taxa,
+            sistema,
+            numero_parcelas: 12,
+            primeiro_vencimento: DateTime::parse_from_rfc3339("2025-01-31T12:00:00Z").unwrap().with_timezone(&Utc),
+        }
+    }
+
+    #[test]
+    fn test_cronograma_price_e_sac() {
+        let price = cronograma(&condicoes(Sistema::Price, 2.0));
+        assert_eq!(price.len(), 12);
+        // PMT de R$ 10.000 a 2% a.m. em 12x = R$ 945,60
+        assert_eq!(price[0].valor, 94_560);
+        assert_eq!(price[0].juros, 20_000);
+        assert!(price[..11].iter().all(|p| p.valor == 94_560));
+        assert_eq!(price.iter().map(|p| p.amortizacao as i64).sum::<i64>(), 1_000_000);
+        assert_eq!(price[11].saldo_devedor, 0);
+        // fevereiro não tem dia 31
+        assert_eq!(price[1].vencimento.to_rfc3339(), "2025-02-28T12:00:00+00:00");
+
+        let sac = cronograma(&condicoes(Sistema::Sac, 2.0));
+        assert!(sac.iter().all(|p| (83_333..=83_337).contains(&p.amortizacao)));
+        assert!(sac.windows(2).all(|w| w[1].valor < w[0].valor));
+        assert_eq!(sac.iter().map(|p| p.amortizacao as i64).sum::<i64>(), 1_000_000);
+    }
+
+    #[test]
+    fn test_valor_quitacao() {
+        let parcelas: Vec<(DateTime<Utc>, i32)> =
+            cronograma(&condicoes(Sistema::Price, 2.0)).iter().map(|p| (p.vencimento, p.valor)).collect();
+        let inicio = DateTime::parse_from_rfc3339("2024-12-31T12:00:00Z").unwrap().with_timezone(&Utc);
+        // um mês antes da primeira parcela, o valor presente volta perto do principal
+        let valor = valor_quitacao(&parcelas, 2.0, inicio);
+        assert!((valor - 1_000_000).abs() < 2_000, "{valor}");
+        // sem juros não há desconto; vencidas entram cheias
+        assert_eq!(valor_quitacao(&parcelas, 0.0, inicio), parcelas.iter().map(|p| p.1 as i64).sum::<i64>());
+        let depois = DateTime::parse_from_rfc3339("2026-06-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(valor_quitacao(&parcelas, 2.0, depois), parcelas.iter().map(|p| p.1 as i64).sum::<i64>());
+    }
+}
@@ -0,0 +1,729 @@
+//! Envelopes: dinheiro separado para manutenção, impostos, reserva de emergência
+//!
+//! O saldo do envelope é a soma dos movimentos. A alocação automática segue a regra do envelope:
+//! `entrada` separa um percentual de cada entrada criada; `lucro_diario` separa um percentual do
+//! lucro (entradas menos saídas) de cada dia UTC e é recalculada sempre que uma transação daquele
+//! dia é criada, editada ou excluída. Movimentos ligados a uma transação que está na lixeira não
+//! contam no saldo.
+//!
+//! A retirada lança a despesa como transação de saída; essa despesa sai do dinheiro já separado e
+//! por isso não entra no lucro diário.
+
+use std::collections::{BTreeSet, HashMap};
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::db;
+use crate::models::{Envelope, MovimentoEnvelope, NewTransacao, Transacao};
+use crate::schema::envelopes::dsl as e_dsl;
+use crate::schema::movimentos_envelopes::dsl as m_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
+
+pub const MAX_TAMANHO_NOME: usize = 40;
+const MAX_MOVIMENTOS: i64 = 1000;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Regra {
+    /// Só movimentos manuais
+    Manual,
+    /// Percentual de cada entrada
+    Entrada,
+    /// Percentual do lucro de cada dia
+    LucroDiario,
+}
+
+impl Regra {
+    pub fn nome(self) -> &'static str {
+        match self {
+            Regra::Manual => "manual",
+            Regra::Entrada => "entrada",
+            Regra::LucroDiario => "lucro_diario",
+        }
+    }
+
+    pub fn de_nome(nome: &str) -> Option<Self> {
+        [Regra::Manual, Regra::Entrada, Regra::LucroDiario].into_iter().find(|r| r.nome() == nome)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateEnvelopePayload {
+    pub nome: String,
+    /// Centavos
+    pub valor_alvo: Option<i32>,
+    pub regra: Option<Regra>,
+    pub percentual: Option<f64>,
+    pub id_categoria: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateEnvelopePayload {
+    pub nome: Option<String>,
+    /// 0 remove o alvo
+    pub valor_alvo: Option<i32>,
+    pub regra: Option<Regra>,
+    pub percentual: Option<f64>,
+    /// Texto vazio remove a categoria
+    pub id_categoria: Option<String>,
+    pub arquivado: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListarEnvelopesParams {
+    #[serde(default)]
+    pub incluir_arquivados: bool,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListarMovimentosParams {
+    pub inicio: Option<DateTime<Utc>>,
+    pub fim: Option<DateTime<Utc>>,
+}
+
+/// Sem origem é depósito, sem destino é resgate (volta ao dinheiro livre), com os dois é transferência
+#[derive(Deserialize)]
+pub struct MoverPayload {
+    pub id_origem: Option<String>,
+    pub id_destino: Option<String>,
+    /// Centavos
+    pub valor: i32,
+    pub descricao: Option<String>,
+    pub data: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct RetiradaPayload {
+    /// Centavos
+    pub valor: i32,
+    pub descricao: Option<String>,
+    /// Padrão: a categoria do envelope
+    pub id_categoria: Option<String>,
+    pub id_carteira: Option<String>,
+    pub data: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EnvelopeComSaldo {
+    #[serde(flatten)]
+    pub envelope: Envelope,
+    pub saldo: i64,
+    /// Quanto falta para o alvo (0 quando atingido)
+    pub falta: Option<i64>,
+    /// Percentual do alvo atingido
+    pub progresso: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Retirada {
+    pub movimento: MovimentoEnvelope,
+    pub transacao: Transacao,
+}
+
+/// Saldo de envelope para o card do dashboard
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SaldoEnvelope {
+    pub id: String,
+    pub nome: String,
+    pub saldo: i64,
+    pub valor_alvo: Option<i32>,
+}
+
+#[derive(QueryableByName)]
+struct LinhaSaldo {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = BigInt)]
+    saldo: i64,
+}
+
+#[derive(QueryableByName)]
+struct LinhaLucro {
+    #[diesel(sql_type = BigInt)]
+    lucro: i64,
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn nao_encontrado() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Envelope não encontrado".to_string())
+}
+
+fn nome_duplicado(e: diesel::result::Error, nome: &str) -> (StatusCode, String) {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) =>
+            (StatusCode::CONFLICT, format!("Já existe o envelope {nome}")),
+        e => erro_interno(e),
+    }
+}
+
+/// Percentual aplicado pela regra; envelopes manuais ficam com 0
+pub fn validar_regra(regra: Regra, percentual: Option<f64>) -> Result<f64, String> {
+    match (regra, percentual) {
+        (Regra::Manual, _) => Ok(0.0),
+        (_, Some(p)) if p.is_finite() && p > 0.0 && p <= 100.0 => Ok(p),
+        _ => Err("Informe um percentual entre 0 e 100 para a alocação automática".to_string()),
+    }
+}
+
+/// Valor separado de `base` (nada quando a base não é positiva)
+pub fn parcela(base: i64, percentual: f64) -> i64 {
+    if base <= 0 {
+        return 0;
+    }
+    (base as f64 * percentual / 100.0).round() as i64
+}
+
+fn inicio_do_dia(dia: NaiveDate) -> DateTime<Utc> {
+    dia.and_time(NaiveTime::MIN).and_utc()
+}
+
+fn buscar(conn: &mut PgConnection, id_usuario: &str, id_envelope: &str) -> QueryResult<Option<Envelope>> {
+    e_dsl::envelopes
+        .filter(e_dsl::id.eq(id_envelope))
+        .filter(e_dsl::id_usuario.eq(id_usuario))
+        .first(conn)
+        .optional()
+}
+
+/// Soma dos percentuais dos outros envelopes ativos com a mesma regra (não pode passar de 100%)
+fn percentual_comprometido(conn: &mut PgConnection, id_usuario: &str, regra: Regra, exceto: Option<&str>) -> QueryResult<f64> {
+    let outros: Vec<(String, f64)> = e_dsl::envelopes
+        .filter(e_dsl::id_usuario.eq(id_usuario))
+        .filter(e_dsl::regra.eq(regra.nome()))
+        .filter(e_dsl::arquivado.eq(false))
+        .select((e_dsl::id, e_dsl::percentual))
+        .load(conn)?;
+    Ok(outros.into_iter().filter(|(id, _)| Some(id.as_str()) != exceto).map(|(_, p)| p).sum())
+}
+
+pub fn saldos(conn: &mut PgConnection, id_usuario: &str) -> QueryResult<HashMap<String, i64>> {
+    let linhas: Vec<LinhaSaldo> = diesel::sql_query(
+        "SELECT m.id_envelope AS id, SUM(m.valor)::BIGINT AS saldo
+         FROM movimentos_envelopes m
+         LEFT JOIN transacoes t ON t.id = m.id_transacao
+         WHERE m.id_usuario = $1 AND t.excluido_em IS NULL
+         GROUP BY m.id_envelope",
+    )
+    .bind::<Text, _>(id_usuario)
+    .load(conn)?;
+    Ok(linhas.into_iter().map(|l| (l.id, l.saldo)).collect())
+}
+
+fn saldo(conn: &mut PgConnection, envelope: &Envelope) -> QueryResult<i64> {
+    Ok(saldos(conn, &envelope.id_usuario)?.remove(&envelope.id).unwrap_or(0))
+}
+
+fn com_saldo(envelope: Envelope, saldo: i64) -> EnvelopeComSaldo {
+    let alvo = envelope.valor_alvo.map(|a| a as i64);
+    EnvelopeComSaldo {
+        falta: alvo.map(|a| (a - saldo).max(0)),
+        progresso: alvo.map(|a| (saldo as f64 * 10_000.0 / a as f64).round() / 100.0),
+        envelope,
+        saldo,
+    }
+}
+
+/// Envelopes ativos com saldo, para o dashboard
+pub fn resumo(conn: &mut PgConnection, id_usuario: &str) -> Vec<SaldoEnvelope> {
+    let envelopes: Vec<Envelope> = e_dsl::envelopes
+        .filter(e_dsl::id_usuario.eq(id_usuario))
+        .filter(e_dsl::arquivado.eq(false))
+        .order(e_dsl::nome.asc())
+        .load(conn)
+        .unwrap_or_default();
+    if envelopes.is_empty() {
+        return Vec::new();
+    }
+    let mut saldos = saldos(conn, id_usuario).unwrap_or_default();
+    envelopes
+        .into_iter()
+        .map(|e| SaldoEnvelope { saldo: saldos.remove(&e.id).unwrap_or(0), id: e.id, nome: e.nome, valor_alvo: e.valor_alvo })
+        .collect()
+}
+
+/// Lucro do dia sem as despesas pagas com dinheiro de envelope
+fn lucro_do_dia(conn: &mut PgConnection, id_usuario: &str, dia: NaiveDate) -> QueryResult<i64> {
+    let inicio = inicio_do_dia(dia);
+    let linha: LinhaLucro = diesel::sql_query(
+        "SELECT COALESCE(SUM(CASE WHEN t.tipo = 'entrada' THEN t.valor ELSE -t.valor END), 0)::BIGINT AS lucro
+         FROM transacoes t
+         WHERE t.id_usuario = $1 AND t.excluido_em IS NULL AND t.data >= $2 AND t.data < $3
+           AND NOT EXISTS (SELECT 1 FROM movimentos_envelopes m WHERE m.id_transacao = t.id AND m.tipo = 'retirada')",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Timestamptz, _>(inicio)
+    .bind::<Timestamptz, _>(inicio + Duration::days(1))
+    .get_result(conn)?;
+    Ok(linha.lucro)
+}
+
+/// Refaz a alocação do lucro de `dia` nos envelopes `lucro_diario` criados até aquele dia; devolve
+/// quantos movimentos foram gravados ou apagados
+fn recalcular_lucro(conn: &mut PgConnection, id_usuario: &str, dia: NaiveDate) -> QueryResult<usize> {
+    let envelopes: Vec<Envelope> = e_dsl::envelopes
+        .filter(e_dsl::id_usuario.eq(id_usuario))
+        .filter(e_dsl::regra.eq(Regra::LucroDiario.nome()))
+        .filter(e_dsl::arquivado.eq(false))
+        .load(conn)?;
+    let envelopes: Vec<Envelope> = envelopes.into_iter().filter(|e| e.criado_em.date_naive() <= dia).collect();
+    if envelopes.is_empty() {
+        return Ok(0);
+    }
+    let lucro = lucro_do_dia(conn, id_usuario, dia)?;
+    let mut alterados = 0;
+    for envelope in envelopes {
+        let valor = parcela(lucro, envelope.percentual) as i32;
+        let do_dia = m_dsl::movimentos_envelopes.filter(m_dsl::id_envelope.eq(&envelope.id)).filter(m_dsl::dia.eq(dia));
+        if valor == 0 {
+            alterados += diesel::delete(do_dia).execute(conn)?;
+            continue;
+        }
+        let movimento = MovimentoEnvelope {
+            id: ulid::Ulid::new().to_string(),
+            id_usuario: id_usuario.to_string(),
+            id_envelope: envelope.id.clone(),
+            tipo: "alocacao".to_string(),
+            valor,
+            descricao: Some(format!("{}% do lucro de {}", envelope.percentual, dia.format("%d/%m/%Y"))),
+            data: inicio_do_dia(dia),
+            id_transacao: None,
+            dia: Some(dia),
+            criado_em: Utc::now(),
+        };
+        alterados += diesel::insert_into(m_dsl::movimentos_envelopes)
+            .values(&movimento)
+            .on_conflict((m_dsl::id_envelope, m_dsl::dia))
+            .do_update()
+            .set((m_dsl::valor.eq(valor), m_dsl::descricao.eq(&movimento.descricao)))
+            .execute(conn)?;
+    }
+    Ok(alterados)
+}
+
+/// Movimentos da própria transação: alocações da entrada nova e ajuste das já feitas na edição;
+/// devolve quantos movimentos mudaram, inclusive os que passam a contar ou deixam de contar no
+/// saldo porque a transação foi para a lixeira ou voltou dela
+fn ajustar_movimentos(conn: &mut PgConnection, antes: Option<&Transacao>, depois: Option<&Transacao>) -> QueryResult<usize> {
+    let ativa = |t: Option<&Transacao>| t.is_some_and(|t| t.excluido_em.is_none());
+    let mut alterados = 0;
+    if let (Some(t), true) = (antes.or(depois), antes.is_some() && ativa(antes) != ativa(depois)) {
+        let vinculados: i64 = m_dsl::movimentos_envelopes.filter(m_dsl::id_transacao.eq(&t.id)).count().get_result(conn)?;
+        alterados += vinculados as usize;
+    }
+    if let Some(t) = depois.filter(|t| t.excluido_em.is_none()) {
+        let id_usuario = t.id_usuario.clone();
+        if antes.is_none() && t.tipo == "entrada" {
+            let envelopes: Vec<Envelope> = e_dsl::envelopes
+                .filter(e_dsl::id_usuario.eq(&id_usuario))
+                .filter(e_dsl::regra.eq(Regra::Entrada.nome()))
+                .filter(e_dsl::arquivado.eq(false))
+                .load(conn)?;
+            let movimentos: Vec<MovimentoEnvelope> = envelopes
+                .iter()
+                .map(|e| (e, parcela(t.valor as i64, e.percentual) as i32))
+                .filter(|(_, valor)| *valor > 0)
+                .map(|(e, valor)| MovimentoEnvelope {
+                    id: ulid::Ulid::new().to_string(),
+                    id_usuario: id_usuario.clone(),
+                    id_envelope: e.id.clone(),
+                    tipo: "alocacao".to_string(),
+                    valor,
+                    descricao: Some(format!("{}% da entrada", e.percentual)),
+                    data: t.data,
+                    id_transacao: Some(t.id.clone()),
+                    dia: None,
+                    criado_em: Utc::now(),
+                })
+                .collect();
+            alterados += diesel::insert_into(m_dsl::movimentos_envelopes).values(&movimentos).execute(conn)?;
+        } else if antes.is_some() {
+            // edição: as alocações já feitas acompanham o novo valor; a retirada acompanha a despesa
+            let da_transacao = m_dsl::movimentos_envelopes.filter(m_dsl::id_transacao.eq(&t.id));
+            if t.tipo == "entrada" {
+                let alocacoes: Vec<(String, f64)> = da_transacao
+                    .filter(m_dsl::tipo.eq("alocacao"))
+                    .inner_join(e_dsl::envelopes)
+                    .select((m_dsl::id, e_dsl::percentual))
+                    .load(conn)?;
+                for (id_movimento, percentual) in alocacoes {
+                    alterados += diesel::update(m_dsl::movimentos_envelopes.find(id_movimento))
+                        .set((m_dsl::valor.eq(parcela(t.valor as i64, percentual) as i32), m_dsl::data.eq(t.data)))
+                        .execute(conn)?;
+                }
+            } else {
+                alterados += diesel::delete(da_transacao.filter(m_dsl::tipo.eq("alocacao"))).execute(conn)?;
+            }
+            alterados += diesel::update(da_transacao.filter(m_dsl::tipo.eq("retirada")))
+                .set((m_dsl::valor.eq(-t.valor), m_dsl::data.eq(t.data)))
+                .execute(conn)?;
+        }
+    }
+    Ok(alterados)
+}
+
+/// Refaz o lucro alocado de cada dia tocado pelas transações
+fn recalcular_dias<'a>(conn: &mut PgConnection, transacoes: impl IntoIterator<Item = &'a Transacao>) -> QueryResult<usize> {
+    let dias: BTreeSet<(&str, NaiveDate)> = transacoes.into_iter().map(|t| (t.id_usuario.as_str(), t.data.date_naive())).collect();
+    let mut alterados = 0;
+    for (id_usuario, dia) in dias {
+        alterados += recalcular_lucro(conn, id_usuario, dia)?;
+    }
+    Ok(alterados)
+}
+
+/// Como `sincronizar`, devolvendo o erro: para rodar dentro da transação do banco que grava a mudança
+pub fn aplicar_regras(conn: &mut PgConnection, antes: Option<&Transacao>, depois: Option<&Transacao>) -> QueryResult<bool> {
+    let alterados = ajustar_movimentos(conn, antes, depois)?;
+    Ok(alterados + recalcular_dias(conn, antes.into_iter().chain(depois))? > 0)
+}
+
+/// `aplicar_regras` para várias transações de uma vez (lote, lixeira); `antes` e `depois` são
+/// pareados pelo id e o lucro de cada dia é refeito uma vez só
+pub fn aplicar_regras_lote(conn: &mut PgConnection, antes: &[Transacao], depois: &[Transacao]) -> QueryResult<bool> {
+    let anteriores: HashMap<&str, &Transacao> = antes.iter().map(|t| (t.id.as_str(), t)).collect();
+    let mut alterados = 0;
+    for t in depois {
+        alterados += ajustar_movimentos(conn, anteriores.get(t.id.as_str()).copied(), Some(t))?;
+    }
+    Ok(alterados + recalcular_dias(conn, antes.iter().chain(depois))? > 0)
+}
+
+/// Aplica as regras de alocação à criação (`antes` vazio), edição ou exclusão (`depois` vazio)
+/// de uma transação; falha só gera aviso, a transação já foi gravada. Devolve se algum saldo de
+/// envelope pode ter mudado, caso em que o cache do dashboard precisa ser recarregado
+pub fn sincronizar(conn: &mut PgConnection, antes: Option<&Transacao>, depois: Option<&Transacao>) -> bool {
+    aplicar_regras(conn, antes, depois).unwrap_or_else(|e| {
+        warn!("Falha ao alocar nos envelopes: {}", e);
+        true
+    })
+}
+
+pub async fn list_envelopes_handler(
+    jar: CookieJar,
+    Query(params): Query<ListarEnvelopesParams>,
+) -> Result<Json<Vec<EnvelopeComSaldo>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let mut query = e_dsl::envelopes.filter(e_dsl::id_usuario.eq(&id_usuario)).into_boxed();
+    if !params.incluir_arquivados {
+        query = query.filter(e_dsl::arquivado.eq(false));
+    }
+    let envelopes: Vec<Envelope> = query.order(e_dsl::nome.asc()).load(conn).map_err(erro_interno)?;
+    let mut saldos = saldos(conn, &id_usuario).map_err(erro_interno)?;
+    Ok(Json(
+        envelopes
+            .into_iter()
+            .map(|e| {
+                let saldo = saldos.remove(&e.id).unwrap_or(0);
+                com_saldo(e, saldo)
+            })
+            .collect(),
+    ))
+}
+
+pub async fn create_envelope_handler(
+    jar: CookieJar,
+    Json(payload): Json<CreateEnvelopePayload>,
+) -> Result<(StatusCode, Json<EnvelopeComSaldo>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let nome = crate::services::carteira::normalizar_nome(&payload.nome)
+        .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?;
+    let regra = payload.regra.unwrap_or(Regra::Manual);
+    let percentual = validar_regra(regra, payload.percentual).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if payload.valor_alvo.is_some_and(|v| v <= 0) {
+        return Err((StatusCode::BAD_REQUEST, "O valor alvo deve ser positivo".to_string()));
+    }
+    let conn = &mut db::establish_connection();
+    if let Some(categoria) = payload.id_categoria.as_deref() {
+        if !crate::services::divida::categoria_valida(conn, &id_usuario, categoria).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+        }
+    }
+    if regra != Regra::Manual {
+        let comprometido = percentual_comprometido(conn, &id_usuario, regra, None).map_err(erro_interno)?;
+        if comprometido + percentual > 100.0 {
+            return Err((StatusCode::BAD_REQUEST, format!("Os envelopes com a regra {} já separam {comprometido}%", regra.nome())));
+        }
+    }
+    let agora = Utc::now();
+    let novo = Envelope {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        nome,
+        valor_alvo: payload.valor_alvo,
+        regra: regra.nome().to_string(),
+        percentual,
+        id_categoria: payload.id_categoria,
+        arquivado: false,
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    let envelope = diesel::insert_into(e_dsl::envelopes)
+        .values(&novo)
+        .get_result::<Envelope>(conn)
+        .map_err(|e| nome_duplicado(e, &novo.nome))?;
+    eventos::invalidar_e_publicar(&envelope.id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok((StatusCode::CREATED, Json(com_saldo(envelope, 0))))
+}
+
+pub async fn update_envelope_handler(
+    jar: CookieJar,
+    Path(id_envelope): Path<String>,
+    Json(payload): Json<UpdateEnvelopePayload>,
+) -> Result<Json<EnvelopeComSaldo>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let atual = buscar(conn, &id_usuario, &id_envelope).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    let nome = match payload.nome.as_deref() {
+        Some(nome) => crate::services::carteira::normalizar_nome(nome)
+            .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?,
+        None => atual.nome.clone(),
+    };
+    let regra = payload.regra.or(Regra::de_nome(&atual.regra)).unwrap_or(Regra::Manual);
+    let percentual = validar_regra(regra, payload.percentual.or(Some(atual.percentual)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let valor_alvo = match payload.valor_alvo {
+        Some(v) if v < 0 => return Err((StatusCode::BAD_REQUEST, "O valor alvo deve ser positivo".to_string())),
+        Some(0) => None,
+        Some(v) => Some(v),
+        None => atual.valor_alvo,
+    };
+    let id_categoria = match payload.id_categoria {
+        Some(c) if c.is_empty() => None,
+        Some(c) => {
+            if !crate::services::divida::categoria_valida(conn, &id_usuario, &c).map_err(erro_interno)? {
+                return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+            }
+            Some(c)
+        }
+        None => atual.id_categoria.clone(),
+    };
+    let arquivado = payload.arquivado.unwrap_or(atual.arquivado);
+    if regra != Regra::Manual && !arquivado {
+        let comprometido = percentual_comprometido(conn, &id_usuario, regra, Some(&atual.id)).map_err(erro_interno)?;
+        if comprometido + percentual > 100.0 {
+            return Err((StatusCode::BAD_REQUEST, format!("Os envelopes com a regra {} já separam {comprometido}%", regra.nome())));
+        }
+    }
+    let envelope = diesel::update(e_dsl::envelopes.find(&atual.id))
+        .set((
+            e_dsl::nome.eq(&nome),
+            e_dsl::valor_alvo.eq(valor_alvo),
+            e_dsl::regra.eq(regra.nome()),
+            e_dsl::percentual.eq(percentual),
+            e_dsl::id_categoria.eq(id_categoria),
+            e_dsl::arquivado.eq(arquivado),
+            e_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Envelope>(conn)
+        .map_err(|e| nome_duplicado(e, &nome))?;
+    let saldo = saldo(conn, &envelope).map_err(erro_interno)?;
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok(Json(com_saldo(envelope, saldo)))
+}
+
+/// Só apaga envelope vazio; o que sobrou deve ser resgatado ou transferido antes
+pub async fn delete_envelope_handler(
+    jar: CookieJar,
+    Path(id_envelope): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let envelope = buscar(conn, &id_usuario, &id_envelope).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    if saldo(conn, &envelope).map_err(erro_interno)? != 0 {
+        return Err((StatusCode::CONFLICT, "O envelope ainda tem saldo; resgate ou transfira antes de excluir".to_string()));
+    }
+    diesel::delete(e_dsl::envelopes.find(&envelope.id)).execute(conn).map_err(erro_interno)?;
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Movimentos que contam no saldo, do mais recente para o mais antigo
+pub async fn list_movimentos_handler(
+    jar: CookieJar,
+    Path(id_envelope): Path<String>,
+    Query(params): Query<ListarMovimentosParams>,
+) -> Result<Json<Vec<MovimentoEnvelope>>, (StatusCode, String)> {
+    use crate::schema::transacoes::dsl as t_dsl;
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let envelope = buscar(conn, &id_usuario, &id_envelope).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    let mut query = m_dsl::movimentos_envelopes
+        .left_join(t_dsl::transacoes)
+        .filter(m_dsl::id_envelope.eq(&envelope.id))
+        .filter(t_dsl::excluido_em.is_null())
+        .select(MovimentoEnvelope::as_select())
+        .into_boxed();
+    if let Some(inicio) = params.inicio {
+        query = query.filter(m_dsl::data.ge(inicio));
+    }
+    if let Some(fim) = params.fim {
+        query = query.filter(m_dsl::data.le(fim));
+    }
+    query
+        .order((m_dsl::data.desc(), m_dsl::id.desc()))
+        .limit(MAX_MOVIMENTOS)
+        .load(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+pub async fn mover_handler(
+    jar: CookieJar,
+    Json(payload): Json<MoverPayload>,
+) -> Result<(StatusCode, Json<Vec<MovimentoEnvelope>>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    if payload.valor <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "O valor deve ser positivo".to_string()));
+    }
+    if payload.id_origem.is_none() && payload.id_destino.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Informe o envelope de origem, o de destino ou ambos".to_string()));
+    }
+    if payload.id_origem.is_some() && payload.id_origem == payload.id_destino {
+        return Err((StatusCode::BAD_REQUEST, "Origem e destino devem ser diferentes".to_string()));
+    }
+    let conn = &mut db::establish_connection();
+    let mut envelopes = Vec::new();
+    for id in [&payload.id_origem, &payload.id_destino] {
+        envelopes.push(match id {
+            Some(id) => Some(buscar(conn, &id_usuario, id).map_err(erro_interno)?.ok_or_else(nao_encontrado)?),
+            None => None,
+        });
+    }
+    let (origem, destino) = (envelopes[0].take(), envelopes[1].take());
+    if let Some(origem) = &origem {
+        if saldo(conn, origem).map_err(erro_interno)? < payload.valor as i64 {
+            return Err((StatusCode::BAD_REQUEST, format!("Saldo insuficiente no envelope {}", origem.nome)));
+        }
+    }
+    let tipo = match (&origem, &destino) {
+        (Some(_), Some(_)) => "transferencia",
+        (Some(_), None) => "resgate",
+        _ => "deposito",
+    };
+    let data = payload.data.unwrap_or_else(Utc::now);
+    let descricao = payload.descricao.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    let movimentos: Vec<MovimentoEnvelope> = [(origem, -payload.valor), (destino, payload.valor)]
+        .into_iter()
+        .filter_map(|(envelope, valor)| envelope.map(|e| (e, valor)))
+        .map(|(envelope, valor)| MovimentoEnvelope {
+            id: ulid::Ulid::new().to_string(),
+            id_usuario: id_usuario.clone(),
+            id_envelope: envelope.id,
+            tipo: tipo.to_string(),
+            valor,
+            descricao: descricao.clone(),
+            data,
+            id_transacao: None,
+            dia: None,
+            criado_em: Utc::now(),
+        })
+        .collect();
+    diesel::insert_into(m_dsl::movimentos_envelopes).values(&movimentos).execute(conn).map_err(erro_interno)?;
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    Ok((StatusCode::CREATED, Json(movimentos)))
+}
+
+/// Gasta o dinheiro do envelope: lança a despesa e tira o valor do saldo
+pub async fn retirar_handler(
+    jar: CookieJar,
+    Path(id_envelope): Path<String>,
+    Json(payload): Json<RetiradaPayload>,
+) -> Result<(StatusCode, Json<Retirada>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    if payload.valor <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "O valor deve ser positivo".to_string()));
+    }
+    let conn = &mut db::establish_connection();
+    let envelope = buscar(conn, &id_usuario, &id_envelope).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    let Some(id_categoria) = payload.id_categoria.clone().or(envelope.id_categoria.clone()) else {
+        return Err((StatusCode::BAD_REQUEST, "Informe a categoria da despesa".to_string()));
+    };
+    if !crate::services::divida::categoria_valida(conn, &id_usuario, &id_categoria).map_err(erro_interno)? {
+        return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+    }
+    if let Some(carteira) = payload.id_carteira.as_deref() {
+        if !crate::services::carteira::carteira_valida(conn, &id_usuario, carteira).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+        }
+    }
+    if saldo(conn, &envelope).map_err(erro_interno)? < payload.valor as i64 {
+        return Err((StatusCode::BAD_REQUEST, format!("Saldo insuficiente no envelope {}", envelope.nome)));
+    }
+    let data = payload.data.unwrap_or_else(Utc::now);
+    let descricao = payload
+        .descricao
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| format!("Retirada do envelope {}", envelope.nome));
+    let nova = NewTransacao {
+        eventos: 0,
+        km: None,
+        descricao: Some(descricao.clone()),
+        data,
+        id_carteira: payload.id_carteira,
+        ..NewTransacao::new(id_usuario.clone(), id_categoria, payload.valor, "saida".to_string())
+    };
+
+    let (movimento, transacao) = conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            use crate::schema::transacoes::dsl as t_dsl;
+            let transacao: Transacao = diesel::insert_into(t_dsl::transacoes).values(&nova).get_result(conn)?;
+            let movimento = MovimentoEnvelope {
+                id: ulid::Ulid::new().to_string(),
+                id_usuario: id_usuario.clone(),
+                id_envelope: envelope.id.clone(),
+                tipo: "retirada".to_string(),
+                valor: -payload.valor,
+                descricao: Some(descricao),
+                data,
+                id_transacao: Some(transacao.id.clone()),
+                dia: None,
+                criado_em: Utc::now(),
+            };
+            diesel::insert_into(m_dsl::movimentos_envelopes).values(&movimento).execute(conn)?;
+            let origem = Origem::usuario(&id_usuario, "POST /api/envelopes/{id}/retiradas");
+            historico::registrar(conn, &origem, Acao::Criacao, None, Some(&transacao))?;
+            Ok((movimento, transacao))
+        })
+        .map_err(erro_interno)?;
+
+    // a despesa tira dinheiro do envelope, e o saldo dele vem dos dados fixos do razão
+    let evento = EventoUsuario::TransacaoCriada { transacao: transacao.clone(), dashboard: None };
+    eventos::invalidar_e_publicar(&id_usuario, evento).await;
+    crate::services::anomalia::agendar_deteccao(id_usuario);
+    Ok((StatusCode::CREATED, Json(Retirada { movimento, transacao })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regra_e_parcela() {
+        assert_eq!(validar_regra(Regra::Manual, Some(30.0)), Ok(0.0));
+        assert_eq!(validar_regra(Regra::Entrada, Some(12.5)), Ok(12.5));
+        assert!(validar_regra(Regra::LucroDiario, None).is_err());
+        assert!(validar_regra(Regra::LucroDiario, Some(0.0)).is_err());
+        assert!(validar_regra(Regra::Entrada, Some(100.5)).is_err());
+        assert_eq!(Regra::de_nome("lucro_diario"), Some(Regra::LucroDiario));
+
+        assert_eq!(parcela(12_345, 10.0), 1_235);
+        assert_eq!(parcela(0, 10.0), 0);
+        // dia de prejuízo não separa nada
+        assert_eq!(parcela(-5_000, 10.0), 0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Eventos em tempo real por usuário (Server-Sent Events)
+//!
+//! Os mesmos pontos que atualizam ou invalidam o `RIDER_CACHE` publicam aqui o que mudou:
+//! transações (com o delta dos `DashboardStats` quando o dashboard está em cache), início e fim
+//! de sessão e metas concluídas. O frontend assina `/api/eventos` em vez de consultar
+//! `/api/dashboard/stats` periodicamente.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Mutex;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum_extra::extract::cookie::CookieJar;
+use futures_util::stream::Stream;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+use crate::cache::RIDER_CACHE;
+use crate::models::{Meta, SessaoTrabalho, Transacao};
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::dashboard::service::DashboardStats;
+
+/// Eventos pendentes por assinante antes de ele ser considerado atrasado
+const CAPACIDADE_CANAL: usize = 64;
+
+static CANAIS: once_cell::sync::Lazy<Mutex<HashMap<String, broadcast::Sender<EventoUsuario>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Campos do `DashboardStats` que mudaram, com os valores novos
+pub type DeltaDashboard = Map<String, Value>;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum EventoUsuario {
+    TransacaoCriada { transacao: Transacao, dashboard: Option<DeltaDashboard> },
+    TransacaoAtualizada { antes: Box<Transacao>, depois: Box<Transacao>, dashboard: Option<DeltaDashboard> },
+    TransacaoExcluida { transacao: Transacao, dashboard: Option<DeltaDashboard> },
+    SessaoIniciada { sessao: SessaoTrabalho },
+    SessaoEncerrada { sessao: SessaoTrabalho },
+    MetaConcluida { meta: Meta },
+    /// Algo mudou sem detalhe disponível (outra instância, eventos perdidos): recarregar
+    DadosAlterados,
+}
+
+impl EventoUsuario {
+    pub fn nome(&self) -> &'static str {
+        match self {
+            EventoUsuario::TransacaoCriada { .. } => "transacao_criada",
+            EventoUsuario::TransacaoAtualizada { .. } => "transacao_atualizada",
+            EventoUsuario::TransacaoExcluida { .. } => "transacao_excluida",
+            EventoUsuario::SessaoIniciada { .. } => "sessao_iniciada",
+            EventoUsuario::SessaoEncerrada { .. } => "sessao_encerrada",
+            EventoUsuario::MetaConcluida { .. } => "meta_concluida",
+            EventoUsuario::DadosAlterados => "dados_alterados",
+        }
+    }
+
+    fn para_sse(&self) -> Event {
+        Event::default()
+            .event(self.nome())
+            .json_data(self)
+            .unwrap_or_else(|_| Event::default().event("dados_alterados").data("{}"))
+    }
+}
+
+/// Se há alguém conectado (evita calcular deltas que ninguém vai receber)
+pub fn tem_inscritos(id_usuario: &str) -> bool {
+    let canais = CANAIS.lock().unwrap_or_else(|e| e.into_inner());
+    canais.get(id_usuario).is_some_and(|tx| tx.receiver_count() > 0)
+}
+
+/// Envia o evento para as conexões abertas do usuário (sem conexões, é descartado)
+pub fn publicar(id_usuario: &str, evento: EventoUsuario) {
+    let mut canais = CANAIS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(tx) = canais.get(id_usuario) {
+        if tx.send(evento).is_err() {
+            // ninguém mais escutando
+            canais.remove(id_usuario);
+        }
+    }
+}
+
+/// Invalida os caches do usuário e avisa os clientes conectados
+pub async fn invalidar_e_publicar(id_usuario: &str, evento: EventoUsuario) {
+    RIDER_CACHE.invalidate_user_caches(id_usuario).await;
+    publicar(id_usuario, evento);
+}
+
+fn inscrever(id_usuario: &str) -> broadcast::Receiver<EventoUsuario> {
+    let mut canais = CANAIS.lock().unwrap_or_else(|e| e.into_inner());
+    canais.retain(|_, tx| tx.receiver_count() > 0);
+    canais
+        .entry(id_usuario.to_string())
+        .or_insert_with(|| broadcast::channel(CAPACIDADE_CANAL).0)
+        .subscribe()
+}
+
+/// Campos de primeiro nível que diferem entre dois stats
+pub fn delta_dashboard(antes: &DashboardStats, depois: &DashboardStats) -> DeltaDashboard {
+    let (Ok(Value::Object(antes)), Ok(Value::Object(depois))) = (serde_json::to_value(antes), serde_json::to_value(depois)) else {
+        return Map::new();
+    };
+    depois.into_iter().filter(|(campo, valor)| antes.get(campo) != Some(valor)).collect()
+}
+
+/// Stream SSE dos eventos do usuário autenticado
+pub async fn eventos_handler(
+    jar: CookieJar,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let rx = inscrever(&id_usuario);
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await {
+            Ok(evento) => Some((Ok(evento.para_sse()), rx)),
+            // o cliente ficou para trás e perdeu eventos: pede recarga completa
+            Err(RecvError::Lagged(_)) => Some((Ok(EventoUsuario::DadosAlterados.para_sse()), rx)),
+            Err(RecvError::Closed) => None,
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_dashboard_so_campos_alterados() {
+        let antes = DashboardStats { ganhos_hoje: Some(100), gastos_hoje: Some(50), ..Default::default() };
+        let depois = DashboardStats { ganhos_hoje: Some(150), gastos_hoje: Some(50), ..Default::default() };
+        let delta = delta_dashboard(&antes, &depois);
+        assert_eq!(delta.len(), 1);
+        assert_eq!(delta.get("ganhos_hoje"), Some(&Value::from(150)));
+    }
+
+    #[tokio::test]
+    async fn test_publicar_entrega_somente_ao_usuario() {
+        let mut rx = inscrever("eventos_u1");
+        let mut outro = inscrever("eventos_u2");
+        assert!(tem_inscritos("eventos_u1"));
+
+        publicar("eventos_u1", EventoUsuario::DadosAlterados);
+        assert_eq!(rx.recv().await.unwrap().nome(), "dados_alterados");
+        assert!(outro.try_recv().is_err());
+    }
+}
@@ -0,0 +1,667 @@
+//! Regras de segurança contra jornadas longas: horas contínuas sem pausa, horas no dia e horas na
+//! semana
+//!
+//! O tempo trabalhado é o das sessões (a ativa conta até agora) menos as pausas registradas. Uma
+//! pausa mais curta que a mínima configurada não zera as horas contínuas: o bloco segue aberto.
+//! Limites vêm de `configuracoes` (o usuário pode sobrescrever as chaves globais; valor 0 desliga a
+//! regra). Cada limite ultrapassado vira um registro em `eventos_fadiga`, um por bloco, dia ou
+//! semana, para o relatório semanal. Dias e semanas (segunda a domingo) seguem o UTC, como o
+//! dashboard.
+
+use std::collections::BTreeMap;
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Nullable, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::db;
+use crate::models::{EventoFadiga, PausaSessao, SessaoTrabalho};
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+pub const CHAVE_MAX_HORAS_CONTINUAS: &str = "fadiga_max_horas_continuas";
+pub const CHAVE_PAUSA_MINIMA: &str = "fadiga_pausa_minima_minutos";
+pub const CHAVE_MAX_HORAS_DIARIAS: &str = "fadiga_max_horas_diarias";
+pub const CHAVE_MAX_HORAS_SEMANAIS: &str = "fadiga_max_horas_semanais";
+
+pub const TIPO_CONTINUAS: &str = "horas_continuas";
+pub const TIPO_DIARIAS: &str = "horas_diarias";
+pub const TIPO_SEMANAIS: &str = "horas_semanais";
+
+/// Fração do limite a partir da qual o status já avisa
+const ATENCAO: f64 = 0.9;
+
+/// Limites em minutos; None = regra desligada
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Limites {
+    pub horas_continuas: Option<i64>,
+    pub pausa_minima: i64,
+    pub horas_diarias: Option<i64>,
+    pub horas_semanais: Option<i64>,
+}
+
+/// Trecho trabalhado de uma sessão
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intervalo {
+    pub id_sessao: String,
+    pub inicio: DateTime<Utc>,
+    pub fim: DateTime<Utc>,
+}
+
+impl Intervalo {
+    fn minutos(&self) -> i64 {
+        (self.fim - self.inicio).num_minutes()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alerta {
+    pub tipo: &'static str,
+    /// "atencao" (perto do limite) ou "excedido"
+    pub nivel: &'static str,
+    pub minutos: i64,
+    pub limite_minutos: i64,
+    pub mensagem: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Avaliacao {
+    /// Bloco em andamento (zero depois de uma pausa completa)
+    pub minutos_continuos: i64,
+    pub minutos_hoje: i64,
+    pub minutos_semana: i64,
+    pub alertas: Vec<Alerta>,
+}
+
+/// Limite ultrapassado a gravar em `eventos_fadiga`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excesso {
+    pub tipo: &'static str,
+    pub referencia: DateTime<Utc>,
+    pub id_sessao: Option<String>,
+    pub limite_minutos: i64,
+    pub minutos: i64,
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn config(conn: &mut PgConnection, id_usuario: &str, chave: &str) -> Option<f64> {
+    use crate::schema::configuracoes::dsl as config_dsl;
+    config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario).or(config_dsl::id_usuario.is_null()))
+        .filter(config_dsl::chave.eq(chave))
+        .order(config_dsl::id_usuario.desc().nulls_last())
+        .select(config_dsl::valor)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+        .and_then(|v| v.trim().replace(',', ".").parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+/// Chave ausente ou inválida cai no padrão semeado
+pub fn limites(conn: &mut PgConnection, id_usuario: &str) -> Limites {
+    let horas = |conn: &mut PgConnection, chave, padrao: f64| {
+        let minutos = (config(conn, id_usuario, chave).unwrap_or(padrao) * 60.0).round() as i64;
+        (minutos > 0).then_some(minutos)
+    };
+    Limites {
+        horas_continuas: horas(conn, CHAVE_MAX_HORAS_CONTINUAS, 5.5),
+        pausa_minima: config(conn, id_usuario, CHAVE_PAUSA_MINIMA).unwrap_or(30.0).round().max(1.0) as i64,
+        horas_diarias: horas(conn, CHAVE_MAX_HORAS_DIARIAS, 10.0),
+        horas_semanais: horas(conn, CHAVE_MAX_HORAS_SEMANAIS, 60.0),
+    }
+}
+
+/// Sessão menos as pausas (recortadas aos limites dela)
+pub fn trabalhado(sessao: &Intervalo, pausas: &[(DateTime<Utc>, DateTime<Utc>)]) -> Vec<Intervalo> {
+    let mut pausas = pausas.to_vec();
+    pausas.sort();
+    let mut trechos = Vec::new();
+    let mut cursor = sessao.inicio;
+    for (inicio, fim) in pausas {
+        let (inicio, fim) = (inicio.max(sessao.inicio), fim.min(sessao.fim));
+        if fim <= inicio {
+            continue;
+        }
+        if inicio > cursor {
+            trechos.push(Intervalo { inicio: cursor, fim: inicio, ..sessao.clone() });
+        }
+        cursor = cursor.max(fim);
+    }
+    if cursor < sessao.fim {
+        trechos.push(Intervalo { inicio: cursor, ..sessao.clone() });
+    }
+    trechos
+}
+
+/// Junta trechos separados por menos que a pausa mínima; o bloco leva a última sessão
+pub fn blocos(intervalos: &[Intervalo], pausa_minima: i64) -> Vec<Intervalo> {
+    let mut ordenados = intervalos.to_vec();
+    ordenados.sort_by_key(|i| i.inicio);
+    let mut blocos: Vec<Intervalo> = Vec::new();
+    for i in ordenados {
+        match blocos.last_mut() {
+            Some(b) if i.inicio - b.fim < Duration::minutes(pausa_minima) => {
+                if i.fim > b.fim {
+                    b.fim = i.fim;
+                    b.id_sessao = i.id_sessao;
+                }
+            }
+            _ => blocos.push(i),
+        }
+    }
+    blocos
+}
+
+pub fn inicio_do_dia(dia: NaiveDate) -> DateTime<Utc> {
+    dia.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+pub fn segunda_feira(dia: NaiveDate) -> NaiveDate {
+    dia - Duration::days(dia.weekday().num_days_from_monday() as i64)
+}
+
+/// Segundos trabalhados por dia, com a última sessão do dia; sessões sobrepostas não contam duas vezes
+pub fn por_dia(intervalos: &[Intervalo]) -> BTreeMap<NaiveDate, (i64, String)> {
+    let mut dias: BTreeMap<NaiveDate, (i64, String)> = BTreeMap::new();
+    for b in blocos(intervalos, 0) {
+        let mut cursor = b.inicio;
+        while cursor < b.fim {
+            let dia = cursor.date_naive();
+            let fim = b.fim.min(inicio_do_dia(dia + Duration::days(1)));
+            let entrada = dias.entry(dia).or_insert((0, String::new()));
+            entrada.0 += (fim - cursor).num_seconds();
+            entrada.1 = b.id_sessao.clone();
+            cursor = fim;
+        }
+    }
+    dias
+}
+
+fn horas(minutos: i64) -> String {
+    format!("{}h{:02}", minutos / 60, minutos % 60)
+}
+
+fn alerta(tipo: &'static str, minutos: i64, limite: Option<i64>, texto: &str) -> Option<Alerta> {
+    let limite = limite?;
+    let nivel = if minutos > limite {
+        "excedido"
+    } else if minutos as f64 >= limite as f64 * ATENCAO {
+        "atencao"
+    } else {
+        return None;
+    };
+    Some(Alerta {
+        tipo,
+        nivel,
+        minutos,
+        limite_minutos: limite,
+        mensagem: format!("{texto}: {} de {}", horas(minutos), horas(limite)),
+    })
+}
+
+pub fn avaliar(intervalos: &[Intervalo], limites: &Limites, agora: DateTime<Utc>) -> Avaliacao {
+    let minutos_continuos = blocos(intervalos, limites.pausa_minima)
+        .last()
+        .filter(|b| agora - b.fim < Duration::minutes(limites.pausa_minima))
+        .map(Intervalo::minutos)
+        .unwrap_or(0);
+    let dias = por_dia(intervalos);
+    let hoje = agora.date_naive();
+    let minutos_hoje = dias.get(&hoje).map(|d| d.0 / 60).unwrap_or(0);
+    let minutos_semana = dias.range(segunda_feira(hoje)..=hoje).map(|(_, d)| d.0).sum::<i64>() / 60;
+    let alertas = [
+        alerta(TIPO_CONTINUAS, minutos_continuos, limites.horas_continuas, &format!("Trabalhando sem pausa de {} min", limites.pausa_minima)),
+        alerta(TIPO_DIARIAS, minutos_hoje, limites.horas_diarias, "Horas trabalhadas hoje"),
+        alerta(TIPO_SEMANAIS, minutos_semana, limites.horas_semanais, "Horas trabalhadas na semana"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    Avaliacao { minutos_continuos, minutos_hoje, minutos_semana, alertas }
+}
+
+/// Todos os limites ultrapassados no período coberto pelos intervalos
+pub fn excessos(intervalos: &[Intervalo], limites: &Limites) -> Vec<Excesso> {
+    let mut excessos = Vec::new();
+    if let Some(limite) = limites.horas_continuas {
+        for b in blocos(intervalos, limites.pausa_minima) {
+            if b.minutos() > limite {
+                excessos.push(Excesso { tipo: TIPO_CONTINUAS, referencia: b.inicio, id_sessao: Some(b.id_sessao.clone()), limite_minutos: limite, minutos: b.minutos() });
+            }
+        }
+    }
+    let dias = por_dia(intervalos);
+    if let Some(limite) = limites.horas_diarias {
+        for (dia, (segundos, sessao)) in &dias {
+            if segundos / 60 > limite {
+                excessos.push(Excesso { tipo: TIPO_DIARIAS, referencia: inicio_do_dia(*dia), id_sessao: Some(sessao.clone()), limite_minutos: limite, minutos: segundos / 60 });
+            }
+        }
+    }
+    if let Some(limite) = limites.horas_semanais {
+        let mut semanas: BTreeMap<NaiveDate, (i64, String)> = BTreeMap::new();
+        for (dia, (segundos, sessao)) in dias {
+            let semana = semanas.entry(segunda_feira(dia)).or_insert((0, String::new()));
+            semana.0 += segundos;
+            semana.1 = sessao;
+        }
+        for (segunda, (segundos, sessao)) in semanas {
+            if segundos / 60 > limite {
+                excessos.push(Excesso { tipo: TIPO_SEMANAIS, referencia: inicio_do_dia(segunda), id_sessao: Some(sessao), limite_minutos: limite, minutos: segundos / 60 });
+            }
+        }
+    }
+    excessos
+}
+
+/// Trechos trabalhados das sessões que terminam depois de `desde`, até `agora`
+pub fn carregar(conn: &mut PgConnection, id_usuario_param: &str, desde: DateTime<Utc>, agora: DateTime<Utc>) -> QueryResult<Vec<Intervalo>> {
+    use crate::schema::sessoes_trabalho::dsl as s;
+    use crate::schema::pausas_sessoes::dsl as p;
+    let sessoes: Vec<SessaoTrabalho> = s::sessoes_trabalho
+        .filter(s::id_usuario.eq(id_usuario_param))
+        .filter(s::excluido_em.is_null())
+        .filter(s::fim.ge(desde).or(s::fim.is_null().and(s::eh_ativa.eq(true))))
+        .load(conn)?;
+    let ids: Vec<&str> = sessoes.iter().map(|x| x.id.as_str()).collect();
+    let pausas: Vec<PausaSessao> = p::pausas_sessoes.filter(p::id_sessao.eq_any(&ids)).load(conn)?;
+    Ok(sessoes
+        .iter()
+        .filter_map(|x| {
+            // sessão lançada com horário futuro só conta até agora
+            let fim = x.fim.or(x.eh_ativa.then_some(agora))?.min(agora);
+            let sessao = Intervalo { id_sessao: x.id.clone(), inicio: x.inicio, fim };
+            let pausas: Vec<_> = pausas
+                .iter()
+                .filter(|pa| pa.id_sessao == x.id)
+                .map(|pa| (pa.inicio, pa.fim.unwrap_or(agora)))
+                .collect();
+            Some(trabalhado(&sessao, &pausas))
+        })
+        .flatten()
+        .collect())
+}
+
+/// Um registro por bloco, dia ou semana; guarda o maior valor já visto
+pub fn registrar(conn: &mut PgConnection, id_usuario: &str, excessos: &[Excesso]) -> QueryResult<()> {
+    for e in excessos {
+        diesel::sql_query(
+            "INSERT INTO eventos_fadiga (id, id_usuario, id_sessao, tipo, referencia, limite_minutos, minutos) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (id_usuario, tipo, referencia) DO UPDATE SET \
+                minutos = GREATEST(eventos_fadiga.minutos, EXCLUDED.minutos), \
+                limite_minutos = EXCLUDED.limite_minutos, \
+                id_sessao = COALESCE(EXCLUDED.id_sessao, eventos_fadiga.id_sessao), \
+                atualizado_em = NOW()",
+        )
+        .bind::<Text, _>(ulid::Ulid::new().to_string())
+        .bind::<Text, _>(id_usuario)
+        .bind::<Nullable<Text>, _>(e.id_sessao.as_deref())
+        .bind::<Text, _>(e.tipo)
+        .bind::<Timestamptz, _>(e.referencia)
+        .bind::<Integer, _>(e.limite_minutos as i32)
+        .bind::<Integer, _>(e.minutos as i32)
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Avalia a semana corrente (e o bloco que veio da véspera) e grava os excessos
+fn verificar(conn: &mut PgConnection, id_usuario: &str, limites: &Limites, agora: DateTime<Utc>) -> QueryResult<Avaliacao> {
+    let desde = inicio_do_dia(segunda_feira(agora.date_naive())).min(agora - Duration::days(1));
+    let intervalos = carregar(conn, id_usuario, desde, agora)?;
+    registrar(conn, id_usuario, &excessos(&intervalos, limites))?;
+    Ok(avaliar(&intervalos, limites, agora))
+}
+
+/// Fecha a pausa aberta no fim da sessão e grava os excessos; falha só vira aviso
+pub fn ao_encerrar(conn: &mut PgConnection, sessao: &SessaoTrabalho) {
+    use crate::schema::pausas_sessoes::dsl as p;
+    let Some(fim_sessao) = sessao.fim else { return };
+    let abertas = p::pausas_sessoes.filter(p::id_sessao.eq(&sessao.id)).filter(p::fim.is_null());
+    let resultado = diesel::update(abertas.filter(p::inicio.lt(fim_sessao)))
+        .set(p::fim.eq(Some(fim_sessao)))
+        .execute(conn)
+        .and_then(|_| diesel::delete(abertas).execute(conn))
+        .and_then(|_| {
+            let limites = limites(conn, &sessao.id_usuario);
+            verificar(conn, &sessao.id_usuario, &limites, Utc::now())
+        });
+    if let Err(e) = resultado {
+        warn!("Falha ao verificar fadiga da sessão {}: {}", sessao.id, e);
+    }
+}
+
+#[derive(Serialize)]
+pub struct StatusSessao {
+    pub sessao: Option<SessaoTrabalho>,
+    /// Pausa em andamento na sessão ativa
+    pub pausa: Option<PausaSessao>,
+    pub limites: Limites,
+    #[serde(flatten)]
+    pub avaliacao: Avaliacao,
+}
+
+/// GET /api/sessao/status
+pub async fn status_handler(jar: CookieJar) -> Result<Json<StatusSessao>, (StatusCode, String)> {
+    use crate::schema::sessoes_trabalho::dsl as s;
+    use crate::schema::pausas_sessoes::dsl as p;
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let sessao: Option<SessaoTrabalho> = s::sessoes_trabalho
+        .filter(s::id_usuario.eq(&id_usuario))
+        .filter(s::excluido_em.is_null())
+        .filter(s::eh_ativa.eq(true))
+        .filter(s::fim.is_null())
+        .order(s::inicio.desc())
+        .first(conn)
+        .optional()
+        .map_err(erro_interno)?;
+    let pausa = match &sessao {
+        Some(x) => p::pausas_sessoes
+            .filter(p::id_sessao.eq(&x.id))
+            .filter(p::fim.is_null())
+            .first::<PausaSessao>(conn)
+            .optional()
+            .map_err(erro_interno)?,
+        None => None,
+    };
+    let limites = limites(conn, &id_usuario);
+    let avaliacao = verificar(conn, &id_usuario, &limites, agora).map_err(erro_interno)?;
+    Ok(Json(StatusSessao { sessao, pausa, limites, avaliacao }))
+}
+
+fn sessao_do_usuario(conn: &mut PgConnection, id_usuario: &str, id_sessao: &str) -> Result<SessaoTrabalho, (StatusCode, String)> {
+    use crate::schema::sessoes_trabalho::dsl as s;
+    s::sessoes_trabalho
+        .filter(s::id.eq(id_sessao))
+        .filter(s::id_usuario.eq(id_usuario))
+        .filter(s::excluido_em.is_null())
+        .first(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Sessão não encontrada".to_string()))
+}
+
+#[derive(Deserialize, Default)]
+pub struct PausaPayload {
+    /// Padrão: agora
+    pub inicio: Option<DateTime<Utc>>,
+    /// Informado = pausa já concluída; vazio = pausa começando (só na sessão ativa)
+    pub fim: Option<DateTime<Utc>>,
+    pub motivo: Option<String>,
+}
+
+/// POST /api/sessao/{id}/pausa
+pub async fn iniciar_pausa_handler(
+    jar: CookieJar,
+    Path(id_sessao): Path<String>,
+    payload: Option<Json<PausaPayload>>,
+) -> Result<(StatusCode, Json<PausaSessao>), (StatusCode, String)> {
+    use crate::schema::pausas_sessoes::dsl as p;
+    let id_usuario = usuario(&jar)?;
+    let payload = payload.map(|Json(x)| x).unwrap_or_default();
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let sessao = sessao_do_usuario(conn, &id_usuario, &id_sessao)?;
+    let inicio = payload.inicio.unwrap_or(agora);
+    let fim_sessao = sessao.fim.unwrap_or(agora);
+    if payload.fim.is_none() && (!sessao.eh_ativa || sessao.fim.is_some()) {
+        return Err((StatusCode::CONFLICT, "Sessão encerrada: informe o fim da pausa".to_string()));
+    }
+    if payload.fim.is_some_and(|f| f <= inicio) {
+        return Err((StatusCode::BAD_REQUEST, "O fim da pausa deve ser posterior ao início".to_string()));
+    }
+    if inicio < sessao.inicio || payload.fim.unwrap_or(inicio) > fim_sessao {
+        return Err((StatusCode::BAD_REQUEST, "A pausa deve estar dentro do horário da sessão".to_string()));
+    }
+    let sobreposta: i64 = p::pausas_sessoes
+        .filter(p::id_sessao.eq(&sessao.id))
+        .filter(p::inicio.lt(payload.fim.unwrap_or(agora)))
+        .filter(p::fim.is_null().or(p::fim.gt(inicio)))
+        .count()
+        .get_result(conn)
+        .map_err(erro_interno)?;
+    if sobreposta > 0 {
+        return Err((StatusCode::CONFLICT, "Já existe uma pausa nesse período".to_string()));
+    }
+    let pausa = PausaSessao {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        id_sessao: sessao.id,
+        inicio,
+        fim: payload.fim,
+        motivo: payload.motivo.map(|m| m.trim().to_string()).filter(|m| !m.is_empty()),
+        criado_em: agora,
+    };
+    diesel::insert_into(p::pausas_sessoes)
+        .values(&pausa)
+        .execute(conn)
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
+                (StatusCode::CONFLICT, "Já existe uma pausa em andamento".to_string())
+            }
+            e => erro_interno(e),
+        })?;
+    Ok((StatusCode::CREATED, Json(pausa)))
+}
+
+#[derive(Deserialize, Default)]
+pub struct EncerrarPausaPayload {
+    /// Padrão: agora
+    pub fim: Option<DateTime<Utc>>,
+}
+
+/// POST /api/sessao/{id}/pausa/encerrar
+pub async fn encerrar_pausa_handler(
+    jar: CookieJar,
+    Path(id_sessao): Path<String>,
+    payload: Option<Json<EncerrarPausaPayload>>,
+) -> Result<Json<PausaSessao>, (StatusCode, String)> {
+    use crate::schema::pausas_sessoes::dsl as p;
+    let id_usuario = usuario(&jar)?;
+    let fim = payload.and_then(|Json(x)| x.fim).unwrap_or_else(Utc::now);
+    let conn = &mut db::establish_connection();
+    let sessao = sessao_do_usuario(conn, &id_usuario, &id_sessao)?;
+    let pausa: PausaSessao = p::pausas_sessoes
+        .filter(p::id_sessao.eq(&sessao.id))
+        .filter(p::fim.is_null())
+        .first(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Nenhuma pausa em andamento".to_string()))?;
+    if fim <= pausa.inicio {
+        return Err((StatusCode::BAD_REQUEST, "O fim da pausa deve ser posterior ao início".to_string()));
+    }
+    diesel::update(p::pausas_sessoes.find(&pausa.id))
+        .set(p::fim.eq(Some(fim)))
+        .get_result(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+/// GET /api/sessao/{id}/pausas
+pub async fn listar_pausas_handler(jar: CookieJar, Path(id_sessao): Path<String>) -> Result<Json<Vec<PausaSessao>>, (StatusCode, String)> {
+    use crate::schema::pausas_sessoes::dsl as p;
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let sessao = sessao_do_usuario(conn, &id_usuario, &id_sessao)?;
+    p::pausas_sessoes
+        .filter(p::id_sessao.eq(&sessao.id))
+        .order(p::inicio.asc())
+        .load(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+#[derive(Deserialize)]
+pub struct PeriodoQuery {
+    pub inicio: Option<DateTime<Utc>>,
+    pub fim: Option<DateTime<Utc>>,
+}
+
+/// GET /api/fadiga/eventos — padrão: últimos 30 dias
+pub async fn listar_eventos_handler(jar: CookieJar, Query(q): Query<PeriodoQuery>) -> Result<Json<Vec<EventoFadiga>>, (StatusCode, String)> {
+    use crate::schema::eventos_fadiga::dsl as e;
+    let id_usuario = usuario(&jar)?;
+    let fim = q.fim.unwrap_or_else(Utc::now);
+    let inicio = q.inicio.unwrap_or(fim - Duration::days(30));
+    let conn = &mut db::establish_connection();
+    e::eventos_fadiga
+        .filter(e::id_usuario.eq(&id_usuario))
+        .filter(e::referencia.ge(inicio))
+        .filter(e::referencia.le(fim))
+        .order(e::referencia.desc())
+        .load(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+#[derive(Deserialize)]
+pub struct RelatorioQuery {
+    pub semanas: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct EventosPorTipo {
+    pub horas_continuas: i64,
+    pub horas_diarias: i64,
+    pub horas_semanais: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SemanaFadiga {
+    /// Segunda-feira
+    pub semana: NaiveDate,
+    pub minutos_trabalhados: i64,
+    pub dias_trabalhados: i64,
+    pub maior_bloco_minutos: i64,
+    /// Quantas vezes cada limite foi ultrapassado
+    pub eventos: EventosPorTipo,
+}
+
+/// Semanas da mais recente para a mais antiga
+pub fn semanas(intervalos: &[Intervalo], eventos: &[EventoFadiga], pausa_minima: i64, hoje: NaiveDate, quantidade: u32) -> Vec<SemanaFadiga> {
+    let dias = por_dia(intervalos);
+    let blocos = blocos(intervalos, pausa_minima);
+    (0..quantidade as i64)
+        .map(|i| {
+            let semana = segunda_feira(hoje) - Duration::weeks(i);
+            let (de, ate) = (inicio_do_dia(semana), inicio_do_dia(semana + Duration::weeks(1)));
+            let dias_semana: Vec<i64> = dias.range(semana..semana + Duration::weeks(1)).map(|(_, d)| d.0).collect();
+            let mut contagem = EventosPorTipo::default();
+            for e in eventos.iter().filter(|e| e.referencia >= de && e.referencia < ate) {
+                match e.tipo.as_str() {
+                    TIPO_CONTINUAS => contagem.horas_continuas += 1,
+                    TIPO_DIARIAS => contagem.horas_diarias += 1,
+                    TIPO_SEMANAIS => contagem.horas_semanais += 1,
+                    _ => {}
+                }
+            }
+            SemanaFadiga {
+                semana,
+                minutos_trabalhados: dias_semana.iter().sum::<i64>() / 60,
+                dias_trabalhados: dias_semana.iter().filter(|s| **s > 0).count() as i64,
+                maior_bloco_minutos: blocos.iter().filter(|b| b.inicio >= de && b.inicio < ate).map(Intervalo::minutos).max().unwrap_or(0),
+                eventos: contagem,
+            }
+        })
+        .collect()
+}
+
+/// GET /api/fadiga/relatorio?semanas=8
+pub async fn relatorio_handler(jar: CookieJar, Query(q): Query<RelatorioQuery>) -> Result<Json<Vec<SemanaFadiga>>, (StatusCode, String)> {
+    use crate::schema::eventos_fadiga::dsl as e;
+    let id_usuario = usuario(&jar)?;
+    let quantidade = q.semanas.unwrap_or(8);
+    if !(1..=52).contains(&quantidade) {
+        return Err((StatusCode::BAD_REQUEST, "semanas deve estar entre 1 e 52".to_string()));
+    }
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let hoje = agora.date_naive();
+    let desde = inicio_do_dia(segunda_feira(hoje) - Duration::weeks(quantidade as i64 - 1));
+    let limites = limites(conn, &id_usuario);
+    let intervalos = carregar(conn, &id_usuario, desde, agora).map_err(erro_interno)?;
+    // sessões lançadas depois do fato também entram no histórico de eventos
+    registrar(conn, &id_usuario, &excessos(&intervalos, &limites)).map_err(erro_interno)?;
+    let eventos: Vec<EventoFadiga> = e::eventos_fadiga
+        .filter(e::id_usuario.eq(&id_usuario))
+        .filter(e::referencia.ge(desde))
+        .load(conn)
+        .map_err(erro_interno)?;
+    Ok(Json(semanas(&intervalos, &eventos, limites.pausa_minima, hoje, quantidade)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(dia: u32, hora: u32, minuto: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(2025, 9, dia).unwrap().and_hms_opt(hora, minuto, 0).unwrap().and_utc()
+    }
+
+    fn trecho(sessao: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> Intervalo {
+        Intervalo { id_sessao: sessao.to_string(), inicio, fim }
+    }
+
+    fn limites() -> Limites {
+        Limites { horas_continuas: Some(330), pausa_minima: 30, horas_diarias: Some(600), horas_semanais: Some(3600) }
+    }
+
+    #[test]
+    fn test_pausas_curtas_nao_zeram_horas_continuas() {
+        // 08:00–14:00 com café de 10 min e almoço de 40 min
+        let sessao = trecho("s1", t(15, 8, 0), t(15, 14, 0));
+        let trechos = trabalhado(&sessao, &[(t(15, 12, 0), t(15, 12, 40)), (t(15, 10, 0), t(15, 10, 10))]);
+        assert_eq!(trechos.len(), 3);
+        assert_eq!(trechos.iter().map(Intervalo::minutos).sum::<i64>(), 310);
+        let b = blocos(&trechos, 30);
+        assert_eq!(b, vec![trecho("s1", t(15, 8, 0), t(15, 12, 0)), trecho("s1", t(15, 12, 40), t(15, 14, 0))]);
+
+        // sem o almoço vira um bloco só de 6h, acima do limite de 5h30
+        let trechos = trabalhado(&sessao, &[(t(15, 10, 0), t(15, 10, 10))]);
+        let a = avaliar(&trechos, &limites(), t(15, 14, 0));
+        assert_eq!(a.minutos_continuos, 360);
+        assert_eq!(a.minutos_hoje, 350);
+        assert_eq!(a.alertas.len(), 1);
+        assert_eq!((a.alertas[0].tipo, a.alertas[0].nivel), (TIPO_CONTINUAS, "excedido"));
+
+        // depois de uma pausa completa o bloco fecha
+        assert_eq!(avaliar(&trechos, &limites(), t(15, 14, 30)).minutos_continuos, 0);
+    }
+
+    #[test]
+    fn test_excessos_diarios_e_semanais() {
+        // segunda a sábado, 11h por dia em sessões que cruzam a meia-noite no sábado
+        let mut trechos: Vec<Intervalo> = (15..=19).map(|d| trecho(&format!("s{d}"), t(d, 8, 0), t(d, 19, 0))).collect();
+        trechos.push(trecho("s20", t(20, 18, 0), t(21, 5, 0)));
+        let lim = Limites { horas_continuas: None, ..limites() };
+        let e = excessos(&trechos, &lim);
+        let diarios: Vec<_> = e.iter().filter(|x| x.tipo == TIPO_DIARIAS).collect();
+        assert_eq!(diarios.len(), 5);
+        assert!(diarios.iter().all(|x| x.minutos == 660));
+        // 66h na semana de 15/09: o domingo 21/09 ainda é da mesma semana
+        let semanal = e.iter().find(|x| x.tipo == TIPO_SEMANAIS).unwrap();
+        assert_eq!((semanal.referencia, semanal.minutos, semanal.id_sessao.as_deref()), (t(15, 0, 0), 3960, Some("s20")));
+        assert!(excessos(&trechos, &Limites { horas_semanais: Some(4000), ..lim }).iter().all(|x| x.tipo != TIPO_SEMANAIS));
+
+        let a = avaliar(&trechos, &limites(), t(21, 5, 0));
+        assert_eq!((a.minutos_hoje, a.minutos_semana), (300, 3960));
+        assert!(a.alertas.iter().any(|x| x.tipo == TIPO_SEMANAIS && x.nivel == "excedido"));
+
+        let r = semanas(&trechos, &[], 30, t(22, 9, 0).date_naive(), 2);
+        assert_eq!(r[0].minutos_trabalhados, 0);
+        assert_eq!((r[1].semana, r[1].minutos_trabalhados, r[1].dias_trabalhados, r[1].maior_bloco_minutos), (t(15, 0, 0).date_naive(), 3960, 7, 660));
+    }
+}
@@ -0,0 +1,432 @@
+//! Histórico de alterações (trilha de auditoria) de transações, sessões, metas e categorias
+//!
+//! Toda criação, edição, exclusão, restauração e expurgo grava uma versão com o registro
+//! inteiro antes e depois, quem fez (usuário, admin ou rotina do sistema) e por qual endpoint.
+//! A versão guarda só a linha da tabela: divisões e comprovantes da transação ficam de fora, e as
+//! tags só entram nas versões gravadas pela troca de tags (`TransacaoComTags`).
+//!
+//! Reverter para a versão N grava de volta o estado `depois` dela (recriando o registro se ele
+//! já foi expurgado) e vira uma nova versão, então a reversão também pode ser revertida.
+
+use std::collections::HashMap;
+use axum::{Json, extract::Path, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::db;
+use crate::models::{Categoria, HistoricoAlteracao, Meta, SessaoTrabalho, Transacao};
+use crate::schema::historico_alteracoes::dsl as h_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::eventos::{self, EventoUsuario};
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Entidade {
+    Transacao,
+    Sessao,
+    Meta,
+    Categoria,
+}
+
+impl Entidade {
+    pub fn nome(self) -> &'static str {
+        match self {
+            Entidade::Transacao => "transacao",
+            Entidade::Sessao => "sessao",
+            Entidade::Meta => "meta",
+            Entidade::Categoria => "categoria",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Acao {
+    Criacao,
+    Atualizacao,
+    /// Foi para a lixeira
+    Exclusao,
+    /// Voltou da lixeira
+    Restauracao,
+    /// Apagado de vez
+    Expurgo,
+    Reversao,
+}
+
+impl Acao {
+    pub fn nome(self) -> &'static str {
+        match self {
+            Acao::Criacao => "criacao",
+            Acao::Atualizacao => "atualizacao",
+            Acao::Exclusao => "exclusao",
+            Acao::Restauracao => "restauracao",
+            Acao::Expurgo => "expurgo",
+            Acao::Reversao => "reversao",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ator {
+    Usuario(String),
+    Admin(String),
+    /// Rotinas internas (expurgo automático da lixeira)
+    Sistema,
+}
+
+/// Quem fez a alteração e por onde
+#[derive(Clone, Debug)]
+pub struct Origem {
+    pub ator: Ator,
+    pub endpoint: &'static str,
+}
+
+impl Origem {
+    pub fn usuario(id_usuario: &str, endpoint: &'static str) -> Self {
+        Origem { ator: Ator::Usuario(id_usuario.to_string()), endpoint }
+    }
+
+    pub fn sistema(endpoint: &'static str) -> Self {
+        Origem { ator: Ator::Sistema, endpoint }
+    }
+
+    fn ator_sql(&self) -> (&'static str, Option<&str>) {
+        match &self.ator {
+            Ator::Usuario(id) => ("usuario", Some(id)),
+            Ator::Admin(id) => ("admin", Some(id)),
+            Ator::Sistema => ("sistema", None),
+        }
+    }
+}
+
+/// Registros com histórico
+pub trait Auditavel: Serialize {
+    const ENTIDADE: Entidade;
+    fn id_registro(&self) -> &str;
+    fn dono(&self) -> Option<&str>;
+}
+
+impl Auditavel for Transacao {
+    const ENTIDADE: Entidade = Entidade::Transacao;
+    fn id_registro(&self) -> &str {
+        &self.id
+    }
+    fn dono(&self) -> Option<&str> {
+        Some(&self.id_usuario)
+    }
+}
+
+/// Transação com os nomes das suas tags, para versões que registram a troca de tags
+#[derive(Serialize)]
+pub struct TransacaoComTags<'a> {
+    #[serde(flatten)]
+    pub transacao: &'a Transacao,
+    pub tags: Vec<String>,
+}
+
+impl Auditavel for TransacaoComTags<'_> {
+    const ENTIDADE: Entidade = Entidade::Transacao;
+    fn id_registro(&self) -> &str {
+        &self.transacao.id
+    }
+    fn dono(&self) -> Option<&str> {
+        Some(&self.transacao.id_usuario)
+    }
+}
+
+impl Auditavel for SessaoTrabalho {
+    const ENTIDADE: Entidade = Entidade::Sessao;
+    fn id_registro(&self) -> &str {
+        &self.id
+    }
+    fn dono(&self) -> Option<&str> {
+        Some(&self.id_usuario)
+    }
+}
+
+impl Auditavel for Meta {
+    const ENTIDADE: Entidade = Entidade::Meta;
+    fn id_registro(&self) -> &str {
+        &self.id
+    }
+    fn dono(&self) -> Option<&str> {
+        Some(&self.id_usuario)
+    }
+}
+
+impl Auditavel for Categoria {
+    const ENTIDADE: Entidade = Entidade::Categoria;
+    fn id_registro(&self) -> &str {
+        &self.id
+    }
+    fn dono(&self) -> Option<&str> {
+        self.id_usuario.as_deref()
+    }
+}
+
+fn como_json<T: Serialize>(registro: Option<&T>) -> Option<serde_json::Value> {
+    registro.and_then(|r| serde_json::to_value(r).ok())
+}
+
+/// Grava a próxima versão do registro (`antes` e/ou `depois` precisam existir)
+pub fn registrar<T: Auditavel>(
+    conn: &mut PgConnection,
+    origem: &Origem,
+    acao: Acao,
+    antes: Option<&T>,
+    depois: Option<&T>,
+) -> QueryResult<()> {
+    let Some(registro) = depois.or(antes) else {
+        return Ok(());
+    };
+    let (ator_tipo, ator_id) = origem.ator_sql();
+    diesel::sql_query(
+        "INSERT INTO historico_alteracoes
+            (id, id_usuario, entidade, id_entidade, versao, acao, antes, depois, ator_tipo, ator_id, origem)
+         SELECT $1, $2, $3, $4, COALESCE(MAX(versao), 0) + 1, $5, $6, $7, $8, $9, $10
+         FROM historico_alteracoes WHERE entidade = $3 AND id_entidade = $4",
+    )
+    .bind::<Text, _>(ulid::Ulid::new().to_string())
+    .bind::<Nullable<Text>, _>(registro.dono())
+    .bind::<Text, _>(T::ENTIDADE.nome())
+    .bind::<Text, _>(registro.id_registro())
+    .bind::<Text, _>(acao.nome())
+    .bind::<Nullable<Jsonb>, _>(como_json(antes))
+    .bind::<Nullable<Jsonb>, _>(como_json(depois))
+    .bind::<Text, _>(ator_tipo)
+    .bind::<Nullable<Text>, _>(ator_id)
+    .bind::<Text, _>(origem.endpoint)
+    .execute(conn)
+    .map(|_| ())
+}
+
+/// Como `registrar`, para caminhos fora de transação: uma falha no histórico não desfaz a operação
+pub fn anotar<T: Auditavel>(conn: &mut PgConnection, origem: &Origem, acao: Acao, antes: Option<&T>, depois: Option<&T>) {
+    if let Err(e) = registrar(conn, origem, acao, antes, depois) {
+        warn!("Falha ao gravar histórico ({} {}): {}", T::ENTIDADE.nome(), origem.endpoint, e);
+    }
+}
+
+/// Uma versão por registro de uma operação em lote; `antes` e `depois` são pareados pelo id
+/// (o que some de `depois` foi apagado)
+pub fn registrar_lote<T: Auditavel>(
+    conn: &mut PgConnection,
+    origem: &Origem,
+    acao: Acao,
+    antes: &[T],
+    depois: &[T],
+) -> QueryResult<()> {
+    let novos: HashMap<&str, &T> = depois.iter().map(|r| (r.id_registro(), r)).collect();
+    for anterior in antes {
+        registrar(conn, origem, acao, Some(anterior), novos.get(anterior.id_registro()).copied())?;
+    }
+    Ok(())
+}
+
+/// Linha do tempo do registro, da versão mais antiga para a mais nova
+pub fn linha_do_tempo(conn: &mut PgConnection, entidade: Entidade, id_entidade: &str) -> QueryResult<Vec<HistoricoAlteracao>> {
+    h_dsl::historico_alteracoes
+        .filter(h_dsl::entidade.eq(entidade.nome()))
+        .filter(h_dsl::id_entidade.eq(id_entidade))
+        .order(h_dsl::versao.asc())
+        .load(conn)
+}
+
+enum ErroHistorico {
+    Banco(diesel::result::Error),
+    Http(StatusCode, String),
+}
+
+impl From<diesel::result::Error> for ErroHistorico {
+    fn from(e: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+        match e {
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => ErroHistorico::Http(
+                StatusCode::CONFLICT,
+                format!("A versão referencia um registro que não existe mais: {}", info.message()),
+            ),
+            e => ErroHistorico::Banco(e),
+        }
+    }
+}
+
+impl From<ErroHistorico> for (StatusCode, String) {
+    fn from(e: ErroHistorico) -> Self {
+        match e {
+            ErroHistorico::Banco(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            ErroHistorico::Http(status, msg) => (status, msg),
+        }
+    }
+}
+
+fn invalido(msg: &str) -> ErroHistorico {
+    ErroHistorico::Http(StatusCode::BAD_REQUEST, msg.to_string())
+}
+
+/// Usuário logado ou admin; admin vê e reverte o histórico de qualquer usuário
+fn ator(jar: &CookieJar) -> Result<Ator, (StatusCode, String)> {
+    if let Some(admin) = crate::services::admin::validate_admin_cookie(jar) {
+        return Ok(Ator::Admin(admin));
+    }
+    extract_user_id_from_cookie(jar)
+        .map(Ator::Usuario)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+/// Versões visíveis para o ator; vazio conta como não encontrado
+fn versoes_do_ator(
+    conn: &mut PgConnection,
+    ator: &Ator,
+    entidade: Entidade,
+    id_entidade: &str,
+) -> Result<Vec<HistoricoAlteracao>, ErroHistorico> {
+    let versoes = linha_do_tempo(conn, entidade, id_entidade)?;
+    let permitido = match ator {
+        Ator::Usuario(id) => versoes.iter().all(|v| v.id_usuario.as_deref() == Some(id.as_str())),
+        _ => true,
+    };
+    if versoes.is_empty() || !permitido {
+        return Err(ErroHistorico::Http(StatusCode::NOT_FOUND, "Histórico não encontrado".to_string()));
+    }
+    Ok(versoes)
+}
+
+/// Grava `estado` no registro (atualiza ou recria) e devolve (atual, gravado) para o histórico
+macro_rules! regravar {
+    ($conn:expr, $tabela:path, $tipo:ty, $estado:expr) => {{
+        use $tabela as tabela;
+        let mut alvo: $tipo = serde_json::from_value($estado).map_err(|e| invalido(&format!("Versão ilegível: {e}")))?;
+        alvo.atualizado_em = Utc::now();
+        let atual: Option<$tipo> = tabela::table.find(&alvo.id).first($conn).optional()?;
+        if atual.is_some() {
+            diesel::update(tabela::table.find(&alvo.id)).set(&alvo).execute($conn)?;
+        } else {
+            diesel::insert_into(tabela::table).values(&alvo).execute($conn)?;
+        }
+        (atual, alvo)
+    }};
+}
+
+/// Reverte o registro para o estado `depois` da versão; devolve o dono para invalidar caches
+fn reverter(
+    conn: &mut PgConnection,
+    ator: &Ator,
+    entidade: Entidade,
+    id_entidade: &str,
+    versao: i32,
+) -> Result<Option<String>, ErroHistorico> {
+    let versoes = versoes_do_ator(conn, ator, entidade, id_entidade)?;
+    let escolhida = versoes
+        .iter()
+        .find(|v| v.versao == versao)
+        .ok_or(ErroHistorico::Http(StatusCode::NOT_FOUND, "Versão não encontrada".to_string()))?;
+    let estado = escolhida.depois.clone().ok_or_else(|| invalido("Esta versão não tem estado para restaurar (registro expurgado)"))?;
+    let origem = Origem { ator: ator.clone(), endpoint: "POST /api/historico/{entidade}/{id}/reverter" };
+
+    let dono = match entidade {
+        Entidade::Transacao => {
+            // versões da troca de tags trazem a lista; as demais não mexem nas tags
+            let tags: Option<Vec<String>> = estado.get("tags").cloned().and_then(|t| serde_json::from_value(t).ok());
+            let (atual, alvo) = regravar!(conn, crate::schema::transacoes, Transacao, estado);
+            if let Some(tags) = &tags {
+                crate::services::tag::definir_tags_transacao(conn, &alvo.id_usuario, &alvo.id, tags)?;
+            }
+            let divisoes_quebradas = atual.as_ref().is_some_and(|a| a.valor != alvo.valor)
+                && crate::services::transacao::divisao::tem_divisao(conn, &alvo.id);
+            if divisoes_quebradas {
+                return Err(ErroHistorico::Http(
+                    StatusCode::CONFLICT,
+                    "A transação está dividida e a versão tem outro valor; ajuste as divisões antes".to_string(),
+                ));
+            }
+            registrar(conn, &origem, Acao::Reversao, atual.as_ref(), Some(&alvo))?;
+            crate::services::envelope::aplicar_regras(conn, atual.as_ref(), Some(&alvo))?;
+            alvo.dono().map(str::to_string)
+        }
+        Entidade::Sessao => {
+            let (atual, alvo) = regravar!(conn, crate::schema::sessoes_trabalho, SessaoTrabalho, estado);
+            registrar(conn, &origem, Acao::Reversao, atual.as_ref(), Some(&alvo))?;
+            alvo.dono().map(str::to_string)
+        }
+        Entidade::Meta => {
+            let (atual, alvo) = regravar!(conn, crate::schema::metas, Meta, estado);
+            registrar(conn, &origem, Acao::Reversao, atual.as_ref(), Some(&alvo))?;
+            alvo.dono().map(str::to_string)
+        }
+        Entidade::Categoria => {
+            let (atual, alvo) = regravar!(conn, crate::schema::categorias, Categoria, estado);
+            registrar(conn, &origem, Acao::Reversao, atual.as_ref(), Some(&alvo))?;
+            alvo.dono().map(str::to_string)
+        }
+    };
+    Ok(dono)
+}
+
+pub async fn historico_handler(
+    jar: CookieJar,
+    Path((entidade, id_entidade)): Path<(Entidade, String)>,
+) -> Result<Json<Vec<HistoricoAlteracao>>, (StatusCode, String)> {
+    let ator = ator(&jar)?;
+    let conn = &mut db::establish_connection();
+    Ok(Json(versoes_do_ator(conn, &ator, entidade, &id_entidade)?))
+}
+
+#[derive(Deserialize)]
+pub struct ReverterPayload {
+    pub versao: i32,
+}
+
+pub async fn reverter_handler(
+    jar: CookieJar,
+    Path((entidade, id_entidade)): Path<(Entidade, String)>,
+    Json(payload): Json<ReverterPayload>,
+) -> Result<Json<Vec<HistoricoAlteracao>>, (StatusCode, String)> {
+    let ator = ator(&jar)?;
+    let conn = &mut db::establish_connection();
+    let dono = conn.transaction::<_, ErroHistorico, _>(|conn| reverter(conn, &ator, entidade, &id_entidade, payload.versao))?;
+    if let Some(dono) = dono {
+        eventos::invalidar_e_publicar(&dono, EventoUsuario::DadosAlterados).await;
+        if entidade == Entidade::Transacao {
+            crate::services::anomalia::agendar_deteccao(dono);
+        }
+    }
+    let versoes = linha_do_tempo(conn, entidade, &id_entidade).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(versoes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entidade_e_origem() {
+        let entidade: Entidade = serde_json::from_str(r#""sessao""#).unwrap();
+        assert_eq!(entidade, Entidade::Sessao);
+        assert_eq!(entidade.nome(), "sessao");
+        assert!(serde_json::from_str::<Entidade>(r#""usuario""#).is_err());
+
+        assert_eq!(Origem::usuario("u1", "PUT /api/meta/{id}").ator_sql(), ("usuario", Some("u1")));
+        assert_eq!(Origem::sistema("expurgo da lixeira").ator_sql(), ("sistema", None));
+    }
+
+    #[test]
+    fn test_versao_com_tags_continua_legivel_como_transacao() {
+        let transacao: Transacao = serde_json::from_value(serde_json::json!({
+            "id": "t1", "id_usuario": "u1", "id_categoria": "c1", "valor": 1500, "eventos": 1, "km": null,
+            "descricao": null, "tipo": "entrada", "data": "2025-09-01T12:00:00Z", "criado_em": "2025-09-01T12:00:00Z",
+            "atualizado_em": "2025-09-01T12:00:00Z", "id_sessao": null, "excluido_em": null, "id_carteira": null
+        }))
+        .unwrap();
+        let versao = TransacaoComTags { transacao: &transacao, tags: vec!["aeroporto".to_string()] };
+        assert_eq!(versao.id_registro(), "t1");
+        assert_eq!(versao.dono(), Some("u1"));
+
+        let estado = como_json(Some(&versao)).unwrap();
+        assert_eq!(estado["tags"], serde_json::json!(["aeroporto"]));
+        assert_eq!(estado["valor"], 1500);
+        let lida: Transacao = serde_json::from_value(estado).unwrap();
+        assert_eq!(lida.id, "t1");
+    }
+}
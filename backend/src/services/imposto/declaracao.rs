@@ -0,0 +1,211 @@
+//! Informe anual de rendimentos (PDF e XLSX) a partir do resumo de impostos
+
+use printpdf::*;
+use crate::utils::relatorio::formatar_moeda;
+use super::{ResumoImposto, SituacaoMei};
+
+const MESES: [&str; 12] = [
+    "Janeiro", "Fevereiro", "Março", "Abril", "Maio", "Junho",
+    "Julho", "Agosto", "Setembro", "Outubro", "Novembro", "Dezembro",
+];
+const TOPO_MM: f32 = 280.0;
+const RODAPE_MM: f32 = 20.0;
+const MARGEM_MM: f32 = 15.0;
+
+/// Identificação de quem declara
+pub struct Contribuinte {
+    pub nome: String,
+    pub documento: String,
+}
+
+/// As fontes embutidas declaram WinAnsi, mas o printpdf grava o texto em UTF-8; os acentos do
+/// português estão em Latin-1, cujo código no WinAnsi é o próprio ponto Unicode
+fn win_ansi(texto: &str) -> Vec<u8> {
+    texto
+        .chars()
+        .map(|c| match c {
+            '—' => 0x97,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn nome_mes(mes: u32) -> &'static str {
+    MESES.get(mes.saturating_sub(1) as usize).copied().unwrap_or("-")
+}
+
+fn percentual(valor: f64) -> String {
+    format!("{valor:.2}%").replace('.', ",")
+}
+
+fn situacao(s: SituacaoMei) -> &'static str {
+    match s {
+        SituacaoMei::Dentro => "Dentro do limite",
+        SituacaoMei::ExcessoAte20 => "Excesso de até 20%: DAS complementar sobre o excesso e desenquadramento no ano seguinte",
+        SituacaoMei::ExcessoAcima20 => "Excesso acima de 20%: desenquadramento retroativo a janeiro",
+    }
+}
+
+/// Linha do informe: células (coluna em mm, texto), em negrito ou não
+struct Linha {
+    celulas: Vec<(f32, String)>,
+    negrito: bool,
+    tamanho: f32,
+}
+
+impl Linha {
+    fn texto(texto: impl Into<String>) -> Self {
+        Linha { celulas: vec![(MARGEM_MM, texto.into())], negrito: false, tamanho: 10.0 }
+    }
+
+    fn titulo(texto: impl Into<String>) -> Self {
+        Linha { celulas: vec![(MARGEM_MM, texto.into())], negrito: true, tamanho: 12.0 }
+    }
+
+    fn tabela(colunas: &[f32], textos: Vec<String>, negrito: bool) -> Self {
+        Linha { celulas: colunas.iter().copied().zip(textos).collect(), negrito, tamanho: 9.0 }
+    }
+
+    fn vazia() -> Self {
+        Linha { celulas: Vec::new(), negrito: false, tamanho: 6.0 }
+    }
+}
+
+/// Conteúdo do informe, na ordem em que aparece nos dois formatos
+fn linhas(resumo: &ResumoImposto, contribuinte: &Contribuinte) -> Vec<Linha> {
+    let colunas = [MARGEM_MM, 50.0, 88.0, 126.0, 164.0];
+    let mut linhas = vec![
+        Linha { celulas: vec![(MARGEM_MM, format!("Informe de rendimentos — {}", resumo.ano))], negrito: true, tamanho: 16.0 },
+        Linha::texto(format!("Contribuinte: {}", contribuinte.nome)),
+        Linha::texto(format!("CPF/CNPJ: {}", contribuinte.documento)),
+        Linha::vazia(),
+        Linha::titulo("Receita bruta mensal e Carnê-Leão estimado"),
+        Linha::texto(format!(
+            "Rendimento tributável presumido: {} da receita de transporte; desconto simplificado mensal de {}",
+            percentual(resumo.parametros.presuncao),
+            formatar_moeda(resumo.parametros.desconto_simplificado)
+        )),
+        Linha::tabela(
+            &colunas,
+            ["Mês", "Receita bruta", "Base tributável", "Base de cálculo", "Carnê-Leão"].map(String::from).to_vec(),
+            true,
+        ),
+    ];
+    for m in &resumo.meses {
+        linhas.push(Linha::tabela(
+            &colunas,
+            vec![
+                nome_mes(m.mes).to_string(),
+                formatar_moeda(m.carne_leao.receita),
+                formatar_moeda(m.carne_leao.base_tributavel),
+                formatar_moeda(m.carne_leao.base_calculo),
+                formatar_moeda(m.carne_leao.imposto),
+            ],
+            false,
+        ));
+    }
+    linhas.push(Linha::tabela(
+        &colunas,
+        vec![
+            "Total".to_string(),
+            formatar_moeda(resumo.receita_total),
+            formatar_moeda(resumo.meses.iter().map(|m| m.carne_leao.base_tributavel).sum::<i64>()),
+            formatar_moeda(resumo.meses.iter().map(|m| m.carne_leao.base_calculo).sum::<i64>()),
+            formatar_moeda(resumo.carne_leao_total),
+        ],
+        true,
+    ));
+
+    let mei = &resumo.mei;
+    linhas.extend([
+        Linha::vazia(),
+        Linha::titulo("MEI — DASN-SIMEI"),
+        Linha::texto(format!("Receita bruta total: {}", formatar_moeda(mei.dasn.receita_bruta_total))),
+        Linha::texto(format!("Receita de comércio, indústria e transporte (ICMS): {}", formatar_moeda(mei.dasn.receita_icms))),
+        Linha::texto(format!("Receita de prestação de serviços (ISS): {}", formatar_moeda(mei.dasn.receita_servicos))),
+        Linha::texto(format!("Possui empregado: {}", if mei.dasn.possui_empregado { "sim" } else { "não" })),
+        Linha::texto(format!(
+            "Limite no ano ({} meses): {} — utilizado {}",
+            mei.meses_ativos,
+            formatar_moeda(mei.limite),
+            percentual(mei.percentual_utilizado)
+        )),
+        Linha::texto(format!("Excesso: {}", formatar_moeda(mei.excesso))),
+        Linha::texto(format!("Situação: {}", situacao(mei.situacao))),
+        Linha::texto(format!("DAS do ano: {}", formatar_moeda(mei.das_anual))),
+        Linha::vazia(),
+        Linha::titulo("Receita por categoria"),
+    ]);
+    for c in &resumo.por_categoria {
+        linhas.push(Linha::tabela(&[MARGEM_MM, 126.0], vec![c.nome.clone(), formatar_moeda(c.valor)], false));
+    }
+    linhas.extend([
+        Linha::vazia(),
+        Linha::texto("Valores estimados a partir dos lançamentos do aplicativo; confira com o seu contador."),
+    ]);
+    linhas
+}
+
+pub fn gerar_pdf(resumo: &ResumoImposto, contribuinte: &Contribuinte) -> Vec<u8> {
+    let mut doc = PdfDocument::new(&format!("Informe de rendimentos {}", resumo.ano));
+    let mut paginas = Vec::new();
+    let mut ops = Vec::new();
+    let mut y = TOPO_MM;
+    for linha in linhas(resumo, contribuinte) {
+        let altura = linha.tamanho * 0.5;
+        if y - altura < RODAPE_MM {
+            paginas.push(PdfPage::new(Mm(210.0), Mm(297.0), std::mem::take(&mut ops)));
+            y = TOPO_MM;
+        }
+        y -= altura;
+        let fonte = if linha.negrito { BuiltinFont::HelveticaBold } else { BuiltinFont::Helvetica };
+        for (x, texto) in linha.celulas {
+            ops.extend([
+                Op::StartTextSection,
+                Op::SetTextCursor { pos: Point::new(Mm(x), Mm(y)) },
+                Op::SetFontSizeBuiltinFont { size: Pt(linha.tamanho), font: fonte },
+                // texto vazio só para a fonte entrar nos recursos da página
+                Op::WriteTextBuiltinFont { items: Vec::new(), font: fonte },
+                Op::Unknown { key: "Tj".to_string(), value: vec![DictItem::String { data: win_ansi(&texto), literal: false }] },
+                Op::EndTextSection,
+            ]);
+        }
+    }
+    paginas.push(PdfPage::new(Mm(210.0), Mm(297.0), ops));
+    // `secure` descartaria o `Tj` gravado à mão
+    let opcoes = PdfSaveOptions { secure: false, ..Default::default() };
+    doc.with_pages(paginas).save(&opcoes, &mut Vec::new())
+}
+
+pub fn gerar_xlsx(resumo: &ResumoImposto, contribuinte: &Contribuinte) -> Vec<u8> {
+    let mut book = umya_spreadsheet::new_file();
+    let sheet = book.get_sheet_by_name_mut("Sheet1").unwrap();
+    sheet.set_name("Informe");
+    let mut negrito = umya_spreadsheet::Style::default();
+    negrito.get_font_mut().set_bold(true);
+    for (i, linha) in linhas(resumo, contribuinte).into_iter().enumerate() {
+        let row = (i + 1) as u32;
+        for (col, (_, texto)) in linha.celulas.into_iter().enumerate() {
+            let cell = sheet.get_cell_mut(((col + 1) as u32, row));
+            cell.set_value(texto);
+            if linha.negrito {
+                cell.set_style(negrito.clone());
+            }
+        }
+    }
+    let mut buffer = Vec::new();
+    umya_spreadsheet::writer::xlsx::write_writer(&book, &mut buffer).unwrap();
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_win_ansi() {
+        assert_eq!(win_ansi("Março — ação"), b"Mar\xe7o \x97 a\xe7\xe3o".to_vec());
+        assert_eq!(win_ansi("R$ 1,00 €"), b"R$ 1,00 ?".to_vec());
+    }
+}
@@ -0,0 +1,405 @@
+//! Estimativa de impostos sobre as entradas: MEI (limite de faturamento e DASN-SIMEI) e
+//! Carnê-Leão para o autônomo pessoa física
+//!
+//! A receita de cada mês é a soma das entradas (transação dividida conta por linha). No
+//! Carnê-Leão só o percentual presumido da receita de transporte é tributável; dessa base sai o
+//! desconto simplificado mensal e aplica-se a tabela progressiva. Tabela, percentual, limite do MEI
+//! e valor do DAS vêm de `configuracoes` (o usuário pode sobrescrever as chaves globais), nunca do
+//! código: mudam todo ano.
+
+pub mod declaracao;
+
+use std::collections::HashMap;
+use axum::{Json, extract::{Path, Query}, http::{StatusCode, header}, response::Response};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+pub const CHAVE_TABELA: &str = "imposto_tabela_carne_leao";
+pub const CHAVE_DESCONTO_SIMPLIFICADO: &str = "imposto_desconto_simplificado_mensal";
+pub const CHAVE_PRESUNCAO: &str = "imposto_presuncao_transporte";
+pub const CHAVE_LIMITE_MEI: &str = "imposto_mei_limite_anual";
+pub const CHAVE_DAS_MEI: &str = "imposto_mei_das_mensal";
+/// Acima deste excesso sobre o limite o MEI é desenquadrado desde janeiro
+const TOLERANCIA_EXCESSO_MEI: f64 = 0.20;
+
+#[derive(Deserialize, Default)]
+pub struct ImpostoParams {
+    /// Mês (1 a 12) de abertura do MEI no ano, para o limite proporcional
+    pub inicio_atividade: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DeclaracaoParams {
+    /// "pdf" ou "xlsx"
+    pub formato: Option<String>,
+    pub inicio_atividade: Option<u32>,
+}
+
+/// Faixa da tabela como gravada na configuração (valores em reais)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct FaixaConfig {
+    /// Limite superior da base; vazio na última faixa
+    ate: Option<f64>,
+    aliquota: f64,
+    deducao: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Faixa {
+    /// Centavos
+    pub ate: Option<i64>,
+    pub aliquota: f64,
+    pub deducao: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ParametrosImposto {
+    pub faixas: Vec<Faixa>,
+    pub desconto_simplificado: i64,
+    /// Percentual tributável da receita de transporte
+    pub presuncao: f64,
+    pub limite_mei_anual: i64,
+    pub das_mei_mensal: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CalculoCarneLeao {
+    pub receita: i64,
+    /// Parte presumida como rendimento tributável
+    pub base_tributavel: i64,
+    pub desconto: i64,
+    pub base_calculo: i64,
+    pub aliquota: f64,
+    pub imposto: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MesImposto {
+    pub mes: u32,
+    #[serde(flatten)]
+    pub carne_leao: CalculoCarneLeao,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReceitaCategoria {
+    pub id_categoria: String,
+    pub nome: String,
+    pub valor: i64,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SituacaoMei {
+    Dentro,
+    /// Até 20% acima: paga DAS complementar sobre o excesso e sai no ano seguinte
+    ExcessoAte20,
+    /// Mais de 20% acima: desenquadramento retroativo a janeiro
+    ExcessoAcima20,
+}
+
+/// Campos da DASN-SIMEI
+#[derive(Serialize, Debug, Clone)]
+pub struct DasnSimei {
+    pub ano: i32,
+    pub receita_bruta_total: i64,
+    /// Comércio, indústria e transporte intermunicipal/interestadual (ICMS)
+    pub receita_icms: i64,
+    /// Prestação de serviços (ISS)
+    pub receita_servicos: i64,
+    pub possui_empregado: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ResumoMei {
+    pub meses_ativos: u32,
+    /// Limite do ano (proporcional aos meses de atividade)
+    pub limite: i64,
+    pub receita: i64,
+    pub percentual_utilizado: f64,
+    pub excesso: i64,
+    pub situacao: SituacaoMei,
+    pub das_anual: i64,
+    pub dasn: DasnSimei,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ResumoImposto {
+    pub ano: i32,
+    pub receita_total: i64,
+    pub meses: Vec<MesImposto>,
+    pub por_categoria: Vec<ReceitaCategoria>,
+    pub carne_leao_total: i64,
+    pub mei: ResumoMei,
+    pub parametros: ParametrosImposto,
+}
+
+#[derive(QueryableByName)]
+struct LinhaReceita {
+    #[diesel(sql_type = Integer)]
+    mes: i32,
+    #[diesel(sql_type = Text)]
+    id_categoria: String,
+    #[diesel(sql_type = Text)]
+    nome: String,
+    #[diesel(sql_type = BigInt)]
+    valor: i64,
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn centavos(reais: f64) -> i64 {
+    (reais * 100.0).round() as i64
+}
+
+/// Valor do usuário, ou o global
+fn config(conn: &mut PgConnection, id_usuario: &str, chave: &str) -> Option<String> {
+    use crate::schema::configuracoes::dsl as config_dsl;
+    config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario).or(config_dsl::id_usuario.is_null()))
+        .filter(config_dsl::chave.eq(chave))
+        .order(config_dsl::id_usuario.desc().nulls_last())
+        .select(config_dsl::valor)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+}
+
+fn numero(conn: &mut PgConnection, id_usuario: &str, chave: &str) -> Result<f64, String> {
+    config(conn, id_usuario, chave)
+        .and_then(|v| v.trim().replace(',', ".").parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .ok_or_else(|| format!("Configuração {chave} ausente ou inválida"))
+}
+
+/// Faixas em ordem crescente, só a última sem limite
+pub fn faixas_de_json(json: &str) -> Result<Vec<Faixa>, String> {
+    let faixas: Vec<FaixaConfig> = serde_json::from_str(json).map_err(|e| format!("Tabela do Carnê-Leão inválida: {e}"))?;
+    let valida = !faixas.is_empty()
+        && faixas.iter().take(faixas.len() - 1).all(|f| f.ate.is_some())
+        && faixas.last().is_some_and(|f| f.ate.is_none())
+        && faixas.windows(2).all(|w| w[0].ate < w[1].ate || w[1].ate.is_none())
+        && faixas.iter().all(|f| (0.0..=100.0).contains(&f.aliquota) && f.deducao >= 0.0);
+    if !valida {
+        return Err("Tabela do Carnê-Leão inválida: faixas devem ser crescentes e só a última sem limite".to_string());
+    }
+    Ok(faixas
+        .into_iter()
+        .map(|f| Faixa { ate: f.ate.map(centavos), aliquota: f.aliquota, deducao: centavos(f.deducao) })
+        .collect())
+}
+
+pub fn parametros(conn: &mut PgConnection, id_usuario: &str) -> Result<ParametrosImposto, String> {
+    let tabela = config(conn, id_usuario, CHAVE_TABELA).ok_or_else(|| format!("Configuração {CHAVE_TABELA} ausente"))?;
+    let presuncao = numero(conn, id_usuario, CHAVE_PRESUNCAO)?;
+    if presuncao > 100.0 {
+        return Err(format!("Configuração {CHAVE_PRESUNCAO} deve ser de 0 a 100"));
+    }
+    Ok(ParametrosImposto {
+        faixas: faixas_de_json(&tabela)?,
+        desconto_simplificado: centavos(numero(conn, id_usuario, CHAVE_DESCONTO_SIMPLIFICADO)?),
+        presuncao,
+        limite_mei_anual: centavos(numero(conn, id_usuario, CHAVE_LIMITE_MEI)?),
+        das_mei_mensal: centavos(numero(conn, id_usuario, CHAVE_DAS_MEI)?),
+    })
+}
+
+/// Imposto do mês sobre a receita bruta de transporte
+pub fn carne_leao(receita: i64, parametros: &ParametrosImposto) -> CalculoCarneLeao {
+    let base_tributavel = (receita.max(0) as f64 * parametros.presuncao / 100.0).round() as i64;
+    let desconto = parametros.desconto_simplificado.min(base_tributavel);
+    let base_calculo = base_tributavel - desconto;
+    let faixa = parametros
+        .faixas
+        .iter()
+        .find(|f| f.ate.is_none_or(|ate| base_calculo <= ate))
+        .or(parametros.faixas.last());
+    let (aliquota, imposto) = match faixa {
+        Some(f) => (f.aliquota, ((base_calculo as f64 * f.aliquota / 100.0).round() as i64 - f.deducao).max(0)),
+        None => (0.0, 0),
+    };
+    CalculoCarneLeao { receita, base_tributavel, desconto, base_calculo, aliquota, imposto }
+}
+
+pub fn situacao_mei(ano: i32, receita: i64, meses_ativos: u32, parametros: &ParametrosImposto) -> ResumoMei {
+    let limite = parametros.limite_mei_anual * meses_ativos as i64 / 12;
+    let excesso = (receita - limite).max(0);
+    let situacao = if excesso == 0 {
+        SituacaoMei::Dentro
+    } else if excesso as f64 <= limite as f64 * TOLERANCIA_EXCESSO_MEI {
+        SituacaoMei::ExcessoAte20
+    } else {
+        SituacaoMei::ExcessoAcima20
+    };
+    ResumoMei {
+        meses_ativos,
+        limite,
+        receita,
+        percentual_utilizado: if limite > 0 { (receita as f64 * 10_000.0 / limite as f64).round() / 100.0 } else { 0.0 },
+        excesso,
+        situacao,
+        das_anual: parametros.das_mei_mensal * meses_ativos as i64,
+        dasn: DasnSimei { ano, receita_bruta_total: receita, receita_icms: 0, receita_servicos: receita, possui_empregado: false },
+    }
+}
+
+/// Entradas do ano por mês e categoria
+fn receitas(conn: &mut PgConnection, id_usuario: &str, ano: i32) -> QueryResult<Vec<LinhaReceita>> {
+    let inicio = NaiveDate::from_ymd_opt(ano, 1, 1).unwrap_or_default().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let fim = NaiveDate::from_ymd_opt(ano + 1, 1, 1).unwrap_or_default().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    diesel::sql_query(
+        "SELECT EXTRACT(MONTH FROM t.data)::INT AS mes,
+                c.id AS id_categoria, c.nome,
+                SUM(COALESCE(d.valor, t.valor))::BIGINT AS valor
+         FROM transacoes t
+         LEFT JOIN transacoes_divisoes d ON d.id_transacao = t.id
+         JOIN categorias c ON c.id = COALESCE(d.id_categoria, t.id_categoria)
+         WHERE t.id_usuario = $1 AND t.excluido_em IS NULL AND t.tipo = 'entrada'
+           AND t.data >= $2 AND t.data < $3
+         GROUP BY 1, c.id, c.nome
+         ORDER BY 1, c.nome",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Timestamptz, _>(inicio)
+    .bind::<Timestamptz, _>(fim)
+    .load(conn)
+}
+
+pub fn resumo(conn: &mut PgConnection, id_usuario: &str, ano: i32, inicio_atividade: Option<u32>, agora: DateTime<Utc>) -> Result<ResumoImposto, (StatusCode, String)> {
+    if !(2000..=2100).contains(&ano) {
+        return Err((StatusCode::BAD_REQUEST, "Ano inválido".to_string()));
+    }
+    let primeiro_mes = inicio_atividade.unwrap_or(1);
+    if !(1..=12).contains(&primeiro_mes) {
+        return Err((StatusCode::BAD_REQUEST, "inicio_atividade deve ser um mês de 1 a 12".to_string()));
+    }
+    let parametros = parametros(conn, id_usuario).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let linhas = receitas(conn, id_usuario, ano).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut por_mes: HashMap<u32, i64> = HashMap::new();
+    let mut por_categoria: Vec<ReceitaCategoria> = Vec::new();
+    for linha in linhas {
+        *por_mes.entry(linha.mes as u32).or_default() += linha.valor;
+        match por_categoria.iter_mut().find(|c| c.id_categoria == linha.id_categoria) {
+            Some(c) => c.valor += linha.valor,
+            None => por_categoria.push(ReceitaCategoria { id_categoria: linha.id_categoria, nome: linha.nome, valor: linha.valor }),
+        }
+    }
+    por_categoria.sort_by_key(|c| std::cmp::Reverse(c.valor));
+
+    // no ano corrente só os meses já iniciados
+    let ultimo_mes = if ano == agora.year() { agora.month() } else { 12 };
+    let meses: Vec<MesImposto> = (1..=ultimo_mes)
+        .map(|mes| MesImposto { mes, carne_leao: carne_leao(por_mes.get(&mes).copied().unwrap_or(0), &parametros) })
+        .collect();
+    let receita_total: i64 = por_mes.values().sum();
+    Ok(ResumoImposto {
+        ano,
+        receita_total,
+        carne_leao_total: meses.iter().map(|m| m.carne_leao.imposto).sum(),
+        meses,
+        por_categoria,
+        mei: situacao_mei(ano, receita_total, 13 - primeiro_mes, &parametros),
+        parametros,
+    })
+}
+
+pub async fn resumo_imposto_handler(
+    jar: CookieJar,
+    Path(ano): Path<i32>,
+    Query(params): Query<ImpostoParams>,
+) -> Result<Json<ResumoImposto>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    resumo(conn, &id_usuario, ano, params.inicio_atividade, Utc::now()).map(Json)
+}
+
+/// Informe anual de rendimentos para o contador
+pub async fn declaracao_handler(
+    jar: CookieJar,
+    Path(ano): Path<i32>,
+    Query(params): Query<DeclaracaoParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let resumo = resumo(conn, &id_usuario, ano, params.inicio_atividade, Utc::now())?;
+    let contribuinte = {
+        use crate::schema::usuarios::dsl as u_dsl;
+        let (nome, documento) = u_dsl::usuarios
+            .filter(u_dsl::id.eq(&id_usuario))
+            .select((u_dsl::nome_completo, u_dsl::cpfcnpj))
+            .first::<(String, String)>(conn)
+            .unwrap_or_default();
+        declaracao::Contribuinte { nome, documento }
+    };
+    let (bytes, tipo, extensao) = match params.formato.as_deref().unwrap_or("pdf") {
+        "pdf" => (declaracao::gerar_pdf(&resumo, &contribuinte), "application/pdf", "pdf"),
+        "xlsx" => (
+            declaracao::gerar_xlsx(&resumo, &contribuinte),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "xlsx",
+        ),
+        _ => return Err((StatusCode::BAD_REQUEST, "Formato inválido: use pdf ou xlsx".to_string())),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, tipo)
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=informe-rendimentos-{ano}.{extensao}"))
+        .body(bytes.into())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parametros_2025() -> ParametrosImposto {
+        let tabela = r#"[{"ate":2428.80,"aliquota":0,"deducao":0},{"ate":2826.65,"aliquota":7.5,"deducao":182.16},
+            {"ate":3751.05,"aliquota":15,"deducao":394.16},{"ate":4664.68,"aliquota":22.5,"deducao":675.49},
+            {"ate":null,"aliquota":27.5,"deducao":908.73}]"#;
+        ParametrosImposto {
+            faixas: faixas_de_json(tabela).unwrap(),
+            desconto_simplificado: 60_720,
+            presuncao: 60.0,
+            limite_mei_anual: 8_100_000,
+            das_mei_mensal: 8_090,
+        }
+    }
+
+    #[test]
+    fn test_carne_leao() {
+        let p = parametros_2025();
+        // R$ 10.000 de receita: base 6.000, menos 607,20 = 5.392,80 a 27,5% - 908,73
+        let mes = carne_leao(1_000_000, &p);
+        assert_eq!(mes.base_tributavel, 600_000);
+        assert_eq!(mes.base_calculo, 539_280);
+        assert_eq!(mes.aliquota, 27.5);
+        assert_eq!(mes.imposto, 57_429);
+        // R$ 5.000: base 3.000 - 607,20 = 2.392,80, isento
+        assert_eq!(carne_leao(500_000, &p).imposto, 0);
+        assert_eq!(carne_leao(0, &p).desconto, 0);
+
+        assert!(faixas_de_json(r#"[{"ate":null,"aliquota":0,"deducao":0},{"ate":100,"aliquota":5,"deducao":0}]"#).is_err());
+        assert!(faixas_de_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_situacao_mei() {
+        let p = parametros_2025();
+        let dentro = situacao_mei(2025, 7_000_000, 12, &p);
+        assert_eq!(dentro.situacao, SituacaoMei::Dentro);
+        assert_eq!(dentro.das_anual, 97_080);
+        // aberto em julho: limite de 6 meses (R$ 40.500)
+        let proporcional = situacao_mei(2025, 4_500_000, 6, &p);
+        assert_eq!(proporcional.limite, 4_050_000);
+        assert_eq!(proporcional.excesso, 450_000);
+        assert_eq!(proporcional.situacao, SituacaoMei::ExcessoAte20);
+        assert_eq!(situacao_mei(2025, 9_800_000, 12, &p).situacao, SituacaoMei::ExcessoAcima20);
+    }
+}
@@ -0,0 +1,486 @@
+//! Lixeira de transações, sessões, metas e categorias
+//!
+//! Excluir só preenche `excluido_em`; toda leitura dessas tabelas filtra `excluido_em IS NULL`.
+//! Daqui o item volta (restaurar) ou sai de vez (expurgar). Passados `LIXEIRA_RETENCAO_DIAS`
+//! (padrão 30) a rotina diária expurga sozinha.
+//!
+//! Categoria e transações excluídas juntas guardam o mesmo instante: restaurar a categoria traz
+//! de volta essas transações, e expurgá-la apaga as transações dela que estiverem na lixeira.
+
+use std::time::Duration as StdDuration;
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::db;
+use crate::models::{Categoria, Comprovante, Meta, SessaoTrabalho, Transacao};
+use crate::schema::categorias::dsl as c_dsl;
+use crate::schema::metas::dsl as m_dsl;
+use crate::schema::sessoes_trabalho::dsl as s_dsl;
+use crate::schema::transacoes::dsl as t_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::comprovante;
+use crate::services::envelope;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
+
+const RETENCAO_DIAS_PADRAO: i64 = 30;
+/// Itens de cada tipo devolvidos na listagem (os excluídos mais recentemente)
+const MAX_POR_TIPO: i64 = 500;
+const INTERVALO_EXPURGO: StdDuration = StdDuration::from_secs(24 * 60 * 60);
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoItem {
+    Transacao,
+    Sessao,
+    Meta,
+    Categoria,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ItemLixeira {
+    pub tipo: TipoItem,
+    pub id: String,
+    pub titulo: String,
+    /// Centavos: valor da transação, ganhos da sessão ou alvo da meta
+    pub valor: Option<i32>,
+    pub data: Option<DateTime<Utc>>,
+    pub excluido_em: DateTime<Utc>,
+    /// Quando o expurgo automático apaga o item
+    pub expira_em: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResultadoLixeira {
+    /// Itens restaurados ou apagados (inclui as transações que acompanham a categoria)
+    pub quantidade: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ListarLixeiraQuery {
+    pub tipo: Option<TipoItem>,
+}
+
+pub fn retencao_dias() -> i64 {
+    std::env::var("LIXEIRA_RETENCAO_DIAS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|d| *d > 0)
+        .unwrap_or(RETENCAO_DIAS_PADRAO)
+}
+
+pub fn expira_em(excluido_em: DateTime<Utc>, dias: i64) -> DateTime<Utc> {
+    excluido_em + Duration::days(dias)
+}
+
+enum ErroLixeira {
+    Banco(diesel::result::Error),
+    Http(StatusCode, String),
+}
+
+impl From<diesel::result::Error> for ErroLixeira {
+    fn from(e: diesel::result::Error) -> Self {
+        ErroLixeira::Banco(e)
+    }
+}
+
+impl From<ErroLixeira> for (StatusCode, String) {
+    fn from(e: ErroLixeira) -> Self {
+        match e {
+            ErroLixeira::Banco(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            ErroLixeira::Http(status, msg) => (status, msg),
+        }
+    }
+}
+
+fn nao_encontrado() -> ErroLixeira {
+    ErroLixeira::Http(StatusCode::NOT_FOUND, "Item não encontrado na lixeira".to_string())
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+/// (id, título, valor, data, excluído em)
+type Linha<Titulo> = (String, Titulo, i32, DateTime<Utc>, Option<DateTime<Utc>>);
+
+fn listar(conn: &mut PgConnection, id_usuario: &str, tipo: Option<TipoItem>) -> QueryResult<Vec<ItemLixeira>> {
+    let dias = retencao_dias();
+    let item = |tipo, id, titulo, valor, data, excluido: Option<DateTime<Utc>>| {
+        let excluido_em = excluido.unwrap_or_else(Utc::now);
+        ItemLixeira { tipo, id, titulo, valor, data, excluido_em, expira_em: expira_em(excluido_em, dias) }
+    };
+    let quer = |t: TipoItem| tipo.is_none_or(|pedido| pedido == t);
+    let mut itens = Vec::new();
+
+    if quer(TipoItem::Transacao) {
+        let linhas: Vec<Linha<Option<String>>> = t_dsl::transacoes
+            .filter(t_dsl::id_usuario.eq(id_usuario))
+            .filter(t_dsl::excluido_em.is_not_null())
+            .order(t_dsl::excluido_em.desc())
+            .limit(MAX_POR_TIPO)
+            .select((t_dsl::id, t_dsl::descricao, t_dsl::valor, t_dsl::data, t_dsl::excluido_em))
+            .load(conn)?;
+        itens.extend(linhas.into_iter().map(|(id, descricao, valor, data, excluido)| {
+            item(TipoItem::Transacao, id, descricao.unwrap_or_else(|| "Transação".to_string()), Some(valor), Some(data), excluido)
+        }));
+    }
+    if quer(TipoItem::Sessao) {
+        let linhas: Vec<Linha<Option<String>>> = s_dsl::sessoes_trabalho
+            .filter(s_dsl::id_usuario.eq(id_usuario))
+            .filter(s_dsl::excluido_em.is_not_null())
+            .order(s_dsl::excluido_em.desc())
+            .limit(MAX_POR_TIPO)
+            .select((s_dsl::id, s_dsl::plataforma, s_dsl::total_ganhos, s_dsl::inicio, s_dsl::excluido_em))
+            .load(conn)?;
+        itens.extend(linhas.into_iter().map(|(id, plataforma, ganhos, inicio, excluido)| {
+            item(TipoItem::Sessao, id, plataforma.unwrap_or_else(|| "Sessão de trabalho".to_string()), Some(ganhos), Some(inicio), excluido)
+        }));
+    }
+    if quer(TipoItem::Meta) {
+        let linhas: Vec<Linha<String>> = m_dsl::metas
+            .filter(m_dsl::id_usuario.eq(id_usuario))
+            .filter(m_dsl::excluido_em.is_not_null())
+            .order(m_dsl::excluido_em.desc())
+            .limit(MAX_POR_TIPO)
+            .select((m_dsl::id, m_dsl::titulo, m_dsl::valor_alvo, m_dsl::data_inicio, m_dsl::excluido_em))
+            .load(conn)?;
+        itens.extend(linhas.into_iter().map(|(id, titulo, alvo, inicio, excluido)| {
+            item(TipoItem::Meta, id, titulo, Some(alvo), Some(inicio), excluido)
+        }));
+    }
+    if quer(TipoItem::Categoria) {
+        let linhas: Vec<(String, String, Option<DateTime<Utc>>)> = c_dsl::categorias
+            .filter(c_dsl::id_usuario.eq(id_usuario))
+            .filter(c_dsl::excluido_em.is_not_null())
+            .order(c_dsl::excluido_em.desc())
+            .limit(MAX_POR_TIPO)
+            .select((c_dsl::id, c_dsl::nome, c_dsl::excluido_em))
+            .load(conn)?;
+        itens.extend(linhas.into_iter().map(|(id, nome, excluido)| item(TipoItem::Categoria, id, nome, None, None, excluido)));
+    }
+
+    itens.sort_by_key(|i| std::cmp::Reverse(i.excluido_em));
+    Ok(itens)
+}
+
+/// Devolve o item à lista ativa; transação volta com a categoria, categoria com as transações
+fn restaurar(conn: &mut PgConnection, id_usuario: &str, tipo: TipoItem, id: &str, origem: &Origem) -> Result<usize, ErroLixeira> {
+    let sem_exclusao: Option<DateTime<Utc>> = None;
+    let quantidade = match tipo {
+        TipoItem::Transacao => {
+            let alvo = t_dsl::transacoes
+                .filter(t_dsl::id.eq(id))
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.is_not_null());
+            let transacao: Transacao = alvo.first(conn).optional()?.ok_or_else(nao_encontrado)?;
+            diesel::update(alvo).set(t_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+            let restaurada = Transacao { excluido_em: None, ..transacao.clone() };
+            historico::registrar(conn, origem, Acao::Restauracao, Some(&transacao), Some(&restaurada))?;
+            envelope::aplicar_regras(conn, Some(&transacao), Some(&restaurada))?;
+            let alvo_categoria = c_dsl::categorias.filter(c_dsl::id.eq(&transacao.id_categoria)).filter(c_dsl::excluido_em.is_not_null());
+            match alvo_categoria.first::<Categoria>(conn).optional()? {
+                Some(categoria) => {
+                    diesel::update(alvo_categoria).set(c_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+                    let restaurada = Categoria { excluido_em: None, ..categoria.clone() };
+                    historico::registrar(conn, origem, Acao::Restauracao, Some(&categoria), Some(&restaurada))?;
+                    2
+                }
+                None => 1,
+            }
+        }
+        TipoItem::Sessao => {
+            let alvo = s_dsl::sessoes_trabalho
+                .filter(s_dsl::id.eq(id))
+                .filter(s_dsl::id_usuario.eq(id_usuario))
+                .filter(s_dsl::excluido_em.is_not_null());
+            let sessao: SessaoTrabalho = alvo.first(conn).optional()?.ok_or_else(nao_encontrado)?;
+            diesel::update(alvo).set(s_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+            let restaurada = SessaoTrabalho { excluido_em: None, ..sessao.clone() };
+            historico::registrar(conn, origem, Acao::Restauracao, Some(&sessao), Some(&restaurada))?;
+            1
+        }
+        TipoItem::Meta => {
+            let alvo = m_dsl::metas
+                .filter(m_dsl::id.eq(id))
+                .filter(m_dsl::id_usuario.eq(id_usuario))
+                .filter(m_dsl::excluido_em.is_not_null());
+            let meta: Meta = alvo.first(conn).optional()?.ok_or_else(nao_encontrado)?;
+            diesel::update(alvo).set(m_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+            let restaurada = Meta { excluido_em: None, ..meta.clone() };
+            historico::registrar(conn, origem, Acao::Restauracao, Some(&meta), Some(&restaurada))?;
+            1
+        }
+        TipoItem::Categoria => {
+            let alvo = c_dsl::categorias
+                .filter(c_dsl::id.eq(id))
+                .filter(c_dsl::id_usuario.eq(id_usuario))
+                .filter(c_dsl::excluido_em.is_not_null());
+            let categoria: Categoria = alvo.first(conn).optional()?.ok_or_else(nao_encontrado)?;
+            diesel::update(alvo).set(c_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+            let restaurada = Categoria { excluido_em: None, ..categoria.clone() };
+            historico::registrar(conn, origem, Acao::Restauracao, Some(&categoria), Some(&restaurada))?;
+            let junto = t_dsl::transacoes
+                .filter(t_dsl::id_categoria.eq(id))
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.eq(categoria.excluido_em));
+            let transacoes: Vec<Transacao> = junto.load(conn)?;
+            diesel::update(junto).set(t_dsl::excluido_em.eq(sem_exclusao)).execute(conn)?;
+            let restauradas: Vec<Transacao> = transacoes.iter().map(|t| Transacao { excluido_em: None, ..t.clone() }).collect();
+            historico::registrar_lote(conn, origem, Acao::Restauracao, &transacoes, &restauradas)?;
+            envelope::aplicar_regras_lote(conn, &transacoes, &restauradas)?;
+            1 + transacoes.len()
+        }
+    };
+    Ok(quantidade)
+}
+
+/// Apaga de vez as transações (só as que estão na lixeira); devolve os comprovantes a remover do disco
+fn expurgar_transacoes(conn: &mut PgConnection, ids: &[String], origem: &Origem) -> QueryResult<(usize, Vec<Comprovante>)> {
+    if ids.is_empty() {
+        return Ok((0, Vec::new()));
+    }
+    let alvo = t_dsl::transacoes.filter(t_dsl::id.eq_any(ids)).filter(t_dsl::excluido_em.is_not_null());
+    let apagar: Vec<Transacao> = alvo.clone().load(conn)?;
+    let comprovantes = comprovante::das_transacoes(conn, ids)?;
+    // tags, divisões, comprovantes e alertas saem em cascata
+    let apagadas = diesel::delete(alvo).execute(conn)?;
+    historico::registrar_lote(conn, origem, Acao::Expurgo, &apagar, &[])?;
+    Ok((apagadas, comprovantes))
+}
+
+/// Transações vinculadas ficam, só perdem o vínculo (ON DELETE SET NULL)
+fn expurgar_sessoes(conn: &mut PgConnection, ids: &[String], origem: &Origem) -> QueryResult<usize> {
+    let alvo = s_dsl::sessoes_trabalho.filter(s_dsl::id.eq_any(ids)).filter(s_dsl::excluido_em.is_not_null());
+    let apagar: Vec<SessaoTrabalho> = alvo.clone().load(conn)?;
+    let apagadas = diesel::delete(alvo).execute(conn)?;
+    historico::registrar_lote(conn, origem, Acao::Expurgo, &apagar, &[])?;
+    Ok(apagadas)
+}
+
+fn expurgar_metas(conn: &mut PgConnection, ids: &[String], origem: &Origem) -> QueryResult<usize> {
+    let alvo = m_dsl::metas.filter(m_dsl::id.eq_any(ids)).filter(m_dsl::excluido_em.is_not_null());
+    let apagar: Vec<Meta> = alvo.clone().load(conn)?;
+    let apagadas = diesel::delete(alvo).execute(conn)?;
+    historico::registrar_lote(conn, origem, Acao::Expurgo, &apagar, &[])?;
+    Ok(apagadas)
+}
+
+/// Apaga as categorias na lixeira que não são mais usadas por nenhuma transação ou divisão.
+/// (A FK de transações para categorias é ON DELETE CASCADE: apagar uma categoria em uso
+/// levaria transações ativas junto.)
+fn expurgar_categorias(conn: &mut PgConnection, ids: &[String], origem: &Origem) -> QueryResult<usize> {
+    use crate::schema::transacoes_divisoes::dsl as div_dsl;
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let em_uso: Vec<String> = t_dsl::transacoes
+        .filter(t_dsl::id_categoria.eq_any(ids))
+        .select(t_dsl::id_categoria)
+        .distinct()
+        .load(conn)?;
+    let em_divisoes: Vec<String> = div_dsl::transacoes_divisoes
+        .filter(div_dsl::id_categoria.eq_any(ids))
+        .select(div_dsl::id_categoria)
+        .distinct()
+        .load(conn)?;
+    let livres: Vec<&String> = ids.iter().filter(|id| !em_uso.contains(id) && !em_divisoes.contains(id)).collect();
+    let alvo = c_dsl::categorias.filter(c_dsl::id.eq_any(livres)).filter(c_dsl::excluido_em.is_not_null());
+    let apagar: Vec<Categoria> = alvo.clone().load(conn)?;
+    let apagadas = diesel::delete(alvo).execute(conn)?;
+    historico::registrar_lote(conn, origem, Acao::Expurgo, &apagar, &[])?;
+    Ok(apagadas)
+}
+
+fn expurgar(
+    conn: &mut PgConnection,
+    id_usuario: &str,
+    tipo: TipoItem,
+    id: &str,
+    origem: &Origem,
+) -> Result<(usize, Vec<Comprovante>), ErroLixeira> {
+    let resultado = match tipo {
+        TipoItem::Transacao => {
+            let ids: Vec<String> = t_dsl::transacoes
+                .filter(t_dsl::id.eq(id))
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.is_not_null())
+                .select(t_dsl::id)
+                .load(conn)?;
+            expurgar_transacoes(conn, &ids, origem)?
+        }
+        TipoItem::Sessao => {
+            let ids: Vec<String> = s_dsl::sessoes_trabalho
+                .filter(s_dsl::id.eq(id))
+                .filter(s_dsl::id_usuario.eq(id_usuario))
+                .select(s_dsl::id)
+                .load(conn)?;
+            (expurgar_sessoes(conn, &ids, origem)?, Vec::new())
+        }
+        TipoItem::Meta => {
+            let ids: Vec<String> = m_dsl::metas
+                .filter(m_dsl::id.eq(id))
+                .filter(m_dsl::id_usuario.eq(id_usuario))
+                .select(m_dsl::id)
+                .load(conn)?;
+            (expurgar_metas(conn, &ids, origem)?, Vec::new())
+        }
+        TipoItem::Categoria => {
+            let existe: bool = diesel::select(diesel::dsl::exists(
+                c_dsl::categorias
+                    .filter(c_dsl::id.eq(id))
+                    .filter(c_dsl::id_usuario.eq(id_usuario))
+                    .filter(c_dsl::excluido_em.is_not_null()),
+            ))
+            .get_result(conn)?;
+            if !existe {
+                return Err(nao_encontrado());
+            }
+            let na_lixeira: Vec<String> = t_dsl::transacoes
+                .filter(t_dsl::id_categoria.eq(id))
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.is_not_null())
+                .select(t_dsl::id)
+                .load(conn)?;
+            let (transacoes, comprovantes) = expurgar_transacoes(conn, &na_lixeira, origem)?;
+            if expurgar_categorias(conn, &[id.to_string()], origem)? == 0 {
+                return Err(ErroLixeira::Http(
+                    StatusCode::CONFLICT,
+                    "A categoria ainda é usada por transações ativas; restaure-a ou mova as transações".to_string(),
+                ));
+            }
+            (transacoes + 1, comprovantes)
+        }
+    };
+    if resultado.0 == 0 {
+        return Err(nao_encontrado());
+    }
+    Ok(resultado)
+}
+
+/// Expurga tudo o que está na lixeira do usuário (`Some`) ou de todos, excluído antes de `antes_de`
+pub fn esvaziar(
+    conn: &mut PgConnection,
+    id_usuario: Option<&str>,
+    antes_de: Option<DateTime<Utc>>,
+    origem: &Origem,
+) -> QueryResult<(usize, Vec<Comprovante>)> {
+    conn.transaction(|conn| {
+        let mut q_t = t_dsl::transacoes.filter(t_dsl::excluido_em.is_not_null()).select(t_dsl::id).into_boxed();
+        let mut q_s = s_dsl::sessoes_trabalho.filter(s_dsl::excluido_em.is_not_null()).select(s_dsl::id).into_boxed();
+        let mut q_m = m_dsl::metas.filter(m_dsl::excluido_em.is_not_null()).select(m_dsl::id).into_boxed();
+        let mut q_c = c_dsl::categorias.filter(c_dsl::excluido_em.is_not_null()).select(c_dsl::id).into_boxed();
+        if let Some(u) = id_usuario {
+            q_t = q_t.filter(t_dsl::id_usuario.eq(u));
+            q_s = q_s.filter(s_dsl::id_usuario.eq(u));
+            q_m = q_m.filter(m_dsl::id_usuario.eq(u));
+            q_c = q_c.filter(c_dsl::id_usuario.eq(u));
+        }
+        if let Some(limite) = antes_de {
+            q_t = q_t.filter(t_dsl::excluido_em.lt(limite));
+            q_s = q_s.filter(s_dsl::excluido_em.lt(limite));
+            q_m = q_m.filter(m_dsl::excluido_em.lt(limite));
+            q_c = q_c.filter(c_dsl::excluido_em.lt(limite));
+        }
+
+        let ids_transacoes: Vec<String> = q_t.load(conn)?;
+        let ids_sessoes: Vec<String> = q_s.load(conn)?;
+        let ids_metas: Vec<String> = q_m.load(conn)?;
+        let ids_categorias: Vec<String> = q_c.load(conn)?;
+
+        let (transacoes, comprovantes) = expurgar_transacoes(conn, &ids_transacoes, origem)?;
+        let sessoes = expurgar_sessoes(conn, &ids_sessoes, origem)?;
+        let metas = expurgar_metas(conn, &ids_metas, origem)?;
+        let categorias = expurgar_categorias(conn, &ids_categorias, origem)?;
+        Ok((transacoes + sessoes + metas + categorias, comprovantes))
+    })
+}
+
+pub async fn listar_lixeira_handler(
+    jar: CookieJar,
+    Query(q): Query<ListarLixeiraQuery>,
+) -> Result<Json<Vec<ItemLixeira>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    listar(conn, &id_usuario, q.tipo)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn restaurar_item_handler(
+    jar: CookieJar,
+    Path((tipo, id)): Path<(TipoItem, String)>,
+) -> Result<Json<ResultadoLixeira>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let origem = Origem::usuario(&id_usuario, "POST /api/lixeira/{tipo}/{id}/restaurar");
+    let quantidade = conn.transaction::<_, ErroLixeira, _>(|conn| restaurar(conn, &id_usuario, tipo, &id, &origem))?;
+    eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+    if matches!(tipo, TipoItem::Transacao | TipoItem::Categoria) {
+        crate::services::anomalia::agendar_deteccao(id_usuario);
+    }
+    Ok(Json(ResultadoLixeira { quantidade }))
+}
+
+pub async fn expurgar_item_handler(
+    jar: CookieJar,
+    Path((tipo, id)): Path<(TipoItem, String)>,
+) -> Result<Json<ResultadoLixeira>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let origem = Origem::usuario(&id_usuario, "DELETE /api/lixeira/{tipo}/{id}");
+    let (quantidade, comprovantes) = conn.transaction::<_, ErroLixeira, _>(|conn| expurgar(conn, &id_usuario, tipo, &id, &origem))?;
+    // itens da lixeira já não aparecem nos agregados: não há cache a invalidar
+    comprovante::remover_arquivos(&comprovantes).await;
+    Ok(Json(ResultadoLixeira { quantidade }))
+}
+
+pub async fn esvaziar_lixeira_handler(jar: CookieJar) -> Result<Json<ResultadoLixeira>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let (quantidade, comprovantes) =
+        esvaziar(conn, Some(&id_usuario), None, &Origem::usuario(&id_usuario, "DELETE /api/lixeira")).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    comprovante::remover_arquivos(&comprovantes).await;
+    Ok(Json(ResultadoLixeira { quantidade }))
+}
+
+/// Expurgo dos itens vencidos na subida do servidor e depois uma vez por dia
+pub fn agendar_expurgo() {
+    tokio::spawn(async {
+        loop {
+            let resultado = tokio::task::spawn_blocking(|| {
+                let conn = &mut db::establish_connection();
+                let origem = Origem::sistema("expurgo automático da lixeira");
+                esvaziar(conn, None, Some(Utc::now() - Duration::days(retencao_dias())), &origem)
+            })
+            .await;
+            match resultado {
+                Ok(Ok((n, comprovantes))) if n > 0 => {
+                    comprovante::remover_arquivos(&comprovantes).await;
+                    info!("{} item(ns) expurgado(s) da lixeira", n);
+                }
+                Ok(Err(e)) => warn!("Falha no expurgo da lixeira: {}", e),
+                _ => {}
+            }
+            tokio::time::sleep(INTERVALO_EXPURGO).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tipo_item_e_expiracao() {
+        let tipo: TipoItem = serde_json::from_str(r#""categoria""#).unwrap();
+        assert_eq!(tipo, TipoItem::Categoria);
+        assert!(serde_json::from_str::<TipoItem>(r#""usuario""#).is_err());
+
+        let excluido = DateTime::parse_from_rfc3339("2025-09-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(expira_em(excluido, 30).to_rfc3339(), "2025-10-01T12:00:00+00:00");
+    }
+}
@@ -33,6 +33,7 @@ pub fn buscar_metas_ativas_com_transacoes(conn: &mut PgConnection, usuario_id: &
     // Busca todas as metas ativas do usuário
     let metas_ativas: Vec<Meta> = metas_dsl::metas
         .filter(metas_dsl::id_usuario.eq(usuario_id))
+        .filter(metas_dsl::excluido_em.is_null())
         .filter(metas_dsl::eh_ativa.eq(true))
         .load::<Meta>(conn)?;
 
@@ -64,6 +65,7 @@ pub fn buscar_metas_ativas_com_transacoes(conn: &mut PgConnection, usuario_id: &
     // Busca todas as transações do usuário que estejam no intervalo de qualquer meta ativa
     let mut query = trans_dsl::transacoes
         .filter(trans_dsl::id_usuario.eq(usuario_id))
+        .filter(trans_dsl::excluido_em.is_null())
         .into_boxed();
     if let Some(min_inicio) = min_inicio {
         query = query.filter(trans_dsl::data.ge(min_inicio));
@@ -27,6 +27,8 @@ use crate::schema::metas;
 use crate::schema::metas::dsl::*;
 use diesel::{QueryDsl, RunQueryDsl, ExpressionMethods};
 use crate::models::{Meta, NewMeta};
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
@@ -72,6 +74,7 @@ pub async fn list_metas_a_cumprir_handler(jar: CookieJar) -> Json<Vec<Meta>> {
     let user_id = extract_user_id_from_cookie(&jar).expect("Usuário não autenticado");
     let results = metas
         .filter(id_usuario.eq(user_id))
+        .filter(excluido_em.is_null())
         .filter(eh_ativa.eq(true))
         .order(data_inicio.desc())
         .load::<Meta>(conn)
@@ -83,6 +86,7 @@ pub async fn list_metas_cumpridas_handler(Path(id_usuario_param): Path<String>)
     let conn = &mut db::establish_connection();
     let results = metas
         .filter(id_usuario.eq(id_usuario_param))
+        .filter(excluido_em.is_null())
         .filter(eh_ativa.eq(false))
         .order(data_inicio.desc())
         .load::<Meta>(conn)
@@ -123,12 +127,14 @@ pub async fn create_meta_handler(jar: CookieJar, Json(payload): Json<CreateMetaP
         .filter(id.eq(&nova_meta.id))
         .first::<Meta>(conn)
         .expect("Meta não encontrada após inserção");
+    historico::anotar(conn, &Origem::usuario(&user_id, "POST /api/meta"), Acao::Criacao, None, Some(&meta));
+    eventos::invalidar_e_publicar(&user_id, EventoUsuario::DadosAlterados).await;
     Json(meta)
 }
 
 pub async fn get_meta_handler(Path(id_param): Path<String>) -> Json<Option<Meta>> {
     let conn = &mut db::establish_connection();
-    match metas.filter(id.eq(id_param)).first::<Meta>(conn) {
+    match metas.filter(id.eq(id_param)).filter(excluido_em.is_null()).first::<Meta>(conn) {
         Ok(m) => Json(Some(m)),
         Err(_) => Json(None),
     }
@@ -138,6 +144,7 @@ pub async fn list_metas_handler(Path(id_usuario_param): Path<String>) -> Json<Ve
     let conn = &mut db::establish_connection();
     let results = metas
         .filter(id_usuario.eq(id_usuario_param))
+        .filter(excluido_em.is_null())
         .order(data_inicio.desc())
         .load::<Meta>(conn)
         .unwrap_or_default();
@@ -146,6 +153,7 @@ pub async fn list_metas_handler(Path(id_usuario_param): Path<String>) -> Json<Ve
 
 pub async fn update_meta_handler(Path(id_param): Path<String>, Json(payload): Json<UpdateMetaPayload>) -> Json<Option<Meta>> {
     let conn = &mut db::establish_connection();
+    let anterior = metas.filter(id.eq(&id_param)).filter(excluido_em.is_null()).first::<Meta>(conn).ok();
     let changeset = MetaChangeset {
         titulo: payload.titulo,
         descricao: payload.descricao,
@@ -163,12 +171,18 @@ pub async fn update_meta_handler(Path(id_param): Path<String>, Json(payload): Js
         atualizado_em: Some(chrono::Utc::now()),
         concluida_com: payload.concluida_com,
     };
-    diesel::update(metas.filter(id.eq(&id_param)))
+    diesel::update(metas.filter(id.eq(&id_param)).filter(excluido_em.is_null()))
         .set(changeset)
         .execute(conn)
         .ok();
-    match metas.filter(id.eq(id_param)).first::<Meta>(conn) {
-        Ok(m) => Json(Some(m)),
+    match metas.filter(id.eq(id_param)).filter(excluido_em.is_null()).first::<Meta>(conn) {
+        Ok(m) => {
+            historico::anotar(conn, &Origem::usuario(&m.id_usuario, "PUT /api/meta/{id}"), Acao::Atualizacao, anterior.as_ref(), Some(&m));
+            let concluiu_agora = m.eh_concluida && anterior.is_some_and(|a| !a.eh_concluida);
+            let evento = if concluiu_agora { EventoUsuario::MetaConcluida { meta: m.clone() } } else { EventoUsuario::DadosAlterados };
+            eventos::invalidar_e_publicar(&m.id_usuario, evento).await;
+            Json(Some(m))
+        }
         Err(_) => Json(None),
     }
 }
@@ -176,9 +190,20 @@ pub async fn update_meta_handler(Path(id_param): Path<String>, Json(payload): Js
 pub async fn delete_meta_handler(jar: CookieJar, Path(id_param): Path<String>) -> Json<bool> {
     let conn = &mut db::establish_connection();
     let user_id = extract_user_id_from_cookie(&jar).expect("Usuário não autenticado");
-    // Só deleta se a meta for do usuário autenticado
-    let count = diesel::delete(metas.filter(id.eq(&id_param)).filter(id_usuario.eq(&user_id)))
+    // Só manda para a lixeira se a meta for do usuário autenticado
+    let alvo = metas.filter(id.eq(&id_param)).filter(id_usuario.eq(&user_id)).filter(excluido_em.is_null());
+    let antes: Option<Meta> = alvo.first(conn).ok();
+    let agora = chrono::Utc::now();
+    let count = diesel::update(alvo)
+        .set(excluido_em.eq(Some(agora)))
         .execute(conn)
         .unwrap_or(0);
+    if let (Some(antes), true) = (&antes, count > 0) {
+        let depois = Meta { excluido_em: Some(agora), ..antes.clone() };
+        historico::anotar(conn, &Origem::usuario(&user_id, "DELETE /api/meta/{id}"), Acao::Exclusao, Some(antes), Some(&depois));
+    }
+    if count > 0 {
+        eventos::invalidar_e_publicar(&user_id, EventoUsuario::DadosAlterados).await;
+    }
     Json(count > 0)
 }
@@ -12,6 +12,22 @@ pub mod assinatura;
 pub mod usuario;
 pub mod sessao_trabalho;
 pub mod admin;
+pub mod analytics;
 
 
 
+pub mod simulacao;
+pub mod anomalia;
+pub mod eventos;
+pub mod busca;
+pub mod tag;
+pub mod comprovante;
+pub mod lixeira;
+pub mod historico;
+pub mod carteira;
+pub mod repasse;
+pub mod divida;
+pub mod envelope;
+pub mod imposto;
+pub mod fadiga;
+pub mod corrida;
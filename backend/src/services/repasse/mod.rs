@@ -0,0 +1,385 @@
+//! Repasses das plataformas e conciliação com as corridas registradas
+//!
+//! O repasse é o que a plataforma pagou por um período (em geral a semana). A conciliação soma as
+//! transações da categoria da plataforma no período (entradas menos saídas, contando as linhas de
+//! transações divididas) e compara com o valor recebido. A diferença pode virar uma transação de
+//! ajuste, que fica vinculada ao repasse e entra na conta das conciliações seguintes.
+
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::{NewTransacao, Repasse, Transacao};
+use crate::schema::repasses::dsl as r_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::historico::{self, Acao, Origem};
+use crate::services::eventos::{self, EventoUsuario};
+
+#[derive(Deserialize)]
+pub struct RepassePayload {
+    pub plataforma: String,
+    pub id_categoria: String,
+    pub periodo_inicio: DateTime<Utc>,
+    pub periodo_fim: DateTime<Utc>,
+    /// Centavos recebidos
+    pub valor: i32,
+    pub data_recebimento: Option<DateTime<Utc>>,
+    pub descricao: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RepassesParams {
+    pub plataforma: Option<String>,
+    /// Repasses cujo período cruza o intervalo
+    pub data_inicio: Option<DateTime<Utc>>,
+    pub data_fim: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct AjustePayload {
+    pub descricao: Option<String>,
+    pub id_carteira: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusConciliacao {
+    /// Registrado bate com o recebido
+    Conferido,
+    /// Bate graças à transação de ajuste
+    Ajustado,
+    Divergente,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConciliacaoRepasse {
+    pub repasse: Repasse,
+    /// Soma das transações da categoria no período, sem o ajuste (centavos)
+    pub registrado: i64,
+    pub quantidade_transacoes: i64,
+    /// Valor com sinal da transação de ajuste ativa (0 sem ajuste)
+    pub ajuste: i64,
+    /// Recebido menos registrado menos ajuste: positivo é corrida sem lançamento
+    pub diferenca: i64,
+    pub status: StatusConciliacao,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RelatorioConciliacao {
+    pub itens: Vec<ConciliacaoRepasse>,
+    pub total_recebido: i64,
+    pub total_registrado: i64,
+    pub total_diferenca: i64,
+    pub divergentes: usize,
+}
+
+pub fn diferenca(recebido: i64, registrado: i64, ajuste: i64) -> (i64, StatusConciliacao) {
+    let diferenca = recebido - registrado - ajuste;
+    let status = match (diferenca, ajuste) {
+        (0, 0) => StatusConciliacao::Conferido,
+        (0, _) => StatusConciliacao::Ajustado,
+        _ => StatusConciliacao::Divergente,
+    };
+    (diferenca, status)
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn nao_encontrado() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Repasse não encontrado".to_string())
+}
+
+fn periodo_duplicado(e: diesel::result::Error) -> (StatusCode, String) {
+    match e {
+        diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) =>
+            (StatusCode::CONFLICT, "Já existe repasse desta plataforma para o período".to_string()),
+        e => erro_interno(e),
+    }
+}
+
+pub fn validar(payload: &RepassePayload) -> Result<(), String> {
+    if payload.plataforma.trim().is_empty() {
+        return Err("Informe a plataforma".to_string());
+    }
+    if payload.periodo_fim <= payload.periodo_inicio {
+        return Err("O fim do período deve ser depois do início".to_string());
+    }
+    if payload.valor < 0 {
+        return Err("O valor do repasse não pode ser negativo".to_string());
+    }
+    Ok(())
+}
+
+/// Categoria ativa do usuário (ou global)
+fn categoria_valida(conn: &mut PgConnection, id_usuario: &str, id_categoria: &str) -> QueryResult<bool> {
+    use crate::schema::categorias::dsl as cat_dsl;
+    diesel::select(diesel::dsl::exists(
+        cat_dsl::categorias
+            .filter(cat_dsl::id.eq(id_categoria))
+            .filter(cat_dsl::id_usuario.eq(id_usuario).or(cat_dsl::id_usuario.is_null()))
+            .filter(cat_dsl::excluido_em.is_null()),
+    ))
+    .get_result(conn)
+}
+
+fn buscar(conn: &mut PgConnection, id_usuario: &str, id_repasse: &str) -> QueryResult<Option<Repasse>> {
+    r_dsl::repasses
+        .filter(r_dsl::id.eq(id_repasse))
+        .filter(r_dsl::id_usuario.eq(id_usuario))
+        .first(conn)
+        .optional()
+}
+
+#[derive(QueryableByName)]
+struct Somas {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = BigInt)]
+    registrado: i64,
+    #[diesel(sql_type = BigInt)]
+    quantidade: i64,
+    #[diesel(sql_type = BigInt)]
+    ajuste: i64,
+}
+
+/// Repasses do usuário que cruzam o intervalo, com a conciliação de cada um
+pub fn conciliar(conn: &mut PgConnection, id_usuario: &str, params: &RepassesParams) -> QueryResult<Vec<ConciliacaoRepasse>> {
+    let mut query = r_dsl::repasses.filter(r_dsl::id_usuario.eq(id_usuario)).into_boxed();
+    if let Some(plataforma) = params.plataforma.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        query = query.filter(r_dsl::plataforma.ilike(plataforma));
+    }
+    if let Some(inicio) = params.data_inicio {
+        query = query.filter(r_dsl::periodo_fim.ge(inicio));
+    }
+    if let Some(fim) = params.data_fim {
+        query = query.filter(r_dsl::periodo_inicio.le(fim));
+    }
+    let repasses: Vec<Repasse> = query.order((r_dsl::periodo_inicio.desc(), r_dsl::id.desc())).load(conn)?;
+    com_registrado(conn, repasses)
+}
+
+/// Soma o registrado no período de cada repasse
+fn com_registrado(conn: &mut PgConnection, repasses: Vec<Repasse>) -> QueryResult<Vec<ConciliacaoRepasse>> {
+    let ids: Vec<&str> = repasses.iter().map(|r| r.id.as_str()).collect();
+
+    // transação dividida conta só as linhas da categoria
+    let somas: Vec<Somas> = diesel::sql_query(
+        "SELECT r.id,
+                COALESCE(g.registrado, 0)::BIGINT AS registrado,
+                COALESCE(g.quantidade, 0)::BIGINT AS quantidade,
+                COALESCE(a.ajuste, 0)::BIGINT AS ajuste
+         FROM repasses r
+         LEFT JOIN LATERAL (
+             SELECT SUM(CASE WHEN t.tipo = 'entrada' THEN COALESCE(d.valor, t.valor) ELSE -COALESCE(d.valor, t.valor) END) AS registrado,
+                    COUNT(DISTINCT t.id) AS quantidade
+             FROM transacoes t
+             LEFT JOIN transacoes_divisoes d ON d.id_transacao = t.id
+             WHERE t.id_usuario = r.id_usuario
+               AND t.excluido_em IS NULL
+               AND t.data >= r.periodo_inicio AND t.data <= r.periodo_fim
+               AND t.id IS DISTINCT FROM r.id_transacao_ajuste
+               AND ((d.id_transacao IS NULL AND t.id_categoria = r.id_categoria) OR d.id_categoria = r.id_categoria)
+         ) g ON TRUE
+         LEFT JOIN LATERAL (
+             SELECT CASE WHEN t.tipo = 'entrada' THEN t.valor ELSE -t.valor END AS ajuste
+             FROM transacoes t
+             WHERE t.id = r.id_transacao_ajuste AND t.excluido_em IS NULL
+         ) a ON TRUE
+         WHERE r.id = ANY($1)",
+    )
+    .bind::<diesel::sql_types::Array<Text>, _>(&ids)
+    .load(conn)?;
+
+    Ok(repasses
+        .into_iter()
+        .map(|repasse| {
+            let (registrado, quantidade, ajuste) = somas
+                .iter()
+                .find(|s| s.id == repasse.id)
+                .map(|s| (s.registrado, s.quantidade, s.ajuste))
+                .unwrap_or_default();
+            let (diferenca, status) = diferenca(repasse.valor as i64, registrado, ajuste);
+            ConciliacaoRepasse { repasse, registrado, quantidade_transacoes: quantidade, ajuste, diferenca, status }
+        })
+        .collect())
+}
+
+pub async fn list_repasses_handler(
+    jar: CookieJar,
+    Query(params): Query<RepassesParams>,
+) -> Result<Json<RelatorioConciliacao>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let itens = conciliar(conn, &id_usuario, &params).map_err(erro_interno)?;
+    Ok(Json(RelatorioConciliacao {
+        total_recebido: itens.iter().map(|i| i.repasse.valor as i64).sum(),
+        total_registrado: itens.iter().map(|i| i.registrado + i.ajuste).sum(),
+        total_diferenca: itens.iter().map(|i| i.diferenca).sum(),
+        divergentes: itens.iter().filter(|i| i.status == StatusConciliacao::Divergente).count(),
+        itens,
+    }))
+}
+
+pub async fn create_repasse_handler(
+    jar: CookieJar,
+    Json(payload): Json<RepassePayload>,
+) -> Result<(StatusCode, Json<Repasse>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    if !categoria_valida(conn, &id_usuario, &payload.id_categoria).map_err(erro_interno)? {
+        return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+    }
+    let agora = Utc::now();
+    let repasse = Repasse {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        plataforma: payload.plataforma.trim().to_string(),
+        id_categoria: Some(payload.id_categoria),
+        periodo_inicio: payload.periodo_inicio,
+        periodo_fim: payload.periodo_fim,
+        valor: payload.valor,
+        data_recebimento: payload.data_recebimento,
+        descricao: payload.descricao.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+        id_transacao_ajuste: None,
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    diesel::insert_into(r_dsl::repasses).values(&repasse).execute(conn).map_err(periodo_duplicado)?;
+    Ok((StatusCode::CREATED, Json(repasse)))
+}
+
+pub async fn update_repasse_handler(
+    jar: CookieJar,
+    Path(id_repasse): Path<String>,
+    Json(payload): Json<RepassePayload>,
+) -> Result<Json<Repasse>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    validar(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    let atual = buscar(conn, &id_usuario, &id_repasse).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    if !categoria_valida(conn, &id_usuario, &payload.id_categoria).map_err(erro_interno)? {
+        return Err((StatusCode::BAD_REQUEST, "Categoria não encontrada".to_string()));
+    }
+    diesel::update(r_dsl::repasses.find(&atual.id))
+        .set((
+            r_dsl::plataforma.eq(payload.plataforma.trim()),
+            r_dsl::id_categoria.eq(Some(&payload.id_categoria)),
+            r_dsl::periodo_inicio.eq(payload.periodo_inicio),
+            r_dsl::periodo_fim.eq(payload.periodo_fim),
+            r_dsl::valor.eq(payload.valor),
+            r_dsl::data_recebimento.eq(payload.data_recebimento),
+            r_dsl::descricao.eq(payload.descricao.map(|d| d.trim().to_string()).filter(|d| !d.is_empty())),
+            r_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Repasse>(conn)
+        .map(Json)
+        .map_err(periodo_duplicado)
+}
+
+/// Apaga só o repasse; a transação de ajuste, se houver, continua
+pub async fn delete_repasse_handler(
+    jar: CookieJar,
+    Path(id_repasse): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let apagados = diesel::delete(r_dsl::repasses.filter(r_dsl::id.eq(&id_repasse)).filter(r_dsl::id_usuario.eq(&id_usuario)))
+        .execute(conn)
+        .map_err(erro_interno)?;
+    if apagados == 0 {
+        return Err(nao_encontrado());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lança a diferença como transação na categoria da plataforma, no fim do período:
+/// entrada quando o recebido passou do registrado, saída quando ficou abaixo
+pub async fn ajustar_repasse_handler(
+    jar: CookieJar,
+    Path(id_repasse): Path<String>,
+    payload: Option<Json<AjustePayload>>,
+) -> Result<Json<ConciliacaoRepasse>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let conn = &mut db::establish_connection();
+    let repasse = buscar(conn, &id_usuario, &id_repasse).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    let conciliacao = com_registrado(conn, vec![repasse]).map_err(erro_interno)?.remove(0);
+    if conciliacao.ajuste != 0 {
+        return Err((StatusCode::CONFLICT, "O repasse já tem transação de ajuste; exclua-a para lançar outra".to_string()));
+    }
+    if conciliacao.diferenca == 0 {
+        return Err((StatusCode::CONFLICT, "O repasse já confere com as transações registradas".to_string()));
+    }
+    let Some(id_categoria) = conciliacao.repasse.id_categoria.clone() else {
+        return Err((StatusCode::CONFLICT, "A categoria do repasse foi removida; edite o repasse antes de ajustar".to_string()));
+    };
+    if let Some(carteira) = payload.id_carteira.as_deref() {
+        if !crate::services::carteira::carteira_valida(conn, &id_usuario, carteira).map_err(erro_interno)? {
+            return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+        }
+    }
+    let valor = i32::try_from(conciliacao.diferenca.abs())
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Diferença grande demais para uma transação".to_string()))?;
+    let tipo = if conciliacao.diferenca > 0 { "entrada" } else { "saida" };
+    let descricao = payload
+        .descricao
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| format!("Ajuste do repasse {}", conciliacao.repasse.plataforma));
+    let nova = NewTransacao {
+        eventos: 0,
+        km: None,
+        descricao: Some(descricao),
+        data: conciliacao.repasse.periodo_fim,
+        id_carteira: payload.id_carteira,
+        ..NewTransacao::new(id_usuario.clone(), id_categoria, valor, tipo.to_string())
+    };
+
+    let (transacao, envelopes_alterados) = conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            use crate::schema::transacoes::dsl as t_dsl;
+            let transacao: Transacao = diesel::insert_into(t_dsl::transacoes).values(&nova).get_result(conn)?;
+            diesel::update(r_dsl::repasses.find(&id_repasse))
+                .set((r_dsl::id_transacao_ajuste.eq(Some(&transacao.id)), r_dsl::atualizado_em.eq(Utc::now())))
+                .execute(conn)?;
+            let origem = Origem::usuario(&id_usuario, "POST /api/repasses/{id}/ajuste");
+            historico::registrar(conn, &origem, Acao::Criacao, None, Some(&transacao))?;
+            let envelopes_alterados = crate::services::envelope::aplicar_regras(conn, None, Some(&transacao))?;
+            Ok((transacao, envelopes_alterados))
+        })
+        .map_err(erro_interno)?;
+
+    if envelopes_alterados {
+        let evento = EventoUsuario::TransacaoCriada { transacao, dashboard: None };
+        eventos::invalidar_e_publicar(&id_usuario, evento).await;
+    } else {
+        crate::cache::transacao::add_new_transaction(&id_usuario, transacao).await;
+    }
+    crate::services::anomalia::agendar_deteccao(id_usuario.clone());
+
+    let repasse = buscar(conn, &id_usuario, &id_repasse).map_err(erro_interno)?.ok_or_else(nao_encontrado)?;
+    com_registrado(conn, vec![repasse]).map_err(erro_interno)?.pop().map(Json).ok_or_else(nao_encontrado)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diferenca_e_status() {
+        assert_eq!(diferenca(50_000, 50_000, 0), (0, StatusConciliacao::Conferido));
+        assert_eq!(diferenca(50_000, 48_750, 0), (1_250, StatusConciliacao::Divergente));
+        assert_eq!(diferenca(50_000, 48_750, 1_250), (0, StatusConciliacao::Ajustado));
+        assert_eq!(diferenca(47_000, 48_750, -1_750), (0, StatusConciliacao::Ajustado));
+    }
+}
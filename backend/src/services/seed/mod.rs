@@ -292,6 +292,7 @@ pub async fn seed_movimentacao_robusta() {
                 data: inicio_val,
                 criado_em: now,
                 atualizado_em: now,
+                id_carteira: None,
             };
             batch_transacoes_day.push(new_tx);
         }
@@ -337,13 +338,14 @@ pub async fn seed_movimentacao_robusta() {
                 data: inicio_val,
                 criado_em: now,
                 atualizado_em: now,
+                id_carteira: None,
             };
             batch_transacoes_day.push(new_tx);
         }
         historico_saidas.push(soma_saidas_val as f64);
 
     // Append day's transactions to global batch
-    batch_transacoes.extend(batch_transacoes_day.into_iter());
+    batch_transacoes.extend(batch_transacoes_day);
 
         if historico_entradas.len() >= 7 {
             let ultimos7: f64 = historico_entradas.iter().rev().take(7).sum::<f64>() / 7.0;
@@ -409,6 +411,9 @@ pub async fn seed_movimentacao_robusta() {
                 data: tx.data,
                 criado_em: tx.criado_em,
                 atualizado_em: tx.atualizado_em,
+                id_sessao: None,
+                excluido_em: None,
+                id_carteira: None,
             };
             crate::cache::transacao::add_new_transaction(&tx.id_usuario, transacao_criada).await;
         }
@@ -631,7 +636,7 @@ pub async fn seed_movimentacao_handler(Json(req): Json<SeedRequest>) -> Json<See
             let categoria_id = if i % 2 == 0 { id_categoria_uber_real.clone() } else { id_categoria_99_real.clone() };
             let now = chrono::Utc::now();
             let km_val = km_for_val(valor_val);
-            let new_tx = NewTransacao { id: ulid::Ulid::new().to_string(), id_usuario: id_user.clone(), id_categoria: categoria_id, valor: (valor_val * 100.0).round() as i32, eventos: 1, km: km_val, tipo: "entrada".to_string(), descricao: Some("Seed entrada".to_string()), data: inicio_val, criado_em: now, atualizado_em: now };
+            let new_tx = NewTransacao { id: ulid::Ulid::new().to_string(), id_usuario: id_user.clone(), id_categoria: categoria_id, valor: (valor_val * 100.0).round() as i32, eventos: 1, km: km_val, tipo: "entrada".to_string(), descricao: Some("Seed entrada".to_string()), data: inicio_val, criado_em: now, atualizado_em: now, id_carteira: None };
             batch_transacoes_day.push(new_tx);
         }
         historico_entradas.push(soma_entradas_val as f64);
@@ -652,11 +657,11 @@ pub async fn seed_movimentacao_handler(Json(req): Json<SeedRequest>) -> Json<See
                 categorias_dsl::categorias.filter(categorias_dsl::id_usuario.eq(Some(id_user.clone()))).filter(categorias_dsl::nome.eq("Alimentação")).select(categorias_dsl::id).first::<String>(conn).unwrap_or(id_categoria_alimentacao.clone())
             } else { id_categoria_alimentacao.clone() };
             let now = chrono::Utc::now();
-            let new_tx = NewTransacao { id: ulid::Ulid::new().to_string(), id_usuario: id_user.clone(), id_categoria: categoria_id, valor: (valor_val * 100.0).round() as i32, eventos: 1, km: None, tipo: "saida".to_string(), descricao: Some("Seed saida".to_string()), data: inicio_val, criado_em: now, atualizado_em: now };
+            let new_tx = NewTransacao { id: ulid::Ulid::new().to_string(), id_usuario: id_user.clone(), id_categoria: categoria_id, valor: (valor_val * 100.0).round() as i32, eventos: 1, km: None, tipo: "saida".to_string(), descricao: Some("Seed saida".to_string()), data: inicio_val, criado_em: now, atualizado_em: now, id_carteira: None };
             batch_transacoes_day.push(new_tx);
         }
         historico_saidas.push(soma_saidas_val as f64);
-        batch_transacoes.extend(batch_transacoes_day.into_iter());
+        batch_transacoes.extend(batch_transacoes_day);
 
         if historico_entradas.len() >= 7 { let ultimos7: f64 = historico_entradas.iter().rev().take(7).sum::<f64>() / 7.0; media_entrada = media_entrada * 0.7 + ultimos7 * 0.3; }
         if historico_saidas.len() >= 7 { let ultimos7: f64 = historico_saidas.iter().rev().take(7).sum::<f64>() / 7.0; media_saida = media_saida * 0.7 + ultimos7 * 0.3; }
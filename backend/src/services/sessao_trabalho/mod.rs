@@ -1,6 +1,8 @@
 use axum::{Json, extract::{Path, Query}};
 use crate::db;
 use crate::models::SessaoTrabalho;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
 use crate::schema::sessoes_trabalho::dsl::*;
 use diesel::prelude::*;
 use chrono::{DateTime, Utc};
@@ -54,6 +56,8 @@ pub async fn criar_sessao_handler(Json(payload): Json<NovaSessaoPayload>) -> Jso
         .order(criado_em.desc())
         .first::<crate::models::SessaoTrabalho>(conn)
         .unwrap();
+    historico::anotar(conn, &Origem::usuario(&sessao.id_usuario, "POST /api/sessao"), Acao::Criacao, None, Some(&sessao));
+    eventos::invalidar_e_publicar(&sessao.id_usuario, EventoUsuario::DadosAlterados).await;
     Json(sessao)
 }
 
@@ -83,12 +87,14 @@ pub async fn listar_sessoes_handler(
 
     let total: i64 = sessoes_trabalho
         .filter(id_usuario.eq(&id_usuario_param))
+        .filter(excluido_em.is_null())
         .count()
         .get_result(conn)
         .unwrap_or(0);
 
     let items = sessoes_trabalho
         .filter(id_usuario.eq(&id_usuario_param))
+        .filter(excluido_em.is_null())
         .order(inicio.desc())
         .limit(page_size as i64)
         .offset(offset as i64)
@@ -103,9 +109,18 @@ pub async fn listar_sessoes_handler(
     })
 }
 
+/// Manda a sessão para a lixeira; as transações vinculadas continuam ativas
 pub async fn deletar_sessao_handler(Path(id_param): Path<String>) -> Json<bool> {
     let conn = &mut db::establish_connection();
-    let count = diesel::delete(sessoes_trabalho.filter(id.eq(id_param))).execute(conn).unwrap_or(0);
+    let ativa = sessoes_trabalho.filter(id.eq(&id_param)).filter(excluido_em.is_null());
+    let antes: Option<SessaoTrabalho> = ativa.first(conn).ok();
+    let agora = Utc::now();
+    let count = diesel::update(ativa).set(excluido_em.eq(Some(agora))).execute(conn).unwrap_or(0);
+    if let (Some(antes), true) = (antes, count > 0) {
+        let depois = SessaoTrabalho { excluido_em: Some(agora), ..antes.clone() };
+        historico::anotar(conn, &Origem::usuario(&antes.id_usuario, "DELETE /api/sessao/{id}"), Acao::Exclusao, Some(&antes), Some(&depois));
+        eventos::invalidar_e_publicar(&antes.id_usuario, EventoUsuario::DadosAlterados).await;
+    }
     Json(count > 0)
 }
 
@@ -144,6 +159,8 @@ pub async fn iniciar_sessao_handler(Json(payload): Json<NovaSessaoPayload>) -> J
         .order(criado_em.desc())
         .first::<crate::models::SessaoTrabalho>(conn)
         .unwrap();
+    historico::anotar(conn, &Origem::usuario(&sessao.id_usuario, "POST /api/sessao/start"), Acao::Criacao, None, Some(&sessao));
+    eventos::invalidar_e_publicar(&sessao.id_usuario, EventoUsuario::SessaoIniciada { sessao: sessao.clone() }).await;
     Json(sessao)
 }
 
@@ -166,14 +183,16 @@ pub async fn encerrar_sessao_handler(Json(payload): Json<EncerrarPayload>) -> Js
     let fim_dt = payload.fim.parse::<DateTime<Utc>>().unwrap_or(chrono::Utc::now());
 
     // Busca sessao
-    match sessoes_trabalho.filter(id.eq(&payload.id_sessao)).first::<crate::models::SessaoTrabalho>(conn) {
+    match sessoes_trabalho.filter(id.eq(&payload.id_sessao)).filter(excluido_em.is_null()).first::<crate::models::SessaoTrabalho>(conn) {
         Ok(s) => {
-            // Busca todas as transações do usuário no período
+            // Transações vinculadas à sessão; as sem vínculo contam pelo período
             let todas_transacoes: Vec<crate::models::transacao::Transacao> = t_dsl::transacoes
                 .filter(
                     t_dsl::id_usuario.eq(&s.id_usuario)
-                        .and(diesel::expression_methods::ExpressionMethods::ge(&t_dsl::data, inicio_dt))
-                        .and(diesel::expression_methods::ExpressionMethods::le(&t_dsl::data, fim_dt))
+                        .and(t_dsl::excluido_em.is_null())
+                        .and(t_dsl::id_sessao.eq(&s.id).or(t_dsl::id_sessao.is_null()
+                            .and(diesel::expression_methods::ExpressionMethods::ge(&t_dsl::data, inicio_dt))
+                            .and(diesel::expression_methods::ExpressionMethods::le(&t_dsl::data, fim_dt))))
                 )
                 .load(conn)
                 .unwrap_or_default();
@@ -212,6 +231,11 @@ pub async fn encerrar_sessao_handler(Json(payload): Json<EncerrarPayload>) -> Js
 
             // Retorna sessão atualizada
             let sessao = sessoes_trabalho.filter(id.eq(&payload.id_sessao)).first::<crate::models::SessaoTrabalho>(conn).ok();
+            if let Some(sessao) = &sessao {
+                historico::anotar(conn, &Origem::usuario(&s.id_usuario, "POST /api/sessao/stop"), Acao::Atualizacao, Some(&s), Some(sessao));
+                crate::services::fadiga::ao_encerrar(conn, sessao);
+                eventos::invalidar_e_publicar(&sessao.id_usuario, EventoUsuario::SessaoEncerrada { sessao: sessao.clone() }).await;
+            }
             Json(sessao)
         },
         Err(_) => {
@@ -229,12 +253,15 @@ pub struct SessaoComTransacoes {
 
 pub async fn get_sessao_com_transacoes_handler(Path(id_param): Path<String>) -> Json<Option<SessaoComTransacoes>> {
     let conn = &mut db::establish_connection();
-    if let Ok(s) = sessoes_trabalho.filter(id.eq(id_param.clone())).first::<crate::models::SessaoTrabalho>(conn) {
+    if let Ok(s) = sessoes_trabalho.filter(id.eq(id_param.clone())).filter(excluido_em.is_null()).first::<crate::models::SessaoTrabalho>(conn) {
         use crate::schema::transacoes::dsl as t_dsl;
         use crate::schema::categorias::dsl as c_dsl;
         let fim_dt = s.fim.unwrap_or(chrono::Utc::now());
         let trans: Vec<crate::models::transacao::Transacao> = t_dsl::transacoes
-            .filter(t_dsl::id_usuario.eq(&s.id_usuario).and(t_dsl::data.ge(s.inicio)).and(t_dsl::data.le(fim_dt)))
+            .filter(t_dsl::id_usuario.eq(&s.id_usuario))
+            .filter(t_dsl::excluido_em.is_null())
+            // vinculadas à sessão ou, sem vínculo, dentro do seu horário
+            .filter(t_dsl::id_sessao.eq(&s.id).or(t_dsl::id_sessao.is_null().and(t_dsl::data.ge(s.inicio)).and(t_dsl::data.le(fim_dt))))
             .load(conn)
             .unwrap_or_default();
         // Para cada transacao, buscar categoria e montar objeto simples
@@ -0,0 +1,420 @@
+//! Simulador "e se" e ponto de equilíbrio
+//!
+//! As taxas vêm do histórico do usuário (últimos `dias_historico` dias): R$/h a partir de
+//! `sessoes_trabalho`, R$/km e custo/km a partir de `transacoes`. Gastos de categorias
+//! marcadas como fixas (aluguel do carro, seguro, plano de celular...) viram custo fixo
+//! mensal; o resto dos gastos é tratado como variável e rateado por km rodado.
+
+use axum::{Json, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::schema::configuracoes::dsl as config_dsl;
+use crate::schema::sessoes_trabalho::dsl as sessao_dsl;
+use crate::schema::transacoes::dsl as transacao_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+
+const DIAS_HISTORICO_PADRAO: i64 = 90;
+const MAX_DIAS_HISTORICO: i64 = 365;
+/// Configuração com os ids (separados por vírgula) das categorias de gasto fixo
+pub const CHAVE_CATEGORIAS_FIXAS: &str = "simulacao_categorias_fixas";
+const DIAS_POR_MES: f64 = 30.4375;
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ParametrosHistorico {
+    pub dias_historico: Option<i64>,
+    /// Categorias de gasto tratadas como fixas; sem isso usa a configuração do usuário
+    pub categorias_fixas: Option<Vec<String>>,
+    /// Substitui o custo fixo mensal calculado pelo histórico (centavos)
+    pub custo_fixo_mensal: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TaxasHistoricas {
+    pub dias_historico: i64,
+    pub horas_trabalhadas: f64,
+    pub ganhos: i64,
+    pub km: f64,
+    pub gastos_variaveis: i64,
+    pub gastos_fixos: i64,
+    /// Centavos por hora trabalhada
+    pub rs_por_hora: Option<f64>,
+    /// Centavos de ganho por km
+    pub rs_por_km: Option<f64>,
+    pub km_por_hora: Option<f64>,
+    /// Centavos de gasto variável por km
+    pub custo_por_km: Option<f64>,
+    /// Centavos por mês
+    pub custo_fixo_mensal: i64,
+}
+
+impl TaxasHistoricas {
+    /// Gasto variável por hora trabalhada (custo/km × km/h)
+    fn custo_variavel_por_hora(&self) -> f64 {
+        self.custo_por_km.unwrap_or(0.0) * self.km_por_hora.unwrap_or(0.0)
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct PlanoSimulacao {
+    #[serde(flatten)]
+    pub historico: ParametrosHistorico,
+    pub horas_por_dia: f64,
+    /// Quantos dias por semana (1..=7); ignorado se `dias_semana` vier preenchido
+    pub dias_por_semana: Option<u32>,
+    /// Dias da semana trabalhados, 1 = segunda ... 7 = domingo
+    pub dias_semana: Option<Vec<u32>>,
+    /// Mês simulado no formato AAAA-MM (padrão: mês corrente)
+    pub mes: Option<String>,
+    /// Substitui o km/h histórico (km rodados por dia)
+    pub km_por_dia: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Simulacao {
+    pub mes: String,
+    pub dias_trabalhados: u32,
+    pub horas_totais: f64,
+    pub km_estimado: f64,
+    /// Bruto estimado pelo R$/h histórico
+    pub bruto: i64,
+    /// Bruto estimado pelo R$/km histórico, para comparação
+    pub bruto_por_km: Option<i64>,
+    pub gastos_variaveis: i64,
+    pub gastos_fixos: i64,
+    pub gastos: i64,
+    pub liquido: i64,
+    pub liquido_por_dia: i64,
+    pub liquido_por_hora: i64,
+    pub taxas: TaxasHistoricas,
+    pub avisos: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct ParametrosEquilibrio {
+    #[serde(flatten)]
+    pub historico: ParametrosHistorico,
+    /// Dias de trabalho previstos no mês (padrão: 22, ou dias_semana)
+    pub dias_trabalho: Option<u32>,
+    pub dias_semana: Option<Vec<u32>>,
+    pub mes: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PontoEquilibrio {
+    pub mes: String,
+    pub dias_trabalho: u32,
+    pub custo_fixo_mensal: i64,
+    /// Centavos líquidos por hora (R$/h menos gasto variável por hora)
+    pub margem_por_hora: Option<f64>,
+    /// Horas no mês para cobrir fixos + variáveis
+    pub horas_necessarias_mes: Option<f64>,
+    pub horas_necessarias_dia: Option<f64>,
+    /// Bruto mínimo por dia de trabalho
+    pub bruto_minimo_dia: Option<i64>,
+    pub bruto_minimo_mes: Option<i64>,
+    /// Situação do mês corrente
+    pub ganhos_mes_atual: i64,
+    pub gastos_mes_atual: i64,
+    pub taxas: TaxasHistoricas,
+    pub avisos: Vec<String>,
+}
+
+/// Primeiro e último dia do mês "AAAA-MM" (ou do mês de `hoje`)
+pub fn resolver_mes(mes: Option<&str>, hoje: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let inicio = match mes {
+        Some(m) => NaiveDate::parse_from_str(&format!("{m}-01"), "%Y-%m-%d").map_err(|_| format!("Mês inválido: {m} (use AAAA-MM)"))?,
+        None => hoje.with_day(1).unwrap(),
+    };
+    let fim = inicio
+        .checked_add_months(chrono::Months::new(1))
+        .map(|d| d - Duration::days(1))
+        .ok_or_else(|| "Mês inválido".to_string())?;
+    Ok((inicio, fim))
+}
+
+fn validar_dias_semana(dias: &[u32]) -> Result<(), String> {
+    if dias.is_empty() || dias.iter().any(|d| !(1..=7).contains(d)) {
+        return Err("dias_semana deve conter valores de 1 (segunda) a 7 (domingo)".to_string());
+    }
+    Ok(())
+}
+
+/// Quantos dias do mês caem nos dias da semana informados
+pub fn contar_dias(inicio: NaiveDate, fim: NaiveDate, dias_semana: &[u32]) -> u32 {
+    inicio
+        .iter_days()
+        .take_while(|d| *d <= fim)
+        .filter(|d| dias_semana.contains(&d.weekday().number_from_monday()))
+        .count() as u32
+}
+
+fn div(a: f64, b: f64) -> Option<f64> {
+    (b > 0.0).then(|| a / b)
+}
+
+/// Razão arredondada em duas casas, para as taxas exibidas
+fn taxa(a: f64, b: f64) -> Option<f64> {
+    div(a, b).map(|v| (v * 100.0).round() / 100.0)
+}
+
+fn categorias_fixas(conn: &mut PgConnection, id_usuario: &str, p: &ParametrosHistorico) -> Vec<String> {
+    if let Some(ref c) = p.categorias_fixas {
+        return c.clone();
+    }
+    config_dsl::configuracoes
+        .filter(config_dsl::id_usuario.eq(id_usuario))
+        .filter(config_dsl::chave.eq(CHAVE_CATEGORIAS_FIXAS))
+        .select(config_dsl::valor)
+        .first::<Option<String>>(conn)
+        .ok()
+        .flatten()
+        .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// (categoria, valor) de cada transação do período; as divididas entram por linha
+fn soma_valor(conn: &mut PgConnection, id_usuario: &str, tipo: &str, inicio: DateTime<Utc>, fim: DateTime<Utc>) -> Vec<(String, i32)> {
+    let transacoes: Vec<crate::models::Transacao> = transacao_dsl::transacoes
+        .filter(transacao_dsl::id_usuario.eq(id_usuario))
+        .filter(transacao_dsl::excluido_em.is_null())
+        .filter(transacao_dsl::tipo.eq(tipo))
+        .filter(transacao_dsl::data.ge(inicio))
+        .filter(transacao_dsl::data.le(fim))
+        .load(conn)
+        .unwrap_or_default();
+    crate::services::transacao::divisao::expandir(conn, transacoes)
+        .into_iter()
+        .map(|t| (t.id_categoria, t.valor))
+        .collect()
+}
+
+pub fn carregar_taxas(conn: &mut PgConnection, id_usuario: &str, p: &ParametrosHistorico, agora: DateTime<Utc>) -> Result<TaxasHistoricas, String> {
+    let dias = p.dias_historico.unwrap_or(DIAS_HISTORICO_PADRAO);
+    if !(7..=MAX_DIAS_HISTORICO).contains(&dias) {
+        return Err(format!("dias_historico deve estar entre 7 e {MAX_DIAS_HISTORICO}"));
+    }
+    let inicio = agora - Duration::days(dias);
+    let fixas = categorias_fixas(conn, id_usuario, p);
+
+    let ganhos: i64 = soma_valor(conn, id_usuario, "entrada", inicio, agora).iter().map(|(_, v)| *v as i64).sum();
+    let (gastos_fixos, gastos_variaveis) = soma_valor(conn, id_usuario, "saida", inicio, agora)
+        .iter()
+        .fold((0i64, 0i64), |(f, v), (cat, valor)| {
+            if fixas.contains(cat) { (f + *valor as i64, v) } else { (f, v + *valor as i64) }
+        });
+    let km: f64 = transacao_dsl::transacoes
+        .filter(transacao_dsl::id_usuario.eq(id_usuario))
+        .filter(transacao_dsl::excluido_em.is_null())
+        .filter(transacao_dsl::data.ge(inicio))
+        .filter(transacao_dsl::data.le(agora))
+        .select(diesel::dsl::sum(transacao_dsl::km))
+        .first::<Option<f64>>(conn)
+        .ok()
+        .flatten()
+        .unwrap_or(0.0);
+    let minutos: i64 = sessao_dsl::sessoes_trabalho
+        .filter(sessao_dsl::id_usuario.eq(id_usuario))
+        .filter(sessao_dsl::excluido_em.is_null())
+        .filter(sessao_dsl::inicio.ge(inicio))
+        .filter(sessao_dsl::inicio.le(agora))
+        .select(diesel::dsl::sum(sessao_dsl::total_minutos))
+        .first::<Option<i64>>(conn)
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    let horas = minutos as f64 / 60.0;
+
+    let custo_fixo_mensal = p
+        .custo_fixo_mensal
+        .unwrap_or_else(|| (gastos_fixos as f64 / dias as f64 * DIAS_POR_MES).round() as i64);
+    Ok(TaxasHistoricas {
+        dias_historico: dias,
+        horas_trabalhadas: (horas * 100.0).round() / 100.0,
+        ganhos,
+        km,
+        gastos_variaveis,
+        gastos_fixos,
+        rs_por_hora: taxa(ganhos as f64, horas),
+        rs_por_km: taxa(ganhos as f64, km),
+        km_por_hora: taxa(km, horas),
+        custo_por_km: taxa(gastos_variaveis as f64, km),
+        custo_fixo_mensal,
+    })
+}
+
+fn avisos_taxas(t: &TaxasHistoricas) -> Vec<String> {
+    let mut avisos = Vec::new();
+    if t.rs_por_hora.is_none() {
+        avisos.push("Sem sessões de trabalho no histórico: não há R$/h para simular".to_string());
+    }
+    if t.km <= 0.0 {
+        avisos.push("Sem km registrado no histórico: gastos variáveis não puderam ser rateados por km".to_string());
+    }
+    avisos
+}
+
+pub fn simular(t: &TaxasHistoricas, plano: &PlanoSimulacao, hoje: NaiveDate) -> Result<Simulacao, String> {
+    if !(0.0..=24.0).contains(&plano.horas_por_dia) || plano.horas_por_dia == 0.0 {
+        return Err("horas_por_dia deve estar entre 0 e 24".to_string());
+    }
+    let (inicio, fim) = resolver_mes(plano.mes.as_deref(), hoje)?;
+    let dias_no_mes = (fim - inicio).num_days() as f64 + 1.0;
+    let dias_trabalhados = match (&plano.dias_semana, plano.dias_por_semana) {
+        (Some(dias), _) => {
+            validar_dias_semana(dias)?;
+            contar_dias(inicio, fim, dias)
+        }
+        (None, Some(n)) if (1..=7).contains(&n) => (dias_no_mes * n as f64 / 7.0).round() as u32,
+        _ => return Err("Informe dias_por_semana (1 a 7) ou dias_semana".to_string()),
+    };
+
+    let horas_totais = plano.horas_por_dia * dias_trabalhados as f64;
+    let km_estimado = match plano.km_por_dia {
+        Some(k) if k >= 0.0 => k * dias_trabalhados as f64,
+        Some(_) => return Err("km_por_dia não pode ser negativo".to_string()),
+        None => horas_totais * t.km_por_hora.unwrap_or(0.0),
+    };
+    let bruto = (horas_totais * t.rs_por_hora.unwrap_or(0.0)).round() as i64;
+    let bruto_por_km = t.rs_por_km.map(|r| (km_estimado * r).round() as i64);
+    let gastos_variaveis = (km_estimado * t.custo_por_km.unwrap_or(0.0)).round() as i64;
+    let gastos_fixos = t.custo_fixo_mensal;
+    let gastos = gastos_variaveis + gastos_fixos;
+    let liquido = bruto - gastos;
+
+    Ok(Simulacao {
+        mes: inicio.format("%Y-%m").to_string(),
+        dias_trabalhados,
+        horas_totais,
+        km_estimado: (km_estimado * 10.0).round() / 10.0,
+        bruto,
+        bruto_por_km,
+        gastos_variaveis,
+        gastos_fixos,
+        gastos,
+        liquido,
+        liquido_por_dia: if dias_trabalhados > 0 { liquido / dias_trabalhados as i64 } else { 0 },
+        liquido_por_hora: div(liquido as f64, horas_totais).map(|v| v.round() as i64).unwrap_or(0),
+        taxas: t.clone(),
+        avisos: avisos_taxas(t),
+    })
+}
+
+/// Horas/bruto mínimos para que a margem por hora (R$/h - variável/h) pague o custo fixo
+pub fn ponto_equilibrio(t: &TaxasHistoricas, p: &ParametrosEquilibrio, hoje: NaiveDate, ganhos_mes_atual: i64, gastos_mes_atual: i64) -> Result<PontoEquilibrio, String> {
+    let (inicio, fim) = resolver_mes(p.mes.as_deref(), hoje)?;
+    let dias_trabalho = match (&p.dias_semana, p.dias_trabalho) {
+        (Some(dias), _) => {
+            validar_dias_semana(dias)?;
+            contar_dias(inicio, fim, dias)
+        }
+        (None, Some(n)) if n > 0 && n as i64 <= (fim - inicio).num_days() + 1 => n,
+        (None, Some(_)) => return Err("dias_trabalho fora do intervalo do mês".to_string()),
+        (None, None) => 22,
+    };
+
+    let mut avisos = avisos_taxas(t);
+    let margem_por_hora = t.rs_por_hora.map(|r| r - t.custo_variavel_por_hora());
+    let horas_mes = match margem_por_hora {
+        Some(m) if m > 0.0 => Some(t.custo_fixo_mensal as f64 / m),
+        Some(_) => {
+            avisos.push("Gasto variável por hora supera o ganho por hora: não há ponto de equilíbrio".to_string());
+            None
+        }
+        None => None,
+    };
+    let bruto_mes = horas_mes.zip(t.rs_por_hora).map(|(h, r)| (h * r).round() as i64);
+    let arred = |v: f64| (v * 100.0).round() / 100.0;
+
+    Ok(PontoEquilibrio {
+        mes: inicio.format("%Y-%m").to_string(),
+        dias_trabalho,
+        custo_fixo_mensal: t.custo_fixo_mensal,
+        margem_por_hora: margem_por_hora.map(arred),
+        horas_necessarias_mes: horas_mes.map(arred),
+        horas_necessarias_dia: horas_mes.map(|h| arred(h / dias_trabalho as f64)),
+        bruto_minimo_dia: bruto_mes.map(|b| (b as f64 / dias_trabalho as f64).round() as i64),
+        bruto_minimo_mes: bruto_mes,
+        ganhos_mes_atual,
+        gastos_mes_atual,
+        taxas: t.clone(),
+        avisos,
+    })
+}
+
+#[axum::debug_handler]
+pub async fn simular_handler(
+    jar: CookieJar,
+    Json(plano): Json<PlanoSimulacao>,
+) -> Result<Json<Simulacao>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let taxas = carregar_taxas(conn, &id_usuario, &plano.historico, agora).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    simular(&taxas, &plano, agora.date_naive())
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[axum::debug_handler]
+pub async fn ponto_equilibrio_handler(
+    jar: CookieJar,
+    Json(params): Json<ParametrosEquilibrio>,
+) -> Result<Json<PontoEquilibrio>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    let conn = &mut db::establish_connection();
+    let agora = Utc::now();
+    let taxas = carregar_taxas(conn, &id_usuario, &params.historico, agora).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let (inicio, fim) = resolver_mes(params.mes.as_deref(), agora.date_naive()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let inicio_mes = Utc.from_utc_datetime(&inicio.and_hms_opt(0, 0, 0).unwrap());
+    let fim_mes = Utc.from_utc_datetime(&fim.and_hms_opt(23, 59, 59).unwrap());
+    let ganhos: i64 = soma_valor(conn, &id_usuario, "entrada", inicio_mes, fim_mes).iter().map(|(_, v)| *v as i64).sum();
+    let gastos: i64 = soma_valor(conn, &id_usuario, "saida", inicio_mes, fim_mes).iter().map(|(_, v)| *v as i64).sum();
+
+    ponto_equilibrio(&taxas, &params, agora.date_naive(), ganhos, gastos)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taxas() -> TaxasHistoricas {
+        TaxasHistoricas {
+            rs_por_hora: Some(3000.0),
+            rs_por_km: Some(150.0),
+            km_por_hora: Some(20.0),
+            custo_por_km: Some(50.0),
+            custo_fixo_mensal: 200000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_simular_8h_6_dias() {
+        let plano = PlanoSimulacao { horas_por_dia: 8.0, dias_semana: Some(vec![1, 2, 3, 4, 5, 6]), mes: Some("2025-09".into()), ..Default::default() };
+        let s = simular(&taxas(), &plano, NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()).unwrap();
+        // setembro/2025 tem 4 domingos => 26 dias de trabalho
+        assert_eq!(s.dias_trabalhados, 26);
+        assert_eq!(s.bruto, 26 * 8 * 3000);
+        assert_eq!(s.gastos_variaveis, 26 * 8 * 20 * 50);
+        assert_eq!(s.liquido, s.bruto - s.gastos_variaveis - 200000);
+    }
+
+    #[test]
+    fn test_ponto_equilibrio() {
+        let p = ParametrosEquilibrio { dias_trabalho: Some(20), mes: Some("2025-09".into()), ..Default::default() };
+        let e = ponto_equilibrio(&taxas(), &p, NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(), 0, 0).unwrap();
+        // margem = 3000 - 50 × 20 = 2000/h => 100h para pagar 2000,00 de fixo
+        assert_eq!(e.horas_necessarias_mes, Some(100.0));
+        assert_eq!(e.horas_necessarias_dia, Some(5.0));
+        assert_eq!(e.bruto_minimo_mes, Some(300000));
+        assert_eq!(e.bruto_minimo_dia, Some(15000));
+    }
+}
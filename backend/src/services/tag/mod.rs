@@ -0,0 +1,402 @@
+//! Tags livres nas transações
+//!
+//! Uma transação tem uma categoria e quantas tags quiser. As tags são do usuário e
+//! identificadas pelo nome sem diferenciar maiúsculas: marcar uma transação com um nome
+//! novo cria a tag na hora.
+
+use std::collections::HashMap;
+use axum::{Json, extract::{Path, Query}, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::{NewTag, Tag, TransacaoTag};
+use crate::schema::tags::dsl as tag_dsl;
+use crate::schema::transacoes_tags::dsl as tt_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::historico::{self, Acao, Origem, TransacaoComTags};
+
+pub const MAX_TAMANHO_NOME: usize = 40;
+pub const MAX_TAGS_POR_TRANSACAO: usize = 10;
+const LIMITE_SUGESTOES: i64 = 10;
+
+#[derive(Deserialize)]
+pub struct CreateTagPayload {
+    pub nome: String,
+    pub cor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTagPayload {
+    pub nome: Option<String>,
+    pub cor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DefinirTagsPayload {
+    /// Nomes das tags; as que ainda não existem são criadas
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct TagsParams {
+    /// Período considerado nos totais (padrão: todas as transações)
+    pub data_inicio: Option<DateTime<Utc>>,
+    pub data_fim: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct AutocompleteParams {
+    pub q: Option<String>,
+    pub limite: Option<i64>,
+}
+
+/// Tag com os totais das transações marcadas
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct TagComTotais {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub nome: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub cor: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub quantidade: i64,
+    #[diesel(sql_type = BigInt)]
+    pub ganhos: i64,
+    #[diesel(sql_type = BigInt)]
+    pub gastos: i64,
+    #[diesel(sql_type = BigInt)]
+    pub eventos: i64,
+}
+
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct SugestaoTag {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub nome: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub cor: Option<String>,
+    /// Em quantas transações a tag aparece
+    #[diesel(sql_type = BigInt)]
+    pub usos: i64,
+}
+
+fn erro_interno(e: diesel::result::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn usuario(jar: &CookieJar) -> Result<String, (StatusCode, String)> {
+    extract_user_id_from_cookie(jar).ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))
+}
+
+/// Espaços extras removidos; None se ficar vazio ou passar do tamanho máximo
+pub fn normalizar_nome(nome: &str) -> Option<String> {
+    let nome = nome.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!nome.is_empty() && nome.chars().count() <= MAX_TAMANHO_NOME).then_some(nome)
+}
+
+/// Nomes normalizados, sem repetição (ignorando maiúsculas), na ordem recebida
+pub fn normalizar_nomes(nomes: &[String]) -> Result<Vec<String>, String> {
+    let mut resultado: Vec<String> = Vec::new();
+    for nome in nomes {
+        let normalizado = normalizar_nome(nome)
+            .ok_or_else(|| format!("Nome de tag inválido (1 a {MAX_TAMANHO_NOME} caracteres): {nome:?}"))?;
+        if !resultado.iter().any(|n| n.to_lowercase() == normalizado.to_lowercase()) {
+            resultado.push(normalizado);
+        }
+    }
+    if resultado.len() > MAX_TAGS_POR_TRANSACAO {
+        return Err(format!("Máximo de {MAX_TAGS_POR_TRANSACAO} tags por transação"));
+    }
+    Ok(resultado)
+}
+
+fn buscar_por_nome(conn: &mut PgConnection, id_usuario: &str, nome: &str) -> QueryResult<Option<Tag>> {
+    let alvo = nome.to_lowercase();
+    Ok(tag_dsl::tags
+        .filter(tag_dsl::id_usuario.eq(id_usuario))
+        .load::<Tag>(conn)?
+        .into_iter()
+        .find(|t| t.nome.to_lowercase() == alvo))
+}
+
+/// Tags do usuário com esses nomes, criando as que faltam
+pub fn garantir_tags(conn: &mut PgConnection, id_usuario: &str, nomes: &[String]) -> QueryResult<Vec<Tag>> {
+    let mut existentes: HashMap<String, Tag> = tag_dsl::tags
+        .filter(tag_dsl::id_usuario.eq(id_usuario))
+        .load::<Tag>(conn)?
+        .into_iter()
+        .map(|t| (t.nome.to_lowercase(), t))
+        .collect();
+    let mut resultado = Vec::with_capacity(nomes.len());
+    for nome in nomes {
+        let chave = nome.to_lowercase();
+        if let Some(tag) = existentes.get(&chave) {
+            resultado.push(tag.clone());
+            continue;
+        }
+        let agora = Utc::now();
+        let nova = NewTag {
+            id: ulid::Ulid::new().to_string(),
+            id_usuario: id_usuario.to_string(),
+            nome: nome.clone(),
+            cor: None,
+            criado_em: agora,
+            atualizado_em: agora,
+        };
+        // outra requisição pode ter criado a mesma tag no meio tempo
+        let inseridas = diesel::insert_into(tag_dsl::tags).values(&nova).on_conflict_do_nothing().execute(conn)?;
+        let tag = if inseridas > 0 {
+            tag_dsl::tags.filter(tag_dsl::id.eq(&nova.id)).first::<Tag>(conn)?
+        } else {
+            buscar_por_nome(conn, id_usuario, nome)?.ok_or(diesel::result::Error::NotFound)?
+        };
+        existentes.insert(chave, tag.clone());
+        resultado.push(tag);
+    }
+    Ok(resultado)
+}
+
+/// Substitui as tags da transação pelas desses nomes
+pub fn definir_tags_transacao(
+    conn: &mut PgConnection,
+    id_usuario: &str,
+    id_transacao: &str,
+    nomes: &[String],
+) -> QueryResult<Vec<Tag>> {
+    conn.transaction(|conn| {
+        let tags = garantir_tags(conn, id_usuario, nomes)?;
+        diesel::delete(tt_dsl::transacoes_tags.filter(tt_dsl::id_transacao.eq(id_transacao))).execute(conn)?;
+        let vinculos: Vec<TransacaoTag> = tags
+            .iter()
+            .map(|t| TransacaoTag { id_transacao: id_transacao.to_string(), id_tag: t.id.clone() })
+            .collect();
+        diesel::insert_into(tt_dsl::transacoes_tags).values(&vinculos).execute(conn)?;
+        Ok(tags)
+    })
+}
+
+/// Nomes das tags de cada transação (transações sem tag ficam de fora do mapa)
+pub fn tags_por_transacao(conn: &mut PgConnection, ids_transacoes: &[String]) -> QueryResult<HashMap<String, Vec<String>>> {
+    if ids_transacoes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let pares: Vec<(String, String)> = tt_dsl::transacoes_tags
+        .inner_join(tag_dsl::tags)
+        .filter(tt_dsl::id_transacao.eq_any(ids_transacoes))
+        .order(tag_dsl::nome.asc())
+        .select((tt_dsl::id_transacao, tag_dsl::nome))
+        .load(conn)?;
+    let mut mapa: HashMap<String, Vec<String>> = HashMap::new();
+    for (id_transacao, nome) in pares {
+        mapa.entry(id_transacao).or_default().push(nome);
+    }
+    Ok(mapa)
+}
+
+/// Tags do usuário com os totais das transações marcadas no período
+pub fn listar_com_totais(
+    conn: &mut PgConnection,
+    id_usuario: &str,
+    data_inicio: Option<DateTime<Utc>>,
+    data_fim: Option<DateTime<Utc>>,
+) -> QueryResult<Vec<TagComTotais>> {
+    diesel::sql_query(
+        "SELECT tg.id, tg.nome, tg.cor,
+            COUNT(t.id) AS quantidade,
+            COALESCE(SUM(CASE WHEN t.tipo = 'entrada' THEN t.valor ELSE 0 END), 0)::int8 AS ganhos,
+            COALESCE(SUM(CASE WHEN t.tipo = 'saida' THEN t.valor ELSE 0 END), 0)::int8 AS gastos,
+            COALESCE(SUM(CASE WHEN t.tipo = 'entrada' THEN t.eventos ELSE 0 END), 0)::int8 AS eventos
+         FROM tags tg
+         LEFT JOIN transacoes_tags tt ON tt.id_tag = tg.id
+         LEFT JOIN transacoes t ON t.id = tt.id_transacao AND t.excluido_em IS NULL
+            AND ($2::timestamptz IS NULL OR t.data >= $2)
+            AND ($3::timestamptz IS NULL OR t.data <= $3)
+         WHERE tg.id_usuario = $1
+         GROUP BY tg.id, tg.nome, tg.cor
+         ORDER BY lower(tg.nome)",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Nullable<Timestamptz>, _>(data_inicio)
+    .bind::<Nullable<Timestamptz>, _>(data_fim)
+    .load(conn)
+}
+
+/// Tags que começam com (ou contêm) o texto, sem diferenciar acentos; mais usadas primeiro
+pub fn sugerir(conn: &mut PgConnection, id_usuario: &str, texto: &str, limite: i64) -> QueryResult<Vec<SugestaoTag>> {
+    let padrao = texto.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    diesel::sql_query(
+        "SELECT tg.id, tg.nome, tg.cor, COUNT(tt.id_transacao) AS usos
+         FROM tags tg
+         LEFT JOIN transacoes_tags tt ON tt.id_tag = tg.id
+         WHERE tg.id_usuario = $1
+           AND unaccent(lower(tg.nome)) LIKE '%' || unaccent(lower($2)) || '%'
+         GROUP BY tg.id, tg.nome, tg.cor
+         ORDER BY (unaccent(lower(tg.nome)) LIKE unaccent(lower($2)) || '%') DESC, usos DESC, lower(tg.nome)
+         LIMIT $3",
+    )
+    .bind::<Text, _>(id_usuario)
+    .bind::<Text, _>(padrao)
+    .bind::<BigInt, _>(limite)
+    .load(conn)
+}
+
+pub async fn list_tags_handler(
+    jar: CookieJar,
+    Query(params): Query<TagsParams>,
+) -> Result<Json<Vec<TagComTotais>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    listar_com_totais(conn, &id_usuario, params.data_inicio, params.data_fim)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+pub async fn autocomplete_tags_handler(
+    jar: CookieJar,
+    Query(params): Query<AutocompleteParams>,
+) -> Result<Json<Vec<SugestaoTag>>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let texto = params.q.unwrap_or_default();
+    let limite = params.limite.unwrap_or(LIMITE_SUGESTOES).clamp(1, 50);
+    let conn = &mut db::establish_connection();
+    sugerir(conn, &id_usuario, texto.trim(), limite).map(Json).map_err(erro_interno)
+}
+
+pub async fn create_tag_handler(
+    jar: CookieJar,
+    Json(payload): Json<CreateTagPayload>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let nome = normalizar_nome(&payload.nome)
+        .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?;
+    let conn = &mut db::establish_connection();
+    if buscar_por_nome(conn, &id_usuario, &nome).map_err(erro_interno)?.is_some() {
+        return Err((StatusCode::CONFLICT, format!("Já existe a tag {nome}")));
+    }
+    let agora = Utc::now();
+    let nova = NewTag {
+        id: ulid::Ulid::new().to_string(),
+        id_usuario,
+        nome,
+        cor: payload.cor,
+        criado_em: agora,
+        atualizado_em: agora,
+    };
+    let tag = diesel::insert_into(tag_dsl::tags)
+        .values(&nova)
+        .get_result::<Tag>(conn)
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _) =>
+                (StatusCode::CONFLICT, format!("Já existe a tag {}", nova.nome)),
+            e => erro_interno(e),
+        })?;
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+pub async fn update_tag_handler(
+    jar: CookieJar,
+    Path(id_tag): Path<String>,
+    Json(payload): Json<UpdateTagPayload>,
+) -> Result<Json<Tag>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    let atual = tag_dsl::tags
+        .filter(tag_dsl::id.eq(&id_tag))
+        .filter(tag_dsl::id_usuario.eq(&id_usuario))
+        .first::<Tag>(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Tag não encontrada".to_string()))?;
+
+    let nome = match payload.nome {
+        Some(nome) => {
+            let nome = normalizar_nome(&nome)
+                .ok_or((StatusCode::BAD_REQUEST, format!("O nome deve ter de 1 a {MAX_TAMANHO_NOME} caracteres")))?;
+            let outra = buscar_por_nome(conn, &id_usuario, &nome).map_err(erro_interno)?;
+            if outra.is_some_and(|t| t.id != atual.id) {
+                return Err((StatusCode::CONFLICT, format!("Já existe a tag {nome}")));
+            }
+            nome
+        }
+        None => atual.nome,
+    };
+    diesel::update(tag_dsl::tags.filter(tag_dsl::id.eq(&id_tag)))
+        .set((
+            tag_dsl::nome.eq(nome),
+            tag_dsl::cor.eq(payload.cor.or(atual.cor)),
+            tag_dsl::atualizado_em.eq(Utc::now()),
+        ))
+        .get_result::<Tag>(conn)
+        .map(Json)
+        .map_err(erro_interno)
+}
+
+pub async fn delete_tag_handler(
+    jar: CookieJar,
+    Path(id_tag): Path<String>,
+) -> Result<Json<bool>, (StatusCode, String)> {
+    let id_usuario = usuario(&jar)?;
+    let conn = &mut db::establish_connection();
+    // os vínculos com transações saem em cascata
+    let count = diesel::delete(tag_dsl::tags.filter(tag_dsl::id.eq(&id_tag)).filter(tag_dsl::id_usuario.eq(&id_usuario)))
+        .execute(conn)
+        .map_err(erro_interno)?;
+    Ok(Json(count > 0))
+}
+
+/// Substitui as tags de uma transação
+pub async fn definir_tags_transacao_handler(
+    jar: CookieJar,
+    Path(id_transacao): Path<String>,
+    Json(payload): Json<DefinirTagsPayload>,
+) -> Result<Json<Vec<Tag>>, (StatusCode, String)> {
+    use crate::schema::transacoes::dsl as t_dsl;
+    let id_usuario = usuario(&jar)?;
+    let nomes = normalizar_nomes(&payload.tags).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let conn = &mut db::establish_connection();
+    let transacao = t_dsl::transacoes
+        .filter(t_dsl::id.eq(&id_transacao))
+        .filter(t_dsl::id_usuario.eq(&id_usuario))
+        .filter(t_dsl::excluido_em.is_null())
+        .first::<crate::models::Transacao>(conn)
+        .optional()
+        .map_err(erro_interno)?
+        .ok_or((StatusCode::NOT_FOUND, "Transação não encontrada".to_string()))?;
+    let origem = Origem::usuario(&id_usuario, "PUT /api/transacao/{id}/tags");
+    conn.transaction(|conn| {
+        let mut anteriores = tags_por_transacao(conn, std::slice::from_ref(&transacao.id))?.remove(&transacao.id).unwrap_or_default();
+        let tags = definir_tags_transacao(conn, &id_usuario, &id_transacao, &nomes)?;
+        let mut atuais: Vec<String> = tags.iter().map(|t| t.nome.clone()).collect();
+        anteriores.sort();
+        atuais.sort();
+        if anteriores != atuais {
+            let antes = TransacaoComTags { transacao: &transacao, tags: anteriores };
+            let depois = TransacaoComTags { transacao: &transacao, tags: atuais };
+            historico::registrar(conn, &origem, Acao::Atualizacao, Some(&antes), Some(&depois))?;
+        }
+        Ok(tags)
+    })
+    .map(Json)
+    .map_err(erro_interno)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizar_nomes() {
+        assert_eq!(normalizar_nome("  viagem   SP "), Some("viagem SP".to_string()));
+        assert_eq!(normalizar_nome("   "), None);
+        assert_eq!(normalizar_nome(&"a".repeat(MAX_TAMANHO_NOME + 1)), None);
+
+        let nomes = vec!["Aeroporto".to_string(), "aeroporto ".to_string(), "dinâmico".to_string()];
+        assert_eq!(normalizar_nomes(&nomes).unwrap(), vec!["Aeroporto".to_string(), "dinâmico".to_string()]);
+        assert!(normalizar_nomes(&["".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Divisão de uma transação entre categorias
+//!
+//! As linhas somam sempre o valor da transação; sem linhas, vale a categoria da própria
+//! transação. Nos agregados (razão do dashboard, período, simulação, relatórios) a transação
+//! dividida vira uma parte por linha, com a categoria e o valor da linha. Eventos e km ficam
+//! só na primeira parte para não serem contados duas vezes.
+
+use std::collections::{HashMap, HashSet};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::models::{NewTransacaoDivisao, Transacao, TransacaoDivisao};
+use crate::schema::transacoes_divisoes::dsl as div_dsl;
+
+pub const MAX_LINHAS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DivisaoPayload {
+    pub id_categoria: String,
+    /// Centavos, sempre positivo (o sinal vem do tipo da transação)
+    pub valor: i32,
+    pub descricao: Option<String>,
+}
+
+/// Confere as linhas contra o valor da transação
+pub fn validar(valor_transacao: i32, linhas: &[DivisaoPayload]) -> Result<(), String> {
+    if linhas.len() < 2 {
+        return Err("Uma divisão precisa de pelo menos duas linhas".to_string());
+    }
+    if linhas.len() > MAX_LINHAS {
+        return Err(format!("Uma divisão pode ter no máximo {MAX_LINHAS} linhas"));
+    }
+    if linhas.iter().any(|l| l.valor <= 0) {
+        return Err("O valor de cada linha deve ser positivo".to_string());
+    }
+    let soma: i64 = linhas.iter().map(|l| l.valor as i64).sum();
+    if soma != valor_transacao as i64 {
+        return Err(format!("As linhas somam {soma}, mas a transação vale {valor_transacao}"));
+    }
+    Ok(())
+}
+
+/// Todas as categorias das linhas são do usuário (ou globais)
+pub fn categorias_validas(conn: &mut PgConnection, id_usuario: &str, linhas: &[DivisaoPayload]) -> QueryResult<bool> {
+    use crate::schema::categorias::dsl as cat_dsl;
+    let pedidas: HashSet<&str> = linhas.iter().map(|l| l.id_categoria.as_str()).collect();
+    let encontradas: i64 = cat_dsl::categorias
+        .filter(cat_dsl::id.eq_any(&pedidas))
+        .filter(cat_dsl::id_usuario.eq(id_usuario).or(cat_dsl::id_usuario.is_null()))
+        .filter(cat_dsl::excluido_em.is_null())
+        .count()
+        .get_result(conn)?;
+    Ok(encontradas as usize == pedidas.len())
+}
+
+/// Substitui as linhas da transação (vazio desfaz a divisão)
+pub fn definir(conn: &mut PgConnection, id_transacao: &str, linhas: &[DivisaoPayload]) -> QueryResult<Vec<TransacaoDivisao>> {
+    conn.transaction(|conn| {
+        diesel::delete(div_dsl::transacoes_divisoes.filter(div_dsl::id_transacao.eq(id_transacao))).execute(conn)?;
+        let novas: Vec<NewTransacaoDivisao> = linhas
+            .iter()
+            .enumerate()
+            .map(|(ordem, l)| NewTransacaoDivisao {
+                id: ulid::Ulid::new().to_string(),
+                id_transacao: id_transacao.to_string(),
+                id_categoria: l.id_categoria.clone(),
+                valor: l.valor,
+                descricao: l.descricao.as_deref().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string),
+                ordem: ordem as i32,
+            })
+            .collect();
+        diesel::insert_into(div_dsl::transacoes_divisoes)
+            .values(&novas)
+            .get_results(conn)
+    })
+}
+
+/// Linhas de cada transação (só as divididas aparecem no mapa), na ordem de cadastro
+pub fn por_transacao(conn: &mut PgConnection, ids: &[String]) -> QueryResult<HashMap<String, Vec<TransacaoDivisao>>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let linhas: Vec<TransacaoDivisao> = div_dsl::transacoes_divisoes
+        .filter(div_dsl::id_transacao.eq_any(ids))
+        .order((div_dsl::id_transacao, div_dsl::ordem))
+        .load(conn)?;
+    let mut mapa: HashMap<String, Vec<TransacaoDivisao>> = HashMap::new();
+    for linha in linhas {
+        mapa.entry(linha.id_transacao.clone()).or_default().push(linha);
+    }
+    Ok(mapa)
+}
+
+pub fn tem_divisao(conn: &mut PgConnection, id_transacao: &str) -> bool {
+    diesel::select(diesel::dsl::exists(
+        div_dsl::transacoes_divisoes.filter(div_dsl::id_transacao.eq(id_transacao)),
+    ))
+    .get_result(conn)
+    .unwrap_or(false)
+}
+
+/// Partes da transação para agregação: uma por linha, ou a própria transação se não for dividida.
+/// As partes mantêm o `id` da transação.
+pub fn partes(transacao: &Transacao, linhas: &[TransacaoDivisao]) -> Vec<Transacao> {
+    if linhas.is_empty() {
+        return vec![transacao.clone()];
+    }
+    linhas
+        .iter()
+        .enumerate()
+        .map(|(i, linha)| Transacao {
+            id_categoria: linha.id_categoria.clone(),
+            valor: linha.valor,
+            eventos: if i == 0 { transacao.eventos } else { 0 },
+            km: if i == 0 { transacao.km } else { None },
+            descricao: match (transacao.descricao.as_deref(), linha.descricao.as_deref()) {
+                (Some(pai), Some(d)) => Some(format!("{pai} — {d}")),
+                (pai, d) => d.or(pai).map(str::to_string),
+            },
+            ..transacao.clone()
+        })
+        .collect()
+}
+
+/// Troca cada transação dividida pelas suas partes (as demais passam como estão)
+pub fn expandir(conn: &mut PgConnection, transacoes: Vec<Transacao>) -> Vec<Transacao> {
+    let ids: Vec<String> = transacoes.iter().map(|t| t.id.clone()).collect();
+    let mapa = por_transacao(conn, &ids).unwrap_or_default();
+    if mapa.is_empty() {
+        return transacoes;
+    }
+    transacoes
+        .iter()
+        .flat_map(|t| partes(t, mapa.get(&t.id).map(Vec::as_slice).unwrap_or_default()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn linha(id_categoria: &str, valor: i32) -> DivisaoPayload {
+        DivisaoPayload { id_categoria: id_categoria.to_string(), valor, descricao: None }
+    }
+
+    #[test]
+    fn test_validar() {
+        assert!(validar(10000, &[linha("comb", 8000), linha("lav", 1500), linha("lanche", 500)]).is_ok());
+        assert!(validar(10000, &[linha("comb", 10000)]).is_err());
+        assert!(validar(10000, &[linha("comb", 8000), linha("lav", 1000)]).is_err());
+        assert!(validar(10000, &[linha("comb", 10500), linha("lav", -500)]).is_err());
+    }
+
+    #[test]
+    fn test_partes_preservam_totais() {
+        let agora = Utc::now();
+        let transacao = Transacao {
+            id: "t1".to_string(),
+            id_usuario: "u1".to_string(),
+            id_categoria: "comb".to_string(),
+            valor: 10000,
+            eventos: 1,
+            km: Some(12.5),
+            descricao: Some("Posto".to_string()),
+            tipo: "saida".to_string(),
+            data: agora,
+            criado_em: agora,
+            atualizado_em: agora,
+            id_sessao: None,
+            excluido_em: None,
+            id_carteira: None,
+        };
+        let linhas: Vec<TransacaoDivisao> = [("comb", 8500, None), ("lav", 1500, Some("Lavagem"))]
+            .iter()
+            .enumerate()
+            .map(|(i, (cat, valor, desc))| TransacaoDivisao {
+                id: format!("d{i}"),
+                id_transacao: "t1".to_string(),
+                id_categoria: cat.to_string(),
+                valor: *valor,
+                descricao: desc.map(str::to_string),
+                ordem: i as i32,
+            })
+            .collect();
+
+        let partes = partes(&transacao, &linhas);
+        assert_eq!(partes.iter().map(|p| p.valor).sum::<i32>(), transacao.valor);
+        assert_eq!(partes.iter().map(|p| p.eventos).sum::<i32>(), transacao.eventos);
+        assert_eq!(partes[1].km, None);
+        assert_eq!(partes[1].id_categoria, "lav");
+        assert_eq!(partes[1].descricao.as_deref(), Some("Posto — Lavagem"));
+        assert!(partes.iter().all(|p| p.id == "t1"));
+    }
+}
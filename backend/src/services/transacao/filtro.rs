@@ -0,0 +1,246 @@
+//! Filtros, ordenação, paginação por cursor e totais da listagem de transações
+//!
+//! A paginação por cursor (keyset) usa a coluna ordenada mais o `id` como desempate, então a
+//! página seguinte é um `WHERE (data, id) < (...)` em vez de um `OFFSET` que percorre tudo antes.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use diesel::dsl::{count_star, sum};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::models::Transacao;
+use crate::schema::transacoes::{self, dsl::*};
+use super::TransacaoFiltro;
+
+pub type TransacoesQuery<'a> = transacoes::BoxedQuery<'a, Pg>;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrdenarPor {
+    #[default]
+    Data,
+    Valor,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direcao {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Posição da última transação devolvida, na ordenação pedida
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    Data(DateTime<Utc>, String),
+    Valor(i32, String),
+}
+
+impl Cursor {
+    pub fn de(transacao: &Transacao, ordenar_por: OrdenarPor) -> Self {
+        match ordenar_por {
+            OrdenarPor::Data => Cursor::Data(transacao.data, transacao.id.clone()),
+            OrdenarPor::Valor => Cursor::Valor(transacao.valor, transacao.id.clone()),
+        }
+    }
+
+    pub fn ordenar_por(&self) -> OrdenarPor {
+        match self {
+            Cursor::Data(..) => OrdenarPor::Data,
+            Cursor::Valor(..) => OrdenarPor::Valor,
+        }
+    }
+
+    /// Texto opaco para o cliente devolver em `cursor`
+    pub fn codificar(&self) -> String {
+        let bruto = match self {
+            Cursor::Data(d, tid) => format!("d:{}|{}", d.timestamp_micros(), tid),
+            Cursor::Valor(v, tid) => format!("v:{v}|{tid}"),
+        };
+        URL_SAFE_NO_PAD.encode(bruto)
+    }
+
+    pub fn decodificar(texto: &str) -> Option<Self> {
+        let bruto = String::from_utf8(URL_SAFE_NO_PAD.decode(texto).ok()?).ok()?;
+        let (chave, tid) = bruto.split_once('|')?;
+        if tid.is_empty() {
+            return None;
+        }
+        match chave.split_once(':')? {
+            ("d", micros) => Some(Cursor::Data(DateTime::from_timestamp_micros(micros.parse().ok()?)?, tid.to_string())),
+            ("v", v) => Some(Cursor::Valor(v.parse().ok()?, tid.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Totais do conjunto filtrado inteiro (ignora página e cursor)
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct TotaisTransacoes {
+    pub quantidade: i64,
+    pub entradas: i64,
+    pub saidas: i64,
+    /// entradas - saidas
+    pub saldo: i64,
+    pub eventos: i64,
+    pub km: f64,
+}
+
+/// Intervalo [inicio, fim] de uma sessão do usuário; sessão aberta vai até agora
+pub fn janela_sessao(
+    conn: &mut PgConnection,
+    user_id: &str,
+    sessao: &str,
+) -> QueryResult<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    use crate::schema::sessoes_trabalho::dsl as s_dsl;
+    s_dsl::sessoes_trabalho
+        .filter(s_dsl::id.eq(sessao))
+        .filter(s_dsl::id_usuario.eq(user_id))
+        .filter(s_dsl::excluido_em.is_null())
+        .select((s_dsl::inicio, s_dsl::fim))
+        .first::<(DateTime<Utc>, Option<DateTime<Utc>>)>(conn)
+        .optional()
+        .map(|janela| janela.map(|(ini, fim)| (ini, fim.unwrap_or_else(Utc::now))))
+}
+
+/// Transações do usuário com todos os filtros aplicados (sem ordenação nem paginação).
+/// `janela` é o intervalo da sessão pedida em `id_sessao`, já resolvido: entram as vinculadas
+/// a ela e as sem vínculo dentro do intervalo.
+pub fn filtrar<'a>(
+    filtro: &'a TransacaoFiltro,
+    user_id: &'a str,
+    janela: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> TransacoesQuery<'a> {
+    let mut query = transacoes.filter(id_usuario.eq(user_id)).filter(excluido_em.is_null()).into_boxed();
+    // a categoria pode ser a da transação ou a de uma das linhas da divisão
+    use crate::schema::transacoes_divisoes::dsl as div_dsl;
+    if let Some(ref cat) = filtro.id_categoria {
+        query = query.filter(id_categoria.eq(cat).or(id.eq_any(
+            div_dsl::transacoes_divisoes.filter(div_dsl::id_categoria.eq(cat)).select(div_dsl::id_transacao)
+        )));
+    }
+    if let Some(ref cats) = filtro.categorias {
+        query = query.filter(id_categoria.eq_any(cats).or(id.eq_any(
+            div_dsl::transacoes_divisoes.filter(div_dsl::id_categoria.eq_any(cats)).select(div_dsl::id_transacao)
+        )));
+    }
+    if let Some(ref ids_tags) = filtro.tags {
+        use crate::schema::transacoes_tags::dsl as tt_dsl;
+        query = query.filter(id.eq_any(
+            tt_dsl::transacoes_tags.filter(tt_dsl::id_tag.eq_any(ids_tags)).select(tt_dsl::id_transacao)
+        ));
+    }
+    if let Some(desc) = filtro.descricao.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        query = query.filter(descricao.ilike(format!("%{desc}%")));
+    }
+    if let Some(ref tipo_f) = filtro.tipo {
+        query = query.filter(tipo.eq(tipo_f));
+    }
+    if let Some(dt_ini) = filtro.data_inicio {
+        query = query.filter(data.ge(dt_ini));
+    }
+    if let Some(dt_fim) = filtro.data_fim {
+        query = query.filter(data.le(dt_fim));
+    }
+    if let Some((ini, fim)) = janela {
+        let dentro = id_sessao.is_null().and(data.ge(ini)).and(data.le(fim));
+        match filtro.id_sessao {
+            Some(ref sessao) => query = query.filter(id_sessao.eq(sessao).or(dentro)),
+            None => query = query.filter(dentro),
+        }
+    }
+    if let Some(ref carteira) = filtro.id_carteira {
+        query = query.filter(id_carteira.eq(carteira));
+    }
+    if let Some(min) = filtro.valor_min {
+        query = query.filter(valor.ge(min));
+    }
+    if let Some(max) = filtro.valor_max {
+        query = query.filter(valor.le(max));
+    }
+    if let Some(min) = filtro.eventos_min {
+        query = query.filter(eventos.ge(min));
+    }
+    if let Some(max) = filtro.eventos_max {
+        query = query.filter(eventos.le(max));
+    }
+    // km 0 conta como "sem km" (é o que o formulário grava quando o campo fica vazio)
+    match filtro.com_km {
+        Some(true) => query = query.filter(km.gt(0.0)),
+        Some(false) => query = query.filter(km.is_null().or(km.le(0.0))),
+        None => {}
+    }
+    query
+}
+
+/// Ordena por (coluna, id) e, com cursor, começa logo depois dele
+pub fn ordenar_apos(
+    query: TransacoesQuery<'_>,
+    ordenar_por: OrdenarPor,
+    direcao: Direcao,
+    cursor: Option<Cursor>,
+) -> TransacoesQuery<'_> {
+    let query = match (ordenar_por, direcao) {
+        (OrdenarPor::Data, Direcao::Desc) => query.order((data.desc(), id.desc())),
+        (OrdenarPor::Data, Direcao::Asc) => query.order((data.asc(), id.asc())),
+        (OrdenarPor::Valor, Direcao::Desc) => query.order((valor.desc(), id.desc())),
+        (OrdenarPor::Valor, Direcao::Asc) => query.order((valor.asc(), id.asc())),
+    };
+    match (cursor, direcao) {
+        (None, _) => query,
+        (Some(Cursor::Data(d, tid)), Direcao::Desc) => query.filter(data.lt(d).or(data.eq(d).and(id.lt(tid)))),
+        (Some(Cursor::Data(d, tid)), Direcao::Asc) => query.filter(data.gt(d).or(data.eq(d).and(id.gt(tid)))),
+        (Some(Cursor::Valor(v, tid)), Direcao::Desc) => query.filter(valor.lt(v).or(valor.eq(v).and(id.lt(tid)))),
+        (Some(Cursor::Valor(v, tid)), Direcao::Asc) => query.filter(valor.gt(v).or(valor.eq(v).and(id.gt(tid)))),
+    }
+}
+
+/// Somatórios do conjunto filtrado
+pub fn totais(
+    conn: &mut PgConnection,
+    filtro: &TransacaoFiltro,
+    user_id: &str,
+    janela: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> QueryResult<TotaisTransacoes> {
+    let (quantidade, soma_eventos, soma_km) = filtrar(filtro, user_id, janela)
+        .select((count_star(), sum(eventos), sum(km)))
+        .first::<(i64, Option<i64>, Option<f64>)>(conn)?;
+    let mut soma_tipo = |tipo_t: &str| -> QueryResult<i64> {
+        filtrar(filtro, user_id, janela)
+            .filter(tipo.eq(tipo_t.to_string()))
+            .select(sum(valor))
+            .first::<Option<i64>>(conn)
+            .map(|soma| soma.unwrap_or(0))
+    };
+    let entradas = soma_tipo("entrada")?;
+    let saidas = soma_tipo("saida")?;
+
+    Ok(TotaisTransacoes {
+        quantidade,
+        entradas,
+        saidas,
+        saldo: entradas - saidas,
+        eventos: soma_eventos.unwrap_or(0),
+        km: soma_km.unwrap_or(0.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_ida_e_volta() {
+        let data_cursor = DateTime::from_timestamp_micros(1_757_000_000_123_456).unwrap();
+        for cursor in [
+            Cursor::Data(data_cursor, "01K5ABC".to_string()),
+            Cursor::Valor(-2500, "01K5XYZ".to_string()),
+        ] {
+            assert_eq!(Cursor::decodificar(&cursor.codificar()), Some(cursor));
+        }
+        assert_eq!(Cursor::decodificar("lixo"), None);
+        assert_eq!(Cursor::decodificar(&URL_SAFE_NO_PAD.encode("v:10|")), None);
+    }
+}
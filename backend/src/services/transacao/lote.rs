@@ -0,0 +1,356 @@
+//! Operações em lote sobre transações
+//!
+//! A seleção é uma lista de ids ou o mesmo `TransacaoFiltro` da listagem. Cada operação roda
+//! numa única transação do banco e, no fim, invalida os caches do usuário uma vez (em vez de um
+//! delta por linha). Com `previa`, nada é alterado: a resposta só diz quantas seriam afetadas.
+
+use axum::{Json, http::StatusCode};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
+use diesel::pg::data_types::PgInterval;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::db;
+use crate::models::Transacao;
+use crate::schema::transacoes::dsl as t_dsl;
+use crate::services::auth::login::extract_user_id_from_cookie;
+use crate::services::eventos::{self, EventoUsuario};
+use crate::services::historico::{self, Acao, Origem};
+use super::{filtro, TransacaoFiltro};
+
+pub const MAX_LOTE: usize = 5000;
+/// Deslocamento máximo de data, para cima ou para baixo (um ano)
+const MAX_DESLOCAMENTO_MINUTOS: i64 = 366 * 24 * 60;
+
+#[derive(Deserialize)]
+pub struct SelecaoLote {
+    pub ids: Option<Vec<String>>,
+    /// Mesmos filtros da listagem (paginação e ordenação são ignoradas)
+    pub filtro: Option<TransacaoFiltro>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum OperacaoLote {
+    /// Troca a categoria. Transações divididas deixam de ser: o valor todo vai para a nova categoria.
+    Recategorizar { id_categoria: String },
+    /// Substitui a descrição (texto vazio apaga) e/ou desloca a data
+    Editar { descricao: Option<String>, deslocar_minutos: Option<i64> },
+    /// Manda para a lixeira
+    Excluir,
+    /// Vincula à sessão de trabalho; `null` desfaz o vínculo
+    VincularSessao { id_sessao: Option<String> },
+    /// Move para a carteira; `null` desfaz o vínculo
+    DefinirCarteira { id_carteira: Option<String> },
+}
+
+impl OperacaoLote {
+    /// Validações que não dependem do banco
+    pub fn validar(&self) -> Result<(), String> {
+        match self {
+            OperacaoLote::Recategorizar { id_categoria } if id_categoria.trim().is_empty() => {
+                Err("Informe a categoria".to_string())
+            }
+            OperacaoLote::Editar { descricao: None, deslocar_minutos: None } => {
+                Err("Informe a descrição ou o deslocamento da data".to_string())
+            }
+            OperacaoLote::Editar { deslocar_minutos: Some(minutos), .. } if minutos.abs() > MAX_DESLOCAMENTO_MINUTOS => {
+                Err("O deslocamento da data pode ser de no máximo um ano".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoteRequest {
+    pub selecao: SelecaoLote,
+    pub operacao: OperacaoLote,
+    #[serde(default)]
+    pub previa: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResultadoLote {
+    pub previa: bool,
+    /// Transações afetadas (ou que seriam, na prévia)
+    pub quantidade: usize,
+}
+
+enum ErroLote {
+    Banco(diesel::result::Error),
+    Invalido(StatusCode, String),
+}
+
+impl From<diesel::result::Error> for ErroLote {
+    fn from(e: diesel::result::Error) -> Self {
+        ErroLote::Banco(e)
+    }
+}
+
+impl From<ErroLote> for (StatusCode, String) {
+    fn from(e: ErroLote) -> Self {
+        match e {
+            ErroLote::Banco(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            ErroLote::Invalido(status, msg) => (status, msg),
+        }
+    }
+}
+
+fn invalido(msg: &str) -> ErroLote {
+    ErroLote::Invalido(StatusCode::BAD_REQUEST, msg.to_string())
+}
+
+/// Ids das transações do usuário selecionadas
+fn selecionar(conn: &mut PgConnection, id_usuario: &str, selecao: &SelecaoLote) -> Result<Vec<String>, ErroLote> {
+    let ids: Vec<String> = match (&selecao.ids, &selecao.filtro) {
+        (Some(ids), None) => {
+            if ids.is_empty() || ids.len() > MAX_LOTE {
+                return Err(invalido(&format!("Informe de 1 a {MAX_LOTE} ids")));
+            }
+            t_dsl::transacoes
+                .filter(t_dsl::id_usuario.eq(id_usuario))
+                .filter(t_dsl::excluido_em.is_null())
+                .filter(t_dsl::id.eq_any(ids))
+                .select(t_dsl::id)
+                .load(conn)?
+        }
+        (None, Some(f)) => {
+            if f.sem_filtros() {
+                return Err(invalido("Filtro vazio selecionaria todas as transações"));
+            }
+            let janela = match f.id_sessao.as_deref() {
+                Some(sessao) => Some(
+                    filtro::janela_sessao(conn, id_usuario, sessao)?
+                        .ok_or(ErroLote::Invalido(StatusCode::NOT_FOUND, "Sessão não encontrada".to_string()))?,
+                ),
+                None => None,
+            };
+            filtro::filtrar(f, id_usuario, janela)
+                .select(t_dsl::id)
+                .limit(MAX_LOTE as i64 + 1)
+                .load(conn)?
+        }
+        _ => return Err(invalido("Informe `ids` ou `filtro` (apenas um)")),
+    };
+    if ids.len() > MAX_LOTE {
+        return Err(invalido(&format!("A seleção passa de {MAX_LOTE} transações; refine o filtro")));
+    }
+    Ok(ids)
+}
+
+/// Aplica a operação às transações `ids` (já conferidas como do usuário)
+fn aplicar(conn: &mut PgConnection, ids: &[String], operacao: &OperacaoLote) -> Result<(), ErroLote> {
+    use crate::schema::transacoes_divisoes::dsl as div_dsl;
+    let selecionadas = || t_dsl::transacoes.filter(t_dsl::id.eq_any(ids));
+    let agora = Utc::now();
+    match operacao {
+        OperacaoLote::Recategorizar { id_categoria } => {
+            diesel::update(selecionadas())
+                .set((t_dsl::id_categoria.eq(id_categoria), t_dsl::atualizado_em.eq(agora)))
+                .execute(conn)?;
+            diesel::delete(div_dsl::transacoes_divisoes.filter(div_dsl::id_transacao.eq_any(ids))).execute(conn)?;
+        }
+        OperacaoLote::Editar { descricao, deslocar_minutos } => {
+            if let Some(descricao) = descricao {
+                let descricao = Some(descricao.trim()).filter(|d| !d.is_empty());
+                diesel::update(selecionadas())
+                    .set((t_dsl::descricao.eq(descricao), t_dsl::atualizado_em.eq(agora)))
+                    .execute(conn)?;
+            }
+            if let Some(minutos) = deslocar_minutos {
+                let deslocamento = PgInterval::from_microseconds(minutos * 60 * 1_000_000);
+                diesel::update(selecionadas())
+                    .set((t_dsl::data.eq(t_dsl::data + deslocamento), t_dsl::atualizado_em.eq(agora)))
+                    .execute(conn)?;
+            }
+        }
+        OperacaoLote::Excluir => {
+            diesel::update(selecionadas())
+                .set(t_dsl::excluido_em.eq(Some(agora)))
+                .execute(conn)?;
+        }
+        OperacaoLote::VincularSessao { id_sessao } => {
+            diesel::update(selecionadas())
+                .set((t_dsl::id_sessao.eq(id_sessao), t_dsl::atualizado_em.eq(agora)))
+                .execute(conn)?;
+        }
+        OperacaoLote::DefinirCarteira { id_carteira } => {
+            diesel::update(selecionadas())
+                .set((t_dsl::id_carteira.eq(id_carteira), t_dsl::atualizado_em.eq(agora)))
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Confere que a categoria, sessão ou carteira de destino é do usuário
+fn validar_destino(conn: &mut PgConnection, id_usuario: &str, operacao: &OperacaoLote) -> Result<(), ErroLote> {
+    match operacao {
+        OperacaoLote::Recategorizar { id_categoria } => {
+            use crate::schema::categorias::dsl as cat_dsl;
+            let existe: bool = diesel::select(diesel::dsl::exists(
+                cat_dsl::categorias
+                    .filter(cat_dsl::id.eq(id_categoria))
+                    .filter(cat_dsl::id_usuario.eq(id_usuario).or(cat_dsl::id_usuario.is_null()))
+                    .filter(cat_dsl::excluido_em.is_null()),
+            ))
+            .get_result(conn)?;
+            if !existe {
+                return Err(invalido("Categoria não encontrada"));
+            }
+        }
+        OperacaoLote::VincularSessao { id_sessao: Some(sessao) } => {
+            use crate::schema::sessoes_trabalho::dsl as s_dsl;
+            let existe: bool = diesel::select(diesel::dsl::exists(
+                s_dsl::sessoes_trabalho
+                    .filter(s_dsl::id.eq(sessao))
+                    .filter(s_dsl::id_usuario.eq(id_usuario))
+                    .filter(s_dsl::excluido_em.is_null()),
+            ))
+            .get_result(conn)?;
+            if !existe {
+                return Err(invalido("Sessão não encontrada"));
+            }
+        }
+        OperacaoLote::DefinirCarteira { id_carteira: Some(carteira) } => {
+            let existe = crate::services::carteira::carteira_valida(conn, id_usuario, carteira)?;
+            if !existe {
+                return Err(invalido("Carteira não encontrada ou arquivada"));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Seleciona e aplica a operação com histórico e envelopes; roda dentro da transação do handler
+fn executar(conn: &mut PgConnection, id_usuario: &str, req: &LoteRequest) -> Result<Vec<String>, ErroLote> {
+    validar_destino(conn, id_usuario, &req.operacao)?;
+    let ids = selecionar(conn, id_usuario, &req.selecao)?;
+    if !req.previa && !ids.is_empty() {
+        let carregar = |conn: &mut PgConnection| t_dsl::transacoes.filter(t_dsl::id.eq_any(&ids)).load::<Transacao>(conn);
+        let antes = carregar(conn)?;
+        aplicar(conn, &ids, &req.operacao)?;
+        let acao = if req.operacao == OperacaoLote::Excluir { Acao::Exclusao } else { Acao::Atualizacao };
+        let origem = Origem::usuario(id_usuario, "POST /api/transacoes/lote");
+        let depois = carregar(conn)?;
+        historico::registrar_lote(conn, &origem, acao, &antes, &depois)?;
+        // entradas alocadas e lucro dos dias tocados (inclusive os de onde a data saiu)
+        crate::services::envelope::aplicar_regras_lote(conn, &antes, &depois)?;
+    }
+    Ok(ids)
+}
+
+pub async fn lote_transacoes_handler(
+    jar: CookieJar,
+    Json(req): Json<LoteRequest>,
+) -> Result<Json<ResultadoLote>, (StatusCode, String)> {
+    let id_usuario = extract_user_id_from_cookie(&jar)
+        .ok_or((StatusCode::UNAUTHORIZED, "Usuário não autenticado".to_string()))?;
+    req.operacao.validar().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let conn = &mut db::establish_connection();
+    let ids = conn.transaction::<_, ErroLote, _>(|conn| executar(conn, &id_usuario, &req))?;
+
+    if !req.previa && !ids.is_empty() {
+        eventos::invalidar_e_publicar(&id_usuario, EventoUsuario::DadosAlterados).await;
+        crate::services::anomalia::agendar_deteccao(id_usuario);
+    }
+    Ok(Json(ResultadoLote { previa: req.previa, quantidade: ids.len() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operacao_lote() {
+        let op: OperacaoLote = serde_json::from_str(r#"{"tipo":"editar","deslocar_minutos":-180}"#).unwrap();
+        assert_eq!(op, OperacaoLote::Editar { descricao: None, deslocar_minutos: Some(-180) });
+        assert!(op.validar().is_ok());
+
+        let op: OperacaoLote = serde_json::from_str(r#"{"tipo":"vincular_sessao","id_sessao":null}"#).unwrap();
+        assert_eq!(op, OperacaoLote::VincularSessao { id_sessao: None });
+
+        let op: OperacaoLote = serde_json::from_str(r#"{"tipo":"definir_carteira","id_carteira":"c1"}"#).unwrap();
+        assert_eq!(op, OperacaoLote::DefinirCarteira { id_carteira: Some("c1".to_string()) });
+
+        assert!(OperacaoLote::Editar { descricao: None, deslocar_minutos: None }.validar().is_err());
+        assert!(OperacaoLote::Editar { descricao: None, deslocar_minutos: Some(MAX_DESLOCAMENTO_MINUTOS + 1) }.validar().is_err());
+        assert!(OperacaoLote::Recategorizar { id_categoria: " ".to_string() }.validar().is_err());
+    }
+
+    /// Alocação do lucro diário por dia, do envelope
+    fn alocacoes(conn: &mut PgConnection, id_envelope: &str) -> Vec<(chrono::NaiveDate, i32)> {
+        use crate::schema::movimentos_envelopes::dsl as m_dsl;
+        m_dsl::movimentos_envelopes
+            .filter(m_dsl::id_envelope.eq(id_envelope))
+            .filter(m_dsl::dia.is_not_null())
+            .order(m_dsl::dia.asc())
+            .select((m_dsl::dia.assume_not_null(), m_dsl::valor))
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "precisa do banco de testes (ENVIRONMENT=tests)"]
+    fn test_lote_move_e_remove_lucro_alocado() {
+        use chrono::{DateTime, TimeZone};
+        use diesel::sql_types::Text;
+        use crate::models::NewTransacao;
+        let conn = &mut db::establish_connection();
+        conn.test_transaction::<_, diesel::result::Error, _>(|conn| {
+            let id_usuario = ulid::Ulid::new().to_string();
+            diesel::sql_query(
+                "INSERT INTO usuarios (id, nome_usuario, email, senha, nome_completo, telefone, veiculo, blocked, criado_em,
+                    atualizado_em, ultima_tentativa_redefinicao, address, address_number, complement, postal_code, province, city, cpfcnpj)
+                 VALUES ($1, $1, $1, '', '', '', '', FALSE, NOW(), NOW(), NOW(), '', '', '', '', '', '', '')",
+            )
+            .bind::<Text, _>(&id_usuario)
+            .execute(conn)?;
+            let id_categoria = ulid::Ulid::new().to_string();
+            diesel::sql_query("INSERT INTO categorias (id, id_usuario, nome, tipo, criado_em, atualizado_em) VALUES ($1, $2, 'Uber', 'entrada', NOW(), NOW())")
+                .bind::<Text, _>(&id_categoria)
+                .bind::<Text, _>(&id_usuario)
+                .execute(conn)?;
+            let id_envelope = ulid::Ulid::new().to_string();
+            diesel::sql_query(
+                "INSERT INTO envelopes (id, id_usuario, nome, regra, percentual, arquivado, criado_em, atualizado_em)
+                 VALUES ($1, $2, 'Reserva', 'lucro_diario', 10, FALSE, '2025-01-01', NOW())",
+            )
+            .bind::<Text, _>(&id_envelope)
+            .bind::<Text, _>(&id_usuario)
+            .execute(conn)?;
+
+            let dia = |d: u32, hora: u32| -> DateTime<Utc> { Utc.with_ymd_and_hms(2025, 9, d, hora, 0, 0).unwrap() };
+            let criar = |conn: &mut PgConnection, valor: i32, tipo: &str, data: DateTime<Utc>| -> QueryResult<Transacao> {
+                let nova = NewTransacao { data, ..NewTransacao::new(id_usuario.clone(), id_categoria.clone(), valor, tipo.to_string()) };
+                let t: Transacao = diesel::insert_into(t_dsl::transacoes).values(&nova).get_result(conn)?;
+                crate::services::envelope::aplicar_regras(conn, None, Some(&t))?;
+                Ok(t)
+            };
+            let entrada = criar(conn, 10_000, "entrada", dia(1, 12))?;
+            criar(conn, 2_000, "saida", dia(1, 15))?;
+            let primeiro = dia(1, 0).date_naive();
+            let segundo = dia(2, 0).date_naive();
+            assert_eq!(alocacoes(conn, &id_envelope), vec![(primeiro, 800)]);
+
+            // a entrada passa para o dia seguinte: o dia 1 fica no prejuízo e o dia 2 ganha a alocação
+            let lote = |operacao: OperacaoLote| LoteRequest {
+                selecao: SelecaoLote { ids: Some(vec![entrada.id.clone()]), filtro: None },
+                operacao,
+                previa: false,
+            };
+            let ids = executar(conn, &id_usuario, &lote(OperacaoLote::Editar { descricao: None, deslocar_minutos: Some(24 * 60) }))
+                .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+            assert_eq!(ids.len(), 1);
+            assert_eq!(alocacoes(conn, &id_envelope), vec![(segundo, 1_000)]);
+
+            // excluída em lote, a alocação some
+            executar(conn, &id_usuario, &lote(OperacaoLote::Excluir)).map_err(|_| diesel::result::Error::RollbackTransaction)?;
+            assert_eq!(alocacoes(conn, &id_envelope), vec![]);
+            Ok(())
+        });
+    }
+}
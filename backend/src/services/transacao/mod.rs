@@ -1,6 +1,11 @@
+pub mod divisao;
+pub mod filtro;
+pub mod lote;
+
 use axum::{ response::{ Response }, http::{ StatusCode, header } };
 use crate::utils::relatorio::{ gerar_pdf, gerar_xlsx };
-use crate::cache::RIDER_CACHE;
+use divisao::DivisaoPayload;
+use filtro::{ Cursor, Direcao, OrdenarPor, TotaisTransacoes };
 
 #[derive(Deserialize)]
 pub struct RelatorioTransacoesRequest {
@@ -13,7 +18,6 @@ pub async fn relatorio_transacoes_handler(
     jar: CookieJar,
     Json(req): Json<RelatorioTransacoesRequest>
 ) -> Response {
-    use crate::schema::transacoes::dsl::*;
     let conn = &mut db::establish_connection();
     let token = jar
         .get("auth_token")
@@ -29,29 +33,30 @@ pub async fn relatorio_transacoes_handler(
         .unwrap_or_else(|_| Claims { sub: "".to_string(), email: "".to_string(), exp: 0 });
     let user_id = claims.sub.clone();
 
-    // Monta query base
-    let mut query = transacoes.filter(id_usuario.eq(&user_id)).into_boxed();
+    // Mesmos filtros e ordenação da listagem, sem paginação
     let filtro = req.filtros;
-    if let Some(cat) = filtro.id_categoria.clone() {
-        query = query.filter(id_categoria.eq(cat));
-    }
-    if let Some(desc) = filtro.descricao.clone() {
-        if !desc.trim().is_empty() {
-            query = query.filter(descricao.ilike(format!("%{desc}%")));
-        }
-    }
-    if let Some(tipo_f) = filtro.tipo.clone() {
-        query = query.filter(tipo.eq(tipo_f));
-    }
-    if let (Some(dt_ini), Some(dt_fim)) = (filtro.data_inicio, filtro.data_fim) {
-        query = query.filter(data.ge(dt_ini).and(data.le(dt_fim)));
-    } else if let Some(dt_ini) = filtro.data_inicio {
-        query = query.filter(data.ge(dt_ini));
-    } else if let Some(dt_fim) = filtro.data_fim {
-        query = query.filter(data.le(dt_fim));
-    }
-
-    let results = query.order(data.desc()).load::<Transacao>(conn).unwrap_or_default();
+    let janela = match filtro.id_sessao.as_deref() {
+        Some(sessao) => match filtro::janela_sessao(conn, &user_id, sessao) {
+            Ok(Some(janela)) => Some(janela),
+            _ => {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body("Sessão não encontrada".into())
+                    .unwrap();
+            }
+        },
+        None => None,
+    };
+    let results = filtro::ordenar_apos(
+        filtro::filtrar(&filtro, &user_id, janela),
+        filtro.ordenar_por.unwrap_or_default(),
+        filtro.direcao.unwrap_or_default(),
+        None
+    )
+        .load::<Transacao>(conn)
+        .unwrap_or_default();
+    // transações divididas saem com uma linha por categoria
+    let results = divisao::expandir(conn, results);
 
     match req.tipo_arquivo.as_str() {
         "pdf" => {
@@ -91,7 +96,9 @@ use diesel::prelude::*;
 use diesel::AsChangeset;
 use crate::db;
 use crate::schema::transacoes::dsl::*;
-use crate::models::Transacao;
+use crate::models::{ Transacao, TransacaoDivisao };
+use crate::services::eventos::EventoUsuario;
+use crate::services::historico::{self, Acao, Origem};
 use jsonwebtoken::{ decode, DecodingKey, Validation };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -109,6 +116,12 @@ pub struct UpdateTransacaoPayload {
     pub data: Option<chrono::DateTime<chrono::Utc>>, // Aceitar diretamente DateTime<Utc>
     pub eventos: Option<i32>,
     pub km: Option<f64>,
+    /// Nomes das tags (substituem as atuais); ausente mantém as tags
+    pub tags: Option<Vec<String>>,
+    /// Linhas da divisão (substituem as atuais; lista vazia desfaz); ausente mantém
+    pub divisoes: Option<Vec<DivisaoPayload>>,
+    /// Carteira da transação; texto vazio desfaz o vínculo
+    pub id_carteira: Option<String>,
 }
 
 use crate::schema::transacoes;
@@ -122,16 +135,48 @@ pub struct TransacaoChangeset {
     pub data: Option<chrono::DateTime<chrono::Utc>>, // Mantém como DateTime<Utc> para o Diesel
     pub eventos: Option<i32>,
     pub km: Option<f64>,
+    pub id_carteira: Option<Option<String>>,
 }
 
 pub async fn update_transacao_handler(
     Path(id_param): Path<String>,
     Json(payload): Json<UpdateTransacaoPayload>
-) -> Json<Option<TransacaoResponse>> {
+) -> Result<Json<Option<TransacaoResponse>>, (StatusCode, String)> {
     let conn = &mut db::establish_connection();
+    let nomes_tags = payload.tags
+        .as_deref()
+        .map(crate::services::tag::normalizar_nomes)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
     // Buscar transação original para obter user_id
-    let original_transaction = transacoes.filter(id.eq(&id_param)).first::<Transacao>(conn);
+    let original_transaction = transacoes.filter(id.eq(&id_param)).filter(excluido_em.is_null()).first::<Transacao>(conn);
+    let dividida_antes = original_transaction.is_ok() && divisao::tem_divisao(conn, &id_param);
+    if let Ok(original) = &original_transaction {
+        let valor_final = payload.valor.unwrap_or(original.valor);
+        match payload.divisoes.as_deref() {
+            Some([]) => {}
+            Some(linhas) => {
+                divisao::validar(valor_final, linhas).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+                if !divisao::categorias_validas(conn, &original.id_usuario, linhas)
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                {
+                    return Err((StatusCode::BAD_REQUEST, "Categoria inválida na divisão".to_string()));
+                }
+            }
+            None if dividida_antes && valor_final != original.valor => {
+                return Err((StatusCode::BAD_REQUEST, "Envie as novas divisões ao alterar o valor de uma transação dividida".to_string()));
+            }
+            None => {}
+        }
+        if let Some(carteira) = payload.id_carteira.as_deref().filter(|c| !c.is_empty()) {
+            if !crate::services::carteira::carteira_valida(conn, &original.id_usuario, carteira)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            {
+                return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+            }
+        }
+    }
 
     // Certificar que a data está em UTC antes de salvar
     let data_utc = payload.data.map(|d| d.with_timezone(&Utc));
@@ -144,52 +189,85 @@ pub async fn update_transacao_handler(
         data: data_utc, // Gravar diretamente como DateTime<Utc>
         eventos: payload.eventos,
     km: payload.km,
+        id_carteira: payload.id_carteira.map(|c| Some(c).filter(|c| !c.is_empty())),
     };
 
-    diesel
-        ::update(transacoes.filter(id.eq(&id_param)))
-        .set(changeset)
-        .execute(conn)
-        .ok();
+    // a linha, tags, divisões, histórico e envelopes mudam juntos ou nenhum muda
+    let (atualizada, envelopes_alterados) = match &original_transaction {
+        Ok(original) => conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                diesel
+                    ::update(transacoes.filter(id.eq(&id_param)).filter(excluido_em.is_null()))
+                    .set(changeset)
+                    .execute(conn)?;
+                let Some(t) = transacoes.filter(id.eq(&id_param)).filter(excluido_em.is_null()).first::<Transacao>(conn).optional()? else {
+                    return Ok((None, false));
+                };
+                if let Some(nomes) = &nomes_tags {
+                    crate::services::tag::definir_tags_transacao(conn, &t.id_usuario, &t.id, nomes)?;
+                }
+                if let Some(linhas) = &payload.divisoes {
+                    divisao::definir(conn, &t.id, linhas)?;
+                }
+                let origem = Origem::usuario(&original.id_usuario, "PUT /api/transacao/{id}");
+                historico::registrar(conn, &origem, Acao::Atualizacao, Some(original), Some(&t))?;
+                let envelopes_alterados = crate::services::envelope::aplicar_regras(conn, Some(original), Some(&t))?;
+                Ok((Some(t), envelopes_alterados))
+            })
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao atualizar transação: {e}")))?,
+        Err(_) => (None, false),
+    };
+    let dividida_depois = atualizada.is_some() && divisao::tem_divisao(conn, &id_param);
 
-    // CACHE LAYER: Invalidar ambos os caches após edição
+    // CACHE LAYER: delta da edição (sai a versão original, entra a atualizada)
     if let Ok(original) = original_transaction {
-        RIDER_CACHE.invalidate_user_caches(&original.id_usuario).await;
+        if let Some(t) = &atualizada {
+            if dividida_antes || dividida_depois || envelopes_alterados {
+                // o razão soma divisões por linha e guarda o saldo dos envelopes nos dados fixos;
+                // o delta da transação inteira não representa nenhum dos dois
+                let evento = EventoUsuario::TransacaoAtualizada { antes: Box::new(original.clone()), depois: Box::new(t.clone()), dashboard: None };
+                crate::services::eventos::invalidar_e_publicar(&original.id_usuario, evento).await;
+            } else {
+                crate::cache::transacao::update_cached_transaction(&original.id_usuario, &original, t.clone()).await;
+            }
+        }
+        crate::services::anomalia::agendar_deteccao(original.id_usuario);
     }
 
-    match transacoes.filter(id.eq(id_param)).first::<Transacao>(conn) {
-        Ok(t) =>
-            Json(
-                Some(TransacaoResponse {
-                    id: t.id,
-                    id_usuario: t.id_usuario,
-                    id_categoria: t.id_categoria,
-                    valor: t.valor,
-                    eventos: t.eventos,
-                    km: t.km,
-                    tipo: t.tipo,
-                    descricao: t.descricao,
-                    data: t.data,
-                })
-            ),
-        Err(_) => Json(None),
-    }
+    Ok(Json(atualizada.map(|t| com_detalhes(conn, t))))
 }
 
+/// Manda a transação para a lixeira; tags, divisões e comprovantes ficam até o expurgo
 pub async fn delete_transacao_handler(Path(id_param): Path<String>) -> Json<bool> {
     let conn = &mut db::establish_connection();
 
-    // Buscar transação antes de deletar para obter user_id
-    let transaction_to_delete = transacoes.filter(id.eq(&id_param)).first::<Transacao>(conn);
+    // Buscar transação antes de excluir para obter user_id
+    let transaction_to_delete = transacoes.filter(id.eq(&id_param)).filter(excluido_em.is_null()).first::<Transacao>(conn);
+    let dividida = divisao::tem_divisao(conn, &id_param);
 
+    let agora = Utc::now();
     let count = diesel
-        ::delete(transacoes.filter(id.eq(id_param)))
+        ::update(transacoes.filter(id.eq(id_param)).filter(excluido_em.is_null()))
+        .set(excluido_em.eq(Some(agora)))
         .execute(conn)
         .unwrap_or(0);
 
-    // CACHE LAYER: Invalidar ambos os caches após deleção
+    // CACHE LAYER: retirar a transação excluída (delta negativo)
     if let Ok(deleted_transaction) = transaction_to_delete {
-        RIDER_CACHE.invalidate_user_caches(&deleted_transaction.id_usuario).await;
+        let mut envelopes_alterados = false;
+        if count > 0 {
+            let na_lixeira = Transacao { excluido_em: Some(agora), ..deleted_transaction.clone() };
+            let origem = Origem::usuario(&deleted_transaction.id_usuario, "DELETE /api/transacao/{id}");
+            historico::anotar(conn, &origem, Acao::Exclusao, Some(&deleted_transaction), Some(&na_lixeira));
+            envelopes_alterados = crate::services::envelope::sincronizar(conn, Some(&deleted_transaction), None);
+        }
+        if count > 0 && (dividida || envelopes_alterados) {
+            let evento = EventoUsuario::TransacaoExcluida { transacao: deleted_transaction.clone(), dashboard: None };
+            crate::services::eventos::invalidar_e_publicar(&deleted_transaction.id_usuario, evento).await;
+        } else if count > 0 {
+            crate::cache::transacao::remove_cached_transaction(&deleted_transaction.id_usuario, &deleted_transaction).await;
+        }
+        crate::services::anomalia::agendar_deteccao(deleted_transaction.id_usuario);
     }
 
     Json(count > 0)
@@ -204,6 +282,11 @@ pub struct CreateTransacaoPayload {
     pub data: Option<String>, // Alterado para String para aceitar formato do frontend
     pub eventos: Option<i32>,
     pub km: Option<f64>,
+    /// Nomes das tags; as que não existem são criadas
+    pub tags: Option<Vec<String>>,
+    /// Divide o valor entre categorias; as linhas devem somar `valor`
+    pub divisoes: Option<Vec<DivisaoPayload>>,
+    pub id_carteira: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -218,12 +301,23 @@ pub struct TransacaoResponse {
     pub tipo: String,
     pub descricao: Option<String>,
     pub data: chrono::DateTime<chrono::Utc>,
+    pub tags: Vec<String>,
+    /// Vazio quando a transação não é dividida
+    pub divisoes: Vec<TransacaoDivisao>,
+    pub id_sessao: Option<String>,
+    pub id_carteira: Option<String>,
 }
 
 pub async fn create_transacao_handler(
     jar: CookieJar,
     Json(payload): Json<CreateTransacaoPayload>
-) -> Json<TransacaoResponse> {
+) -> Result<Json<TransacaoResponse>, (StatusCode, String)> {
+    let nomes_tags = payload.tags
+        .as_deref()
+        .map(crate::services::tag::normalizar_nomes)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        .unwrap_or_default();
     let conn = &mut db::establish_connection();
     let now: chrono::DateTime<chrono::Utc> = chrono::Utc::now();
     let token = jar
@@ -239,6 +333,22 @@ pub async fn create_transacao_handler(
         .map(|token_data| token_data.claims)
         .unwrap_or_else(|_| Claims { sub: "".to_string(), email: "".to_string(), exp: 0 });
     let user_id = claims.sub.clone();
+    let linhas = payload.divisoes.unwrap_or_default();
+    if !linhas.is_empty() {
+        divisao::validar(payload.valor, &linhas).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        if !divisao::categorias_validas(conn, &user_id, &linhas)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        {
+            return Err((StatusCode::BAD_REQUEST, "Categoria inválida na divisão".to_string()));
+        }
+    }
+    if let Some(carteira) = payload.id_carteira.as_deref() {
+        if !crate::services::carteira::carteira_valida(conn, &user_id, carteira)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        {
+            return Err((StatusCode::BAD_REQUEST, "Carteira não encontrada ou arquivada".to_string()));
+        }
+    }
     let nova_data: chrono::DateTime<chrono::Utc> = match payload.data {
         Some(ref data_str) => {
             println!("Recebendo data do payload: {data_str}");
@@ -271,16 +381,29 @@ pub async fn create_transacao_handler(
         data: nova_data,
         criado_em: now,
         atualizado_em: now,
+        id_carteira: payload.id_carteira,
     };
     
     println!("Criando transação com data UTC: {nova_data}");
     println!("Timezone da data: {}", nova_data.timezone());
     
-    let _result = diesel
-        ::insert_into(transacoes)
-        .values(&nova_transacao)
-        .execute(conn)
-        .expect("Erro ao inserir transação");
+    // a transação, suas tags e divisões entram juntas ou nenhuma entra
+    let (tags_criadas, divisoes_criadas) = conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(transacoes).values(&nova_transacao).execute(conn)?;
+            let tags_criadas = if nomes_tags.is_empty() {
+                Vec::new()
+            } else {
+                crate::services::tag::definir_tags_transacao(conn, &user_id, &nova_transacao.id, &nomes_tags)?
+            };
+            let divisoes_criadas = if linhas.is_empty() {
+                Vec::new()
+            } else {
+                divisao::definir(conn, &nova_transacao.id, &linhas)?
+            };
+            Ok((tags_criadas, divisoes_criadas))
+        })
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro ao inserir transação: {e}")))?;
 
     // CACHE LAYER: Adicionar ao cache como transação nova
     let transacao_criada = Transacao {
@@ -295,11 +418,24 @@ pub async fn create_transacao_handler(
         data: nova_transacao.data,
         criado_em: nova_transacao.criado_em,
         atualizado_em: nova_transacao.atualizado_em,
+        id_sessao: None,
+        excluido_em: None,
+        id_carteira: nova_transacao.id_carteira.clone(),
     };
+    historico::anotar(conn, &Origem::usuario(&user_id, "POST /api/transacao"), Acao::Criacao, None, Some(&transacao_criada));
+    let envelopes_alterados = crate::services::envelope::sincronizar(conn, None, Some(&transacao_criada));
+
+    if divisoes_criadas.is_empty() && !envelopes_alterados {
+        crate::cache::transacao::add_new_transaction(&user_id, transacao_criada).await;
+    } else {
+        // dividida ou alocada em envelope: o razão precisa das linhas e dos novos saldos, então
+        // recarrega em vez de aplicar o delta
+        let evento = EventoUsuario::TransacaoCriada { transacao: transacao_criada, dashboard: None };
+        crate::services::eventos::invalidar_e_publicar(&user_id, evento).await;
+    }
+    crate::services::anomalia::agendar_deteccao(user_id.clone());
 
-    crate::cache::transacao::add_new_transaction(&user_id, transacao_criada).await;
-
-    Json(TransacaoResponse {
+    Ok(Json(TransacaoResponse {
         id: nova_transacao.id,
         id_usuario: nova_transacao.id_usuario,
         id_categoria: nova_transacao.id_categoria,
@@ -309,28 +445,19 @@ pub async fn create_transacao_handler(
         tipo: nova_transacao.tipo,
         descricao: nova_transacao.descricao,
         data: nova_transacao.data,
-    })
+        tags: tags_criadas.into_iter().map(|t| t.nome).collect(),
+        divisoes: divisoes_criadas,
+        id_sessao: None,
+        id_carteira: nova_transacao.id_carteira,
+    }))
 }
 
 pub async fn get_transacao_handler(Path(
     id_param,
 ): Path<String>) -> Json<Option<TransacaoResponse>> {
     let conn = &mut db::establish_connection();
-    match transacoes.filter(id.eq(id_param)).first::<Transacao>(conn) {
-        Ok(t) =>
-            Json(
-                Some(TransacaoResponse {
-                    id: t.id,
-                    id_usuario: t.id_usuario,
-                    id_categoria: t.id_categoria,
-                    valor: t.valor,
-                    eventos: t.eventos,
-                    km: t.km,
-                    tipo: t.tipo,
-                    descricao: t.descricao,
-                    data: t.data,
-                })
-            ),
+    match transacoes.filter(id.eq(id_param)).filter(excluido_em.is_null()).first::<Transacao>(conn) {
+        Ok(t) => Json(Some(com_detalhes(conn, t))),
         Err(_) => Json(None),
     }
 }
@@ -344,6 +471,43 @@ pub struct TransacaoFiltro {
     pub tipo: Option<String>,
     pub data_inicio: Option<chrono::DateTime<Utc>>,
     pub data_fim: Option<chrono::DateTime<Utc>>,
+    /// Qualquer uma destas categorias (também casa com linhas de transações divididas)
+    pub categorias: Option<Vec<String>>,
+    /// Ids de tags: transações com pelo menos uma delas
+    pub tags: Option<Vec<String>>,
+    /// Limites de valor em centavos (inclusivos)
+    pub valor_min: Option<i32>,
+    pub valor_max: Option<i32>,
+    pub eventos_min: Option<i32>,
+    pub eventos_max: Option<i32>,
+    /// true: só com km informado; false: só sem km
+    pub com_km: Option<bool>,
+    /// Transações dentro do período da sessão de trabalho
+    pub id_sessao: Option<String>,
+    pub id_carteira: Option<String>,
+    pub ordenar_por: Option<OrdenarPor>,
+    pub direcao: Option<Direcao>,
+    /// `next_cursor` da resposta anterior; quando presente, `page` é ignorado
+    pub cursor: Option<String>,
+}
+
+impl TransacaoFiltro {
+    fn sem_filtros(&self) -> bool {
+        self.id_categoria.is_none() &&
+            self.categorias.is_none() &&
+            self.tags.is_none() &&
+            self.descricao.is_none() &&
+            self.tipo.is_none() &&
+            self.data_inicio.is_none() &&
+            self.data_fim.is_none() &&
+            self.valor_min.is_none() &&
+            self.valor_max.is_none() &&
+            self.eventos_min.is_none() &&
+            self.eventos_max.is_none() &&
+            self.com_km.is_none() &&
+            self.id_sessao.is_none() &&
+            self.id_carteira.is_none()
+    }
 }
 
 #[derive(Serialize)]
@@ -352,14 +516,44 @@ pub struct PaginatedTransacoes {
     pub page: usize,
     pub page_size: usize,
     pub items: Vec<TransacaoResponse>,
+    /// Cursor da próxima página (None na última)
+    pub next_cursor: Option<String>,
+    pub totais: TotaisTransacoes,
+}
+
+impl From<Transacao> for TransacaoResponse {
+    fn from(t: Transacao) -> Self {
+        TransacaoResponse {
+            id: t.id,
+            id_usuario: t.id_usuario,
+            id_categoria: t.id_categoria,
+            valor: t.valor,
+            eventos: t.eventos,
+            km: t.km,
+            tipo: t.tipo,
+            descricao: t.descricao,
+            data: t.data,
+            tags: Vec::new(),
+            divisoes: Vec::new(),
+            id_sessao: t.id_sessao,
+            id_carteira: t.id_carteira,
+        }
+    }
+}
+
+/// Resposta de uma transação com suas tags e divisões
+fn com_detalhes(conn: &mut PgConnection, t: Transacao) -> TransacaoResponse {
+    let ids = std::slice::from_ref(&t.id);
+    let tags = crate::services::tag::tags_por_transacao(conn, ids).unwrap_or_default().remove(&t.id).unwrap_or_default();
+    let divisoes = divisao::por_transacao(conn, ids).unwrap_or_default().remove(&t.id).unwrap_or_default();
+    TransacaoResponse { tags, divisoes, ..TransacaoResponse::from(t) }
 }
 
 #[axum::debug_handler]
 pub async fn list_transacoes_handler(
     jar: CookieJar,
     Json(filtro): Json<TransacaoFiltro>
-) -> Json<PaginatedTransacoes> {
-    // (nenhum import extra necessário)
+) -> Result<Json<PaginatedTransacoes>, (StatusCode, String)> {
     let conn = &mut db::establish_connection();
     let token = jar
         .get("auth_token")
@@ -375,156 +569,89 @@ pub async fn list_transacoes_handler(
         .unwrap_or_else(|_| Claims { sub: "".to_string(), email: "".to_string(), exp: 0 });
     let user_id = claims.sub.clone();
 
-    // DEBUG: logar informações úteis para diagnosticar carregamento de transações
-    println!("[DEBUG] list_transacoes_handler called - user_id: '{}', raw_token_present: {}", user_id, jar.get("auth_token").is_some());
-    println!("[DEBUG] filtro recebido: page={:?}, page_size={:?}, id_categoria={:?}, descricao={:?}, tipo={:?}, data_inicio={:?}, data_fim={:?}",
-        filtro.page, filtro.page_size, filtro.id_categoria, filtro.descricao, filtro.tipo, filtro.data_inicio, filtro.data_fim
-    );
-
     let page = filtro.page.unwrap_or(1).max(1);
     let page_size = filtro.page_size.unwrap_or(10).clamp(1, 100);
-    let offset = (page - 1) * page_size;
+    let ordenar_por = filtro.ordenar_por.unwrap_or_default();
+    let direcao = filtro.direcao.unwrap_or_default();
+    let cursor = match filtro.cursor.as_deref() {
+        Some(texto) => Some(
+            Cursor::decodificar(texto)
+                .filter(|c| c.ordenar_por() == ordenar_por)
+                .ok_or((StatusCode::BAD_REQUEST, "Cursor inválido para esta ordenação".to_string()))?
+        ),
+        None => None,
+    };
+    let janela = match filtro.id_sessao.as_deref() {
+        Some(sessao) => Some(
+            filtro::janela_sessao(conn, &user_id, sessao)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or((StatusCode::NOT_FOUND, "Sessão não encontrada".to_string()))?
+        ),
+        None => None,
+    };
 
-    // CACHE LAYER: Tentar cache para páginas 1 e 2 sem filtros
+    let totais = filtro::totais(conn, &filtro, &user_id, janela)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let total = totais.quantidade as usize;
+
+    // CACHE LAYER: primeira página sem filtros, na ordenação padrão
     let is_simple_query =
-        filtro.id_categoria.is_none() &&
-        filtro.descricao.is_none() &&
-        filtro.tipo.is_none() &&
-        filtro.data_inicio.is_none() &&
-        filtro.data_fim.is_none();
+        filtro.sem_filtros() &&
+        cursor.is_none() &&
+        ordenar_por == OrdenarPor::Data &&
+        direcao == Direcao::Desc;
 
+    let mut em_cache = None;
     if is_simple_query && page == 1 {
-    println!("[DEBUG] attempting to serve from cache for user {} page {}", user_id, page);
-        if let Some(cached_transactions) = crate::cache::transacao::get_cached_transactions(
-            &user_id,
-            page,
-            page_size
-        ).await {
-            let count_query = transacoes.filter(id_usuario.eq(&user_id)).into_boxed();
-            let total: i64 = count_query.count().get_result(conn).unwrap_or(0);
-
-            let items = cached_transactions
-                .into_iter()
-                .map(|t| TransacaoResponse {
-                    id: t.id,
-                    id_usuario: t.id_usuario,
-                    id_categoria: t.id_categoria,
-                    valor: t.valor,
-                    eventos: t.eventos,
-                    km: t.km,
-                    tipo: t.tipo,
-                    descricao: t.descricao,
-                    data: t.data,
-                })
-                .collect();
-
-            return Json(PaginatedTransacoes {
-                total: total as usize,
-                page,
-                page_size,
-                items,
-            });
+        if let Some(mut cached) = crate::cache::transacao::get_cached_transactions(&user_id, page, page_size).await {
+            // mesma ordem da consulta, para o cursor seguir de onde a página termina
+            cached.sort_by(|a, b| b.data.cmp(&a.data).then_with(|| b.id.cmp(&a.id)));
+            em_cache = Some(cached);
         }
-    } else if is_simple_query && page == 2 {
-    println!("[DEBUG] simple query page 2, loading from DB for user {}", user_id);
-        // Carregar diretamente do banco de dados para a página 2
-        let count_query = transacoes.filter(id_usuario.eq(&user_id)).into_boxed();
-        let total: i64 = count_query.count().get_result(conn).unwrap_or(0);
-        let items: Vec<TransacaoResponse> = transacoes.filter(id_usuario.eq(&user_id))
-            .offset(offset as i64)
-            .limit(page_size as i64)
-            .load::<Transacao>(conn)
-            .unwrap_or_default()
-            .into_iter()
-            .map(|t| TransacaoResponse {
-                id: t.id,
-                id_usuario: t.id_usuario,
-                id_categoria: t.id_categoria,
-                valor: t.valor,
-                eventos: t.eventos,
-                km: t.km,
-                tipo: t.tipo,
-                descricao: t.descricao,
-                data: t.data,
-            })
-            .collect();
-
-        return Json(PaginatedTransacoes {
-            total: total as usize,
-            page,
-            page_size,
-            items,
-        });
-    }
-
-    // Cache miss ou consulta com filtros - usar lógica original
-
-    // Monta query base para count
-    let mut count_query = transacoes.filter(id_usuario.eq(&user_id)).into_boxed();
-    if let Some(ref cat) = filtro.id_categoria {
-        count_query = count_query.filter(id_categoria.eq(cat));
-    }
-    if let Some(ref desc) = filtro.descricao {
-        count_query = count_query.filter(descricao.ilike(format!("%{desc}%")));
-    }
-    if let Some(ref tipo_f) = filtro.tipo {
-        count_query = count_query.filter(tipo.eq(tipo_f));
-    }
-    if let (Some(dt_ini), Some(dt_fim)) = (filtro.data_inicio, filtro.data_fim) {
-        count_query = count_query.filter(data.ge(dt_ini).and(data.le(dt_fim)));
-    } else if let Some(dt_ini) = filtro.data_inicio {
-        count_query = count_query.filter(data.ge(dt_ini));
-    } else if let Some(dt_fim) = filtro.data_fim {
-        count_query = count_query.filter(data.le(dt_fim));
-    }
-
-    let total: i64 = count_query.count().get_result(conn).unwrap_or(0);
-    
-    // Monta query base para busca paginada com os mesmos filtros
-    let mut data_query = transacoes.filter(id_usuario.eq(&user_id)).into_boxed();
-    if let Some(ref cat) = filtro.id_categoria {
-        data_query = data_query.filter(id_categoria.eq(cat));
-    }
-    if let Some(ref desc) = filtro.descricao {
-        data_query = data_query.filter(descricao.ilike(format!("%{desc}%")));
     }
-    if let Some(ref tipo_f) = filtro.tipo {
-        data_query = data_query.filter(tipo.eq(tipo_f));
-    }
-    if let (Some(dt_ini), Some(dt_fim)) = (filtro.data_inicio, filtro.data_fim) {
-        data_query = data_query.filter(data.ge(dt_ini).and(data.le(dt_fim)));
-    } else if let Some(dt_ini) = filtro.data_inicio {
-        data_query = data_query.filter(data.ge(dt_ini));
-    } else if let Some(dt_fim) = filtro.data_fim {
-        data_query = data_query.filter(data.le(dt_fim));
-    }
-    
-    let items: Vec<TransacaoResponse> = data_query
-        .order(data.desc())
-        .offset(offset as i64)
-        .limit(page_size as i64)
-        .load::<Transacao>(conn)
-        .unwrap_or_default()
+    let (mut carregadas, tem_mais) = match em_cache {
+        Some(cached) => {
+            let tem_mais = cached.len() == page_size && total > page_size;
+            (cached, tem_mais)
+        }
+        None => {
+            let offset = if cursor.is_some() { 0 } else { (page - 1) * page_size };
+            // um item a mais só para saber se há próxima página
+            let carregadas = filtro::ordenar_apos(filtro::filtrar(&filtro, &user_id, janela), ordenar_por, direcao, cursor)
+                .offset(offset as i64)
+                .limit(page_size as i64 + 1)
+                .load::<Transacao>(conn)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let tem_mais = carregadas.len() > page_size;
+            (carregadas, tem_mais)
+        }
+    };
+    carregadas.truncate(page_size);
+    let next_cursor = carregadas
+        .last()
+        .filter(|_| tem_mais)
+        .map(|t| Cursor::de(t, ordenar_por).codificar());
+
+    let ids: Vec<String> = carregadas.iter().map(|t| t.id.clone()).collect();
+    let mut tags_map = crate::services::tag::tags_por_transacao(conn, &ids)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut divisoes_map = divisao::por_transacao(conn, &ids)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let items = carregadas
         .into_iter()
-        .map(|t| TransacaoResponse {
-            id: t.id,
-            id_usuario: t.id_usuario,
-            id_categoria: t.id_categoria,
-            valor: t.valor,
-            eventos: t.eventos,
-            km: t.km,
-            tipo: t.tipo,
-            descricao: t.descricao,
-            data: t.data,
+        .map(|t| {
+            let tags = tags_map.remove(&t.id).unwrap_or_default();
+            let divisoes = divisoes_map.remove(&t.id).unwrap_or_default();
+            TransacaoResponse { tags, divisoes, ..TransacaoResponse::from(t) }
         })
         .collect();
 
-    println!("[DEBUG] query executed, items_loaded: {} for user {} (page {}, page_size {})", items.len(), user_id, page, page_size);
-
-    Json(PaginatedTransacoes {
-        total: total as usize,
+    Ok(Json(PaginatedTransacoes {
+        total,
         page,
         page_size,
         items,
-    })
+        next_cursor,
+        totais,
+    }))
 }
@@ -23,8 +23,14 @@ pub async fn reset_all_user_data_handler(cookie_jar: CookieJar) -> impl IntoResp
 
     // Use transaction to ensure atomicity
     let res = conn.transaction::<(), diesel::result::Error, _>(|conn_tx| {
+        // Delete anomaly alerts (os ligados a transações cairiam em cascata, os diários não)
+        let _ = diesel::delete(crate::schema::alertas_anomalia::dsl::alertas_anomalia.filter(crate::schema::alertas_anomalia::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
+        // Delete corridas
+        let _ = diesel::delete(crate::schema::corridas::dsl::corridas.filter(crate::schema::corridas::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
         // Delete transactions belonging to user
         let _ = diesel::delete(crate::schema::transacoes::dsl::transacoes.filter(crate::schema::transacoes::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
+        // Delete eventos de fadiga (as pausas saem com as sessões)
+        let _ = diesel::delete(crate::schema::eventos_fadiga::dsl::eventos_fadiga.filter(crate::schema::eventos_fadiga::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
         // Delete work sessions
         let _ = diesel::delete(crate::schema::sessoes_trabalho::dsl::sessoes_trabalho.filter(crate::schema::sessoes_trabalho::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
         // Delete metas
@@ -32,6 +38,14 @@ pub async fn reset_all_user_data_handler(cookie_jar: CookieJar) -> impl IntoResp
     // Nota: assinaturas NÃO serão deletadas pelo reset (preservar assinaturas do usuário)
         // Delete categorias of user
         let _ = diesel::delete(crate::schema::categorias::dsl::categorias.filter(crate::schema::categorias::dsl::id_usuario.eq(Some(user_id.clone())))).execute(conn_tx)?;
+        // Delete envelopes (os movimentos saem em cascata)
+        let _ = diesel::delete(crate::schema::envelopes::dsl::envelopes.filter(crate::schema::envelopes::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
+        // Delete dívidas (as parcelas vão junto)
+        let _ = diesel::delete(crate::schema::dividas::dsl::dividas.filter(crate::schema::dividas::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
+        // Delete repasses (antes das transações de ajuste que eles referenciam)
+        let _ = diesel::delete(crate::schema::repasses::dsl::repasses.filter(crate::schema::repasses::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
+        // Delete carteiras (as transferências saem em cascata)
+        let _ = diesel::delete(crate::schema::carteiras::dsl::carteiras.filter(crate::schema::carteiras::dsl::id_usuario.eq(&user_id))).execute(conn_tx)?;
         // Delete configuracoes of user
         let _ = diesel::delete(crate::schema::configuracoes::dsl::configuracoes.filter(crate::schema::configuracoes::dsl::id_usuario.eq(Some(user_id.clone())))).execute(conn_tx)?;
 
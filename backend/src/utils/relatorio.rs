@@ -10,6 +10,7 @@ fn categorias_map(conn: &mut diesel::PgConnection, usuario_id: &str) -> HashMap<
     use crate::schema::categorias::dsl::*;
     let results: Vec<Categoria> = categorias
     .filter(id_usuario.eq(usuario_id))
+        .filter(excluido_em.is_null())
         .load(conn)
         .unwrap_or_default();
     results.into_iter().map(|c| (c.id, c.nome)).collect()
@@ -30,8 +31,8 @@ fn buscar_mask_data(conn: &mut diesel::PgConnection, usuario_id: &str) -> String
         .unwrap_or_else(|| "%Y-%m-%d %H:%M:%S".to_string())
 }
 
-fn formatar_moeda(valor: i32) -> String {
-    let abs = (valor.abs() as f64) / 100.0;
+pub fn formatar_moeda(valor: impl Into<i64>) -> String {
+    let abs = (valor.into().abs() as f64) / 100.0;
     let mut s = format!("{abs:.2}");
     let parts: Vec<&str> = s.split('.').collect();
     let int_part = parts[0];
@@ -63,6 +64,8 @@ pub fn gerar_pdf(_transacoes: &[Transacao], usuario_id: &str, conn: &mut diesel:
 pub fn gerar_xlsx(transacoes: &[Transacao], usuario_id: &str, conn: &mut diesel::PgConnection) -> Vec<u8> {
     let cat_map = categorias_map(conn, usuario_id);
     let mask_data = buscar_mask_data(conn, usuario_id);
+    let ids: Vec<String> = transacoes.iter().map(|t| t.id.clone()).collect();
+    let tags_map = crate::services::tag::tags_por_transacao(conn, &ids).unwrap_or_default();
     let mut book = umya_spreadsheet::new_file();
     let sheet_name = "Sheet1";
     let sheet = book.get_sheet_by_name_mut(sheet_name).unwrap();
@@ -71,8 +74,9 @@ pub fn gerar_xlsx(transacoes: &[Transacao], usuario_id: &str, conn: &mut diesel:
     sheet.get_cell_mut((2, 1)).set_value("Descrição");
     sheet.get_cell_mut((3, 1)).set_value("Categoria");
     sheet.get_cell_mut((4, 1)).set_value("Valor");
+    sheet.get_cell_mut((5, 1)).set_value("Tags");
     // Formatação do cabeçalho
-    for col in 1..=4 {
+    for col in 1..=5 {
         let mut style = umya_spreadsheet::Style::default();
         style.get_font_mut().set_bold(true);
         sheet.get_cell_mut((col, 1)).set_style(style.clone());
@@ -88,6 +92,9 @@ pub fn gerar_xlsx(transacoes: &[Transacao], usuario_id: &str, conn: &mut diesel:
         sheet.get_cell_mut((2, row)).set_value(t.descricao.as_deref().unwrap_or(""));
         sheet.get_cell_mut((3, row)).set_value(&nome_categoria);
         sheet.get_cell_mut((4, row)).set_value(valor_final);
+        if let Some(tags) = tags_map.get(&t.id) {
+            sheet.get_cell_mut((5, row)).set_value(tags.join(", "));
+        }
         total += if t.tipo == "saida" { -t.valor } else { t.valor };
     }
     // Linha de total